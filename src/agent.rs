@@ -1,13 +1,87 @@
+pub mod book;
 pub mod implementations;
+pub mod logging;
+pub mod registry;
+pub mod tournament;
 
-use std::cmp::Ordering;
+use std::cell::RefCell;
+use std::fmt;
+use std::io;
+use std::panic;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::thread;
+use std::time::Instant;
 
-use crate::gameplay::{Gamestate, Turn, States, Players};
+use crate::agent::tournament::{GameRecord, PlyRecord, Record};
+use crate::gameplay::{GameResult, Gamestate, Rules, Turn, States, Players};
+use crate::mcst::Analysis;
+
+/// Errors that can stop an agent from making a move.
+#[derive(Debug)]
+pub enum AgentError {
+    /// The agent was asked to move in a game that is over.
+    NoMoves,
+    /// The agent was told about, or came up with, a move that is not legal.
+    InvalidState(String),
+    /// The agent could not read its move, such as when a
+    /// [HumanAgent](implementations::HumanAgent)'s input ends.
+    Io(io::Error),
+}
+
+impl fmt::Display for AgentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AgentError::NoMoves => write!(f, "asked for a move in a game with no moves"),
+            AgentError::InvalidState(message) => write!(f, "{message}"),
+            AgentError::Io(e) => write!(f, "could not read move: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for AgentError {}
+
+impl From<io::Error> for AgentError {
+    fn from(e: io::Error) -> Self {
+        AgentError::Io(e)
+    }
+}
 
 /// An Agent implements what is the bare minimum to play a game:
 /// taking a look at a board and spitting out a valid turn.
+///
+/// Any closure taking a [Gamestate] and returning a [Turn] is an Agent.
 pub trait Agent {
+    /// Picks a move in `state`.
+    ///
+    /// May panic if there are no moves; see [try_make_move](Agent::try_make_move).
     fn make_move(&self, state: &Gamestate) -> Turn;
+
+    /// Like [make_move](Agent::make_move), but returns an error where it might panic.
+    ///
+    /// By default, returns [AgentError::NoMoves] if the game is over and
+    /// asks [make_move](Agent::make_move) otherwise.
+    fn try_make_move(&self, state: &Gamestate) -> Result<Turn, AgentError> {
+        if state.is_terminal() {
+            return Err(AgentError::NoMoves);
+        }
+        Ok(self.make_move(state))
+    }
+
+    /// Whether this agent always picks uniformly at random from the legal
+    /// moves, and so can [play games out](Agent::random_playout) without
+    /// being asked for every move. False by default.
+    fn is_uniform_random(&self) -> bool {
+        false
+    }
+
+    /// If this agent [is uniformly random](Agent::is_uniform_random),
+    /// plays out the rest of the game against itself with
+    /// [Gamestate::random_playout_plies] (instead of being asked for every
+    /// move) and returns the final score and how many plies it took.
+    /// Returns [None] otherwise.
+    fn random_playout(&self, _state: &Gamestate) -> Option<(i8, usize)> {
+        None
+    }
 }
 
 /// A MemoryAgent is a little more complicated than an [Agent].
@@ -18,6 +92,52 @@ pub trait MemoryAgent {
     fn initialize_game(&mut self, state: Gamestate);
     fn opponent_move(&mut self, op: &Turn);
     fn make_move(&mut self) -> Turn;
+
+    /// Like [opponent_move](MemoryAgent::opponent_move), but returns an error
+    /// where it might panic. Never fails by default.
+    fn try_opponent_move(&mut self, op: &Turn) -> Result<(), AgentError> {
+        self.opponent_move(op);
+        Ok(())
+    }
+
+    /// Like [make_move](MemoryAgent::make_move), but returns an error
+    /// where it might panic. Never fails by default.
+    fn try_make_move(&mut self) -> Result<Turn, AgentError> {
+        Ok(self.make_move())
+    }
+
+    /// Called once the opponent has decided on its move, but before
+    /// [opponent_move](MemoryAgent::opponent_move) is told what it is,
+    /// with how long the opponent took (in hundredths of a second). Games
+    /// are played one move after another, so this stands in for thinking
+    /// while the opponent did: agents that search may use up to that long
+    /// to think ahead.
+    ///
+    /// Does nothing by default.
+    fn ponder(&mut self, _budget: u128) {}
+
+    /// Returns the agent's estimate of the probability that Black wins the
+    /// game, if it has one. Used by [play_game_adjudicated].
+    ///
+    /// Returns [None] by default.
+    fn evaluate(&self) -> Option<f64> {
+        None
+    }
+
+    /// Returns what the agent thought of the position it last moved from, if
+    /// it searches in a way that can be shown. Recorded with each ply by
+    /// [play_game_recorded].
+    ///
+    /// Returns [None] by default.
+    fn analysis(&self) -> Option<Analysis> {
+        None
+    }
+}
+
+impl<F: Fn(&Gamestate) -> Turn> Agent for F {
+    fn make_move(&self, state: &Gamestate) -> Turn {
+        self(state)
+    }
 }
 
 /// A MemorifiedAgent is a wrapper that turns any [Agent] into a [MemoryAgent].
@@ -43,26 +163,141 @@ impl<A: Agent> MemoryAgent for MemorifiedAgent<A> {
     }
 
     fn opponent_move(&mut self, op: &Turn) {
-        if !self.memory.make_move_fast(*op) {
-            panic!("opponent_move passed invalid turn.");
-        }
+        self.try_opponent_move(op).unwrap_or_else(|e| panic!("{e}."));
     }
 
     fn make_move(&mut self) -> Turn {
-        let turn = self.agent.make_move(&self.memory);
-        if !self.memory.make_move_fast(turn) {
-            panic!("agent.make_move returned invalid turn.");
+        self.try_make_move().unwrap_or_else(|e| panic!("{e}."))
+    }
+
+    fn try_opponent_move(&mut self, op: &Turn) -> Result<(), AgentError> {
+        self.memory.try_move(*op).map_err(|e| {
+            AgentError::InvalidState(format!("opponent_move passed invalid turn {op:?}: {e}"))
+        })?;
+        Ok(())
+    }
+
+    fn try_make_move(&mut self) -> Result<Turn, AgentError> {
+        let turn = self.agent.try_make_move(&self.memory)?;
+        self.memory.try_move(turn).map_err(|e| {
+            AgentError::InvalidState(format!("agent.make_move returned invalid turn {turn:?}: {e}"))
+        })?;
+        Ok(turn)
+    }
+}
+
+/// A StatelessWrapper is the reverse of a [MemorifiedAgent]: it turns any
+/// [MemoryAgent] into an [Agent], so it can be used where only an Agent will do,
+/// such as the rollout policy of a [McstAgent](crate::mcst::McstAgent).
+/// It does this by [initializing](MemoryAgent::initialize_game) a new game from
+/// the state it is given every time it is asked for a move.
+///
+/// Nothing the inner agent works out carries over from one move to the next,
+/// so agents which build up state over a game, like the search tree of a
+/// [McstMemoryAgent](implementations::McstMemoryAgent), start from scratch on
+/// every call and play worse for the same time than they would as a MemoryAgent.
+pub struct StatelessWrapper<M: MemoryAgent> {
+    agent: RefCell<M>,
+}
+
+impl<M: MemoryAgent> StatelessWrapper<M> {
+    pub fn new(agent: M) -> Self {
+        StatelessWrapper { agent: RefCell::new(agent) }
+    }
+
+    pub fn into_inner(self) -> M {
+        self.agent.into_inner()
+    }
+}
+
+impl<M: MemoryAgent> Agent for StatelessWrapper<M> {
+    fn make_move(&self, state: &Gamestate) -> Turn {
+        let mut agent = self.agent.borrow_mut();
+        agent.initialize_game(state.clone());
+        agent.make_move()
+    }
+
+    fn try_make_move(&self, state: &Gamestate) -> Result<Turn, AgentError> {
+        if state.is_terminal() {
+            return Err(AgentError::NoMoves);
         }
-        turn
+        let mut agent = self.agent.borrow_mut();
+        agent.initialize_game(state.clone());
+        agent.try_make_move()
     }
 }
 
-pub fn play_memory_agents_from
-<A1: MemoryAgent, A2: MemoryAgent>
-(agent_black: &mut A1, agent_white: &mut A2, mut game: Gamestate) -> (i8, Vec<Turn>) {
-    let mut history: Vec<Turn> = Vec::new();
+/// Plays a game between two agents starting from `game` and returns
+/// the result along with every turn taken.
+///
+/// After every move but the first, the agent that was waiting is given as long
+/// to [ponder](MemoryAgent::ponder) as its opponent took to move, in place of
+/// thinking at the same time.
+pub fn play_game_from
+<A1: MemoryAgent + ?Sized, A2: MemoryAgent + ?Sized>
+(agent_black: &mut A1, agent_white: &mut A2, game: Gamestate) -> (GameResult, Vec<Turn>) {
+    try_play_game_from(agent_black, agent_white, game).unwrap_or_else(|e| panic!("{e}."))
+}
+
+/// Like [play_game_from], but plays by `rules`: Black's handicap tiles are
+/// placed on `game` before it starts, and the result takes the komi into account.
+pub fn play_game_from_with_rules
+<A1: MemoryAgent + ?Sized, A2: MemoryAgent + ?Sized>
+(agent_black: &mut A1, agent_white: &mut A2, game: Gamestate, rules: Rules) -> (GameResult, Vec<Turn>) {
+    let (result, turns) = play_game_from(agent_black, agent_white, rules.apply_handicap(&game));
+    (result.with_komi(rules.komi), turns)
+}
+
+/// Like [play_game_from], but returns the first error either agent runs into
+/// (or the [illegal move](AgentError::InvalidState) one makes) instead of panicking.
+pub fn try_play_game_from
+<A1: MemoryAgent + ?Sized, A2: MemoryAgent + ?Sized>
+(agent_black: &mut A1, agent_white: &mut A2, game: Gamestate) -> Result<(GameResult, Vec<Turn>), AgentError> {
+    let (result, plies, _) = play_game_inner(agent_black, agent_white, game, None)?;
+    Ok((result, plies.into_iter().map(|ply| ply.turn).collect()))
+}
+
+/// Like [play_game_from], but records how long each agent took over each
+/// turn, what the turn flipped, and the score after it, along with the names
+/// `(black, white)` of the agents.
+pub fn play_game_recorded
+<A1: MemoryAgent + ?Sized, A2: MemoryAgent + ?Sized>
+(agent_black: &mut A1, agent_white: &mut A2, game: Gamestate, (black, white): (&str, &str)) -> GameRecord {
+    let start = game.board().to_compact();
+    let (result, plies, _) = play_game_inner(agent_black, agent_white, game, None)
+        .unwrap_or_else(|e| panic!("{e}."));
+    GameRecord { black: black.to_string(), white: white.to_string(), start, plies, result }
+}
+
+/// Like [play_game], but ends the game early once it is decided: when, for two
+/// plies in a row after the first `min_ply`, both agents
+/// [evaluate](MemoryAgent::evaluate) the game as won for the same player with
+/// at least `resign_threshold` probability (so `0.95` means the loser's chances
+/// are 5% or less).
+///
+/// Returns whether the game was adjudicated along with the usual result.
+/// The winner of an adjudicated game wins by the tiles they are ahead by when
+/// it ends, or by one tile if they are not ahead. [game_to_str](crate::data::game_to_str)
+/// records the game with that flag, for data collection to weigh it less.
+pub fn play_game_adjudicated
+<A1: MemoryAgent + ?Sized, A2: MemoryAgent + ?Sized>
+(agent_black: &mut A1, agent_white: &mut A2, resign_threshold: f64, min_ply: usize)
+-> (GameResult, Vec<Turn>, bool) {
+    let (result, plies, adjudicated) =
+        play_game_inner(agent_black, agent_white, Gamestate::new(), Some((resign_threshold, min_ply)))
+            .unwrap_or_else(|e| panic!("{e}."));
+    (result, plies.into_iter().map(|ply| ply.turn).collect(), adjudicated)
+}
+
+/// Plays a game as described by [play_game_from], adjudicating it
+/// as described by [play_game_adjudicated] if `adjudication` is given.
+fn play_game_inner
+<A1: MemoryAgent + ?Sized, A2: MemoryAgent + ?Sized>
+(agent_black: &mut A1, agent_white: &mut A2, mut game: Gamestate, adjudication: Option<(f64, usize)>)
+-> Result<(GameResult, Vec<PlyRecord>, bool), AgentError> {
+    let mut plies: Vec<PlyRecord> = Vec::new();
     let black_first = match game.whose_turn() {
-        States::Empty => return (game.score(), Vec::new()),
+        States::Empty => return Ok((GameResult::from_score(game.score()), Vec::new(), false)),
         States::Taken(Players::Black) => true,
         States::Taken(Players::White) => false,
     };
@@ -70,63 +305,528 @@ pub fn play_memory_agents_from
     match black_first {
         true => {
             agent_black.initialize_game(game.clone());
-            let first_move = agent_black.make_move();
-            history.push(first_move);
-            if !game.make_move_fast(first_move) {
-                panic!("illegal move");
-            }
+            play_ply(&mut game, &mut plies, agent_black)?;
             agent_white.initialize_game(game.clone());
         }
         false => {
             agent_white.initialize_game(game.clone());
-            let first_move = agent_white.make_move();
-            history.push(first_move);
-            if !game.make_move_fast(first_move) {
-                panic!("illegal move");
-            }
+            play_ply(&mut game, &mut plies, agent_white)?;
             agent_black.initialize_game(game.clone());
         }
     }
 
+    // the player both agents have agreed is winning, and for how many plies
+    let mut agreement: Option<(Players, u32)> = None;
     loop {
         let valid_moves = game.get_moves();
         if valid_moves.is_empty() {
-            break (game.score(), history);
+            break Ok((GameResult::from_score(game.score()), plies, false));
         }
 
-        let player_move = match game.whose_turn() {
-            States::Taken(Players::Black) => agent_black.make_move(),
-            States::Taken(Players::White) => agent_white.make_move(),
+        let (player_move, thinking_time) = match game.whose_turn() {
+            States::Taken(Players::Black) => play_ply(&mut game, &mut plies, agent_black)?,
+            States::Taken(Players::White) => play_ply(&mut game, &mut plies, agent_white)?,
             _ => panic!("game should not be over"),
         };
-        if !game.make_move_fast(player_move) {
-            panic!("illegal move {:?} on game \n{game}\n.", player_move);
-        }
-        history.push(player_move);
         match game.whose_turn() { // whose turn has just been updated
-            States::Taken(Players::Black) => agent_black.opponent_move(&player_move),
-            States::Taken(Players::White) => agent_white.opponent_move(&player_move),
+            States::Taken(Players::Black) => {
+                agent_black.ponder(thinking_time);
+                agent_black.try_opponent_move(&player_move)?;
+            }
+            States::Taken(Players::White) => {
+                agent_white.ponder(thinking_time);
+                agent_white.try_opponent_move(&player_move)?;
+            }
             _ => (),
         };
+
+        let Some((threshold, min_ply)) = adjudication else { continue };
+        let favored = match (agent_black.evaluate(), agent_white.evaluate()) {
+            _ if plies.len() <= min_ply || game.is_terminal() => None,
+            (Some(b), Some(w)) if b >= threshold && w >= threshold => Some(Players::Black),
+            (Some(b), Some(w)) if b <= 1.0 - threshold && w <= 1.0 - threshold => Some(Players::White),
+            _ => None,
+        };
+        agreement = match (favored, agreement) {
+            (Some(player), Some((agreed, count))) if player == agreed => Some((player, count + 1)),
+            (Some(player), _) => Some((player, 1)),
+            (None, _) => None,
+        };
+        if let Some((winner, 2)) = agreement {
+            let margin = match winner {
+                Players::Black => game.score(),
+                Players::White => -game.score(),
+            }.max(1);
+            let result = match winner {
+                Players::Black => GameResult::BlackWin(margin),
+                Players::White => GameResult::WhiteWin(margin),
+            };
+            break Ok((result, plies, true));
+        }
     }
 }
 
+/// Plays the turn `agent` chooses in `game` and records it in `plies`.
+/// Returns the turn along with how long it took to choose, in hundredths of a second.
+fn play_ply<A: MemoryAgent + ?Sized>(game: &mut Gamestate, plies: &mut Vec<PlyRecord>, agent: &mut A)
+-> Result<(Turn, u128), AgentError> {
+    let start = Instant::now();
+    let turn = agent.try_make_move()?;
+    let time = start.elapsed();
+    let flips = game.try_move(turn).map_err(|e| {
+        AgentError::InvalidState(format!("illegal move {turn:?} on game \n{game}\n: {e}"))
+    })?;
+    plies.push(PlyRecord { turn, flips, time, score: game.score(), analysis: agent.analysis() });
+    Ok((turn, time.as_millis() / 10))
+}
+
+/// Plays a game between two agents from the standard starting position.
+/// See [play_game_from].
+pub fn play_game
+<A1: MemoryAgent + ?Sized, A2: MemoryAgent + ?Sized>
+(agent1: &mut A1, agent2: &mut A2) -> (GameResult, Vec<Turn>) {
+    play_game_from(agent1, agent2, Gamestate::new())
+}
+
+#[deprecated(note = "use play_game_from, which returns a GameResult")]
+pub fn play_memory_agents_from
+<A1: MemoryAgent, A2: MemoryAgent>
+(agent_black: &mut A1, agent_white: &mut A2, game: Gamestate) -> (i8, Vec<Turn>) {
+    let (result, turns) = play_game_from(agent_black, agent_white, game);
+    (result.score(), turns)
+}
+
+#[deprecated(note = "use play_game, which returns a GameResult")]
 pub fn play_memory_agents
 <A1: MemoryAgent, A2: MemoryAgent>
 (agent1: &mut A1, agent2: &mut A2) -> (i8, Vec<Turn>) {
-    play_memory_agents_from(agent1, agent2, Gamestate::new())
+    let (result, turns) = play_game(agent1, agent2);
+    (result.score(), turns)
 }
 
 pub fn benchmark_memory_agents
 <A1: MemoryAgent, A2: MemoryAgent>
 (agent1: &mut A1, agent2: &mut A2, count: u32) -> f64 {
+    benchmark_memory_agents_with_rules(agent1, agent2, count, Rules::default())
+}
+
+/// Like [benchmark_memory_agents], but plays every game by `rules`
+/// (see [play_game_from_with_rules]), so `agent1` gets any handicap.
+pub fn benchmark_memory_agents_with_rules
+<A1: MemoryAgent, A2: MemoryAgent>
+(agent1: &mut A1, agent2: &mut A2, count: u32, rules: Rules) -> f64 {
     let mut a1_score: f64 = 0_f64;
     for _ in 0..count {
-        a1_score += match play_memory_agents(agent1, agent2).0.cmp(&0) {
-            Ordering::Greater => 1_f64,
-            Ordering::Less => 0_f64,
-            _ => 0.5_f64,
+        a1_score += match play_game_from_with_rules(agent1, agent2, Gamestate::new(), rules).0 {
+            GameResult::BlackWin(_) => 1_f64,
+            GameResult::WhiteWin(_) => 0_f64,
+            GameResult::Draw => 0.5_f64,
         }
     }
     a1_score / f64::from(count)
 }
+
+/// Plays `games` games from the standard starting position on up to
+/// `threads` threads and returns their results in order.
+///
+/// `make_black(i)` and `make_white(i)` construct the agents for game `i`.
+/// Since every game gets its own agents, which thread plays it has no effect
+/// on the result: seeding the agents' randomness from `i` gives the same results
+/// as playing the games one after another.
+///
+/// # Panics
+/// If an agent panics, once every thread has stopped, with the agent's message.
+pub fn play_parallel<A1, A2, F1, F2>(make_black: F1, make_white: F2, games: u32, threads: usize)
+    -> Vec<(GameResult, Vec<Turn>)>
+where
+    A1: MemoryAgent,
+    A2: MemoryAgent,
+    F1: Fn(u32) -> A1 + Sync,
+    F2: Fn(u32) -> A2 + Sync,
+{
+    let next_game = AtomicU32::new(0);
+    let mut results: Vec<Option<(GameResult, Vec<Turn>)>> = vec![None; games as usize];
+
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads.clamp(1, games.max(1) as usize))
+            .map(|_| scope.spawn(|| {
+                let mut played = Vec::new();
+                loop {
+                    let game = next_game.fetch_add(1, Ordering::Relaxed);
+                    if game >= games {
+                        break played;
+                    }
+                    let mut black = make_black(game);
+                    let mut white = make_white(game);
+                    played.push((game, play_game(&mut black, &mut white)));
+                }
+            }))
+            .collect();
+
+        let mut panic = None;
+        for worker in workers {
+            match worker.join() {
+                Ok(played) => for (game, result) in played {
+                    results[game as usize] = Some(result);
+                },
+                Err(payload) => {
+                    // stop handing out games so the other threads finish quickly
+                    next_game.store(games, Ordering::Relaxed);
+                    panic.get_or_insert(payload);
+                }
+            }
+        }
+        if let Some(payload) = panic {
+            panic::resume_unwind(payload);
+        }
+    });
+
+    results.into_iter().map(|result| result.expect("every game is played")).collect()
+}
+
+/// The results of [benchmark_memory_agents_balanced],
+/// from the point of view of the first agent.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BenchmarkResult {
+    /// The games the first agent played as Black.
+    pub as_black: Record,
+    /// The games the first agent played as White.
+    pub as_white: Record,
+}
+
+impl BenchmarkResult {
+    /// Returns the first agent's record over every game.
+    pub fn total(&self) -> Record {
+        Record {
+            wins: self.as_black.wins + self.as_white.wins,
+            draws: self.as_black.draws + self.as_white.draws,
+            losses: self.as_black.losses + self.as_white.losses,
+        }
+    }
+
+    /// Returns the fraction of points the first agent won, as
+    /// [benchmark_memory_agents] does, or `0.5` if no games were played.
+    pub fn score(&self) -> f64 {
+        Self::fraction(self.total())
+    }
+
+    /// Returns the fraction of points the first agent won as Black.
+    pub fn black_score(&self) -> f64 {
+        Self::fraction(self.as_black)
+    }
+
+    /// Returns the fraction of points the first agent won as White.
+    pub fn white_score(&self) -> f64 {
+        Self::fraction(self.as_white)
+    }
+
+    fn fraction(record: Record) -> f64 {
+        match record.games() {
+            0 => 0.5_f64,
+            games => record.points() / f64::from(games),
+        }
+    }
+
+    /// Returns the 95% Wilson score interval around [score](BenchmarkResult::score),
+    /// counting draws as half a win.
+    pub fn confidence_interval(&self) -> (f64, f64) {
+        const Z: f64 = 1.96;
+        let n = f64::from(self.total().games());
+        if n == 0_f64 {
+            return (0_f64, 1_f64);
+        }
+        let p = self.score();
+        let center = (p + Z * Z / (2_f64 * n)) / (1_f64 + Z * Z / n);
+        let spread = Z / (1_f64 + Z * Z / n)
+            * (p * (1_f64 - p) / n + Z * Z / (4_f64 * n * n)).sqrt();
+        (center - spread, center + spread)
+    }
+}
+
+/// Like [benchmark_memory_agents], but `agent1` plays Black in every other
+/// game (starting with the first), so that the result isn't skewed by whatever
+/// advantage the first player has.
+pub fn benchmark_memory_agents_balanced
+<A1: MemoryAgent, A2: MemoryAgent>
+(agent1: &mut A1, agent2: &mut A2, count: u32) -> BenchmarkResult {
+    let mut result = BenchmarkResult::default();
+    for game in 0..count {
+        let (record, winner, agent1_color) = match game % 2 {
+            0 => (&mut result.as_black, play_game(agent1, agent2).0.winner(), Players::Black),
+            _ => (&mut result.as_white, play_game(agent2, agent1).0.winner(), Players::White),
+        };
+        match winner {
+            None => record.draws += 1,
+            Some(winner) if winner == agent1_color => record.wins += 1,
+            Some(_) => record.losses += 1,
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::implementations::{
+        BfsExpansion, GreedyAgent, McstMemoryAgent, RandomAgent, UctDecision, UctSelection,
+    };
+    use crate::mcst::McstAgent;
+    use crate::mechanics::Board;
+
+    #[test]
+    fn test_benchmark_balanced() {
+        let mut random1 = MemorifiedAgent::new(RandomAgent::seeded(1));
+        let mut random2 = MemorifiedAgent::new(RandomAgent::seeded(2));
+        let result = benchmark_memory_agents_balanced(&mut random1, &mut random2, 400);
+        assert_eq!(result.as_black.games(), 200);
+        assert_eq!(result.as_white.games(), 200);
+        let (low, high) = result.confidence_interval();
+        assert!((result.score() - 0.5).abs() < 0.1, "{result:?}");
+        assert!(low < 0.5 && 0.5 < high, "{result:?}");
+        assert!(low < result.score() && result.score() < high);
+        assert!(high - low < 0.1);
+
+        let mut greedy = MemorifiedAgent::new(GreedyAgent::new());
+        let result = benchmark_memory_agents_balanced(&mut greedy, &mut random1, 100);
+        assert!(result.confidence_interval().0 > 0.5, "{result:?}");
+    }
+
+    /// Plays greedily and evaluates the game as `script` says
+    /// for the number of moves made so far.
+    struct ScriptedAgent {
+        agent: MemorifiedAgent<GreedyAgent>,
+        plies: usize,
+        script: fn(usize) -> Option<f64>,
+    }
+
+    impl ScriptedAgent {
+        fn new(script: fn(usize) -> Option<f64>) -> Self {
+            ScriptedAgent { agent: MemorifiedAgent::new(GreedyAgent::new()), plies: 0, script }
+        }
+    }
+
+    impl MemoryAgent for ScriptedAgent {
+        fn initialize_game(&mut self, state: Gamestate) {
+            self.plies = 60 - usize::from(state.board().counts().2);
+            self.agent.initialize_game(state);
+        }
+
+        fn opponent_move(&mut self, op: &Turn) {
+            self.plies += 1;
+            self.agent.opponent_move(op);
+        }
+
+        fn make_move(&mut self) -> Turn {
+            self.plies += 1;
+            self.agent.make_move()
+        }
+
+        fn evaluate(&self) -> Option<f64> {
+            (self.script)(self.plies)
+        }
+    }
+
+    #[test]
+    fn test_adjudication() {
+        let (full, turns) = play_game(
+            &mut MemorifiedAgent::new(GreedyAgent::new()),
+            &mut MemorifiedAgent::new(GreedyAgent::new()),
+        );
+
+        // both agents are sure from the start, but the first 20 plies don't count
+        let sure = |_| Some(0.99);
+        let (result, history, adjudicated) = play_game_adjudicated(
+            &mut ScriptedAgent::new(sure), &mut ScriptedAgent::new(sure), 0.95, 20,
+        );
+        assert!(adjudicated);
+        assert_eq!(history, turns[..22]);
+        assert_eq!(result.winner(), Some(Players::Black));
+        // and is recorded as such, so that data collection can weigh it less
+        let record = crate::data::game_to_str(result, &history, adjudicated);
+        assert!(crate::data::str_is_adjudicated(&record));
+        assert_eq!(crate::data::str_to_states(&record).unwrap().0, 0.0);
+
+        // White is losing from ply 30, but Black doesn't realize until ply 40
+        let (result, history, adjudicated) = play_game_adjudicated(
+            &mut ScriptedAgent::new(|ply| Some(if ply >= 40 { 0.0 } else { 0.5 })),
+            &mut ScriptedAgent::new(|ply| (ply >= 30).then_some(0.01)),
+            0.95, 20,
+        );
+        assert!(adjudicated);
+        assert_eq!(history.len(), 41);
+        assert_eq!(result.winner(), Some(Players::White));
+
+        // agents that never agree for two plies in a row play to the end
+        let (result, history, adjudicated) = play_game_adjudicated(
+            &mut ScriptedAgent::new(|_| Some(1.0)),
+            &mut ScriptedAgent::new(|ply| if ply % 2 == 0 { Some(1.0) } else { None }),
+            0.95, 0,
+        );
+        assert!(!adjudicated);
+        assert_eq!((result, history), (full, turns));
+    }
+
+    #[test]
+    fn test_play_parallel() {
+        let make_black = |game: u32| MemorifiedAgent::new(RandomAgent::seeded(u64::from(game)));
+        let make_white = |game: u32| MemorifiedAgent::new(RandomAgent::seeded(u64::from(game) + 1000));
+        let serial: Vec<_> = (0..20)
+            .map(|game| play_game(&mut make_black(game), &mut make_white(game)))
+            .collect();
+        assert_eq!(play_parallel(make_black, make_white, 20, 4), serial);
+        assert_eq!(play_parallel(make_black, make_white, 20, 1), serial);
+        assert!(play_parallel(make_black, make_white, 0, 4).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "game 7 went wrong")]
+    fn test_play_parallel_panic() {
+        let make_black = |game: u32| {
+            assert_ne!(game, 7, "game 7 went wrong");
+            MemorifiedAgent::new(RandomAgent::seeded(u64::from(game)))
+        };
+        play_parallel(make_black, |_| MemorifiedAgent::new(GreedyAgent::new()), 20, 4);
+    }
+
+    #[test]
+    fn test_play_game_recorded() {
+        let mut game = Gamestate::new();
+        game.make_moves_fast(&[Some((4, 5)), Some((5, 5))]);
+        let record = play_game_recorded(
+            &mut MemorifiedAgent::new(RandomAgent::seeded(1)),
+            &mut MemorifiedAgent::new(RandomAgent::seeded(2)),
+            game.clone(),
+            ("random \"one\"", "random two"),
+        );
+        let (result, turns) = play_game_from(
+            &mut MemorifiedAgent::new(RandomAgent::seeded(1)),
+            &mut MemorifiedAgent::new(RandomAgent::seeded(2)),
+            game.clone(),
+        );
+        assert_eq!((record.result, record.turns()), (result, turns.clone()));
+        assert_eq!(record.start, game.board().to_compact());
+
+        // flipping the recorded tiles gives the same boards as playing the turns
+        let mut board = Board::from_compact(record.start).unwrap();
+        for ply in &record.plies {
+            for &(x, y) in &ply.flips {
+                let flipped = match board.at(x, y) {
+                    Some(States::Taken(Players::Black)) => States::Taken(Players::White),
+                    Some(States::Taken(Players::White)) => States::Taken(Players::Black),
+                    other => panic!("flipped {other:?} at {:?}", (x, y)),
+                };
+                board.change(x, y, flipped);
+            }
+            if let Some((x, y)) = ply.turn {
+                let (fx, fy) = ply.flips[0];
+                board.change(x, y, board.at(fx, fy).unwrap());
+            }
+            assert_eq!(board.score(), ply.score);
+        }
+        game.make_moves_fast(&turns);
+        assert_eq!(&board, game.board());
+
+        let json: serde_json::Value = serde_json::from_str(&record.to_json()).unwrap();
+        assert_eq!(json["black"], "random \"one\"");
+        assert_eq!(json["start"], record.start.to_string());
+        assert_eq!(json["plies"].as_array().unwrap().len(), record.plies.len());
+        assert_eq!(json["result"], i64::from(result.score()));
+    }
+
+    #[test]
+    fn test_play_game_with_rules() {
+        let rules = Rules { komi: 6, handicap_corners: 2 };
+        let (result, turns) = play_game_from_with_rules(
+            &mut MemorifiedAgent::new(RandomAgent::seeded(1)),
+            &mut MemorifiedAgent::new(RandomAgent::seeded(2)),
+            Gamestate::new(),
+            rules,
+        );
+        let mut g = rules.apply_handicap(&Gamestate::new());
+        assert!(g.make_moves_fast(&turns));
+        assert!(g.is_terminal());
+        assert_eq!(result, rules.result(g.score()));
+
+        // with enough komi, White can't lose
+        let mut greedy = MemorifiedAgent::new(GreedyAgent::new());
+        let mut random = MemorifiedAgent::new(RandomAgent::seeded(3));
+        let rules = Rules { komi: 64, handicap_corners: 0 };
+        assert_eq!(benchmark_memory_agents_with_rules(&mut greedy, &mut random, 10, rules), 0_f64);
+    }
+
+    #[test]
+    fn test_stateless_wrapper() {
+        // a small search for every move of every rollout of a bigger one
+        let inner = || StatelessWrapper::new(McstMemoryAgent::new(
+            McstAgent::new(
+                UctSelection::new(2_f64.sqrt()),
+                BfsExpansion {},
+                UctDecision {},
+                RandomAgent::new(),
+                RandomAgent::new(),
+                Gamestate::new(),
+            ),
+            0,
+            0.0,
+        ));
+        let g = Gamestate::from_transcript(
+            "f5d6c6f4d3d2c5f6d7c7c8e3f2b4g3g1c1g4e1e7g7d8b5b8e8f3e6a5c4h3\
+             b7a8c3f1b6d1g2a7c2b1g5g6a4h8h1f8h4b3a3h2h7"
+        ).unwrap();
+        let mut outer = McstAgent::new(
+            UctSelection::new(2_f64.sqrt()),
+            BfsExpansion {},
+            UctDecision {},
+            inner(),
+            inner(),
+            g.clone(),
+        );
+        for _ in 0..3 {
+            assert!(outer.cycle().unwrap());
+        }
+        assert_eq!(*outer.tree().root().total(), 3);
+        let turn = outer.decide().unwrap();
+        assert!(g.valid_move(turn));
+
+        let mut done = g.clone();
+        done.make_moves_fast(&play_game_from(
+            &mut MemorifiedAgent::new(GreedyAgent::new()),
+            &mut MemorifiedAgent::new(GreedyAgent::new()),
+            g,
+        ).1);
+        assert!(matches!(inner().try_make_move(&done), Err(AgentError::NoMoves)));
+    }
+
+    #[test]
+    fn test_closure_agent() {
+        let first = |state: &Gamestate| state.get_moves()[0];
+        let mut black = MemorifiedAgent::new(first);
+        let mut white = MemorifiedAgent::new(RandomAgent::seeded(3));
+        let (_, turns) = play_game(&mut black, &mut white);
+        assert_eq!(turns[0], Gamestate::new().get_moves()[0]);
+
+        let (_, turns) = play_game(&mut black, &mut white);
+        let mut g = Gamestate::new();
+        g.make_moves_fast(&turns);
+        assert!(matches!(first.try_make_move(&g), Err(AgentError::NoMoves)));
+        assert!(matches!(RandomAgent::new().try_make_move(&g), Err(AgentError::NoMoves)));
+    }
+
+    #[test]
+    fn test_agent_errors() {
+        let mut agent = MemorifiedAgent::new(GreedyAgent::new());
+        agent.initialize_game(Gamestate::new());
+        assert!(matches!(agent.try_opponent_move(&Some((0, 0))), Err(AgentError::InvalidState(_))));
+        assert!(agent.try_opponent_move(&Some((3, 2))).is_ok());
+
+        // an agent that plays the same tile every time soon plays an illegal move
+        let mut stubborn = MemorifiedAgent::new(|_: &Gamestate| Some((3, 2)));
+        let result = try_play_game_from(&mut stubborn, &mut MemorifiedAgent::new(GreedyAgent::new()), Gamestate::new());
+        assert!(matches!(result, Err(AgentError::InvalidState(_))), "{result:?}");
+
+        let mut done = Gamestate::new();
+        done.make_moves_fast(&play_game(&mut agent, &mut MemorifiedAgent::new(GreedyAgent::new())).1);
+        agent.initialize_game(done);
+        assert!(matches!(agent.try_make_move(), Err(AgentError::NoMoves)));
+    }
+}
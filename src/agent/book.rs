@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::agent::Agent;
+use crate::gameplay::{Gamestate, Turn};
+
+/// Errors that can occur when reading an opening book with [OpeningBook::load].
+#[derive(Debug)]
+pub enum OpeningBookError {
+    Io(io::Error),
+    /// The line with the given (1-based) number is not of the form `compact,x,y`.
+    BadLine { line: usize, text: String },
+}
+
+impl fmt::Display for OpeningBookError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OpeningBookError::Io(e) => write!(f, "could not read opening book: {e}"),
+            OpeningBookError::BadLine { line, text } => {
+                write!(f, "line {line} of opening book is not `compact,x,y`: {text:?}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for OpeningBookError {}
+
+impl From<io::Error> for OpeningBookError {
+    fn from(e: io::Error) -> Self {
+        OpeningBookError::Io(e)
+    }
+}
+
+/// A table of which move to play in known positions.
+///
+/// Positions are stored by their [canonical](crate::mechanics::Board::canonical)
+/// board, so a move stored for one position is also found for every rotation
+/// and reflection of it. Only the board is stored, not whose turn it is.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OpeningBook {
+    /// The move for every canonical board, on the canonical board.
+    moves: HashMap<u128, (u8, u8)>,
+}
+
+impl OpeningBook {
+    /// Creates an empty book.
+    pub fn new() -> Self {
+        OpeningBook { moves: HashMap::new() }
+    }
+
+    /// Records `(x, y)` as the move to play in `state`, replacing any move
+    /// already recorded for it (or for a symmetric position).
+    pub fn insert(&mut self, state: &Gamestate, (x, y): (u8, u8)) {
+        let (compact, sym, _) = state.board().canonical();
+        self.moves.insert(compact, sym.map(x, y));
+    }
+
+    /// Returns the move recorded for `state`, if there is one.
+    /// The move is not checked for legality.
+    pub fn get(&self, state: &Gamestate) -> Option<(u8, u8)> {
+        let (compact, _, inverse) = state.board().canonical();
+        self.moves.get(&compact).map(|&(x, y)| inverse.map(x, y))
+    }
+
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+
+    /// Reads a book written by [save](OpeningBook::save): one position per
+    /// line as `compact,x,y`, where `compact` is the canonical board and `(x, y)`
+    /// the move on it. Blank lines are skipped.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, OpeningBookError> {
+        let mut book = OpeningBook::new();
+        for (index, line) in fs::read_to_string(path)?.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let bad_line = || OpeningBookError::BadLine { line: index + 1, text: line.to_string() };
+            let fields: Vec<&str> = line.trim().split(',').collect();
+            let [compact, x, y] = fields[..] else {
+                return Err(bad_line());
+            };
+            let compact: u128 = compact.parse().map_err(|_| bad_line())?;
+            let x: u8 = x.parse().map_err(|_| bad_line())?;
+            let y: u8 = y.parse().map_err(|_| bad_line())?;
+            if x >= 8 || y >= 8 || compact >= 3_u128.pow(64) {
+                return Err(bad_line());
+            }
+            book.moves.insert(compact, (x, y));
+        }
+        Ok(book)
+    }
+
+    /// Writes the book in the format read by [load](OpeningBook::load),
+    /// sorted by position so the same book always gives the same file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut entries: Vec<(&u128, &(u8, u8))> = self.moves.iter().collect();
+        entries.sort();
+        let contents: String = entries.iter()
+            .map(|(compact, (x, y))| format!("{compact},{x},{y}\n"))
+            .collect();
+        fs::write(path, contents)
+    }
+}
+
+/// An agent which plays from an [OpeningBook] whenever it knows the position,
+/// and otherwise asks the agent it wraps.
+pub struct OpeningBookAgent<A: Agent> {
+    book: OpeningBook,
+    agent: A,
+}
+
+impl<A: Agent> OpeningBookAgent<A> {
+    /// Creates a new `OpeningBookAgent` which falls back on `agent`.
+    pub fn new(book: OpeningBook, agent: A) -> Self {
+        OpeningBookAgent { book, agent }
+    }
+
+    pub fn book(&self) -> &OpeningBook {
+        &self.book
+    }
+}
+
+impl<A: Agent> Agent for OpeningBookAgent<A> {
+    /// Plays the book move if there is a legal one, or whatever the wrapped
+    /// agent picks otherwise.
+    fn make_move(&self, state: &Gamestate) -> Turn {
+        match self.book.get(state) {
+            Some(tile) if state.check_move(Some(tile)).is_ok() => Some(tile),
+            _ => self.agent.make_move(state),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::implementations::GreedyAgent;
+    use crate::gameplay::Symmetry;
+
+    #[test]
+    fn test_opening_book() {
+        let start = Gamestate::new();
+        let mut book = OpeningBook::new();
+        book.insert(&start, (4, 5));
+        let agent = OpeningBookAgent::new(book.clone(), GreedyAgent::new());
+        assert_eq!(agent.make_move(&start), Some((4, 5)));
+
+        // the initial position is its own reflection across the main diagonal
+        assert_eq!(agent.make_move(&start.transformed(Symmetry::FlipMainDiagonal)), Some((4, 5)));
+
+        // a move stored for one position is found for its rotations too
+        let mut g = Gamestate::new();
+        g.make_move_fast(Some((4, 5)));
+        book.insert(&g, (5, 5));
+        let agent = OpeningBookAgent::new(book, GreedyAgent::new());
+        for sym in Symmetry::ALL {
+            let (x, y) = sym.map(5, 5);
+            assert_eq!(agent.make_move(&g.transformed(sym)), Some((x, y)), "{sym:?}");
+        }
+
+        // unknown positions go to the inner agent
+        g.make_move_fast(Some((5, 5)));
+        assert_eq!(agent.make_move(&g), GreedyAgent::new().make_move(&g));
+    }
+
+    #[test]
+    fn test_opening_book_illegal_move() {
+        // the book move is illegal for the side to move, so it's ignored
+        let mut book = OpeningBook::new();
+        book.insert(&Gamestate::new(), (0, 0));
+        let agent = OpeningBookAgent::new(book, GreedyAgent::new());
+        assert_eq!(agent.make_move(&Gamestate::new()), GreedyAgent::new().make_move(&Gamestate::new()));
+    }
+
+    #[test]
+    fn test_opening_book_files() {
+        let mut book = OpeningBook::new();
+        let mut g = Gamestate::new();
+        for tile in [(4, 5), (5, 5), (5, 4)] {
+            book.insert(&g, tile);
+            g.make_move_fast(Some(tile));
+        }
+        let path = std::env::temp_dir().join(format!("othello-book-{}.txt", std::process::id()));
+        book.save(&path).unwrap();
+        let loaded = OpeningBook::load(&path);
+        fs::write(&path, "12,3,4\n\n12,3\n").unwrap();
+        let bad = OpeningBook::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.unwrap(), book);
+        assert!(matches!(bad, Err(OpeningBookError::BadLine { line: 3, .. })));
+        assert!(matches!(OpeningBook::load(&path), Err(OpeningBookError::Io(_))));
+    }
+}
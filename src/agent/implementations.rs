@@ -1,15 +1,27 @@
-use std::time::Instant;
-use std::cell::RefCell;
+use std::time::{Duration, Instant};
+use std::cell::{Cell, Ref, RefCell};
 use std::cmp::Ordering;
 use std::collections::VecDeque;
-use std::io;
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Stdin, Stdout, Write};
+use std::path::Path;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
 
 use rand::prelude::IndexedRandom;
-use rand::rngs::ThreadRng;
+use rand::{Rng, SeedableRng};
+use rand::rngs::{StdRng, ThreadRng};
 
-use crate::agent::{Agent, MemoryAgent};
-use crate::gameplay::{Gamestate, Turn};
-use crate::mcst::{McstNode, McstTree, McstAgent, SelectionPolicy, ExpansionPolicy, DecisionPolicy};
+use crate::agent::{Agent, AgentError, MemoryAgent};
+use crate::gameplay::{
+    loc_to_alg, transcript_to_turns, transform_turn, Gamestate, Players, RenderStyle, States, TranscriptError, Turn,
+};
+use crate::mechanics::Board;
+use crate::mcst::{Analysis, CycleError, McstNode, McstTree, McstAgent, Proven, RolloutFactory, RolloutPolicy, SearchSummary, SelectionPolicy, ExpansionPolicy, DecisionPolicy, SymmetricMcstAgent, TimeAllotment};
+use crate::transposition::{Bound, TableEntry, TranspositionTable};
+
+pub use crate::evaluation::{Evaluator, WeightedSquareEvaluator};
 
 /// A simple agent that selects moves based on a predefined ranking of board cells.
 ///
@@ -43,420 +55,4950 @@ impl Agent for RankedCellAgent {
     }
 }
 
-/// An agent that selects a random valid move each turn.
-pub struct RandomAgent {
-    r: RefCell<ThreadRng>,
+/// The stage a game is in, judged by how many tiles are empty.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Phase {
+    /// More than 40 empty tiles.
+    Opening,
+    Midgame,
+    /// 20 or fewer empty tiles.
+    Endgame,
 }
 
-impl RandomAgent {
-    /// Constructs a new `RandomAgent` using thread-local RNG.
-    pub fn new() -> Self {
-        RandomAgent {r: RefCell::new(rand::rng())}
+impl Phase {
+    /// Every phase, in the order they happen.
+    pub const ALL: [Phase; 3] = [Phase::Opening, Phase::Midgame, Phase::Endgame];
+
+    /// Returns the phase `state` is in.
+    pub fn of(state: &Gamestate) -> Phase {
+        match state.board().counts().2 {
+            41.. => Phase::Opening,
+            21..=40 => Phase::Midgame,
+            _ => Phase::Endgame,
+        }
     }
-}
 
-impl Agent for RandomAgent {
-    /// Chooses a random move from the list of valid moves.
-    /// Will panic if there are no moves.
-    fn make_move(&self, state: &Gamestate) -> Turn {
-        let valid_moves = state.get_moves();
-        valid_moves.choose(&mut self.r.borrow_mut())
-                   .copied()
-                   .expect("make_move passed state with no moves.")
+    /// The name of the phase in [RankedTables] files.
+    fn name(&self) -> &'static str {
+        match self {
+            Phase::Opening => "opening",
+            Phase::Midgame => "midgame",
+            Phase::Endgame => "endgame",
+        }
     }
 }
 
-/// An agent that plays the move resulting in the most flips (greedy strategy).
-pub struct GreedyAgent {}
+/// Errors that can occur when reading tables with [RankedTables::load].
+#[derive(Debug)]
+pub enum RankedTablesError {
+    Io(io::Error),
+    /// The line with the given (1-based) number is neither a phase name nor
+    /// a row of 8 numbers following one.
+    BadLine { line: usize, text: String },
+    /// The table for a phase is missing or has fewer than 8 rows.
+    MissingTable(Phase),
+}
 
-impl Agent for GreedyAgent {
-    /// Selects the move that flips the most opponent pieces.
-    /// Panics if there are no valid moves.
-    fn make_move(&self, state: &Gamestate) -> Turn {
-        state.get_moves()
-             .iter()
-             .max_by(|t1, t2| -> Ordering {
-                 // TODO: figure out wth derefing does to borrowing
-                 let v1 = state.clone().make_move(**t1).expect("").len();
-                 let v2 = state.clone().make_move(**t2).expect("").len();
-                 v1.cmp(&v2)
-             })
-            .copied()
-            .expect("make_moves passed state with no moves.")
+impl fmt::Display for RankedTablesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RankedTablesError::Io(e) => write!(f, "could not read tables: {e}"),
+            RankedTablesError::BadLine { line, text } => write!(f, "line {line} of tables is not valid: {text:?}"),
+            RankedTablesError::MissingTable(phase) => write!(f, "the {} table is missing or incomplete", phase.name()),
+        }
     }
 }
 
-/// A human-controlled agent.
-pub struct HumanAgent {}
+impl std::error::Error for RankedTablesError {}
 
-impl HumanAgent {
-    /// Constructs a new human agent with a fresh game state.
-    pub fn new() -> Self {
-        HumanAgent {}
+impl From<io::Error> for RankedTablesError {
+    fn from(e: io::Error) -> Self {
+        RankedTablesError::Io(e)
     }
 }
 
-impl Agent for HumanAgent {
-    /// Interacts with the user to input a valid move.
-    /// Panics if there are no valid moves.
-    fn make_move(&self, state: &Gamestate) -> Turn {
-        let stdin = io::stdin();
-        let mut input = String::new();
-        let valid_moves = state.get_moves();
-        println!("{}", state);
+/// A table of how much each tile is worth (indexed as `table[y][x]`)
+/// for each [Phase] of the game, for [PhasedRankedAgent].
+#[derive(Clone, Debug, PartialEq)]
+pub struct RankedTables {
+    tables: [[[f64; 8]; 8]; 3],
+}
 
-        if valid_moves.is_empty() {
-            panic!("make_move passed state with no moves.");
-        }
+impl RankedTables {
+    pub fn new(opening: [[f64; 8]; 8], midgame: [[f64; 8]; 8], endgame: [[f64; 8]; 8]) -> Self {
+        RankedTables { tables: [opening, midgame, endgame] }
+    }
 
-        if valid_moves.contains(&None) {
-            println!("No available moves - return to pass:");
-            stdin.read_line(&mut input).expect("stdio could not be read from");
-            None
-        } else {
-            loop {
-                println!("Enter a coordinate:");
-                input.clear();
-                stdin.read_line(&mut input).expect("stdio could not be read from");
-                input.pop();
+    /// Uses the same table for every phase.
+    pub fn uniform(table: [[f64; 8]; 8]) -> Self {
+        RankedTables::new(table, table, table)
+    }
 
-                if let Some(location) = crate::gameplay::str_to_loc(&input) {
-                    if valid_moves.contains(&Some(location)) {
-                        break Some(location)
-                    } else {
-                        println!("Not a valid move!");
-                    }
-                } else {
-                    println!("Could not parse coordinate!");
-                }
-            }
-        }
+    pub fn table(&self, phase: Phase) -> &[[f64; 8]; 8] {
+        &self.tables[phase as usize]
     }
-}
 
-/// A human agent for debugging and interactive play with command support.
-pub struct HumanDebugger {}
+    pub fn table_mut(&mut self, phase: Phase) -> &mut [[f64; 8]; 8] {
+        &mut self.tables[phase as usize]
+    }
 
-impl Agent for HumanDebugger {
-    /// Allows user to enter moves and execute debugging commands like `/moves` and `/history`.
-    fn make_move(&self, state: &Gamestate) -> Turn {
-        let stdin = io::stdin();
-        let mut input = String::new();
-        let valid_moves = state.get_moves();
-        println!("{}", state);
+    /// Reads tables written by [save](RankedTables::save): for each phase, a line
+    /// with its name (`opening`, `midgame` or `endgame`) followed by 8 lines of
+    /// 8 numbers separated by spaces, one line per row. Blank lines and lines
+    /// starting with `#` are skipped.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, RankedTablesError> {
+        let mut tables = [[[0.0; 8]; 8]; 3];
+        // how many rows have been read for each phase
+        let mut rows = [0; 3];
+        let mut phase: Option<Phase> = None;
+        for (index, line) in fs::read_to_string(path)?.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let bad_line = || RankedTablesError::BadLine { line: index + 1, text: line.to_string() };
+            if let Some(named) = Phase::ALL.into_iter().find(|phase| phase.name() == line) {
+                phase = Some(named);
+                rows[named as usize] = 0;
+                continue;
+            }
 
-        if valid_moves.contains(&None) {
-            loop {
-                println!("Only valid move is to pass. Return to confirm:");
-                input.clear();
-                stdin.read_line(&mut input).expect("stdio could not be read from");
-                input.pop();
-
-                if input == "/moves" {
-                    println!("There are no valid moves besides passing your turn");
-                } else if input == "/history" {
-                    println!("This is a reminder to fix the history feature");
-                    //                println!("{}", state.view_history().iter().map(
-                    //                        |(x, y)| -> String { format!("({}, {})", x, y) }
-                    //                ).collect::<Vec<String>>().join(", "));
-                } else {
-                    break None;
-                }
+            let current = phase.ok_or_else(bad_line)? as usize;
+            if rows[current] == 8 {
+                return Err(bad_line());
             }
-        } else {
-            loop {
-                println!("Enter a coordinate:");
-                input.clear();
-                stdin.read_line(&mut input).expect("stdio could not be read from");
-                input.pop();
-
-                if input == "/moves" {
-                    println!("{}", valid_moves.iter().map(
-                            |turn| -> String {
-                                if let Some((x, y)) = turn {
-                                    format!("({}, {})", x, y) 
-                                } else {
-                                    format!("(Pass)")
-                                }
-                            }
-                    ).collect::<Vec<String>>().join(", "));
-                } else if input == "/history" {
-                    println!("This is a reminder to fix the history feature");
-                    //                println!("{}", state.view_history().iter().map(
-                    //                        |(x, y)| -> String { format!("({}, {})", x, y) }
-                    //                ).collect::<Vec<String>>().join(", "));
-                } else {
-                    if let Some(turn) = crate::gameplay::str_to_loc(&input) {
-                        if valid_moves.contains(&Some(turn)) {
-                            break Some(turn);
-                        } else {
-                            println!("Not a valid move!");
-                            continue;
-                        }
-                    } else {
-                        println!("Could not parse coordinate!");
-                    }
-                }
+            let values: Vec<f64> = line.split_whitespace()
+                .map(str::parse)
+                .collect::<Result<_, _>>()
+                .map_err(|_| bad_line())?;
+            tables[current][rows[current]] = values.try_into().map_err(|_| bad_line())?;
+            rows[current] += 1;
+        }
+
+        if let Some(phase) = Phase::ALL.into_iter().find(|phase| rows[*phase as usize] < 8) {
+            return Err(RankedTablesError::MissingTable(phase));
+        }
+        Ok(RankedTables { tables })
+    }
+
+    /// Writes the tables in the format read by [load](RankedTables::load).
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut contents = String::new();
+        for phase in Phase::ALL {
+            contents.push_str(phase.name());
+            contents.push('\n');
+            for row in self.table(phase) {
+                let row: Vec<String> = row.iter().map(f64::to_string).collect();
+                contents.push_str(&row.join(" "));
+                contents.push('\n');
             }
         }
+        fs::write(path, contents)
     }
 }
 
-// A UCT (Upper Confidence Bound applied to Trees) selection policy
-pub struct UctSelection {
-    /// Exploration constant.
-    c: f64
+impl Default for RankedTables {
+    /// Uses [WeightedSquareEvaluator::STANDARD_WEIGHTS] for every phase.
+    fn default() -> Self {
+        RankedTables::uniform(WeightedSquareEvaluator::STANDARD_WEIGHTS)
+    }
 }
 
-impl UctSelection {
-    /// Creates a new `UctSelection` with the specified exploration constant `c`.
-    pub fn new(c: f64) -> Self {
-        UctSelection { c: c }
+/// Like [RankedCellAgent], but with a different table for each [Phase]
+/// of the game.
+///
+/// Negative values are taken to be penalties for tiles that give the opponent
+/// a corner, so the X- and C-tiles next to a corner the agent already holds
+/// are never ranked below zero.
+pub struct PhasedRankedAgent {
+    tables: RankedTables,
+}
+
+impl PhasedRankedAgent {
+    /// The corners, each followed by the X- and C-tiles next to it.
+    const CORNERS: [[(u8, u8); 4]; 4] = [
+        [(0, 0), (1, 1), (1, 0), (0, 1)],
+        [(7, 0), (6, 1), (6, 0), (7, 1)],
+        [(0, 7), (1, 6), (1, 7), (0, 6)],
+        [(7, 7), (6, 6), (6, 7), (7, 6)],
+    ];
+
+    pub fn new(tables: RankedTables) -> Self {
+        PhasedRankedAgent { tables }
     }
 
-    /// Recursively selects nodes from the current player's perspective using UCT.
-    /// Adds moves to the path until a node with no or unexplored children is reached.
-    fn select_mine(&self, node: &McstNode, path: &mut Vec<Turn>) {
-        if node.children().len() < node.game().get_moves().len()
-           || node.children().len() == 0 {
-        } else {
-            let new_child = node.children().iter().max_by(
-                |n1, n2| -> Ordering {
-                    let n1w = f64::from(*n1.1.wins());
-                    let n1t = f64::from(*n1.1.total());
-                    let n2w = f64::from(*n2.1.wins());
-                    let n2t = f64::from(*n2.1.total());
-                    (n1w / n1t + self.c * (f64::from(*node.total()).ln() / n1t).sqrt()).total_cmp(
-                        &(n2w / n2t + self.c * (f64::from(*node.total()).ln() / n2t).sqrt())
-                    )
-                }
-            ).expect("There were no children?");
-            path.push(*new_child.0);
-            self.select_your(new_child.1, path);
-        }
+    pub fn tables(&self) -> &RankedTables {
+        &self.tables
     }
 
-    /// Recursively selects nodes from the opponent's perspective using inverted reward.
-    fn select_your(&self, node: &McstNode, path: &mut Vec<Turn>) {
-        if node.children().len() < node.game().get_moves().len()
-           || node.children().len() == 0 {
-        } else {
-            let new_child = node.children().iter().max_by(
-                |n1, n2| -> Ordering {
-                    let n1w = f64::from(*n1.1.wins());
-                    let n1t = f64::from(*n1.1.total());
-                    let n2w = f64::from(*n2.1.wins());
-                    let n2t = f64::from(*n2.1.total());
-                    (-n1w / n1t + self.c * (f64::from(*node.total()).ln() / n1t).sqrt()).total_cmp(
-                        &(-n2w / n2t + self.c * (f64::from(*node.total()).ln() / n2t).sqrt())
-                    )
+    /// Returns the table to rank moves in `state` by, with the penalties
+    /// lifted around corners the side to move holds.
+    fn ranking(&self, state: &Gamestate) -> [[f64; 8]; 8] {
+        let mut ranking = *self.tables.table(Phase::of(state));
+        let mine = Some(States::Taken(state.side_to_move()));
+        for [(cx, cy), neighbors @ ..] in Self::CORNERS {
+            if state.board().at(cx, cy) == mine {
+                for (x, y) in neighbors {
+                    let value = &mut ranking[usize::from(y)][usize::from(x)];
+                    *value = value.max(0.0);
                 }
-            ).expect("There were no children?");
-            path.push(*new_child.0);
-            self.select_mine(new_child.1, path);
+            }
         }
+        ranking
     }
 }
 
-impl SelectionPolicy for UctSelection {
-    /// Returns a path through the tree according to UCT-based selection.
-    fn select(&mut self, tree: &McstTree) -> Option<Vec<Turn>> {
-        let mut turns: Vec<Turn> = Vec::new();
-        self.select_mine(tree.root(), &mut turns);
-        Some(turns)
+impl Agent for PhasedRankedAgent {
+    fn make_move(&self, state: &Gamestate) -> Turn {
+        let ranking = self.ranking(state);
+        state.get_moves().into_iter().max_by(|turn1, turn2| match (turn1, turn2) {
+            (Some((x1, y1)), Some((x2, y2))) => ranking[usize::from(*y1)][usize::from(*x1)]
+                .total_cmp(&ranking[usize::from(*y2)][usize::from(*x2)]),
+            _ => Ordering::Equal,
+        }).expect("make_move passed state with no moves")
     }
 }
 
-/// A breadth-first search selection policy for MCTS.
-/// Expands nodes level-by-level in the tree.
-pub struct BfsSelectionFast {
-    /// Queue of paths to nodes in the tree.
-    queue: VecDeque<Vec<Turn>>,
+/// An agent that selects a random valid move each turn.
+///
+/// Uses the thread-local RNG unless [seeded](RandomAgent::seeded)
+/// or given another RNG, in which case its moves are reproducible.
+pub struct RandomAgent<R: Rng = ThreadRng> {
+    r: RefCell<R>,
 }
 
-impl BfsSelectionFast {
-    /// Creates a new BFS selection policy initialized with the root node.
+impl RandomAgent {
+    /// Constructs a new `RandomAgent` using thread-local RNG.
     pub fn new() -> Self {
-        BfsSelectionFast {
-            queue: VecDeque::from([Vec::new()]),
-        }
+        RandomAgent {r: RefCell::new(rand::rng())}
     }
 }
 
-impl SelectionPolicy for BfsSelectionFast {
-    /// Returns the next unexplored path according to BFS order.
-    fn select(&mut self, tree: &McstTree) -> Option<Vec<Turn>> {
-        loop {
-            if let Some(path) = self.queue.pop_front() {
-                let current_moves = tree.root()
-                                        .search(&path)
-                                        .unwrap()
-                                        .game()
-                                        .get_moves();
+impl RandomAgent<StdRng> {
+    /// Constructs a new `RandomAgent` whose moves are determined by `seed`.
+    pub fn seeded(seed: u64) -> Self {
+        RandomAgent {r: RefCell::new(StdRng::seed_from_u64(seed))}
+    }
+}
 
-                if !current_moves.is_empty() {
-                    // there are moves to make
-                    let move_ct = current_moves.len();
-                    if move_ct - tree.root().search(&path).unwrap().children().len() == 0 {
-                        // we have already been here... put in the children and try again
-                        // TODO: also find out if there is a way that doesn't need &*
-                        for m in &*current_moves {
-                            let mut next_path = path.clone();
-                            next_path.push(*m);
-                            self.queue.push_back(next_path);
-                        }
-                    } else {
-                        self.queue.push_front(path.clone());
-                        break Some(path);
-                    }
-                } // else game is over and cannot be selected
-            } else {
-                break None;
-            }
-        }
+impl<R: Rng> RandomAgent<R> {
+    /// Constructs a new `RandomAgent` drawing from `rng`.
+    pub fn from_rng(rng: R) -> Self {
+        RandomAgent {r: RefCell::new(rng)}
     }
+}
 
-    /// Resets the BFS queue at the start of a new turn.
-    fn turns_passed(&mut self, _tree: &McstTree) {
-        self.queue.clear();
-        self.queue.push_back(Vec::new());
+impl<R: Rng> Agent for RandomAgent<R> {
+    /// Chooses a random move from the list of valid moves.
+    /// Will panic if there are no moves.
+    fn make_move(&self, state: &Gamestate) -> Turn {
+        let valid_moves = state.get_moves();
+        valid_moves.choose(&mut *self.r.borrow_mut())
+                   .copied()
+                   .expect("make_move passed state with no moves.")
     }
 
-    /// Resets the BFS queue.
-    fn set_state(&mut self, _state: Gamestate) {
-        self.queue.clear();
-        self.queue.push_back(Vec::new());
+    fn is_uniform_random(&self) -> bool {
+        true
+    }
+
+    fn random_playout(&self, state: &Gamestate) -> Option<(i8, usize)> {
+        Some(state.random_playout_plies(&mut *self.r.borrow_mut()))
     }
 }
 
-/// A basic expansion policy that expands the first unvisited move.
-pub struct BfsExpansion {}
+/// How [GreedyAgent] chooses between moves which flip the same number of tiles.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TieBreak {
+    /// Play whichever comes first in [Gamestate::get_moves].
+    First,
+    /// Play one of them at random.
+    Random,
+    /// Play a corner if there is one, otherwise whichever comes first.
+    PreferCorners,
+}
 
-impl ExpansionPolicy for BfsExpansion {
-    /// Returns the first legal move from the given node that hasn't been expanded yet.
-    fn expand(&mut self, tree: &McstTree, path: &Vec<Turn>) -> Turn {
-        let node = tree.root().search(&path).unwrap();
-        for next_turn in &*node.game().get_moves() {
-            if !node.children().contains_key(&next_turn) {
-                return *next_turn;
-            }
+/// An agent that plays like `primary` except that, with probability
+/// `epsilon`, it plays like `secondary` (typically a [RandomAgent]) instead.
+pub struct MixtureAgent<A: Agent, B: Agent, R: Rng = ThreadRng> {
+    primary: A,
+    secondary: B,
+    epsilon: f64,
+    r: RefCell<R>,
+    /// How many moves each agent has chosen since the last reset.
+    usage: Cell<(u64, u64)>,
+}
+
+impl<A: Agent, B: Agent> MixtureAgent<A, B> {
+    /// Constructs a new `MixtureAgent` using thread-local RNG.
+    ///
+    /// # Panics
+    /// If `epsilon` is not between 0 and 1.
+    pub fn new(primary: A, secondary: B, epsilon: f64) -> Self {
+        MixtureAgent::from_rng(primary, secondary, epsilon, rand::rng())
+    }
+}
+
+impl<A: Agent, B: Agent, R: Rng> MixtureAgent<A, B, R> {
+    /// Constructs a new `MixtureAgent` which decides who moves using `rng`.
+    ///
+    /// # Panics
+    /// If `epsilon` is not between 0 and 1.
+    pub fn from_rng(primary: A, secondary: B, epsilon: f64, rng: R) -> Self {
+        assert!((0.0..=1.0).contains(&epsilon), "epsilon {epsilon} is not a probability");
+        MixtureAgent { primary, secondary, epsilon, r: RefCell::new(rng), usage: Cell::new((0, 0)) }
+    }
+
+    /// Returns how many moves were chosen by the primary and secondary agent
+    /// since the agent was created or [reset](MixtureAgent::reset_usage).
+    pub fn usage(&self) -> (u64, u64) {
+        self.usage.get()
+    }
+
+    /// Resets the [usage](MixtureAgent::usage) counts,
+    /// for example at the start of every game.
+    pub fn reset_usage(&self) {
+        self.usage.set((0, 0));
+    }
+}
+
+impl<A: Agent, B: Agent, R: Rng> Agent for MixtureAgent<A, B, R> {
+    /// Asks the secondary agent with probability `epsilon`
+    /// and the primary agent otherwise.
+    fn make_move(&self, state: &Gamestate) -> Turn {
+        let (primary, secondary) = self.usage.get();
+        if self.r.borrow_mut().random_bool(self.epsilon) {
+            self.usage.set((primary, secondary + 1));
+            self.secondary.make_move(state)
+        } else {
+            self.usage.set((primary + 1, secondary));
+            self.primary.make_move(state)
         }
-        panic!("No nodes to expand on given path {:?}", path);
     }
 }
 
-/// Decision policy that selects the move with the most simulations.
-pub struct UctDecision {}
+/// An agent that gives another agent a fixed amount of time to choose each
+/// move, and plays a fallback move instead if it takes too long.
+///
+/// The inner agent runs on its own thread. When it overruns, the thread is
+/// left to finish in the background and its move is thrown away; until it
+/// does, later moves have to wait for it, so they are likely to overrun too.
+/// A panicking inner agent is treated like one that never finishes.
+pub struct TimedAgent<A: Agent + Send + 'static> {
+    agent: Arc<Mutex<A>>,
+    /// How long the inner agent has for each move, in hundredths of a second.
+    compute_time: u128,
+    /// Chooses the move when the inner agent overruns,
+    /// or [None] to play the first legal move.
+    fallback: Option<Box<dyn Agent>>,
+    overruns: Cell<u64>,
+    max_latency: Arc<Mutex<Duration>>,
+}
 
-impl DecisionPolicy for UctDecision {
-    /// Picks the move with the highest visit count from the root node.
-    fn decide(&mut self, tree: &McstTree) -> Turn {
-        tree.root().children().keys().max_by(
-            |link1, link2| -> Ordering {
-                let node1 = tree.root().children().get(link1).unwrap();
-                let node2 = tree.root().children().get(link2).unwrap();
-                node1.total().cmp(node2.total())
-            }
-        ).copied().expect("Somehow there no moves?")
+impl<A: Agent + Send + 'static> TimedAgent<A> {
+    /// Constructs a new `TimedAgent` which gives `agent` `compute_time`
+    /// hundredths of a second per move, and otherwise plays the first legal move.
+    pub fn new(agent: A, compute_time: u128) -> Self {
+        TimedAgent {
+            agent: Arc::new(Mutex::new(agent)),
+            compute_time,
+            fallback: None,
+            overruns: Cell::new(0),
+            max_latency: Arc::new(Mutex::new(Duration::ZERO)),
+        }
+    }
+
+    /// Like [TimedAgent::new], but asks `fallback` for a move when `agent` overruns.
+    pub fn with_fallback(agent: A, compute_time: u128, fallback: impl Agent + 'static) -> Self {
+        TimedAgent { fallback: Some(Box::new(fallback)), ..TimedAgent::new(agent, compute_time) }
+    }
+
+    /// Returns how many moves the inner agent failed to choose in time.
+    pub fn overruns(&self) -> u64 {
+        self.overruns.get()
+    }
+
+    /// Returns the longest the inner agent has taken to choose a move,
+    /// including moves it finished after overrunning.
+    pub fn max_latency(&self) -> Duration {
+        *self.max_latency.lock().unwrap()
     }
 }
 
-/// Decision policy that selects the move with the best average win rate.
-pub struct WinAverageDecision {}
+impl<A: Agent + Send + 'static> Agent for TimedAgent<A> {
+    /// Returns the inner agent's move if it arrives in time, or the fallback move.
+    /// Panics if there are no valid moves.
+    fn make_move(&self, state: &Gamestate) -> Turn {
+        let (sender, receiver) = mpsc::channel();
+        let agent = Arc::clone(&self.agent);
+        let max_latency = Arc::clone(&self.max_latency);
+        let worker_state = state.clone();
+        thread::spawn(
+            move || {
+                let agent = agent.lock().unwrap();
+                let start = Instant::now();
+                let turn = agent.make_move(&worker_state);
+                let latency = start.elapsed();
+                let mut max_latency = max_latency.lock().unwrap();
+                *max_latency = latency.max(*max_latency);
+                // the receiver is gone if the move came too late
+                let _ = sender.send(turn);
+            }
+        );
 
-impl DecisionPolicy for WinAverageDecision  {
-    /// Picks the move with the highest win average (wins / total simulations).
-    fn decide(&mut self, tree: &McstTree) -> Turn {
-        tree.root().children().keys().max_by(
-            |link1, link2| -> Ordering {
-                let node1 = tree.root().children().get(link1).unwrap();
-                let node2 = tree.root().children().get(link2).unwrap();
-                match (node1.wins(), node1.total(), node2.wins(), node2.total()) {
-                    (_, 0, _, 0) => Ordering::Equal,
-                    (_, 0, _, _) => Ordering::Less,
-                    (_, _, _, 0) => Ordering::Greater,
-                    (w1, t1, w2, t2) =>
-                        (f64::from(*w1) / f64::from(*t1)).total_cmp(&(f64::from(*w2) / f64::from(*t2)))
+        match receiver.recv_timeout(Duration::from_millis(self.compute_time as u64 * 10)) {
+            Ok(turn) => turn,
+            Err(_) => {
+                self.overruns.set(self.overruns.get() + 1);
+                match &self.fallback {
+                    Some(fallback) => fallback.make_move(state),
+                    None => *state.get_moves().first().expect("make_move passed state with no moves."),
                 }
             }
-        ).copied().expect("Somehow there no moves?")
+        }
     }
 }
 
-pub struct McstMemoryAgent<S: SelectionPolicy, E: ExpansionPolicy, D: DecisionPolicy, A: Agent> {
-    agent: McstAgent<S, E, D, A>,
-    compute_time: u128,
-    last_turn: Turn,
+/// An agent that plays the move resulting in the most flips (greedy strategy).
+pub struct GreedyAgent<R: Rng = ThreadRng> {
+    tie_break: TieBreak,
+    /// Only used to break ties [at random](TieBreak::Random).
+    r: RefCell<R>,
 }
 
-impl<S, E, D, A> McstMemoryAgent<S, E, D, A>
-where
-    S: SelectionPolicy,
-    E: ExpansionPolicy,
-    D: DecisionPolicy,
-    A: Agent,
-{
-    pub fn new(agent: McstAgent<S, E, D, A>, compute_time: u128) -> Self {
-        Self {
-            agent,
-            compute_time,
-            last_turn: None
-        }
+impl GreedyAgent {
+    /// Constructs a new `GreedyAgent` which breaks ties by playing
+    /// the [first](TieBreak::First) move.
+    pub fn new() -> Self {
+        GreedyAgent::with_tie_break(TieBreak::First)
     }
 
-    pub fn agent(&self) -> &McstAgent<S, E, D, A> {
-        &self.agent
+    /// Constructs a new `GreedyAgent` which breaks ties as given,
+    /// using thread-local RNG if needed.
+    pub fn with_tie_break(tie_break: TieBreak) -> Self {
+        GreedyAgent { tie_break, r: RefCell::new(rand::rng()) }
     }
 }
 
-impl<S, E, D, A> MemoryAgent for McstMemoryAgent<S, E, D, A>
-where
-    S: SelectionPolicy,
-    E: ExpansionPolicy,
-    D: DecisionPolicy,
-    A: Agent,
-{
-    fn initialize_game(&mut self, state: Gamestate) {
-        self.agent.set_state(state);
+impl<R: Rng> GreedyAgent<R> {
+    /// Constructs a new `GreedyAgent` which breaks ties as given,
+    /// drawing from `rng` if needed.
+    pub fn from_rng(tie_break: TieBreak, rng: R) -> Self {
+        GreedyAgent { tie_break, r: RefCell::new(rng) }
     }
+}
 
-    fn make_move(&mut self) -> Turn {
-        let time_0 = Instant::now();
-        let mut hundreths: u128 = 0;
-        loop {
-            match self.agent.cycle() {
-                Ok(continuing) => {
-                    if !continuing {
-                        break;
-                    } else {
-                        let delta = time_0.elapsed().as_millis() / 10;
-                        if delta >= hundreths {
-                            hundreths = delta;
-                            if hundreths > self.compute_time {
-                                break;
-                            }
-                        }
-                    }
-                }
-                Err(e) => { panic!("errored on {:?}", e) },
-            };
+impl<R: Rng> Agent for GreedyAgent<R> {
+    /// Selects the move that flips the most opponent pieces.
+    /// Panics if there are no valid moves.
+    fn make_move(&self, state: &Gamestate) -> Turn {
+        let mover = match state.whose_turn() {
+            States::Taken(mover) => mover,
+            States::Empty => panic!("make_moves passed state with no moves."),
+        };
+        let moves = state.get_moves();
+        if moves.as_slice() == [None] {
+            return None;
         }
 
-        let decision = match self.agent.decide() {
-            Some(Some(loc)) => {
-                Some(loc)
-            },
-            Some(Option::None) => {
-                None
+        let flips: Vec<u8> = moves.iter().flatten().map(|(x, y)| state.board().count_flips(*x, *y, mover)).collect();
+        let most = *flips.iter().max().expect("make_moves passed state with no moves.");
+        let mut best = moves.iter().zip(flips).filter(|(_, flips)| *flips == most).map(|(turn, _)| *turn);
+        match self.tie_break {
+            TieBreak::First => best.next(),
+            TieBreak::Random => best.collect::<Vec<Turn>>().choose(&mut *self.r.borrow_mut()).copied(),
+            TieBreak::PreferCorners => {
+                let ties: Vec<Turn> = best.collect();
+                ties.iter()
+                    .find(|turn| matches!(turn, Some((0 | 7, 0 | 7))))
+                    .or(ties.first())
+                    .copied()
             }
-            _ => panic!("Decision could not be made"),
-        };
+        }.unwrap()
+    }
+}
 
-        self.last_turn = decision;
-        decision
+/// An agent that plays to have as many more moves than its opponent as
+/// possible afterwards, taking any corner it can and otherwise breaking
+/// ties by flipping as many tiles as possible.
+pub struct MobilityAgent {}
+
+impl Agent for MobilityAgent {
+    /// Selects the move that leaves the best mobility difference.
+    /// Panics if there are no valid moves.
+    fn make_move(&self, state: &Gamestate) -> Turn {
+        let mover = match state.whose_turn() {
+            States::Taken(mover) => mover,
+            States::Empty => panic!("make_moves passed state with no moves."),
+        };
+        state.children().max_by_key(
+            |(turn, child)| {
+                let corner = matches!(turn, Some((0 | 7, 0 | 7)));
+                let mobility = match mover {
+                    Players::Black => child.mobility_difference(),
+                    Players::White => -child.mobility_difference(),
+                };
+                // every flip (and the placed tile) adds to the mover's count
+                (corner, mobility, child.board().count(mover))
+            }
+        ).map(|(turn, _)| turn).expect("make_moves passed state with no moves.")
     }
+}
 
-    fn opponent_move(&mut self, op: &Turn) {
-        self.agent.next_two_moves(self.last_turn, *op);
+/// An agent that searches a fixed number of plies ahead with minimax and
+/// alpha-beta pruning, judging the positions it stops at with an [Evaluator].
+///
+/// A pass counts as a ply like any other move. Finished games are scored by
+/// their exact disc difference, pushed past anything an evaluator could
+/// reasonably return so that a win is always preferred to a good position.
+///
+/// Given a [TranspositionTable], positions reached again by a different
+/// move order are looked up instead of searched. Only results of exactly
+/// the same depth are reused, so the table changes how many positions are
+/// searched but never which move is chosen.
+pub struct AlphaBetaAgent<E: Evaluator> {
+    evaluator: E,
+    /// How many plies to search; at least one is always searched.
+    depth: u32,
+    table: Option<RefCell<TranspositionTable>>,
+    /// How many positions have been visited, for benchmarking.
+    nodes: Cell<u64>,
+}
+
+impl<E: Evaluator> AlphaBetaAgent<E> {
+    /// How much a finished game is worth beyond its disc difference.
+    const WIN_VALUE: f64 = 1_000_000.0;
+
+    /// Creates a new `AlphaBetaAgent` which searches `depth` plies ahead.
+    pub fn new(evaluator: E, depth: u32) -> Self {
+        AlphaBetaAgent { evaluator, depth, table: None, nodes: Cell::new(0) }
+    }
+
+    /// Creates a new `AlphaBetaAgent` which searches `depth` plies ahead
+    /// and remembers what it finds in `table`.
+    pub fn with_table(evaluator: E, depth: u32, table: TranspositionTable) -> Self {
+        AlphaBetaAgent { evaluator, depth, table: Some(RefCell::new(table)), nodes: Cell::new(0) }
+    }
+
+    /// Borrows the transposition table, if there is one,
+    /// to look at its statistics.
+    pub fn table(&self) -> Option<Ref<'_, TranspositionTable>> {
+        self.table.as_ref().map(RefCell::borrow)
+    }
+
+    /// Returns how many positions have been visited by every search so far.
+    pub fn nodes_searched(&self) -> u64 {
+        self.nodes.get()
+    }
+
+    /// Returns the value of `state` for the side to move
+    /// when searched `depth` plies deep,
+    /// or [None] if `deadline` passed before the search finished.
+    fn negamax(
+        &self,
+        state: &Gamestate,
+        depth: u32,
+        mut alpha: f64,
+        beta: f64,
+        deadline: Option<Instant>,
+    ) -> Option<f64> {
+        self.nodes.set(self.nodes.get() + 1);
+        let sign = match state.side_to_move() {
+            Players::Black => 1.0,
+            Players::White => -1.0,
+        };
+        if state.is_terminal() {
+            let score = f64::from(state.score());
+            return Some(sign * match state.score().cmp(&0) {
+                Ordering::Greater => score + Self::WIN_VALUE,
+                Ordering::Less => score - Self::WIN_VALUE,
+                Ordering::Equal => 0.0,
+            });
+        }
+        if depth == 0 {
+            return Some(sign * self.evaluator.evaluate(state));
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return None;
+        }
+
+        let key = state.zobrist_hash();
+        let mut first = None;
+        if let Some(entry) = self.table.as_ref().and_then(|table| table.borrow_mut().probe(key)) {
+            if entry.depth == depth {
+                match entry.bound {
+                    Bound::Exact => return Some(entry.value),
+                    Bound::Lower if entry.value >= beta => return Some(entry.value),
+                    Bound::Upper if entry.value <= alpha => return Some(entry.value),
+                    _ => (),
+                }
+            }
+            first = entry.best;
+        }
+
+        let mut moves = state.get_moves();
+        if let Some(index) = first.and_then(|first| moves.iter().position(|turn| *turn == first)) {
+            moves[..=index].rotate_right(1);
+        }
+
+        let original_alpha = alpha;
+        let mut best = f64::NEG_INFINITY;
+        let mut best_turn = None;
+        for turn in moves {
+            let mut child = state.clone();
+            child.make_move_fast(turn);
+            let value = -self.negamax(&child, depth - 1, -beta, -alpha, deadline)?;
+            if value > best {
+                best = value;
+                best_turn = Some(turn);
+            }
+            alpha = alpha.max(value);
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        if let Some(table) = &self.table {
+            let bound = if best <= original_alpha {
+                Bound::Upper
+            } else if best >= beta {
+                Bound::Lower
+            } else {
+                Bound::Exact
+            };
+            table.borrow_mut().store(TableEntry::new(key, depth, bound, best, best_turn));
+        }
+        Some(best)
+    }
+
+    /// Searches every move from `state` `depth` plies deep (trying `first`
+    /// before the rest if it is valid) and returns the best one with its
+    /// value, or [None] if `deadline` passed before the search finished.
+    ///
+    /// # Panics
+    /// If there are no valid moves.
+    fn search(
+        &self,
+        state: &Gamestate,
+        depth: u32,
+        first: Turn,
+        deadline: Option<Instant>,
+    ) -> Option<(Turn, f64)> {
+        if let Some(table) = &self.table {
+            table.borrow_mut().new_search();
+        }
+        let mut moves = state.get_moves();
+        if let Some(index) = moves.iter().position(|turn| *turn == first) {
+            moves[..=index].rotate_right(1);
+        }
+
+        let mut alpha = f64::NEG_INFINITY;
+        let mut best = None;
+        for turn in moves {
+            let mut child = state.clone();
+            child.make_move_fast(turn);
+            let value = -self.negamax(&child, depth.saturating_sub(1), f64::NEG_INFINITY, -alpha, deadline)?;
+            if best.is_none() || value > alpha {
+                alpha = value;
+                best = Some(turn);
+            }
+        }
+        Some((best.expect("make_moves passed state with no moves."), alpha))
+    }
+}
+
+impl<E: Evaluator> Agent for AlphaBetaAgent<E> {
+    /// Plays the move with the best minimax value,
+    /// preferring earlier moves in [Gamestate::get_moves] on ties.
+    /// Panics if there are no valid moves.
+    fn make_move(&self, state: &Gamestate) -> Turn {
+        // won't fail since there is no deadline
+        self.search(state, self.depth, None, None).unwrap().0
+    }
+}
+
+/// An agent that runs [AlphaBetaAgent]'s search one ply deeper at a time
+/// until it runs out of time, then plays the best move from the deepest
+/// search that finished.
+///
+/// Each search tries the previous search's best move first,
+/// which lets alpha-beta pruning cut off more of the tree.
+pub struct IterativeDeepeningAgent<E: Evaluator> {
+    searcher: AlphaBetaAgent<E>,
+    /// How long to search for each move, in milliseconds.
+    millis: u128,
+    state: Gamestate,
+}
+
+impl<E: Evaluator> IterativeDeepeningAgent<E> {
+    /// Creates a new `IterativeDeepeningAgent` which searches for
+    /// `compute_time` hundredths of a second per move.
+    pub fn new(evaluator: E, compute_time: u128) -> Self {
+        IterativeDeepeningAgent::with_millis(evaluator, compute_time * 10)
+    }
+
+    /// Creates a new `IterativeDeepeningAgent` which searches for `millis`
+    /// milliseconds per move.
+    pub fn with_millis(evaluator: E, millis: u128) -> Self {
+        IterativeDeepeningAgent {
+            searcher: AlphaBetaAgent::new(evaluator, 1),
+            millis,
+            state: Gamestate::new(),
+        }
+    }
+
+    /// Creates a new `IterativeDeepeningAgent` which searches for
+    /// `compute_time` hundredths of a second per move and remembers what it
+    /// finds in `table`, which also carries best moves between iterations.
+    pub fn with_table(evaluator: E, compute_time: u128, table: TranspositionTable) -> Self {
+        IterativeDeepeningAgent {
+            searcher: AlphaBetaAgent::with_table(evaluator, 1, table),
+            millis: compute_time * 10,
+            state: Gamestate::new(),
+        }
+    }
+
+    /// Borrows the transposition table, if there is one,
+    /// to look at its statistics.
+    pub fn table(&self) -> Option<Ref<'_, TranspositionTable>> {
+        self.searcher.table()
+    }
+
+    /// Returns the best move from `state` and the depth of the deepest
+    /// search that finished in time.
+    ///
+    /// A search one ply deep always finishes, however short the budget.
+    /// Stops early once the game's result is known or the search reaches
+    /// past the end of the game.
+    fn deepen(&self, state: &Gamestate) -> (Turn, u32) {
+        let deadline = Instant::now() + Duration::from_millis(self.millis as u64);
+        // won't fail since there is no deadline
+        let (mut best, mut value) = self.searcher.search(state, 1, None, None).unwrap();
+        let mut depth = 1;
+        // every empty tile takes at most a pass and a move to fill
+        let max_depth = 2 * u32::from(state.board().counts().2);
+
+        while depth < max_depth && value.abs() < AlphaBetaAgent::<E>::WIN_VALUE {
+            match self.searcher.search(state, depth + 1, best, Some(deadline)) {
+                Some((turn, turn_value)) => (best, value) = (turn, turn_value),
+                None => break,
+            }
+            depth += 1;
+        }
+        (best, depth)
+    }
+}
+
+impl<E: Evaluator> MemoryAgent for IterativeDeepeningAgent<E> {
+    fn initialize_game(&mut self, state: Gamestate) {
+        self.state = state;
+    }
+
+    fn opponent_move(&mut self, op: &Turn) {
+        if let Err(e) = self.state.try_move(*op) {
+            panic!("opponent_move passed invalid turn {:?}: {e}.", op);
+        }
+    }
+
+    fn make_move(&mut self) -> Turn {
+        let (turn, _) = self.deepen(&self.state);
+        self.state.make_move_fast(turn);
+        turn
+    }
+}
+
+/// The input and output through which a human agent talks to its user.
+struct Console<R: BufRead, W: Write> {
+    input: RefCell<R>,
+    output: RefCell<W>,
+    style: RenderStyle,
+    /// The board as the user's last move left it, to find the move played
+    /// since in a game state without history.
+    left: Cell<Option<Board>>,
+}
+
+impl Console<BufReader<Stdin>, Stdout> {
+    /// Talks through stdin and stdout, drawing boards in color if possible.
+    fn stdio() -> Self {
+        Console::new(BufReader::new(io::stdin()), io::stdout(), RenderStyle::detect())
+    }
+}
+
+impl<R: BufRead, W: Write> Console<R, W> {
+    fn new(input: R, output: W, style: RenderStyle) -> Self {
+        Console { input: RefCell::new(input), output: RefCell::new(output), style, left: Cell::new(None) }
+    }
+
+    /// Writes `text` followed by a newline.
+    fn say(&self, text: &str) {
+        writeln!(self.output.borrow_mut(), "{text}").expect("output could not be written to");
+    }
+
+    /// Draws the board with the legal moves marked and the last move
+    /// highlighted.
+    ///
+    /// The last move is the game state's own if it has
+    /// [history](Gamestate::last_turn), and otherwise the disc placed since
+    /// the board [the user left](Console::left), if exactly one was.
+    fn show(&self, state: &Gamestate) {
+        let last_move = match state.last_turn() {
+            Some(turn) => turn,
+            None => self.left.get().and_then(|left| {
+                let mut placed = (0..8).flat_map(|y| (0..8).map(move |x| (x, y))).filter(|&(x, y)| {
+                    left.at(x, y) == Some(States::Empty) && state.board().at(x, y) != Some(States::Empty)
+                });
+                match (placed.next(), placed.next()) {
+                    (Some(square), None) => Some(square),
+                    _ => None,
+                }
+            }),
+        };
+        self.say(&state.render_with_moves(self.style, last_move));
+    }
+
+    /// Remembers the board `state` is left with after `turn`, which the
+    /// user chose.
+    fn leave(&self, state: &Gamestate, turn: Turn) {
+        let mut after = state.clone();
+        after.make_move_fast(turn);
+        self.left.set(Some(*after.board()));
+    }
+
+    /// Reads a line of input without its line ending.
+    ///
+    /// # Panics
+    /// If the input cannot be read or has ended.
+    fn read_line(&self) -> String {
+        self.try_read_line().unwrap_or_else(|e| panic!("{e}."))
+    }
+
+    /// Like [read_line](Console::read_line), but returns an error if the input
+    /// cannot be read, or one of kind [UnexpectedEof](io::ErrorKind::UnexpectedEof)
+    /// if it has ended.
+    fn try_read_line(&self) -> io::Result<String> {
+        let mut line = String::new();
+        if self.input.borrow_mut().read_line(&mut line)? == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "input ended before a move was entered"));
+        }
+        line.truncate(line.trim_end_matches(['\n', '\r']).len());
+        Ok(line)
+    }
+}
+
+/// A human-controlled agent.
+///
+/// Talks to the user through stdin and stdout unless constructed
+/// [with other input and output](HumanAgent::with_io).
+pub struct HumanAgent<R: BufRead = BufReader<Stdin>, W: Write = Stdout> {
+    console: Console<R, W>,
+}
+
+impl HumanAgent {
+    /// Constructs a new human agent which uses stdin and stdout.
+    pub fn new() -> Self {
+        HumanAgent { console: Console::stdio() }
+    }
+}
+
+impl<R: BufRead, W: Write> HumanAgent<R, W> {
+    /// Constructs a new human agent which reads moves from `input` and writes
+    /// prompts and (plain [Ascii](RenderStyle::Ascii)) boards to `output`.
+    pub fn with_io(input: R, output: W) -> Self {
+        HumanAgent { console: Console::new(input, output, RenderStyle::Ascii) }
+    }
+}
+
+impl<R: BufRead, W: Write> Agent for HumanAgent<R, W> {
+    /// Interacts with the user to input a valid move.
+    /// Panics if there are no valid moves or the input ends.
+    fn make_move(&self, state: &Gamestate) -> Turn {
+        self.try_make_move(state).unwrap_or_else(|e| panic!("{e}."))
+    }
+
+    /// Interacts with the user to input a valid move, returning
+    /// [AgentError::Io] if the input ends or cannot be read.
+    fn try_make_move(&self, state: &Gamestate) -> Result<Turn, AgentError> {
+        let valid_moves = state.get_moves();
+        self.console.show(state);
+
+        if valid_moves.is_empty() {
+            return Err(AgentError::NoMoves);
+        }
+
+        let turn = if valid_moves.contains(&None) {
+            self.console.say("No available moves - return to pass:");
+            self.console.try_read_line()?;
+            None
+        } else {
+            loop {
+                self.console.say("Enter a coordinate (such as d3 or 3,2):");
+                let input = self.console.try_read_line()?;
+
+                if let Some(location) = crate::gameplay::str_to_loc(&input) {
+                    if valid_moves.contains(&Some(location)) {
+                        break Some(location)
+                    } else {
+                        self.console.say("Not a valid move!");
+                    }
+                } else {
+                    self.console.say("Could not parse coordinate!");
+                }
+            }
+        };
+        self.console.leave(state, turn);
+        Ok(turn)
+    }
+}
+
+/// Formats a list of turns in algebraic notation for [HumanDebugger].
+fn turns_to_alg(turns: &[Turn]) -> String {
+    turns.iter().map(
+        |turn| -> String {
+            if let Some(loc) = turn {
+                loc_to_alg(*loc)
+            } else {
+                String::from("(Pass)")
+            }
+        }
+    ).collect::<Vec<String>>().join(", ")
+}
+
+/// Formats the moves made so far in `state` for [HumanDebugger]'s
+/// `/history` command.
+fn history_to_str(state: &Gamestate) -> String {
+    if let Some(history) = state.view_history() {
+        turns_to_alg(&history)
+    } else {
+        String::from("History is not being recorded for this game")
+    }
+}
+
+/// A human agent for debugging and interactive play with command support.
+///
+/// Besides moves, the user can enter these commands:
+/// - `/moves` lists the legal moves.
+/// - `/history` lists the moves made since the agent joined the game.
+/// - `/back` steps back a move to look at the earlier position with the
+///   other commands. It doesn't take the move back: moves entered are
+///   always played in the current position, which is shown again first.
+/// - `/hint` runs a short MCTS search and lists the three best moves found
+///   with their win rates.
+/// - `/eval` shows the score, each player's mobility, and how many of each
+///   player's tiles are [stable](crate::mechanics::Board::stable_mask).
+/// - `/show` draws the board again with the legal moves marked.
+///
+/// Talks to the user through stdin and stdout unless constructed
+/// [with other input and output](HumanDebugger::with_io).
+pub struct HumanDebugger<R: BufRead = BufReader<Stdin>, W: Write = Stdout> {
+    console: Console<R, W>,
+    state: Gamestate,
+}
+
+impl HumanDebugger {
+    /// Constructs a new human debugger which uses stdin and stdout.
+    pub fn new() -> Self {
+        HumanDebugger { console: Console::stdio(), state: Gamestate::new() }
+    }
+}
+
+impl<R: BufRead, W: Write> HumanDebugger<R, W> {
+    /// How many MCTS cycles `/hint` runs.
+    const HINT_CYCLES: u32 = 3000;
+
+    /// Constructs a new human debugger which reads moves and commands from
+    /// `input` and writes prompts and (plain [Ascii](RenderStyle::Ascii))
+    /// boards to `output`.
+    pub fn with_io(input: R, output: W) -> Self {
+        HumanDebugger { console: Console::new(input, output, RenderStyle::Ascii), state: Gamestate::new() }
+    }
+
+    /// Runs the command in `input` on `view`, the position being looked at,
+    /// and returns whether `input` was a command.
+    fn command(&self, input: &str, view: &mut Gamestate) -> bool {
+        match input {
+            "/moves" if view.get_moves().contains(&None) => {
+                self.console.say("There are no valid moves besides passing your turn");
+            }
+            "/moves" => self.console.say(&turns_to_alg(&view.get_moves())),
+            "/history" => self.console.say(&history_to_str(view)),
+            "/back" => match view.undo_move() {
+                Some(turn) => {
+                    self.console.say(&format!("Looking at the position before {}:", turns_to_alg(&[turn])));
+                    self.console.show(view);
+                }
+                None => self.console.say("There are no earlier positions to look at"),
+            },
+            "/hint" => self.hint(view),
+            "/eval" => {
+                let board = view.board();
+                let (black, white, _) = board.counts();
+                self.console.say(&format!(
+                    "Score: {black} - {white}\nMobility: {} - {}\nStable: {} - {}",
+                    board.move_count(Players::Black), board.move_count(Players::White),
+                    board.stable_count(Players::Black), board.stable_count(Players::White),
+                ));
+            }
+            "/show" => self.console.show(view),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Prints the best three moves in `state` according to a short MCTS search.
+    fn hint(&self, state: &Gamestate) {
+        if state.is_terminal() {
+            self.console.say("The game is over");
+            return;
+        }
+        let mut agent = McstAgent::new(
+            UctSelection::new(2_f64.sqrt()),
+            BfsExpansion {},
+            UctDecision {},
+            RandomAgent::new(),
+            RandomAgent::new(),
+            state.clone(),
+        );
+        for _ in 0..Self::HINT_CYCLES {
+            if !agent.cycle().expect("hint search failed") {
+                break;
+            }
+        }
+        let mut children: Vec<(&Turn, McstNode)> = agent.tree().root().children().iter().collect();
+        children.sort_by_key(|(_, child)| std::cmp::Reverse(*child.total()));
+        for (turn, child) in children.into_iter().take(3) {
+            self.console.say(&format!(
+                "{}: {:.1}% of {} playouts won",
+                turns_to_alg(&[*turn]),
+                100.0 * child.wins() / f64::from(*child.total()),
+                child.total(),
+            ));
+        }
+    }
+}
+
+impl<R: BufRead, W: Write> MemoryAgent for HumanDebugger<R, W> {
+    fn initialize_game(&mut self, state: Gamestate) {
+        self.state = state;
+        self.state.enable_history();
+    }
+
+    fn opponent_move(&mut self, op: &Turn) {
+        if let Err(e) = self.state.try_move(*op) {
+            panic!("opponent_move passed invalid turn {:?}: {e}.", op);
+        }
+    }
+
+    /// Allows user to enter moves and execute debugging commands like `/moves` and `/history`.
+    fn make_move(&mut self) -> Turn {
+        let valid_moves = self.state.get_moves();
+        let mut view = self.state.clone();
+        self.console.show(&self.state);
+
+        let turn = loop {
+            if valid_moves.contains(&None) {
+                self.console.say("Only valid move is to pass. Return to confirm:");
+            } else {
+                self.console.say("Enter a coordinate (such as d3 or 3,2):");
+            }
+            let input = self.console.read_line();
+            if self.command(&input, &mut view) {
+                continue;
+            }
+            if view != self.state {
+                view = self.state.clone();
+                self.console.say("Back to the current position:");
+                self.console.show(&view);
+            }
+
+            if valid_moves.contains(&None) {
+                break None;
+            } else if let Some(turn) = crate::gameplay::str_to_loc(&input) {
+                if valid_moves.contains(&Some(turn)) {
+                    break Some(turn);
+                } else {
+                    self.console.say("Not a valid move!");
+                }
+            } else {
+                self.console.say("Could not parse coordinate!");
+            }
+        };
+        self.state.make_move_fast(turn);
+        turn
+    }
+}
+
+/// Refinements to [UctSelection]'s formula, all off by default.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct UctOptions {
+    /// Use UCB1-Tuned: scale a child's exploration term by the square root
+    /// of `min(1/4, V)`, where `V` is the variance of its rewards plus
+    /// `sqrt(2 ln N / n)`, so that moves whose rollouts agree are explored
+    /// less. With an exploration constant of 1 this is the usual UCB1-Tuned.
+    pub tuned: bool,
+    /// Use First-Play Urgency: the value, to the player to move, of a move
+    /// which hasn't been visited yet. A node is descended through once the
+    /// best of its children is worth more than this, not counting its
+    /// exploration term, rather than only once every move from it has been
+    /// expanded, and unvisited children score this rather than being tried
+    /// first.
+    pub first_play_urgency: Option<f64>,
+}
+
+// A UCT (Upper Confidence Bound applied to Trees) selection policy
+pub struct UctSelection {
+    /// Exploration constant.
+    c: f64,
+    options: UctOptions,
+}
+
+impl UctSelection {
+    /// Creates a new `UctSelection` with the specified exploration constant `c`.
+    pub fn new(c: f64) -> Self {
+        UctSelection::with_options(c, UctOptions::default())
+    }
+
+    /// Like [UctSelection::new], but with the given refinements.
+    pub fn with_options(c: f64, options: UctOptions) -> Self {
+        UctSelection { c, options }
+    }
+
+    /// Returns the score of `child` when selecting from a node visited
+    /// `parent_total` times: its value plus its exploration term. Values are
+    /// wins for `me` when `sign` is 1, and minus them when it is -1.
+    fn score(&self, child: McstNode, parent_total: u32, sign: f64) -> f64 {
+        let total = f64::from(*child.total());
+        if total == 0.0 {
+            return match self.options.first_play_urgency {
+                Some(urgency) => self.urgency(urgency, sign),
+                Option::None => f64::INFINITY,
+            };
+        }
+        let log = f64::from(parent_total).ln();
+        let explore = match self.options.tuned {
+            false => (log / total).sqrt(),
+            true => (log / total * (child.variance() + (2.0 * log / total).sqrt()).min(0.25)).sqrt(),
+        };
+        self.value(child, sign) + self.c * explore
+    }
+
+    /// Returns the value of a visited `child` without its exploration term,
+    /// on the same scale as [UctSelection::score].
+    fn value(&self, child: McstNode, sign: f64) -> f64 {
+        sign * child.wins() / f64::from(*child.total())
+    }
+
+    /// Puts a value to the player to move on the scale of
+    /// [UctSelection::score], on which the opponent's values are shifted
+    /// down by 1.
+    fn urgency(&self, value: f64, sign: f64) -> f64 {
+        if sign > 0.0 { value } else { value - 1.0 }
+    }
+
+    /// Recursively selects nodes using UCT, from the perspective of whoever
+    /// is to move at each node: wins are counted for `me`, so they are
+    /// maximized on `me`'s turns and minimized on the opponent's.
+    /// Adds moves to the path until a node with no or unexplored children,
+    /// or whose value is [proven](McstNode::proof), is reached. With
+    /// [First-Play Urgency](UctOptions::first_play_urgency), a node with
+    /// unexplored children is descended through if the value of one of its
+    /// children, leaving out its exploration term, is more than the urgency.
+    ///
+    /// Children proven to lose for the player to move are never chosen. One
+    /// proven to win never needs to be, since it would have proven its parent.
+    fn select_from(&self, node: McstNode, me: Players, path: &mut Vec<Turn>) {
+        let unexpanded = node.children().len() < node.game().move_count();
+        if node.proof().is_some()
+           || (unexpanded && self.options.first_play_urgency.is_none())
+           || node.children().len() == 0 {
+            return;
+        }
+        let (sign, losing) = if node.game().side_to_move() == me {
+            (1.0, Proven::Loss)
+        } else {
+            (-1.0, Proven::Win)
+        };
+        let best = node.children().iter().filter(
+            |(_, child)| child.proof() != Some(losing)
+        ).max_by(
+            |(_, n1), (_, n2)| -> Ordering {
+                self.score(*n1, *node.total(), sign).total_cmp(&self.score(*n2, *node.total(), sign))
+            }
+        );
+        let new_child = match (best, self.options.first_play_urgency) {
+            (Some(best), Some(urgency)) if unexpanded => {
+                let known = node.children().values().filter(
+                    |child| child.proof() != Some(losing) && *child.total() > 0
+                ).map(|child| self.value(child, sign)).fold(f64::NEG_INFINITY, f64::max);
+                if known <= self.urgency(urgency, sign) {
+                    return;
+                }
+                best
+            }
+            (Option::None, _) if unexpanded => return,
+            (best, _) => best.expect("There were no children?"),
+        };
+        path.push(*new_child.0);
+        self.select_from(new_child.1, me, path);
+    }
+}
+
+impl SelectionPolicy for UctSelection {
+    /// Returns a path through the tree according to UCT-based selection.
+    fn select(&mut self, tree: &McstTree) -> Option<Vec<Turn>> {
+        let mut turns: Vec<Turn> = Vec::new();
+        self.select_from(tree.root(), tree.root().game().side_to_move(), &mut turns);
+        Some(turns)
+    }
+}
+
+/// A PUCT selection policy, as used by AlphaZero, which weighs exploration
+/// by each move's [prior](McstNode::prior).
+///
+/// Children are scored `Q + c_puct · prior · sqrt(N) / (1 + n)`, where `Q` is
+/// the fraction of the `n` rollouts through the child won by the player to
+/// move and `N` is the parent's rollouts. A node with unexpanded moves is
+/// selected itself, leaving the expansion policy to choose which to expand,
+/// unless one of its children scores more than an unexpanded move could: an
+/// unvisited move would score `c_puct · prior · sqrt(N)`, and its prior is at
+/// most what the priors of the expanded moves leave of 1. The priors come
+/// from the expansion policy, so it should be one which gives them, such as
+/// [PriorExpansion](crate::neural::PriorExpansion).
+pub struct PuctSelection {
+    c_puct: f64,
+}
+
+impl PuctSelection {
+    /// Creates a new `PuctSelection` with the exploration constant `c_puct`.
+    pub fn new(c_puct: f64) -> Self {
+        PuctSelection { c_puct }
+    }
+
+    /// Returns the score of `child` of a node visited `parent_total` times,
+    /// for the player to move there, who is `me` when `mine` is true.
+    fn score(&self, child: McstNode, parent_total: u32, mine: bool) -> f64 {
+        let total = f64::from(*child.total());
+        let value = match (child.total(), mine) {
+            (0, _) => 0.0,
+            (_, true) => child.wins() / total,
+            (_, false) => 1.0 - child.wins() / total,
+        };
+        value + self.c_puct * f64::from(child.prior()) * f64::from(parent_total).sqrt() / (1.0 + total)
+    }
+
+    /// Returns the most an unexpanded move from `node` could score, which is
+    /// if it had all the prior its expanded moves don't.
+    fn unexpanded_score(&self, node: McstNode) -> f64 {
+        let expanded: f32 = node.children().values().map(McstNode::prior).sum();
+        self.c_puct * f64::from((1.0 - expanded).max(0.0)) * f64::from(*node.total()).sqrt()
+    }
+
+    /// Recursively selects the best scoring child until a node with no
+    /// moves, or whose value is [proven](McstNode::proof), is reached, or
+    /// one with unexpanded moves none of its children outscore. Children
+    /// proven to lose for the player to move are never chosen (see
+    /// [UctSelection::select_from]).
+    fn select_from(&self, node: McstNode, me: Players, path: &mut Vec<Turn>) {
+        if node.proof().is_some() || node.children().is_empty() {
+            return;
+        }
+        let unexpanded = node.children().len() < node.game().move_count();
+        let mine = node.game().side_to_move() == me;
+        let losing = if mine { Proven::Loss } else { Proven::Win };
+        let best = node.children().iter()
+            .filter(|(_, child)| child.proof() != Some(losing))
+            .map(|(turn, child)| (turn, child, self.score(child, *node.total(), mine)))
+            .max_by(|(_, _, a), (_, _, b)| a.total_cmp(b));
+        let (turn, child) = match best {
+            Some((_, _, score)) if unexpanded && score <= self.unexpanded_score(node) => return,
+            Some((turn, child, _)) => (turn, child),
+            None if unexpanded => return,
+            None => panic!("There were no children?"),
+        };
+        path.push(*turn);
+        self.select_from(child, me, path);
+    }
+}
+
+impl SelectionPolicy for PuctSelection {
+    /// Returns a path through the tree according to PUCT-based selection.
+    fn select(&mut self, tree: &McstTree) -> Option<Vec<Turn>> {
+        let mut turns: Vec<Turn> = Vec::new();
+        self.select_from(tree.root(), tree.root().game().side_to_move(), &mut turns);
+        Some(turns)
+    }
+}
+
+/// A breadth-first search selection policy for MCTS.
+/// Expands nodes level-by-level in the tree.
+pub struct BfsSelectionFast {
+    /// Queue of paths to nodes in the tree.
+    queue: VecDeque<Vec<Turn>>,
+}
+
+impl BfsSelectionFast {
+    /// Creates a new BFS selection policy initialized with the root node.
+    pub fn new() -> Self {
+        BfsSelectionFast {
+            queue: VecDeque::from([Vec::new()]),
+        }
+    }
+}
+
+impl SelectionPolicy for BfsSelectionFast {
+    /// Returns the next unexplored path according to BFS order.
+    fn select(&mut self, tree: &McstTree) -> Option<Vec<Turn>> {
+        loop {
+            if let Some(path) = self.queue.pop_front() {
+                let node = tree.root().search(&path).unwrap();
+                let move_ct = node.game().move_count();
+
+                if move_ct != 0 {
+                    // there are moves to make
+                    if move_ct == node.children().len() {
+                        // we have already been here... put in the children and try again
+                        for m in node.game().get_moves().iter() {
+                            let mut next_path = path.clone();
+                            next_path.push(*m);
+                            self.queue.push_back(next_path);
+                        }
+                    } else {
+                        self.queue.push_front(path.clone());
+                        break Some(path);
+                    }
+                } // else game is over and cannot be selected
+            } else {
+                break None;
+            }
+        }
+    }
+
+    /// Restarts the search from the new root, since the queued paths are
+    /// from the old one.
+    fn root_advanced(&mut self, _tree: &McstTree, _moves: &[Turn]) {
+        self.queue.clear();
+        self.queue.push_back(Vec::new());
+    }
+
+    /// Resets the BFS queue.
+    fn set_state(&mut self, _state: &Gamestate) {
+        self.queue.clear();
+        self.queue.push_back(Vec::new());
+    }
+}
+
+/// A basic expansion policy that expands the first unvisited move.
+pub struct BfsExpansion {}
+
+impl ExpansionPolicy for BfsExpansion {
+    /// Returns the first legal move from the given node that hasn't been expanded yet.
+    fn expand(&mut self, tree: &McstTree, path: &Vec<Turn>) -> Turn {
+        let node = tree.root().search(&path).unwrap();
+        for next_turn in node.game().get_moves().iter() {
+            if !node.children().contains_key(&next_turn) {
+                return *next_turn;
+            }
+        }
+        panic!("No nodes to expand on given path {:?}", path);
+    }
+}
+
+/// An expansion policy that expands a random unvisited move, so that early
+/// statistics aren't biased toward the moves [BfsExpansion] tries first.
+///
+/// Seeds its RNG from the operating system unless [seeded](RandomExpansion::seeded)
+/// or given another RNG, rather than using the thread-local one, so that it
+/// can be used in [parallel searches](McstAgent::cycle_parallel).
+pub struct RandomExpansion<R: Rng = StdRng> {
+    r: R,
+}
+
+impl RandomExpansion {
+    /// Constructs a new `RandomExpansion` using an RNG seeded by the operating system.
+    pub fn new() -> Self {
+        RandomExpansion {r: StdRng::from_os_rng()}
+    }
+
+    /// Constructs a new `RandomExpansion` whose moves are determined by `seed`.
+    pub fn seeded(seed: u64) -> Self {
+        RandomExpansion {r: StdRng::seed_from_u64(seed)}
+    }
+}
+
+impl<R: Rng> RandomExpansion<R> {
+    /// Constructs a new `RandomExpansion` drawing from `rng`.
+    pub fn from_rng(rng: R) -> Self {
+        RandomExpansion {r: rng}
+    }
+}
+
+impl<R: Rng> ExpansionPolicy for RandomExpansion<R> {
+    /// Returns a random legal move from the given node that hasn't been expanded yet.
+    fn expand(&mut self, tree: &McstTree, path: &Vec<Turn>) -> Turn {
+        let node = tree.root().search(path).unwrap();
+        let unexpanded: Vec<Turn> = node.game().get_moves().into_iter()
+            .filter(|turn| !node.children().contains_key(turn))
+            .collect();
+        *unexpanded.choose(&mut self.r)
+            .unwrap_or_else(|| panic!("No nodes to expand on given path {:?}", path))
+    }
+}
+
+/// An expansion policy that expands every move from the selected node at
+/// once, rolling out from the first (as [BfsExpansion] would choose it).
+///
+/// The rest start out unvisited, so this suits selection policies which
+/// score unvisited moves, such as [PuctSelection]; [UctSelection] visits
+/// each before any is visited twice.
+pub struct FullExpansion {}
+
+impl ExpansionPolicy for FullExpansion {
+    /// Returns the first legal move from the given node that hasn't been expanded yet.
+    fn expand(&mut self, tree: &McstTree, path: &Vec<Turn>) -> Turn {
+        BfsExpansion {}.expand(tree, path)
+    }
+
+    /// Returns every legal move from the given node that hasn't been expanded yet.
+    fn expand_all(&mut self, tree: &McstTree, path: &Vec<Turn>) -> Vec<Turn> {
+        let node = tree.root().search(path).unwrap();
+        node.game().get_moves().into_iter()
+            .filter(|turn| !node.children().contains_key(turn))
+            .collect()
+    }
+}
+
+/// Decision policy that selects the move with the most simulations.
+pub struct UctDecision {}
+
+impl DecisionPolicy for UctDecision {
+    /// Picks the move with the highest visit count from the root node.
+    fn decide(&mut self, tree: &McstTree) -> Turn {
+        tree.root().children().keys().max_by(
+            |link1, link2| -> Ordering {
+                let node1 = tree.root().children().get(link1).unwrap();
+                let node2 = tree.root().children().get(link2).unwrap();
+                node1.total().cmp(node2.total())
+            }
+        ).copied().expect("Somehow there no moves?")
+    }
+}
+
+impl SymmetricMcstAgent<UctSelection, BfsExpansion, UctDecision, RandomAgent<StdRng>> {
+    /// Constructs the standard MCTS agent, which selects by [UCT](UctSelection)
+    /// with exploration constant √2, expands breadth first and plays the most
+    /// visited move, rolling out randomly with agents seeded from `seed`.
+    /// Its searches by cycle count are the same on every run.
+    pub fn seeded_default(seed: u64, game: Gamestate) -> Self {
+        McstAgent::new(
+            UctSelection::new(2_f64.sqrt()),
+            BfsExpansion {},
+            UctDecision {},
+            RandomAgent::seeded(seed),
+            RandomAgent::seeded(seed.wrapping_add(1)),
+            game,
+        )
+    }
+}
+
+/// Decision policy that selects the move with the best average win rate.
+pub struct WinAverageDecision {}
+
+impl DecisionPolicy for WinAverageDecision  {
+    /// Picks the move with the highest win average (wins / total simulations).
+    fn decide(&mut self, tree: &McstTree) -> Turn {
+        tree.root().children().keys().max_by(
+            |link1, link2| -> Ordering {
+                let node1 = tree.root().children().get(link1).unwrap();
+                let node2 = tree.root().children().get(link2).unwrap();
+                match (node1.wins(), node1.total(), node2.wins(), node2.total()) {
+                    (_, 0, _, 0) => Ordering::Equal,
+                    (_, 0, _, _) => Ordering::Less,
+                    (_, _, _, 0) => Ordering::Greater,
+                    (w1, t1, w2, t2) =>
+                        (w1 / f64::from(*t1)).total_cmp(&(w2 / f64::from(*t2)))
+                }
+            }
+        ).copied().expect("Somehow there no moves?")
+    }
+}
+
+/// Decision policy for self-play which adds Dirichlet noise to the root,
+/// so that games explore moves the search doesn't favor.
+///
+/// Each decision draws noise from Dirichlet(`alpha`) over the root's children
+/// and picks the move with the highest `(1 - epsilon) * visit_fraction + epsilon * noise`.
+/// Smaller `alpha` concentrates the noise on fewer moves.
+///
+/// The noise is for collecting training data, not for playing well: while
+/// [disabled](NoisyDecision::set_enabled), or if `epsilon` is 0,
+/// it decides exactly like the policy it wraps.
+pub struct NoisyDecision<D: DecisionPolicy, R: Rng = ThreadRng> {
+    decider: D,
+    alpha: f64,
+    epsilon: f64,
+    enabled: bool,
+    r: R,
+}
+
+impl<D: DecisionPolicy> NoisyDecision<D> {
+    /// Constructs a new `NoisyDecision` using thread-local RNG.
+    ///
+    /// # Panics
+    /// If `alpha` is not positive or `epsilon` is not between 0 and 1.
+    pub fn new(decider: D, alpha: f64, epsilon: f64) -> Self {
+        NoisyDecision::from_rng(decider, alpha, epsilon, rand::rng())
+    }
+}
+
+impl<D: DecisionPolicy, R: Rng> NoisyDecision<D, R> {
+    /// Constructs a new `NoisyDecision` which draws its noise using `rng`.
+    ///
+    /// # Panics
+    /// If `alpha` is not positive or `epsilon` is not between 0 and 1.
+    pub fn from_rng(decider: D, alpha: f64, epsilon: f64, rng: R) -> Self {
+        assert!(alpha > 0.0, "alpha {alpha} is not positive");
+        assert!((0.0..=1.0).contains(&epsilon), "epsilon {epsilon} is not a probability");
+        NoisyDecision { decider, alpha, epsilon, enabled: true, r: rng }
+    }
+
+    /// Turns the noise on or off, such as off for evaluation matches.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Draws a sample from Gamma(`shape`, 1) with the method of Marsaglia and Tsang.
+    fn gamma(&mut self, shape: f64) -> f64 {
+        if shape < 1.0 {
+            // boost the shape above 1, then scale the sample back down
+            let u: f64 = self.r.random();
+            return self.gamma(shape + 1.0) * u.powf(1.0 / shape);
+        }
+        let d = shape - 1.0 / 3.0;
+        let c = 1.0 / (9.0 * d).sqrt();
+        loop {
+            // a standard normal sample, by the Box-Muller transform
+            let (u1, u2): (f64, f64) = (1.0 - self.r.random::<f64>(), self.r.random());
+            let x = (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos();
+            let v = (1.0 + c * x).powi(3);
+            if v <= 0.0 {
+                continue;
+            }
+            let u: f64 = 1.0 - self.r.random::<f64>();
+            if u.ln() < 0.5 * x * x + d - d * v + d * v.ln() {
+                return d * v;
+            }
+        }
+    }
+}
+
+impl<D: DecisionPolicy, R: Rng> DecisionPolicy for NoisyDecision<D, R> {
+    fn decide(&mut self, tree: &McstTree) -> Turn {
+        if !self.enabled || self.epsilon == 0.0 {
+            return self.decider.decide(tree);
+        }
+        let children = tree.root().children();
+        let total = f64::from(*tree.root().total()).max(1.0);
+        let noise: Vec<f64> = (0..children.len()).map(|_| self.gamma(self.alpha)).collect();
+        let noise_total: f64 = noise.iter().sum();
+        children.iter()
+            .zip(noise)
+            .map(|((turn, child), noise)| {
+                let visits = f64::from(*child.total()) / total;
+                (turn, (1.0 - self.epsilon) * visits + self.epsilon * noise / noise_total)
+            })
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(turn, _)| *turn)
+            .expect("Somehow there no moves?")
+    }
+}
+
+/// Decision policy for self-play which samples moves in proportion to their
+/// visits raised to the power of 1/`temperature`, rather than always playing
+/// the most visited.
+///
+/// A temperature of 1 samples in proportion to visits, and higher ones even
+/// things out further. At 0 it decides like [UctDecision]. Moves which have
+/// never been visited are never chosen.
+///
+/// The temperature may change over the game, such as to explore in the
+/// opening and play the best moves after (see [TemperatureDecision::opening]).
+/// It is looked up by the [ply](Gamestate::ply) of the root's game, so an
+/// agent doesn't have to tell the policy how far along the game is.
+pub struct TemperatureDecision<R: Rng = ThreadRng> {
+    /// The temperature at each ply.
+    schedule: Box<dyn Fn(u8) -> f64>,
+    r: R,
+}
+
+impl TemperatureDecision {
+    /// Constructs a new `TemperatureDecision` at a fixed `temperature`,
+    /// using thread-local RNG.
+    ///
+    /// # Panics
+    /// If `temperature` is negative.
+    pub fn new(temperature: f64) -> Self {
+        TemperatureDecision::from_rng(temperature, rand::rng())
+    }
+}
+
+impl<R: Rng> TemperatureDecision<R> {
+    /// Constructs a new `TemperatureDecision` at a fixed `temperature`,
+    /// which samples using `rng`.
+    ///
+    /// # Panics
+    /// If `temperature` is negative.
+    pub fn from_rng(temperature: f64, rng: R) -> Self {
+        assert!(temperature >= 0.0, "temperature {temperature} is negative");
+        TemperatureDecision::with_schedule(move |_| temperature, rng)
+    }
+
+    /// Constructs a new `TemperatureDecision` which samples at `temperature`
+    /// for the first `plies` plies of the game and plays the most visited
+    /// move after, as is usual for self-play.
+    ///
+    /// # Panics
+    /// If `temperature` is negative.
+    pub fn opening(temperature: f64, plies: u8, rng: R) -> Self {
+        assert!(temperature >= 0.0, "temperature {temperature} is negative");
+        TemperatureDecision::with_schedule(move |ply| if ply < plies { temperature } else { 0.0 }, rng)
+    }
+
+    /// Constructs a new `TemperatureDecision` whose temperature at each ply
+    /// of the game is given by `schedule`. Negative temperatures count as 0.
+    pub fn with_schedule(schedule: impl Fn(u8) -> f64 + 'static, rng: R) -> Self {
+        TemperatureDecision { schedule: Box::new(schedule), r: rng }
+    }
+
+    /// Returns the temperature moves are chosen at on the given ply.
+    pub fn temperature(&self, ply: u8) -> f64 {
+        (self.schedule)(ply).max(0.0)
+    }
+}
+
+impl<R: Rng> DecisionPolicy for TemperatureDecision<R> {
+    fn decide(&mut self, tree: &McstTree) -> Turn {
+        let temperature = self.temperature(tree.root().game().ply());
+        let children = tree.root().children();
+        let most = children.values().map(|child| *child.total()).max().unwrap_or(0);
+        if temperature == 0.0 || most == 0 {
+            return UctDecision {}.decide(tree);
+        }
+
+        // visits are scaled by the most any move has, so that low
+        // temperatures can't overflow
+        let weights: Vec<(Turn, f64)> = children.iter()
+            .map(|(turn, child)| (*turn, match *child.total() {
+                0 => 0.0,
+                visits => ((f64::from(visits).ln() - f64::from(most).ln()) / temperature).exp(),
+            }))
+            .collect();
+        let mut sample = self.r.random::<f64>() * weights.iter().map(|(_, weight)| weight).sum::<f64>();
+        for (turn, weight) in &weights {
+            if sample < *weight {
+                return *turn;
+            }
+            sample -= weight;
+        }
+        // rounding may leave a sliver past the last move, which goes to the
+        // last visited one
+        weights.iter().rev()
+            .find(|(_, weight)| *weight > 0.0)
+            .map(|(turn, _)| *turn)
+            .unwrap()
+    }
+}
+
+/// How much a [McstMemoryAgent] searches before each move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Budget {
+    /// Search for this many milliseconds.
+    Millis(u128),
+    /// Run this many cycles, however long they take. Unlike a time budget
+    /// this makes agents with seeded rollouts play the same games every time.
+    Cycles(usize),
+}
+
+impl Budget {
+    /// How many cycles are run between looking at the clock
+    /// when searching for a [time](Budget::Millis).
+    const CLOCK_CHECK_CYCLES: usize = 16;
+
+    /// Runs cycles of `agent` until the budget is spent or its selector
+    /// decides to stop, and returns how many were run, or the error
+    /// of the first cycle to fail.
+    fn spend<S, E, D, R>(self, agent: &mut McstAgent<S, E, D, R>) -> Result<usize, CycleError>
+    where
+        S: SelectionPolicy,
+        E: ExpansionPolicy,
+        D: DecisionPolicy,
+        R: RolloutPolicy,
+    {
+        match self {
+            Budget::Cycles(n) => agent.cycle_n(n),
+            Budget::Millis(millis) => {
+                let time_0 = Instant::now();
+                let mut cycles = 0;
+                loop {
+                    let done = agent.cycle_n(Self::CLOCK_CHECK_CYCLES)?;
+                    cycles += done;
+                    if done < Self::CLOCK_CHECK_CYCLES || time_0.elapsed().as_millis() >= millis {
+                        break Ok(cycles);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Tells a [TimeManager] the time, so that tests can use a clock they move
+/// on by hand.
+pub trait Clock: Send {
+    /// Returns how long it has been since some fixed point.
+    fn now(&self) -> Duration;
+}
+
+/// The system's [Clock], counting from when it was made.
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl SystemClock {
+    /// Constructs a clock counting from now.
+    pub fn new() -> Self {
+        SystemClock { start: Instant::now() }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        SystemClock::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// Shares a game's worth of time out between a [McstMemoryAgent]'s moves
+/// (see [McstMemoryAgent::with_time_manager]).
+///
+/// Each move is given a share of the time left, by how many moves the agent
+/// probably has left to make, more of it in the midgame than the opening
+/// and less when there are few moves to choose between. A move with only
+/// one choice is given none. Whatever a move doesn't use is kept for later
+/// moves, and no move is given more than half of what is left.
+pub struct TimeManager {
+    clock: Box<dyn Clock>,
+    total: Duration,
+    remaining: Duration,
+    /// When the move under way started and what it was allotted.
+    started: Option<(Duration, TimeAllotment)>,
+    /// What the last move was allotted and used.
+    last: Option<TimeAllotment>,
+}
+
+impl TimeManager {
+    /// How many choices a move needs to be given its full share of time.
+    const FULL_CHOICE: usize = 10;
+
+    /// Constructs a manager sharing out `total` per game by the system clock.
+    pub fn new(total: Duration) -> Self {
+        TimeManager::with_clock(total, SystemClock::new())
+    }
+
+    /// Like [TimeManager::new], but reads the time from `clock`.
+    pub fn with_clock(total: Duration, clock: impl Clock + 'static) -> Self {
+        TimeManager { clock: Box::new(clock), total, remaining: total, started: None, last: None }
+    }
+
+    /// Returns how much of the game's time is left.
+    pub fn remaining(&self) -> Duration {
+        self.remaining
+    }
+
+    /// Returns what the last move was allotted and used.
+    pub fn last(&self) -> Option<TimeAllotment> {
+        self.last
+    }
+
+    /// Gives the whole of the game's time back, for a new game.
+    pub fn reset(&mut self) {
+        self.remaining = self.total;
+        self.started = None;
+        self.last = None;
+    }
+
+    /// Returns how long to search for a move in `game`, which should have
+    /// moves to make.
+    pub fn allot(&self, game: &Gamestate) -> Duration {
+        let choices = game.move_count();
+        if choices <= 1 {
+            return Duration::ZERO;
+        }
+        let empties = game.board().counts().2;
+        // the player to move fills about every other empty square
+        let moves_left = u32::from(empties).div_ceil(2).max(1);
+        let phase = match empties {
+            0..=14 => 1.0,
+            15..=44 => 1.5,
+            _ => 0.75,
+        };
+        let choice = choices.min(Self::FULL_CHOICE) as f64 / Self::FULL_CHOICE as f64;
+        (self.remaining / moves_left).mul_f64(phase * choice.max(0.5)).min(self.remaining / 2)
+    }
+
+    /// [Allots](TimeManager::allot) time for a move in `game` and starts
+    /// timing it.
+    pub fn start_move(&mut self, game: &Gamestate) -> Duration {
+        let allotted = self.allot(game);
+        let allotment = TimeAllotment { remaining: self.remaining, allotted, used: Duration::ZERO };
+        self.started = Some((self.clock.now(), allotment));
+        allotted
+    }
+
+    /// Stops timing the move under way and takes the time it used from what
+    /// is left, returning what it was allotted and used.
+    pub fn finish_move(&mut self) -> Option<TimeAllotment> {
+        let (start, allotment) = self.started.take()?;
+        let used = self.clock.now().saturating_sub(start);
+        self.remaining = self.remaining.saturating_sub(used);
+        self.last = Some(TimeAllotment { used, ..allotment });
+        self.last
+    }
+}
+
+/// What a [McstMemoryAgent] [decided](McstMemoryAgent::decide) on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Decision {
+    /// The move decided on.
+    pub turn: Turn,
+    /// Whether the search met the agent's [minimums](McstMemoryAgent::set_minimums)
+    /// first. If not, the decision may rest on too few visits to trust.
+    pub minimums_met: bool,
+}
+
+pub struct McstMemoryAgent<S: SelectionPolicy, E: ExpansionPolicy, D: DecisionPolicy, R: RolloutPolicy> {
+    agent: McstAgent<S, E, D, R>,
+    budget: Budget,
+    /// The agent's own last move, until the opponent answers it and the tree
+    /// is advanced past both.
+    last_turn: Option<Turn>,
+    ponder: bool,
+    /// How many threads to search on, and how to make rollout policies for
+    /// all but the first.
+    threads: usize,
+    rollouts: Option<Box<RolloutFactory>>,
+    /// How many cycles have been run, for benchmarking.
+    cycles: u64,
+    /// How many cycles had been run when the search for the current move began.
+    searched_from: u64,
+    /// How many visits every move at the root should have before deciding,
+    /// how many cycles the search should run, and the most extra cycles to
+    /// run past the budget for them (see [McstMemoryAgent::set_minimums]).
+    min_root_visits: u32,
+    min_total_cycles: u64,
+    max_extra_cycles: usize,
+    /// The agent's last decision.
+    last_decision: Option<Decision>,
+    /// Shares a game's worth of time between moves instead of the budget.
+    time_manager: Option<TimeManager>,
+    /// Whether to print the [analysis](MemoryAgent::analysis) of each move.
+    verbose: bool,
+    /// The cycles run and the sum of their depths, as tallied by the
+    /// agent's observer if it [summarizes its searches](McstMemoryAgent::summarize_searches).
+    tally: Option<Arc<Mutex<(u64, usize)>>>,
+    /// The summary of the search for the last move.
+    summary: Option<SearchSummary>,
+}
+
+impl<S, E, D, R> McstMemoryAgent<S, E, D, R>
+where
+    S: SelectionPolicy + Send,
+    E: ExpansionPolicy + Send,
+    D: DecisionPolicy,
+    R: RolloutPolicy,
+{
+    /// How many moves of the principal variation its
+    /// [analysis](MemoryAgent::analysis) gives.
+    pub const PV_LENGTH: usize = 8;
+
+    /// Constructs an agent which searches for `compute_time` hundredths of a
+    /// second per move, valuing draws at `contempt` less than half a win (see
+    /// [McstAgent::set_contempt]).
+    ///
+    /// # Panics
+    /// If `contempt` is not between -0.5 and 0.5.
+    pub fn new(mut agent: McstAgent<S, E, D, R>, compute_time: u128, contempt: f64) -> Self {
+        agent.set_contempt(contempt);
+        McstMemoryAgent::with_budget(agent, Budget::Millis(compute_time * 10))
+    }
+
+    /// Constructs an agent which searches within `budget` for each move.
+    pub fn with_budget(agent: McstAgent<S, E, D, R>, budget: Budget) -> Self {
+        Self {
+            agent,
+            budget,
+            last_turn: Option::None,
+            ponder: false,
+            threads: 1,
+            rollouts: None,
+            cycles: 0,
+            searched_from: 0,
+            min_root_visits: 0,
+            min_total_cycles: 0,
+            max_extra_cycles: 0,
+            last_decision: None,
+            time_manager: None,
+            verbose: false,
+            tally: None,
+            summary: None,
+        }
+    }
+
+    /// Like [McstMemoryAgent::with_budget], but keeps the agent's tree to at
+    /// most `limit` nodes (see [McstAgent::set_node_limit]).
+    pub fn with_node_limit(mut agent: McstAgent<S, E, D, R>, budget: Budget, limit: usize) -> Self {
+        agent.set_node_limit(limit);
+        McstMemoryAgent::with_budget(agent, budget)
+    }
+
+    /// Constructs an agent which searches for as long as `manager` allots
+    /// each move out of the time it has for the game, rather than for a
+    /// fixed budget. What each move was allotted is in its
+    /// [analysis](MemoryAgent::analysis).
+    pub fn with_time_manager(agent: McstAgent<S, E, D, R>, manager: TimeManager) -> Self {
+        McstMemoryAgent { time_manager: Some(manager), ..McstMemoryAgent::with_budget(agent, Budget::Millis(0)) }
+    }
+
+    /// Returns the agent's time manager, if it has one.
+    pub fn time_manager(&self) -> Option<&TimeManager> {
+        self.time_manager.as_ref()
+    }
+
+    /// Like [McstMemoryAgent::new], but keeps growing its tree after each
+    /// of its moves for as long as the opponent took to answer (see
+    /// [MemoryAgent::ponder]). Most of that work goes
+    /// into the move it just played, and the part of it under the opponent's
+    /// actual reply is kept for its next move.
+    pub fn with_pondering(agent: McstAgent<S, E, D, R>, compute_time: u128) -> Self {
+        McstMemoryAgent { ponder: true, ..McstMemoryAgent::new(agent, compute_time, 0.0) }
+    }
+
+    /// Sets whether the agent keeps growing its tree while its opponent
+    /// moves, as one made [with_pondering](McstMemoryAgent::with_pondering)
+    /// does.
+    pub fn set_pondering(&mut self, ponder: bool) {
+        self.ponder = ponder;
+    }
+
+    /// Plays `turns` on the tree, whoever they are by.
+    fn advance(&mut self, turns: &[Turn]) -> Result<(), AgentError> {
+        match self.agent.advance_by(turns) {
+            true => Ok(()),
+            false => Err(AgentError::InvalidState(format!(
+                "could not play {turns:?} on game \n{}", self.agent.tree().root().game()
+            ))),
+        }
+    }
+
+    /// Returns whether all the player to move can do after `turns` from the
+    /// root is pass, for when forced passes aren't asked for or reported.
+    fn must_pass_after(&self, turns: &[Turn]) -> bool {
+        let mut game = self.agent.tree().root().game().clone();
+        game.make_moves_fast(turns) && game.get_moves().as_slice() == [Option::None]
+    }
+
+    /// Makes the agent search on `threads` threads at once (see
+    /// [McstAgent::cycle_parallel]), all but the first rolling out with
+    /// policies made by `rollouts`. Its games are no longer reproducible with more than
+    /// one thread, even with a [cycle budget](Budget::Cycles).
+    pub fn set_threads(
+        &mut self,
+        threads: usize,
+        rollouts: impl Fn(usize) -> Box<dyn RolloutPolicy> + Send + Sync + 'static,
+    ) {
+        self.threads = threads;
+        self.rollouts = Some(Box::new(rollouts));
+    }
+
+    pub fn agent(&self) -> &McstAgent<S, E, D, R> {
+        &self.agent
+    }
+
+    pub fn agent_mut(&mut self) -> &mut McstAgent<S, E, D, R> {
+        &mut self.agent
+    }
+
+    /// Returns how many cycles have been run so far, pondering included.
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// Makes the agent keep searching once its budget is spent, until every
+    /// legal move at the root has been visited at least `root_visits` times
+    /// and at least `total_cycles` cycles have been run for the move, or
+    /// until `max_extra` more cycles have been run. Moves which have been
+    /// [proven](McstNode::proof) need no visits.
+    pub fn set_minimums(&mut self, root_visits: u32, total_cycles: u64, max_extra: usize) {
+        self.min_root_visits = root_visits;
+        self.min_total_cycles = total_cycles;
+        self.max_extra_cycles = max_extra;
+    }
+
+    /// Returns whether the search for the current move has met the agent's
+    /// [minimums](McstMemoryAgent::set_minimums).
+    fn minimums_met(&self) -> bool {
+        let root = self.agent.tree().root();
+        let visited = |turn: &Turn| root.children().get(turn)
+            .is_some_and(|child| child.proof().is_some() || *child.total() >= self.min_root_visits);
+        self.cycles - self.searched_from >= self.min_total_cycles
+            && (self.min_root_visits == 0 || root.proof().is_some() || root.game().get_moves().iter().all(visited))
+    }
+
+    /// Runs cycles one at a time until the agent's
+    /// [minimums](McstMemoryAgent::set_minimums) are met or as many extra
+    /// cycles as they allow have been run, or until the selector decides to
+    /// stop or a cycle fails.
+    fn top_up(&mut self) -> Result<(), CycleError> {
+        for _ in 0..self.max_extra_cycles {
+            if self.minimums_met() || !self.agent.cycle()? {
+                break;
+            }
+            self.cycles += 1;
+        }
+        Ok(())
+    }
+
+    /// Decides on a move from the tree as it is, and says whether the search
+    /// met the agent's [minimums](McstMemoryAgent::set_minimums) first.
+    pub fn decide(&mut self) -> Result<Decision, AgentError> {
+        let turn = self.agent.decide().ok_or_else(|| AgentError::InvalidState(format!(
+            "decided on an illegal move in game \n{}", self.agent.tree().root().game()
+        )))?;
+        let decision = Decision { turn, minimums_met: self.minimums_met() };
+        self.last_decision = Some(decision);
+        Ok(decision)
+    }
+
+    /// Returns the agent's last [decision](McstMemoryAgent::decide), so that
+    /// callers playing it as a [MemoryAgent] can tell whether its moves met
+    /// its minimums.
+    pub fn last_decision(&self) -> Option<Decision> {
+        self.last_decision
+    }
+
+    /// Makes the agent print its [analysis](MemoryAgent::analysis) to
+    /// standard error after each move, or stop doing so.
+    pub fn set_verbose(&mut self, verbose: bool) {
+        self.verbose = verbose;
+    }
+
+    /// Makes the agent sum up the search for each move it makes, which its
+    /// [analysis](MemoryAgent::analysis), and so its game records, include.
+    /// This [observes](McstAgent::set_observer) every cycle, replacing any
+    /// observer already set.
+    pub fn summarize_searches(&mut self) {
+        let tally = Arc::new(Mutex::new((0, 0)));
+        let observed = Arc::clone(&tally);
+        self.agent.set_observer(move |info| {
+            let mut tally = observed.lock().unwrap();
+            tally.0 += 1;
+            tally.1 += info.depth;
+        });
+        self.tally = Some(tally);
+    }
+
+    /// Returns the summary of the search for the agent's last move, if it
+    /// [summarizes its searches](McstMemoryAgent::summarize_searches).
+    /// Cycles run while pondering before the move are not included.
+    pub fn search_summary(&self) -> Option<SearchSummary> {
+        self.summary
+    }
+
+    /// Runs cycles until `budget` is spent or the selector decides to stop,
+    /// or a cycle fails.
+    fn think(&mut self, budget: Budget) -> Result<(), CycleError> {
+        if let Some(rollouts) = self.rollouts.as_deref().filter(|_| self.threads > 1) {
+            let (n, deadline) = match budget {
+                Budget::Cycles(n) => (n, None),
+                Budget::Millis(millis) => (usize::MAX, Some(Instant::now() + Duration::from_millis(millis as u64))),
+            };
+            let done = self.agent.cycle_parallel(self.threads, rollouts, n, deadline)?;
+            self.cycles += done as u64;
+            return Ok(());
+        }
+
+        self.cycles += budget.spend(&mut self.agent)? as u64;
+        Ok(())
+    }
+
+    /// Thinks for `budget` and then [tops up](McstMemoryAgent::set_minimums)
+    /// the search, but if a cycle fails, throws the tree away and searches
+    /// again from the same position with a new one, in case what went wrong
+    /// is in the tree. Fails if that does too.
+    fn think_or_rebuild(&mut self, budget: Budget) -> Result<(), AgentError> {
+        let Err(first) = self.think(budget).and_then(|()| self.top_up()) else {
+            return Ok(());
+        };
+        let state = self.agent.tree().root().game().clone();
+        self.agent.set_state(state);
+        self.think(budget).and_then(|()| self.top_up()).map_err(|again| AgentError::InvalidState(format!(
+            "search failed ({first}), and again with a new tree ({again})"
+        )))
+    }
+}
+
+impl<S, E, D, R> MemoryAgent for McstMemoryAgent<S, E, D, R>
+where
+    S: SelectionPolicy + Send,
+    E: ExpansionPolicy + Send,
+    D: DecisionPolicy,
+    R: RolloutPolicy,
+{
+    fn initialize_game(&mut self, state: Gamestate) {
+        self.last_turn = Option::None;
+        self.summary = Option::None;
+        self.last_decision = Option::None;
+        if let Some(manager) = &mut self.time_manager {
+            manager.reset();
+        }
+        self.agent.set_state(state);
+    }
+
+    fn make_move(&mut self) -> Turn {
+        self.try_make_move().unwrap_or_else(|e| panic!("{e}."))
+    }
+
+    /// Searches for and decides on a move. A search that fails is retried
+    /// once with a new tree (see [McstMemoryAgent::think_or_rebuild]).
+    fn try_make_move(&mut self) -> Result<Turn, AgentError> {
+        // the opponent had to pass, without it being reported
+        if let Some(turn) = self.last_turn.take() {
+            let mut turns = vec![turn];
+            if self.must_pass_after(&turns) {
+                turns.push(Option::None);
+            }
+            self.advance(&turns)?;
+        }
+        if let Some(tally) = &self.tally {
+            *tally.lock().unwrap() = (0, 0);
+        }
+        let start = Instant::now();
+        self.searched_from = self.cycles;
+        let budget = match &mut self.time_manager {
+            Some(manager) => Budget::Millis(manager.start_move(self.agent.tree().root().game()).as_millis()),
+            None => self.budget,
+        };
+        let searched = self.think_or_rebuild(budget);
+        if let Some(manager) = &mut self.time_manager {
+            manager.finish_move();
+        }
+        searched?;
+        if let Some(tally) = &self.tally {
+            let (cycles, depths) = *tally.lock().unwrap();
+            let secs = start.elapsed().as_secs_f64();
+            self.summary = Some(SearchSummary {
+                cycles,
+                average_depth: if cycles == 0 { 0.0 } else { depths as f64 / cycles as f64 },
+                rollouts_per_sec: if secs == 0.0 { 0.0 } else { cycles as f64 / secs },
+            });
+        }
+
+        let decision = self.decide()?;
+
+        self.last_turn = Some(decision.turn);
+        if let Some(analysis) = self.analysis().filter(|_| self.verbose) {
+            eprintln!("{analysis}");
+            if !decision.minimums_met {
+                eprintln!("decided before the search met its minimums");
+            }
+        }
+        Ok(decision.turn)
+    }
+
+    fn opponent_move(&mut self, op: &Turn) {
+        self.try_opponent_move(op).unwrap_or_else(|e| panic!("{e}."));
+    }
+
+    /// Advances the tree past the agent's own last move, if it hasn't been
+    /// already, and then `op`. If the agent had to pass in between without
+    /// being asked to, it passes on the tree too.
+    fn try_opponent_move(&mut self, op: &Turn) -> Result<(), AgentError> {
+        let mut turns: Vec<Turn> = self.last_turn.take().into_iter().collect();
+        if op.is_some() && self.must_pass_after(&turns) {
+            turns.push(Option::None);
+        }
+        turns.push(*op);
+        self.advance(&turns)
+    }
+
+    /// Searches on for `budget` if the agent ponders. A search that fails
+    /// throws the tree away, so that the next move starts afresh.
+    fn ponder(&mut self, budget: u128) {
+        if self.ponder && self.think(Budget::Millis(budget * 10)).is_err() {
+            let state = self.agent.tree().root().game().clone();
+            self.agent.set_state(state);
+        }
+    }
+
+    /// Returns the fraction of playouts from the root of the tree that were
+    /// won, from Black's point of view, counting draws as half a win.
+    fn evaluate(&self) -> Option<f64> {
+        let root = self.agent.tree().root();
+        if *root.total() == 0 {
+            return None;
+        }
+        let rate = root.wins() / f64::from(*root.total());
+        match root.game().side_to_move() {
+            Players::Black => Some(rate),
+            Players::White => Some(1.0 - rate),
+        }
+    }
+
+    /// Returns the [analysis](McstAgent::analysis) of the tree, with a
+    /// principal variation of up to [McstMemoryAgent::PV_LENGTH] moves and
+    /// the [summary](McstMemoryAgent::search_summary) of the last search.
+    /// Until the opponent moves, its root is the position last moved from.
+    fn analysis(&self) -> Option<Analysis> {
+        Some(Analysis {
+            search: self.summary,
+            clock: self.time_manager.as_ref().and_then(TimeManager::last),
+            ..self.agent.analysis(Self::PV_LENGTH)
+        })
+    }
+}
+
+/// Searches from the same position with several independent [McstAgent]s at
+/// once, each on its own thread, then [merges](McstTree::merge) their trees
+/// and decides from the merged root.
+///
+/// Unlike [McstAgent::cycle_parallel], the workers share nothing while they
+/// search, so with seeded rollouts and a [cycle budget](Budget::Cycles)
+/// its moves are the same every time.
+pub struct RootParallelAgent<F, D: DecisionPolicy> {
+    /// Makes each worker's agent, given the worker's number counting from 0,
+    /// so that seeded agents can be seeded differently. Agents are made on
+    /// their worker's thread, so they don't need to be [Send].
+    workers: F,
+    threads: usize,
+    budget: Budget,
+    decider: RefCell<D>,
+}
+
+impl<F, D: DecisionPolicy> RootParallelAgent<F, D> {
+    /// Constructs an agent which searches with `threads` agents made by
+    /// `workers`, each spending `budget` per move, and chooses moves
+    /// with `decider`.
+    pub fn new(workers: F, threads: usize, budget: Budget, decider: D) -> Self {
+        RootParallelAgent { workers, threads, budget, decider: RefCell::new(decider) }
+    }
+}
+
+impl<F, D, S, E, WD, R> Agent for RootParallelAgent<F, D>
+where
+    F: Fn(usize) -> McstAgent<S, E, WD, R> + Sync,
+    D: DecisionPolicy,
+    S: SelectionPolicy,
+    E: ExpansionPolicy,
+    WD: DecisionPolicy,
+    R: RolloutPolicy,
+{
+    fn make_move(&self, state: &Gamestate) -> Turn {
+        let (workers, budget) = (&self.workers, self.budget);
+        let trees: Vec<McstTree> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..self.threads).map(|worker| {
+                let state = state.clone();
+                scope.spawn(move || {
+                    let mut agent = workers(worker);
+                    agent.set_state(state);
+                    budget.spend(&mut agent).unwrap_or_else(|e| panic!("{e}."));
+                    agent.into_tree()
+                })
+            }).collect();
+            handles.into_iter().map(|handle| handle.join().expect("worker panicked")).collect()
+        });
+
+        let mut trees = trees.into_iter();
+        let mut tree = trees.next().expect("searched with no threads");
+        for other in trees {
+            tree.merge(other);
+        }
+        tree.apply_proofs(self.decider.borrow_mut().decide(&tree))
+    }
+}
+
+/// Searches to the end of the game, trying every move, to find the exact
+/// final score with perfect play. This is only feasible with few empty tiles
+/// left; each extra empty tile multiplies the work several times over.
+pub struct EndgameSolver {
+    /// How many positions have been visited, for benchmarking.
+    nodes: Cell<u64>,
+}
+
+impl EndgameSolver {
+    /// Below this many empty tiles, moves are searched in the order they are
+    /// generated, as ordering them costs more than it saves.
+    const ORDERING_EMPTIES: u8 = 7;
+
+    pub fn new() -> Self {
+        EndgameSolver { nodes: Cell::new(0) }
+    }
+
+    /// Returns how many positions have been visited so far.
+    pub fn nodes_searched(&self) -> u64 {
+        self.nodes.get()
+    }
+
+    /// Returns the [final score](Gamestate::score) with perfect play from both
+    /// sides (positive if Black wins), along with a move that achieves it.
+    /// The move is [None] both when the side to move must pass and when the
+    /// game is already over.
+    pub fn solve(&self, state: &Gamestate) -> (i8, Turn) {
+        let sign = match state.side_to_move() {
+            Players::Black => 1,
+            Players::White => -1,
+        };
+        let (value, turn) = self.negamax(state, -i8::MAX, i8::MAX);
+        (sign * value, turn)
+    }
+
+    /// Returns the final score for the side to move and the move achieving it,
+    /// where the score is only exact if it is strictly between `alpha` and `beta`.
+    fn negamax(&self, state: &Gamestate, mut alpha: i8, beta: i8) -> (i8, Turn) {
+        self.nodes.set(self.nodes.get() + 1);
+        let mut moves = state.get_moves();
+        if moves.is_empty() {
+            let score = state.score();
+            return (match state.side_to_move() {
+                Players::Black => score,
+                Players::White => -score,
+            }, None);
+        }
+
+        // try the moves that leave the opponent fewest replies first,
+        // as those tend to be best and cut off the rest soonest
+        if state.board().counts().2 >= Self::ORDERING_EMPTIES {
+            moves.sort_by_cached_key(|turn| {
+                let mut child = state.clone();
+                child.make_move_fast(*turn);
+                child.get_moves().len()
+            });
+        }
+
+        let mut best = (i8::MIN, None);
+        for turn in moves {
+            let mut child = state.clone();
+            child.make_move_fast(turn);
+            let value = -self.negamax(&child, -beta, -alpha).0;
+            if value > best.0 {
+                best = (value, turn);
+            }
+            alpha = alpha.max(value);
+            if alpha >= beta {
+                break;
+            }
+        }
+        best
+    }
+}
+
+impl Default for EndgameSolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An agent that plays perfectly with [EndgameSolver] once few enough tiles are
+/// empty, and asks the agent it wraps the rest of the game.
+pub struct SolverAgent<A: Agent> {
+    threshold_empties: u8,
+    fallback: A,
+    solver: EndgameSolver,
+}
+
+impl<A: Agent> SolverAgent<A> {
+    /// Creates a new `SolverAgent` which solves the game once at most
+    /// `threshold_empties` tiles are empty, and plays `fallback` before that.
+    pub fn new(threshold_empties: u8, fallback: A) -> Self {
+        SolverAgent { threshold_empties, fallback, solver: EndgameSolver::new() }
+    }
+
+    pub fn solver(&self) -> &EndgameSolver {
+        &self.solver
+    }
+}
+
+impl<A: Agent> Agent for SolverAgent<A> {
+    fn make_move(&self, state: &Gamestate) -> Turn {
+        if state.board().counts().2 <= self.threshold_empties {
+            self.solver.solve(state).1
+        } else {
+            self.fallback.make_move(state)
+        }
+    }
+}
+
+/// An agent that shows the agent it wraps every position in the same orientation,
+/// so that it plays the same move (rotated or reflected to match) in positions
+/// that are rotations or reflections of each other.
+///
+/// The inner agent is given the [canonical](crate::mechanics::Board::canonical)
+/// board, and its move is mapped back onto the real one.
+pub struct CanonicalAgent<A: Agent> {
+    agent: A,
+}
+
+impl<A: Agent> CanonicalAgent<A> {
+    pub fn new(agent: A) -> Self {
+        CanonicalAgent { agent }
+    }
+}
+
+impl<A: Agent> Agent for CanonicalAgent<A> {
+    fn make_move(&self, state: &Gamestate) -> Turn {
+        let (_, sym, inverse) = state.board().canonical();
+        transform_turn(self.agent.make_move(&state.transformed(sym)), inverse)
+    }
+
+    fn try_make_move(&self, state: &Gamestate) -> Result<Turn, AgentError> {
+        let (_, sym, inverse) = state.board().canonical();
+        Ok(transform_turn(self.agent.try_make_move(&state.transformed(sym))?, inverse))
+    }
+}
+
+/// An agent that plays the moves of a recorded game from the standard
+/// starting position, for pitting live agents against past opponents.
+///
+/// It can play either side, and works out how far into the recording it is
+/// from the state it is given by [initialize_game](MemoryAgent::initialize_game).
+///
+/// # Panics
+/// When a recorded move is illegal in the actual game, when the opponent
+/// plays something other than what was recorded, or when the recording runs
+/// out. Plies are counted from 1 in the messages.
+pub struct ReplayAgent {
+    turns: Vec<Turn>,
+    /// How many recorded moves have been made.
+    ply: usize,
+    state: Gamestate,
+}
+
+impl ReplayAgent {
+    /// Constructs a new `ReplayAgent` which replays `turns`.
+    pub fn new(turns: Vec<Turn>) -> Self {
+        ReplayAgent { turns, ply: 0, state: Gamestate::new() }
+    }
+
+    /// Constructs a new `ReplayAgent` which replays a transcript
+    /// such as `f5d6c3` (see [transcript_to_turns]).
+    pub fn from_transcript(transcript: &str) -> Result<Self, TranscriptError> {
+        Ok(ReplayAgent::new(transcript_to_turns(transcript)?))
+    }
+
+    /// Returns the next recorded move.
+    fn next_turn(&self) -> Turn {
+        *self.turns.get(self.ply).unwrap_or_else(
+            || panic!("recording ran out after ply {}.", self.ply)
+        )
+    }
+}
+
+impl MemoryAgent for ReplayAgent {
+    fn initialize_game(&mut self, state: Gamestate) {
+        let mut replayed = Gamestate::new();
+        for ply in 0..=self.turns.len() {
+            if replayed == state {
+                self.ply = ply;
+                self.state = state;
+                return;
+            }
+            if ply < self.turns.len() && !replayed.make_move_fast(self.turns[ply]) {
+                break;
+            }
+        }
+        panic!("recording never reaches game \n{state}\n.");
+    }
+
+    fn opponent_move(&mut self, op: &Turn) {
+        let expected = self.next_turn();
+        if *op != expected {
+            panic!(
+                "opponent diverged from recording at ply {}: played {}, recorded {}.",
+                self.ply + 1, turns_to_alg(&[*op]), turns_to_alg(&[expected]),
+            );
+        }
+        if let Err(e) = self.state.try_move(*op) {
+            panic!("opponent_move passed invalid turn {:?}: {e}.", op);
+        }
+        self.ply += 1;
+    }
+
+    fn make_move(&mut self) -> Turn {
+        let turn = self.next_turn();
+        if let Err(e) = self.state.try_move(turn) {
+            panic!("recorded move {} at ply {} is illegal: {e}.", turns_to_alg(&[turn]), self.ply + 1);
+        }
+        self.ply += 1;
+        turn
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+    use std::error::Error;
+
+    use rand::seq::IndexedRandom;
+
+    use super::*;
+    use crate::agent::{play_game, play_game_from, play_game_recorded, MemorifiedAgent};
+    use crate::gameplay::{GameResult, MoveError};
+    use crate::agent::tournament::round_robin;
+    use crate::mcst::{
+        benchmark_with, AgentRollout, CycleInfo, ExpansionError, ExpansionPolicy, NodeStorage, RolloutError,
+        SelectionError, SymmetricMcstAgent, TreeIoError,
+    };
+    use crate::mechanics::{Board, Symmetry};
+
+    #[test]
+    fn test_alpha_beta_beats_greedy() {
+        let mut alpha_beta = MemorifiedAgent::new(
+            AlphaBetaAgent::new(WeightedSquareEvaluator::default(), 4)
+        );
+        let mut greedy = MemorifiedAgent::new(GreedyAgent::from_rng(TieBreak::Random, StdRng::seed_from_u64(1)));
+        let mut rng = StdRng::seed_from_u64(2);
+
+        let mut wins = 0;
+        for game in 0..100 {
+            // alpha-beta always plays the same way, so start from a random
+            // opening, from a fixed seed like greedy's ties
+            let mut opening = Gamestate::new();
+            for _ in 0..4 {
+                opening.make_move_fast(*opening.get_moves().choose(&mut rng).unwrap());
+            }
+            let won = if game % 2 == 0 {
+                let (result, _) = play_game_from(&mut alpha_beta, &mut greedy, opening);
+                matches!(result, GameResult::BlackWin(_))
+            } else {
+                let (result, _) = play_game_from(&mut greedy, &mut alpha_beta, opening);
+                matches!(result, GameResult::WhiteWin(_))
+            };
+            if won { wins += 1; }
+        }
+        assert!(wins > 70, "alpha-beta only won {wins} of 100 games");
+    }
+
+    /// Plain minimax without pruning, to check the pruning against.
+    fn minimax<E: Evaluator>(agent: &AlphaBetaAgent<E>, state: &Gamestate, depth: u32) -> f64 {
+        if depth == 0 || state.is_terminal() {
+            agent.negamax(state, 0, f64::NEG_INFINITY, f64::INFINITY, None).unwrap()
+        } else {
+            state.children()
+                 .map(|(_, child)| -minimax(agent, &child, depth - 1))
+                 .fold(f64::NEG_INFINITY, f64::max)
+        }
+    }
+
+    #[test]
+    fn test_mobility_beats_greedy() {
+        let mut mobility = MemorifiedAgent::new(MobilityAgent {});
+        let mut greedy = MemorifiedAgent::new(GreedyAgent::from_rng(TieBreak::Random, StdRng::seed_from_u64(1)));
+        let mut rng = StdRng::seed_from_u64(2);
+
+        let mut wins = 0;
+        for game in 0..200 {
+            // mobility always plays the same way, so start from a random
+            // opening, from a fixed seed like greedy's ties
+            let mut opening = Gamestate::new();
+            for _ in 0..4 {
+                opening.make_move_fast(*opening.get_moves().choose(&mut rng).unwrap());
+            }
+            let won = if game % 2 == 0 {
+                let (result, _) = play_game_from(&mut mobility, &mut greedy, opening);
+                matches!(result, GameResult::BlackWin(_))
+            } else {
+                let (result, _) = play_game_from(&mut greedy, &mut mobility, opening);
+                matches!(result, GameResult::WhiteWin(_))
+            };
+            if won { wins += 1; }
+        }
+        assert!(wins > 120, "mobility only won {wins} of 200 games");
+    }
+
+    #[test]
+    fn test_mixture() {
+        let g = Gamestate::from_transcript("c4c3c2b4").unwrap();
+        let greedy = GreedyAgent::new().make_move(&g);
+
+        let mixture = MixtureAgent::from_rng(
+            GreedyAgent::new(), RandomAgent::seeded(1), 0.25, StdRng::seed_from_u64(2)
+        );
+        for _ in 0..4000 {
+            assert!(g.valid_move(mixture.make_move(&g)));
+        }
+        let (primary, secondary) = mixture.usage();
+        assert_eq!(primary + secondary, 4000);
+        assert!((900..1100).contains(&secondary), "secondary chose {secondary} of 4000 moves");
+        mixture.reset_usage();
+        assert_eq!(mixture.usage(), (0, 0));
+
+        let pure = MixtureAgent::new(GreedyAgent::new(), RandomAgent::new(), 0.0);
+        assert!((0..100).all(|_| pure.make_move(&g) == greedy));
+        assert_eq!(pure.usage(), (100, 0));
+
+        let swapped = MixtureAgent::new(RandomAgent::new(), GreedyAgent::new(), 1.0);
+        assert!((0..100).all(|_| swapped.make_move(&g) == greedy));
+        assert_eq!(swapped.usage(), (0, 100));
+    }
+
+    /// An agent that takes its time to play the last legal move.
+    struct SlowAgent {
+        delay: Duration,
+    }
+
+    impl Agent for SlowAgent {
+        fn make_move(&self, state: &Gamestate) -> Turn {
+            thread::sleep(self.delay);
+            *state.get_moves().last().unwrap()
+        }
+    }
+
+    #[test]
+    fn test_timed_agent() {
+        let g = Gamestate::from_transcript("c4c3c2b4").unwrap();
+        let moves = g.get_moves();
+
+        let quick = TimedAgent::new(SlowAgent { delay: Duration::ZERO }, 100);
+        assert_eq!(quick.make_move(&g), *moves.last().unwrap());
+        assert_eq!(quick.overruns(), 0);
+        assert!(quick.max_latency() < Duration::from_secs(1));
+
+        let slow = TimedAgent::new(SlowAgent { delay: Duration::from_millis(300) }, 5);
+        assert_eq!(slow.make_move(&g), moves[0]);
+        assert_eq!(slow.overruns(), 1);
+        // the move still finishes in the background
+        thread::sleep(Duration::from_millis(500));
+        assert!(slow.max_latency() >= Duration::from_millis(300));
+
+        let slow = TimedAgent::with_fallback(SlowAgent { delay: Duration::from_millis(300) }, 5, GreedyAgent::new());
+        assert_eq!(slow.make_move(&g), GreedyAgent::new().make_move(&g));
+        assert_eq!(slow.overruns(), 1);
+    }
+
+    #[test]
+    fn test_replay_agent() {
+        let mut black = MemorifiedAgent::new(GreedyAgent::new());
+        let mut white = MemorifiedAgent::new(RandomAgent::seeded(4));
+        let (result, turns) = play_game_from(&mut black, &mut white, Gamestate::new());
+
+        // a live agent which plays the same moves reproduces the game
+        let mut black = MemorifiedAgent::new(GreedyAgent::new());
+        let mut white = ReplayAgent::new(turns.clone());
+        assert_eq!(play_game_from(&mut black, &mut white, Gamestate::new()), (result, turns.clone()));
+
+        // both sides can be replayed, from a transcript too
+        let transcript = crate::gameplay::turns_to_transcript(&turns);
+        let mut black = ReplayAgent::from_transcript(&transcript).unwrap();
+        let mut white = ReplayAgent::new(turns.clone());
+        assert_eq!(play_game_from(&mut black, &mut white, Gamestate::new()).1, turns);
+    }
+
+    #[test]
+    #[should_panic(expected = "opponent diverged from recording at ply 3: played c4, recorded e6")]
+    fn test_replay_agent_divergence() {
+        let mut replay = ReplayAgent::from_transcript("f5f6e6").unwrap();
+        let mut g = Gamestate::new();
+        g.make_move_fast(Some((5, 4)));
+        replay.initialize_game(g);
+        replay.make_move();
+        replay.opponent_move(&Some((2, 3)));
+    }
+
+    /// A game where White has to pass.
+    fn white_must_pass() -> Gamestate {
+        let board = Board::from_ascii(
+            " 01234567\n\
+             0BBBBBBBB\n\
+             1BBBBBBBB\n\
+             2BBBBBBBB\n\
+             3BBBBBBBB\n\
+             4BBBBBBBB\n\
+             5BBBBBBBB\n\
+             6BBBBBBWW\n\
+             7BBBBBB..\n"
+        ).unwrap();
+        Gamestate::new_from(board, 1)
+    }
+
+    #[test]
+    fn test_human_agent() {
+        let mut output = Vec::new();
+        let turn = HumanAgent::with_io("z9\nd4\n3,2\n".as_bytes(), &mut output).make_move(&Gamestate::new());
+        assert_eq!(turn, Some((3, 2)));
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with(&Gamestate::new().render_with_moves(RenderStyle::Ascii, None)));
+        assert_eq!(output.matches("Enter a coordinate").count(), 3);
+        assert!(output.contains("Could not parse coordinate!\n"));
+        assert!(output.contains("Not a valid move!\n"));
+
+        let mut output = Vec::new();
+        let turn = HumanAgent::with_io("\n".as_bytes(), &mut output).make_move(&white_must_pass());
+        assert_eq!(turn, None);
+        assert!(String::from_utf8(output).unwrap().ends_with("No available moves - return to pass:\n"));
+    }
+
+    #[test]
+    fn test_console_last_move() {
+        let start = Gamestate::new();
+        let mut g = start.clone();
+        g.make_move_fast(Some((3, 2)));
+        let reply = g.get_moves()[0];
+        g.make_move_fast(reply);
+        let mut recorded = Gamestate::new();
+        recorded.enable_history();
+        recorded.make_move_fast(Some((5, 4)));
+
+        let mut output = Vec::new();
+        {
+            let console = Console::new("".as_bytes(), &mut output, RenderStyle::Colored);
+            console.show(&start);
+            // the user plays d3, and the reply is found from the board
+            console.leave(&start, Some((3, 2)));
+            console.show(&g);
+            // a game state with history knows its own last move
+            console.show(&recorded);
+        }
+
+        let shown = String::from_utf8(output).unwrap();
+        assert_eq!(shown, format!(
+            "{}\n{}\n{}\n",
+            start.render_with_moves(RenderStyle::Colored, None),
+            g.render_with_moves(RenderStyle::Colored, reply),
+            recorded.render_with_moves(RenderStyle::Colored, Some((5, 4))),
+        ));
+        assert_eq!(shown.matches("\x1b[46;").count(), 2);
+    }
+
+    /// Has a [HumanDebugger] given `input` move in `state`,
+    /// and returns the move along with everything it wrote.
+    fn debug_move(state: &Gamestate, input: &str) -> (Turn, String) {
+        let mut output = Vec::new();
+        let mut debugger = HumanDebugger::with_io(input.as_bytes(), &mut output);
+        debugger.initialize_game(state.clone());
+        let turn = debugger.make_move();
+        (turn, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn test_human_debugger() {
+        let g = Gamestate::new();
+        let (turn, output) = debug_move(&g, "/moves\nf5\n");
+        assert_eq!(turn, Some((5, 4)));
+        assert!(output.contains(&format!("\n{}\n", turns_to_alg(&g.get_moves()))));
+        assert_eq!(output.matches("Enter a coordinate").count(), 2);
+
+        let (turn, output) = debug_move(&white_must_pass(), "/moves\nok\r\n");
+        assert_eq!(turn, None);
+        assert!(output.contains("There are no valid moves besides passing your turn\n"));
+        assert_eq!(output.matches("Only valid move is to pass").count(), 2);
+    }
+
+    #[test]
+    fn test_human_debugger_history() {
+        let mut g = Gamestate::new();
+        g.make_moves_fast(&[Some((5, 4)), Some((5, 5))]);
+        let reply = loc_to_alg(g.get_moves()[0].unwrap());
+
+        let input = format!("f5\n/history\n/back\n/eval\n/back\n/back\n{reply}\n");
+        let mut output = Vec::new();
+        let mut debugger = HumanDebugger::with_io(input.as_bytes(), &mut output);
+        debugger.initialize_game(Gamestate::new());
+        assert_eq!(debugger.make_move(), Some((5, 4)));
+        debugger.opponent_move(&Some((5, 5)));
+        assert_eq!(debugger.make_move(), g.get_moves()[0]);
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.contains("\nf5, f6\n"));
+        assert!(output.contains("Looking at the position before f6:\n"));
+        // evaluated after f5 alone
+        assert!(output.contains("Score: 4 - 1\n"));
+        assert!(output.contains("Looking at the position before f5:\n"));
+        assert!(output.contains("There are no earlier positions to look at\n"));
+        assert!(output.contains(&format!("Back to the current position:\n{}", g.render_with_moves(RenderStyle::Ascii, None))));
+    }
+
+    #[test]
+    fn test_human_debugger_analysis() {
+        let g = Gamestate::new();
+        let (turn, output) = debug_move(&g, "/show\n/eval\n/hint\nd3\n");
+        assert_eq!(turn, Some((3, 2)));
+        assert_eq!(output.matches(&g.render_with_moves(RenderStyle::Ascii, None)).count(), 2);
+        assert!(output.contains("Score: 2 - 2\nMobility: 4 - 4\nStable: 0 - 0\n"));
+
+        let hints: Vec<&str> = output.lines().filter(|line| line.contains("playouts won")).collect();
+        assert_eq!(hints.len(), 3);
+        for hint in hints {
+            let turn = crate::gameplay::alg_to_loc(&hint[..2]);
+            assert!(g.valid_move(turn), "{hint}");
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "input ended before a move was entered")]
+    fn test_human_agent_input_ends() {
+        HumanAgent::with_io("e7\n".as_bytes(), Vec::new()).make_move(&Gamestate::new());
+    }
+
+    #[test]
+    fn test_human_agent_errors() {
+        let agent = HumanAgent::with_io("e7\n".as_bytes(), Vec::new());
+        match agent.try_make_move(&Gamestate::new()) {
+            Err(AgentError::Io(e)) => assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof),
+            other => panic!("expected the input to run out, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_canonical_agent() {
+        // a ranking which isn't symmetric at all
+        let mut ranking = [[0.0; 8]; 8];
+        for (y, row) in ranking.iter_mut().enumerate() {
+            for (x, value) in row.iter_mut().enumerate() {
+                *value = (8 * y + x) as f64;
+            }
+        }
+        let plain = RankedCellAgent::new(ranking);
+        let canonical = CanonicalAgent::new(RankedCellAgent::new(ranking));
+
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut g = Gamestate::new();
+        let mut differed = false;
+        while !g.is_terminal() {
+            let rotated = g.transformed(Symmetry::Rotate90);
+            let turn = canonical.make_move(&g);
+            assert!(g.valid_move(turn));
+            assert_eq!(canonical.make_move(&rotated), transform_turn(turn, Symmetry::Rotate90));
+            differed |= plain.make_move(&rotated) != transform_turn(plain.make_move(&g), Symmetry::Rotate90);
+            g.make_move_fast(*g.get_moves().choose(&mut rng).unwrap());
+        }
+        assert!(differed);
+
+        // passes stay passes
+        assert_eq!(canonical.make_move(&white_must_pass()), None);
+    }
+
+    #[test]
+    fn test_phased_ranked_agent() {
+        // the X-tile b2 is only worth taking once a1 is ours
+        let agent = PhasedRankedAgent::new(RankedTables::default());
+        let mut board = Board::new();
+        for (x, y, player) in [(2, 2, Players::White), (3, 3, Players::White), (0, 2, Players::White)] {
+            board.change(x, y, States::Taken(player));
+        }
+        let g = Gamestate::new_from(board, 0);
+        assert_eq!(agent.ranking(&g)[1][1], -50.0);
+        board.change(0, 0, States::Taken(Players::Black));
+        let g = Gamestate::new_from(board, 0);
+        assert_eq!(agent.ranking(&g)[1][1], 0.0);
+        assert_eq!(agent.ranking(&g)[1][6], -50.0);
+
+        let mut tables = RankedTables::default();
+        tables.table_mut(Phase::Endgame)[3][3] = 1000.0;
+        let mut g = Gamestate::new();
+        assert_eq!(Phase::of(&g), Phase::Opening);
+        let mut rng = StdRng::seed_from_u64(2);
+        while Phase::of(&g) != Phase::Endgame {
+            g.make_move_fast(*g.get_moves().choose(&mut rng).unwrap());
+        }
+        assert_eq!(g.board().counts().2, 20);
+        assert_eq!(PhasedRankedAgent::new(tables).ranking(&g)[3][3], 1000.0);
+    }
+
+    #[test]
+    fn test_phased_beats_ranked() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut score = 0.0;
+        for _ in 0..50 {
+            let mut g = Gamestate::new();
+            for _ in 0..6 {
+                g.make_move_fast(*g.get_moves().choose(&mut rng).unwrap());
+            }
+            let mut phased = MemorifiedAgent::new(PhasedRankedAgent::new(RankedTables::default()));
+            let mut ranked = MemorifiedAgent::new(RankedCellAgent::new(WeightedSquareEvaluator::STANDARD_WEIGHTS));
+            for phased_black in [true, false] {
+                let (result, _) = if phased_black {
+                    play_game_from(&mut phased, &mut ranked, g.clone())
+                } else {
+                    play_game_from(&mut ranked, &mut phased, g.clone())
+                };
+                score += match result.winner() {
+                    None => 0.5,
+                    Some(Players::Black) if phased_black => 1.0,
+                    Some(Players::White) if !phased_black => 1.0,
+                    Some(_) => 0.0,
+                };
+            }
+        }
+        assert!(score > 50.0, "{score}");
+    }
+
+    #[test]
+    fn test_ranked_tables_files() {
+        let mut tables = RankedTables::default();
+        tables.table_mut(Phase::Midgame)[2][5] = 0.125;
+        tables.table_mut(Phase::Endgame)[7][0] = -1e-3;
+        let path = std::env::temp_dir().join(format!("othello-tables-{}.txt", std::process::id()));
+        tables.save(&path).unwrap();
+        let loaded = RankedTables::load(&path);
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::write(&path, &contents[..contents.find("endgame").unwrap()]).unwrap();
+        let missing = RankedTables::load(&path);
+        fs::write(&path, contents.replacen("100", "one hundred", 1)).unwrap();
+        let bad = RankedTables::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.unwrap(), tables);
+        assert!(matches!(missing, Err(RankedTablesError::MissingTable(Phase::Endgame))));
+        assert!(matches!(bad, Err(RankedTablesError::BadLine { line: 2, .. })));
+    }
+
+    #[test]
+    fn test_greedy_tie_break() {
+        // Black can take a1 or b4, each flipping one tile
+        let board = Board::from_ascii(
+            " 01234567\n\
+             0.WB.....\n\
+             1........\n\
+             2........\n\
+             3..WB....\n\
+             4........\n\
+             5........\n\
+             6........\n\
+             7........\n"
+        ).unwrap();
+        let g = Gamestate::new_from(board, 0);
+        let moves = g.get_moves();
+        assert_eq!(moves.len(), 2);
+
+        assert_eq!(GreedyAgent::new().make_move(&g), moves[0]);
+        assert_eq!(GreedyAgent::with_tie_break(TieBreak::PreferCorners).make_move(&g), Some((0, 0)));
+        let random = GreedyAgent::from_rng(TieBreak::Random, StdRng::seed_from_u64(0));
+        let mut played: Vec<Turn> = (0..50).map(|_| random.make_move(&g)).collect();
+        played.sort();
+        played.dedup();
+        assert_eq!(played, vec![Some((0, 0)), Some((1, 3))]);
+
+        // a move flipping more always wins, however ties are broken
+        let g = Gamestate::from_transcript("c4c3c2b4").unwrap();
+        let flips = |turn: Turn| {
+            let (x, y) = turn.unwrap();
+            g.board().count_flips(x, y, Players::Black)
+        };
+        let most = g.get_moves().into_iter().map(flips).max().unwrap();
+        assert_eq!(g.get_moves().into_iter().filter(|turn| flips(*turn) == most).count(), 1);
+        for tie_break in [TieBreak::First, TieBreak::Random, TieBreak::PreferCorners] {
+            assert_eq!(flips(GreedyAgent::with_tie_break(tie_break).make_move(&g)), most);
+        }
+    }
+
+    /// Plays a game between two UCT agents with seeded rollouts,
+    /// running a fixed number of cycles per move.
+    fn seeded_uct_game(seed: u64) -> Vec<Turn> {
+        let new_agent = |seed| McstAgent::new(
+            UctSelection::new(2_f64.sqrt()),
+            BfsExpansion {},
+            UctDecision {},
+            RandomAgent::seeded(seed),
+            RandomAgent::seeded(seed + 1),
+            Gamestate::new(),
+        );
+        let mut agents = [new_agent(seed), new_agent(seed + 2)];
+        let mut g = Gamestate::new();
+        let mut turns = Vec::new();
+        while !g.is_terminal() {
+            let agent = &mut agents[turns.len() % 2];
+            agent.set_state(g.clone());
+            for _ in 0..50 {
+                agent.cycle().unwrap();
+            }
+            let turn = agent.decide().unwrap();
+            g.make_move_fast(turn);
+            turns.push(turn);
+        }
+        turns
+    }
+
+    #[test]
+    fn test_seeded_uct_reproducible() {
+        let turns = seeded_uct_game(7);
+        assert_eq!(seeded_uct_game(7), turns);
+
+        let a = RandomAgent::seeded(3);
+        let b = RandomAgent::seeded(3);
+        let mut g = Gamestate::new();
+        while !g.is_terminal() {
+            assert_eq!(a.random_playout(&g), b.random_playout(&g));
+            let turn = a.make_move(&g);
+            assert_eq!(b.make_move(&g), turn);
+            g.make_move_fast(turn);
+        }
+    }
+
+    #[test]
+    fn test_cycle_budget_reproducible() {
+        let new_agent = |seed| McstMemoryAgent::with_budget(
+            McstAgent::new(
+                UctSelection::new(2_f64.sqrt()),
+                BfsExpansion {},
+                UctDecision {},
+                RandomAgent::seeded(seed),
+                RandomAgent::seeded(seed + 1),
+                Gamestate::new(),
+            ),
+            Budget::Cycles(40),
+        );
+        let play = || {
+            let (mut black, mut white) = (new_agent(1), new_agent(3));
+            let (result, turns) = play_game(&mut black, &mut white);
+            assert_eq!(black.cycles(), 40 * turns.len().div_ceil(2) as u64);
+            format!("{result:?} {turns:?}")
+        };
+        assert_eq!(play(), play());
+    }
+
+    /// Checks that every rollout through `node` is accounted for once,
+    /// with no virtual losses left behind.
+    fn check_visits(node: McstNode, root: bool) {
+        assert!((0.0..=f64::from(*node.total())).contains(node.wins()));
+        if !node.game().is_terminal() {
+            let below: u32 = node.children().values().map(|child| *child.total()).sum();
+            assert_eq!(*node.total(), below + u32::from(!root));
+        }
+        for child in node.children().values() {
+            check_visits(child, false);
+        }
+    }
+
+    fn same_tree(a: McstNode, b: McstNode) -> bool {
+        a.wins() == b.wins() && a.total() == b.total() && a.children().len() == b.children().len()
+            && a.children().iter().all(|(turn, child)| {
+                b.children().get(turn).is_some_and(|other| same_tree(child, other))
+            })
+    }
+
+    #[test]
+    fn test_parallel_cycles() {
+        let new_agent = || McstAgent::new(
+            UctSelection::new(2_f64.sqrt()),
+            BfsExpansion {},
+            UctDecision {},
+            RandomAgent::seeded(4),
+            RandomAgent::seeded(5),
+            Gamestate::new(),
+        );
+        let rollouts = |worker: usize| -> Box<dyn RolloutPolicy> {
+            let seed = 10 * worker as u64;
+            Box::new(AgentRollout::new(RandomAgent::seeded(seed), RandomAgent::seeded(seed + 1)))
+        };
+
+        // on one thread, the same cycles are run as without threads
+        let mut serial = new_agent();
+        serial.cycle_n(300).unwrap();
+        let mut single = new_agent();
+        assert_eq!(single.cycle_parallel(1, &rollouts, 300, None).unwrap(), 300);
+        assert!(same_tree(serial.tree().root(), single.tree().root()));
+
+        let mut parallel = new_agent();
+        assert_eq!(parallel.cycle_parallel(4, &rollouts, 2000, None).unwrap(), 2000);
+        assert_eq!(*parallel.tree().root().total(), 2000);
+        check_visits(parallel.tree().root(), true);
+
+        // a game's worth of moves, with the tree kept between them
+        let new_memory_agent = |seed| {
+            let mut agent = McstMemoryAgent::with_budget(
+                McstAgent::new(
+                    UctSelection::new(2_f64.sqrt()),
+                    BfsExpansion {},
+                    UctDecision {},
+                    RandomAgent::seeded(seed),
+                    RandomAgent::seeded(seed + 1),
+                    Gamestate::new(),
+                ),
+                Budget::Cycles(100),
+            );
+            agent.set_threads(4, rollouts);
+            agent
+        };
+        let (mut black, mut white) = (new_memory_agent(1), new_memory_agent(3));
+        let (_, turns) = play_game(&mut black, &mut white);
+        assert_eq!(black.cycles(), 100 * turns.len().div_ceil(2) as u64);
+        assert_eq!(white.cycles(), 100 * (turns.len() / 2) as u64);
+        for child in black.agent().tree().root().children().values() {
+            check_visits(child, false);
+        }
+    }
+
+    #[test]
+    fn test_tree_merge() {
+        let new_agent = |seed| McstAgent::new(
+            UctSelection::new(2_f64.sqrt()),
+            BfsExpansion {},
+            UctDecision {},
+            RandomAgent::seeded(seed),
+            RandomAgent::seeded(seed + 1),
+            Gamestate::new(),
+        );
+        fn doubled(merged: McstNode, node: McstNode) -> bool {
+            *merged.wins() == 2.0 * node.wins() && *merged.total() == 2 * node.total()
+                && merged.children().len() == node.children().len()
+                && node.children().iter().all(|(turn, child)| doubled(merged.children().get(turn).unwrap(), child))
+        }
+
+        let mut agent = new_agent(4);
+        agent.cycle_n(300).unwrap();
+        let mut tree = agent.tree().clone();
+        tree.merge(agent.tree().clone());
+        assert!(doubled(tree.root(), agent.tree().root()));
+
+        let mut other = new_agent(6);
+        other.cycle_n(300).unwrap();
+        let mut merged = agent.tree().clone();
+        merged.merge(other.tree().clone());
+        assert_eq!(*merged.root().total(), 600);
+        assert_eq!(*merged.root().wins(), agent.tree().root().wins() + other.tree().root().wins());
+        assert!(merged.root().node_count() > agent.tree().root().node_count());
+    }
+
+    #[test]
+    fn test_arena_storage() {
+        let mut agent = McstAgent::new(
+            UctSelection::new(2_f64.sqrt()),
+            BfsExpansion {},
+            UctDecision {},
+            RandomAgent::seeded(8),
+            RandomAgent::seeded(9),
+            Gamestate::new(),
+        );
+        agent.cycle_n(2000).unwrap();
+        let boxed = agent.tree().clone();
+        agent.set_node_storage(NodeStorage::Arena);
+        assert_eq!(agent.tree().storage(), NodeStorage::Arena);
+        assert!(same_tree(agent.tree().root(), boxed.root()));
+
+        // advancing leaves the old nodes in the arena until it compacts
+        let mut compacted = false;
+        for _ in 0..5 {
+            let before = agent.tree().arena_len().unwrap();
+            let pv = agent.principal_variation(2);
+            assert!(agent.next_two_moves(pv[0], pv[1]));
+            assert_eq!(agent.tree().arena_len(), Some(before));
+            let size = agent.tree().size();
+            agent.cycle_n(1000).unwrap();
+            compacted |= agent.tree().arena_len().unwrap() < before + agent.tree().size() - size;
+
+            assert_eq!(agent.tree().size(), agent.tree().root().node_count());
+            // the new root was rolled out from when it was expanded
+            check_visits(agent.tree().root(), false);
+            let mut copy = agent.tree().clone();
+            copy.set_storage(NodeStorage::Boxed);
+            assert!(same_tree(copy.root(), agent.tree().root()));
+        }
+        assert!(compacted);
+    }
+
+    #[test]
+    fn test_root_parallel() {
+        // the seeds were picked for boxed nodes, which break ties between
+        // children differently than an arena does
+        let workers = |worker: usize| {
+            let mut agent = McstAgent::new(
+                UctSelection::new(2_f64.sqrt()),
+                BfsExpansion {},
+                UctDecision {},
+                RandomAgent::seeded(2 * worker as u64),
+                RandomAgent::seeded(2 * worker as u64 + 1),
+                Gamestate::new(),
+            );
+            agent.set_node_storage(NodeStorage::Boxed);
+            agent
+        };
+        let parallel = RootParallelAgent::new(workers, 4, Budget::Cycles(100), UctDecision {});
+        let single = RootParallelAgent::new(workers, 1, Budget::Cycles(400), UctDecision {});
+
+        // positions from random games, where the solver knows which moves are best
+        let solver = EndgameSolver::new();
+        let mut rng = StdRng::seed_from_u64(9);
+        let (mut parallel_right, mut single_right) = (0, 0);
+        for _ in 0..60 {
+            let mut g = Gamestate::new();
+            while g.board().counts().2 > 9 && !g.is_terminal() {
+                g.make_move_fast(*g.get_moves().choose(&mut rng).unwrap());
+            }
+            if g.is_terminal() {
+                continue;
+            }
+            let best = solver.solve(&g).0;
+            let right = |turn| {
+                let mut child = g.clone();
+                child.make_move_fast(turn);
+                solver.solve(&child).0 == best
+            };
+
+            let turn = parallel.make_move(&g);
+            assert_eq!(parallel.make_move(&g), turn);
+            parallel_right += usize::from(right(turn));
+            single_right += usize::from(right(single.make_move(&g)));
+        }
+        assert!(parallel_right >= single_right, "{parallel_right} {single_right}");
+    }
+
+    #[test]
+    fn test_mcts_solver() {
+        let solver = EndgameSolver::new();
+        let mut rng = StdRng::seed_from_u64(5);
+        for seed in 0..10 {
+            let mut g = Gamestate::new();
+            while g.board().counts().2 > 8 && !g.is_terminal() {
+                g.make_move_fast(*g.get_moves().choose(&mut rng).unwrap());
+            }
+            if g.is_terminal() {
+                continue;
+            }
+            let mover = g.side_to_move();
+            let outcome = |score: i8| match (score.signum(), mover) {
+                (0, _) => Proven::Draw,
+                (1, Players::Black) | (-1, Players::White) => Proven::Win,
+                _ => Proven::Loss,
+            };
+
+            let mut agent = McstAgent::new(
+                UctSelection::new(2_f64.sqrt()),
+                BfsExpansion {},
+                UctDecision {},
+                RandomAgent::seeded(seed),
+                RandomAgent::seeded(seed + 1),
+                g.clone(),
+            );
+            while agent.tree().root().proof().is_none() {
+                agent.cycle().unwrap();
+            }
+            let proof = agent.tree().root().proof();
+            assert_eq!(proof, Some(outcome(solver.solve(&g).0)), "\n{g}");
+
+            // the move chosen gets what was proven, however many visits it has
+            let turn = agent.decide().unwrap();
+            let mut child = g.clone();
+            child.make_move_fast(turn);
+            assert_eq!(Some(outcome(solver.solve(&child).0)), proof, "\n{g}");
+
+            // proven nodes are no longer rolled out from, nor expanded
+            let nodes = agent.tree().root().node_count();
+            agent.cycle_n(100).unwrap();
+            assert_eq!(agent.tree().root().node_count(), nodes);
+        }
+    }
+
+    /// Returns every node in `tree` for each position.
+    fn positions(tree: &McstTree) -> HashMap<(u128, Players), Vec<McstNode<'_>>> {
+        fn collect<'a>(node: McstNode<'a>, nodes: &mut HashMap<(u128, Players), Vec<McstNode<'a>>>) {
+            let position = (node.game().board().to_compact(), node.game().side_to_move());
+            nodes.entry(position).or_default().push(node);
+            node.children().values().for_each(|child| collect(child, nodes));
+        }
+
+        let mut nodes = HashMap::new();
+        collect(tree.root(), &mut nodes);
+        nodes
+    }
+
+    /// Checks that every node for a position shares the statistics and proof
+    /// of the others, that at most one of them isn't
+    /// [transposed](McstNode::transposed), and that only that one has
+    /// children. Every one may be, if the root has advanced past the node
+    /// which wasn't and no cycle has reached the rest since.
+    fn check_transpositions(tree: &McstTree) {
+        for occurrences in positions(tree).values() {
+            assert!(occurrences.iter().filter(|node| !node.transposed()).count() <= 1);
+            let first = occurrences[0];
+            for node in occurrences {
+                assert_eq!((node.wins(), node.total(), node.proof()), (first.wins(), first.total(), first.proof()));
+                assert!(!node.transposed() || node.children().is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn test_transpositions() {
+        let new_agent = |transpositions: bool, seed, game: Gamestate| {
+            let args = (
+                UctSelection::new(2_f64.sqrt()),
+                BfsExpansion {},
+                UctDecision {},
+                RandomAgent::seeded(seed),
+                RandomAgent::seeded(seed + 1),
+                game,
+            );
+            match transpositions {
+                true => McstAgent::with_transpositions(args.0, args.1, args.2, args.3, args.4, args.5),
+                false => McstAgent::new(args.0, args.1, args.2, args.3, args.4, args.5),
+            }
+        };
+
+        // the same cycles grow fewer nodes
+        let mut rng = StdRng::seed_from_u64(1);
+        for seed in 0..3 {
+            let mut g = Gamestate::new();
+            for _ in 0..30 {
+                g.make_move_fast(*g.get_moves().choose(&mut rng).unwrap());
+            }
+            let mut plain = new_agent(false, seed, g.clone());
+            let mut shared = new_agent(true, seed, g.clone());
+            plain.cycle_n(2000).unwrap();
+            shared.cycle_n(2000).unwrap();
+            assert!(shared.tree().root().node_count() < plain.tree().root().node_count());
+            check_transpositions(shared.tree());
+        }
+
+        // and play no worse
+        let budget = Budget::Cycles(200);
+        let (mut shared_wins, mut plain_wins) = (0, 0);
+        for seed in 0..6 {
+            let mut g = Gamestate::new();
+            for _ in 0..4 {
+                g.make_move_fast(*g.get_moves().choose(&mut rng).unwrap());
+            }
+            for shared_black in [true, false] {
+                let mut shared = McstMemoryAgent::with_budget(new_agent(true, seed, g.clone()), budget);
+                let mut plain = McstMemoryAgent::with_budget(new_agent(false, seed + 10, g.clone()), budget);
+                let (result, _) = match shared_black {
+                    true => play_game_from(&mut shared, &mut plain, g.clone()),
+                    false => play_game_from(&mut plain, &mut shared, g.clone()),
+                };
+                match result.winner() {
+                    Some(Players::Black) if shared_black => shared_wins += 1,
+                    Some(Players::White) if !shared_black => shared_wins += 1,
+                    Some(_) => plain_wins += 1,
+                    None => (),
+                }
+                // the shared statistics survive moving down the tree
+                check_transpositions(shared.agent().tree());
+            }
+        }
+        assert!(shared_wins >= plain_wins, "{shared_wins} {plain_wins}");
+    }
+
+    /// A game where Black has one move, and after White's reply has to pass
+    /// whatever White plays, with the game going on after.
+    fn black_will_pass() -> Gamestate {
+        let board = Board::from_ascii(
+            " 01234567\n\
+             0BBBBBBBB\n\
+             1.B.B.BBB\n\
+             2WBBBBBBB\n\
+             3.BBBBBBB\n\
+             4BBBBBBB.\n\
+             5.W.B.B.B\n\
+             6WWWWWWW.\n\
+             7.W.B.B..\n"
+        ).unwrap();
+        Gamestate::new_from(board, 44)
+    }
+
+    type UctMemoryAgent = McstMemoryAgent<UctSelection, BfsExpansion, UctDecision, AgentRollout<RandomAgent<StdRng>>>;
+
+    fn new_uct_memory_agent(seed: u64) -> UctMemoryAgent {
+        McstMemoryAgent::with_budget(
+            McstAgent::new(
+                UctSelection::new(2_f64.sqrt()),
+                BfsExpansion {},
+                UctDecision {},
+                RandomAgent::seeded(seed),
+                RandomAgent::seeded(seed + 1),
+                Gamestate::new(),
+            ),
+            Budget::Cycles(200),
+        )
+    }
+
+    /// Asserts that the root of `agent`'s tree is `game`.
+    fn assert_root(agent: &UctMemoryAgent, game: &Gamestate) {
+        let root = agent.agent().tree().root().game();
+        assert_eq!(root.board().to_compact(), game.board().to_compact());
+        assert_eq!(root.side_to_move(), game.side_to_move());
+    }
+
+    /// Keeps track of the game its agent is playing, to check that its tree
+    /// does too.
+    struct Synced {
+        agent: UctMemoryAgent,
+        game: Gamestate,
+    }
+
+    impl MemoryAgent for Synced {
+        fn initialize_game(&mut self, state: Gamestate) {
+            self.game = state.clone();
+            self.agent.initialize_game(state);
+        }
+
+        fn opponent_move(&mut self, op: &Turn) {
+            assert!(self.game.make_move_fast(*op));
+            self.agent.opponent_move(op);
+            assert_root(&self.agent, &self.game);
+        }
+
+        fn make_move(&mut self) -> Turn {
+            let turn = self.agent.make_move();
+            assert_root(&self.agent, &self.game);
+            assert!(self.game.make_move_fast(turn));
+            turn
+        }
+    }
+
+    #[test]
+    fn test_advance_through_passes() {
+        let g = black_will_pass();
+        let mut passes = 0;
+        for seed in 0..4 {
+            let mut black = Synced { agent: new_uct_memory_agent(10 * seed), game: Gamestate::new() };
+            let mut white = Synced { agent: new_uct_memory_agent(10 * seed + 5), game: Gamestate::new() };
+            let (_, turns) = play_game_from(&mut black, &mut white, g.clone());
+            assert_eq!(turns[2], None);
+            passes += turns.iter().filter(|turn| turn.is_none()).count();
+        }
+        assert!(passes >= 4);
+
+        // agents keep up with games whose forced passes they aren't asked
+        // to make or told about
+        let (mut black, mut white) = (new_uct_memory_agent(1), new_uct_memory_agent(2));
+        let mut g = g;
+        black.initialize_game(g.clone());
+        white.initialize_game(g.clone());
+        while !g.is_terminal() {
+            if g.get_moves().as_slice() == [None] {
+                g.make_move_fast(None);
+                continue;
+            }
+            let (mover, other) = match g.side_to_move() {
+                Players::Black => (&mut black, &mut white),
+                Players::White => (&mut white, &mut black),
+            };
+            let turn = mover.make_move();
+            assert_root(mover, &g);
+            assert!(g.make_move_fast(turn));
+            other.opponent_move(&turn);
+            assert_root(other, &g);
+        }
+
+        // and reject moves that aren't legal in the game they're playing
+        let mut agent = new_uct_memory_agent(3);
+        agent.initialize_game(Gamestate::new());
+        assert!(matches!(agent.try_opponent_move(&Some((0, 0))), Err(AgentError::InvalidState(_))));
+        assert_root(&agent, &Gamestate::new());
+    }
+
+    /// Asserts that `root`'s tree has been grown breadth first: while any
+    /// node on a level has moves left to expand, none on the next has
+    /// children.
+    fn check_breadth_first(root: McstNode) {
+        let mut level = vec![root];
+        while !level.is_empty() {
+            let next: Vec<McstNode> = level.iter().flat_map(|node| node.children().values()).collect();
+            if level.iter().any(|node| node.children().len() < node.game().move_count()) {
+                assert!(next.iter().all(|node| node.children().is_empty()));
+            }
+            level = next;
+        }
+    }
+
+    #[test]
+    fn test_bfs_selection_restarts() {
+        let mut agent = McstAgent::new(
+            BfsSelectionFast::new(),
+            BfsExpansion {},
+            UctDecision {},
+            RandomAgent::seeded(1),
+            RandomAgent::seeded(2),
+            Gamestate::new(),
+        );
+        let mut g = Gamestate::new();
+        for round in 0..6 {
+            assert_eq!(agent.cycle_n(100).unwrap(), 100);
+            check_breadth_first(agent.tree().root());
+            // by one move and by two, the search goes on from the new root
+            // rather than from paths queued from the old one
+            let mine = agent.decide().unwrap();
+            let mut next = g.clone();
+            next.make_move_fast(mine);
+            let reply = next.get_moves()[0];
+            match round % 2 {
+                0 => assert!(agent.advance(mine) && agent.advance(reply)),
+                _ => assert!(agent.next_two_moves(mine, reply)),
+            }
+            assert!(g.make_moves_fast(&[mine, reply]));
+            assert_eq!(agent.tree().root().game().board().to_compact(), g.board().to_compact());
+        }
+
+        // and likewise from a new game altogether
+        let mut g = Gamestate::new();
+        g.make_moves_fast(&[Some((2, 3)), Some((2, 2)), Some((3, 2))]);
+        agent.set_state(g.clone());
+        assert_eq!(agent.cycle_n(100).unwrap(), 100);
+        assert_eq!(*agent.tree().root().total(), 100);
+        check_breadth_first(agent.tree().root());
+    }
+
+    #[test]
+    fn test_transpositions_left_behind() {
+        // positions which every node for is transposed, and so can't be expanded
+        let unexpanded = |tree: &McstTree| positions(tree).values()
+            .filter(|occurrences| occurrences.iter().all(|node| node.transposed()))
+            .count();
+
+        for storage in [NodeStorage::Boxed, NodeStorage::Arena] {
+            let mut agent = McstAgent::with_transpositions(
+                UctSelection::new(2_f64.sqrt()),
+                BfsExpansion {},
+                UctDecision {},
+                RandomAgent::seeded(3),
+                RandomAgent::seeded(4),
+                Gamestate::from_transcript("f5d6c3d3c4f4").unwrap(),
+            );
+            agent.set_node_storage(storage);
+            agent.cycle_n(5000).unwrap();
+            assert_eq!(unexpanded(agent.tree()), 0);
+
+            let mut left_behind = 0;
+            for _ in 0..2 {
+                let pv = agent.principal_variation(2);
+                assert!(agent.next_two_moves(pv[0], pv[1]));
+                assert_eq!(agent.tree().size(), agent.tree().root().node_count());
+                check_transpositions(agent.tree());
+                let before = unexpanded(agent.tree());
+                left_behind += before;
+
+                // cycles reaching the nodes expand them in place of those left behind
+                agent.cycle_n(3000).unwrap();
+                assert!(before == 0 || unexpanded(agent.tree()) < before);
+                assert_eq!(agent.tree().size(), agent.tree().root().node_count());
+                check_transpositions(agent.tree());
+            }
+            assert!(left_behind > 0);
+        }
+    }
+
+    #[test]
+    fn test_node_limit() {
+        let limit = 150;
+        let new_agent = |seed| {
+            let mut agent = McstAgent::new(
+                UctSelection::new(2_f64.sqrt()),
+                BfsExpansion {},
+                UctDecision {},
+                RandomAgent::seeded(seed),
+                RandomAgent::seeded(seed + 1),
+                Gamestate::new(),
+            );
+            agent.set_node_limit(limit);
+            agent
+        };
+
+        let mut agent = new_agent(1);
+        for _ in 0..2000 {
+            agent.cycle().unwrap();
+            assert!(agent.tree().size() <= limit);
+        }
+        assert_eq!(agent.tree().size(), agent.tree().root().node_count());
+        assert!(Gamestate::new().check_move(agent.decide().unwrap()).is_ok());
+
+        let mut agent = new_agent(3);
+        let rollouts = |thread| -> Box<dyn RolloutPolicy> {
+            Box::new(AgentRollout::new(RandomAgent::seeded(thread as u64), RandomAgent::seeded(thread as u64 + 10)))
+        };
+        agent.cycle_parallel(3, &rollouts, 2000, None).unwrap();
+        assert!(agent.tree().size() <= limit);
+        assert_eq!(agent.tree().size(), agent.tree().root().node_count());
+        assert_eq!(*agent.tree().root().total(), 2000);
+
+        // the limit lasts the whole game, however the tree is rerooted
+        let mut black = McstMemoryAgent::with_node_limit(new_agent(5), Budget::Cycles(400), limit);
+        let mut white = MemorifiedAgent::new(RandomAgent::seeded(7));
+        let mut g = Gamestate::new();
+        black.initialize_game(g.clone());
+        white.initialize_game(g.clone());
+        while !g.is_terminal() {
+            let turn = match g.side_to_move() {
+                Players::Black => {
+                    let turn = black.make_move();
+                    white.opponent_move(&turn);
+                    assert!(black.agent().tree().size() <= limit);
+                    assert_eq!(black.agent().tree().size(), black.agent().tree().root().node_count());
+                    turn
+                }
+                Players::White => {
+                    let turn = white.make_move();
+                    black.opponent_move(&turn);
+                    turn
+                }
+            };
+            assert!(g.make_move_fast(turn));
+        }
+    }
+
+    #[test]
+    fn test_analysis() {
+        let mut rng = StdRng::seed_from_u64(4);
+        let mut g = Gamestate::new();
+        for _ in 0..10 {
+            g.make_move_fast(*g.get_moves().choose(&mut rng).unwrap());
+        }
+        let new_agent = |seed, game| McstAgent::new(
+            UctSelection::new(2_f64.sqrt()),
+            BfsExpansion {},
+            UctDecision {},
+            RandomAgent::seeded(seed),
+            RandomAgent::seeded(seed + 1),
+            game,
+        );
+        let mut agent = new_agent(1, g.clone());
+        agent.cycle_n(500).unwrap();
+
+        let stats = agent.root_stats();
+        assert_eq!(stats.len(), g.move_count());
+        assert!(stats.windows(2).all(|pair| pair[0].visits >= pair[1].visits));
+        // the root is never rolled out from itself, since it has moves
+        assert_eq!(stats.iter().map(|stats| stats.visits).sum::<u32>(), *agent.tree().root().total());
+        assert!(stats.iter().all(|stats| (0.0..=1.0).contains(&stats.win_rate)));
+
+        let pv = agent.principal_variation(10);
+        assert_eq!(pv.first(), Some(&stats[0].turn));
+        assert!(!pv.is_empty() && pv.len() <= 10);
+        let mut line = g.clone();
+        for turn in &pv {
+            assert!(line.make_move_fast(*turn));
+        }
+        assert!(agent.principal_variation(0).is_empty());
+
+        // each ply the agent plays is recorded with what it thought
+        let mut black = McstMemoryAgent::with_budget(new_agent(3, Gamestate::new()), Budget::Cycles(100));
+        let mut white = MemorifiedAgent::new(RandomAgent::seeded(5));
+        let record = play_game_recorded(&mut black, &mut white, Gamestate::new(), ("mcst", "random"));
+        let mut g = Gamestate::new();
+        for ply in &record.plies {
+            let mover = g.side_to_move();
+            match &ply.analysis {
+                Some(analysis) => {
+                    assert_eq!(mover, Players::Black);
+                    assert!(analysis.pv.len() <= McstMemoryAgent::<UctSelection, BfsExpansion, UctDecision, AgentRollout<RandomAgent>>::PV_LENGTH);
+                    assert!(g.clone().make_moves_fast(&analysis.pv));
+                    assert_eq!(analysis.moves.len(), g.move_count());
+                }
+                None => assert_eq!(mover, Players::White),
+            }
+            g.make_move_fast(ply.turn);
+        }
+        assert!(record.to_json().contains("\"pv\":["));
+    }
+
+    #[test]
+    fn test_tree_save_load() {
+        let mut rng = StdRng::seed_from_u64(6);
+        let mut g = Gamestate::new();
+        for _ in 0..40 {
+            g.make_move_fast(*g.get_moves().choose(&mut rng).unwrap());
+        }
+        let new_agent = |transpositions: bool| {
+            let args = (
+                UctSelection::new(2_f64.sqrt()),
+                BfsExpansion {},
+                UctDecision {},
+                RandomAgent::seeded(1),
+                RandomAgent::seeded(2),
+                g.clone(),
+            );
+            match transpositions {
+                true => McstAgent::with_transpositions(args.0, args.1, args.2, args.3, args.4, args.5),
+                false => McstAgent::new(args.0, args.1, args.2, args.3, args.4, args.5),
+            }
+        };
+
+        for transpositions in [false, true] {
+            let mut agent = new_agent(transpositions);
+            agent.cycle_n(1000).unwrap();
+            let mut saved = Vec::new();
+            agent.tree().save(&mut saved).unwrap();
+
+            let tree = McstTree::load(saved.as_slice()).unwrap();
+            assert_eq!(tree.root().game(), agent.tree().root().game());
+            assert!(same_tree(tree.root(), agent.tree().root()));
+            assert_eq!(tree.root().node_count(), agent.tree().root().node_count());
+            assert_eq!(tree.size(), agent.tree().size());
+            assert_eq!(tree.root().proof(), agent.tree().root().proof());
+            assert_eq!(tree.shares_transpositions(), transpositions);
+            if transpositions {
+                check_transpositions(&tree);
+            }
+
+            let mut reloaded = McstAgent::with_tree(
+                tree,
+                UctSelection::new(2_f64.sqrt()),
+                BfsExpansion {},
+                UctDecision {},
+                RandomAgent::seeded(1),
+                RandomAgent::seeded(2),
+            );
+            assert_eq!(reloaded.root_stats(), agent.root_stats());
+            reloaded.cycle_n(100).unwrap();
+            assert_eq!(*reloaded.tree().root().total(), *agent.tree().root().total() + 100);
+        }
+
+        let mut saved = Vec::new();
+        McstTree::new(g.clone()).save(&mut saved).unwrap();
+        assert!(matches!(McstTree::load(&saved[..saved.len() - 1]), Err(TreeIoError::Io(_))));
+        assert!(matches!(McstTree::load(&b"MCTS"[..]), Err(TreeIoError::Io(_))));
+        saved[0] = b'X';
+        assert!(matches!(McstTree::load(saved.as_slice()), Err(TreeIoError::BadHeader)));
+    }
+
+    #[test]
+    fn test_noisy_decision() {
+        let mut agent = McstAgent::new(
+            UctSelection::new(2_f64.sqrt()),
+            BfsExpansion {},
+            UctDecision {},
+            RandomAgent::seeded(4),
+            RandomAgent::seeded(5),
+            Gamestate::new(),
+        );
+        for _ in 0..200 {
+            agent.cycle().unwrap();
+        }
+        let tree = agent.tree();
+        let best = UctDecision {}.decide(tree);
+
+        let mut noiseless = NoisyDecision::from_rng(UctDecision {}, 0.3, 0.0, StdRng::seed_from_u64(1));
+        let mut disabled = NoisyDecision::from_rng(UctDecision {}, 0.3, 0.25, StdRng::seed_from_u64(1));
+        disabled.set_enabled(false);
+        for _ in 0..20 {
+            assert_eq!(noiseless.decide(tree), best);
+            assert_eq!(disabled.decide(tree), best);
+        }
+
+        // with nothing but noise, every move is as likely as any other
+        let mut noisy = NoisyDecision::from_rng(UctDecision {}, 0.3, 1.0, StdRng::seed_from_u64(2));
+        let mut counts: HashMap<Turn, u32> = HashMap::new();
+        for _ in 0..4000 {
+            *counts.entry(noisy.decide(tree)).or_default() += 1;
+        }
+        assert_eq!(counts.len(), 4);
+        assert!(counts.values().all(|&count| count.abs_diff(1000) < 100), "{counts:?}");
+
+        // the noise itself has the right mean
+        for shape in [0.3, 2.5] {
+            let mean = (0..20_000).map(|_| noisy.gamma(shape)).sum::<f64>() / 20_000.0;
+            assert!((mean - shape).abs() < shape * 0.05, "{shape} {mean}");
+        }
+    }
+
+    #[test]
+    fn test_uct_options() {
+        let g = Gamestate::new();
+        let moves = g.get_moves();
+        let (a, b, c, d) = (moves[0], moves[1], moves[2], moves[3]);
+        let select = |selector: &mut UctSelection, tree: &McstTree| selector.select(tree).unwrap();
+
+        // A has only been drawn from, B and C won or lost half and two fifths
+        // of the time, and D is worse than C
+        let mut tree = McstTree::new(g.clone());
+        tree.set_stats(&[], 4800.0, 3700.0, 10_000);
+        for (turn, wins, squares, total) in [(a, 2500.0, 1250.0, 5000), (b, 2000.0, 2000.0, 4000), (c, 200.0, 200.0, 500), (d, 190.0, 190.0, 500)] {
+            tree.add_child(&[], turn);
+            tree.set_stats(&[turn], wins, squares, total);
+        }
+        assert_eq!(tree.root().children().get(&a).unwrap().variance(), 0.0);
+        assert!((tree.root().children().get(&b).unwrap().variance() - 0.25).abs() < 1e-12);
+
+        // UCB1 explores the least visited move that isn't the worst: C scores
+        // 0.4 + √2·√(ln 10000 / 500) ≈ 0.592, against about 0.568 for B
+        assert_eq!(select(&mut UctSelection::new(2_f64.sqrt()), &tree), vec![c]);
+        // UCB1-Tuned only explores by as much as a move's rollouts vary: B
+        // scores 0.5 + √2·√(ln 10000 / 4000 · 1/4) ≈ 0.534, against about 0.515
+        // for A, whose rollouts never vary, and 0.496 for C
+        let tuned = UctOptions { tuned: true, ..UctOptions::default() };
+        assert_eq!(select(&mut UctSelection::with_options(2_f64.sqrt(), tuned), &tree), vec![b]);
+
+        // with A winning 7 of 10 rollouts and B 2 of 10, and two moves unexpanded
+        let mut tree = McstTree::new(g.clone());
+        tree.set_stats(&[], 9.0, 7.0, 20);
+        for (turn, wins, total) in [(a, 7.0, 10), (b, 2.0, 10)] {
+            tree.add_child(&[], turn);
+            tree.set_stats(&[turn], wins, wins, total);
+        }
+        // plain UCT expands the root first
+        assert_eq!(select(&mut UctSelection::new(0.1), &tree), vec![]);
+        // A's value of 0.7 beats exploring at an urgency of 0.5 but not at 0.9
+        let urgent = |value| UctOptions { first_play_urgency: Some(value), ..UctOptions::default() };
+        assert_eq!(select(&mut UctSelection::with_options(0.1, urgent(0.5)), &tree), vec![a]);
+        assert_eq!(select(&mut UctSelection::with_options(0.1, urgent(0.9)), &tree), vec![]);
+        // nor at 0.72, though A scores 0.7 + 0.1·√(ln 20 / 10) ≈ 0.755 with
+        // its exploration term
+        assert_eq!(select(&mut UctSelection::with_options(0.1, urgent(0.72)), &tree), vec![]);
+
+        // an unvisited child scores the urgency rather than being tried first
+        tree.add_child(&[], c);
+        tree.add_child(&[], d);
+        assert_eq!(select(&mut UctSelection::with_options(0.1, urgent(0.5)), &tree), vec![a]);
+        assert_eq!(select(&mut UctSelection::new(0.1), &tree).len(), 1);
+        assert!([c, d].contains(&select(&mut UctSelection::new(0.1), &tree)[0]));
+
+        // either way, searches still find moves and prove what they can
+        for options in [tuned, urgent(0.5), UctOptions { tuned: true, first_play_urgency: Some(0.0) }] {
+            let mut agent = McstAgent::new(
+                UctSelection::with_options(2_f64.sqrt(), options),
+                BfsExpansion {},
+                UctDecision {},
+                RandomAgent::seeded(1),
+                RandomAgent::seeded(2),
+                g.clone(),
+            );
+            agent.cycle_n(500).unwrap();
+            assert_eq!(*agent.tree().root().total(), 500);
+            assert!(g.valid_move(agent.decide().unwrap()));
+        }
+    }
+
+    /// Expands moves in order, giving the first of them half the prior and
+    /// sharing the rest out evenly.
+    struct SkewedExpansion {}
+
+    impl ExpansionPolicy for SkewedExpansion {
+        fn expand(&mut self, tree: &McstTree, path: &Vec<Turn>) -> Turn {
+            BfsExpansion {}.expand(tree, path)
+        }
+
+        fn prior(&mut self, tree: &McstTree, path: &[Turn], turn: Turn) -> f32 {
+            let moves = tree.root().search(path).unwrap().game().get_moves();
+            match (moves.len(), moves[0] == turn) {
+                (1, _) => 1.0,
+                (_, true) => 0.5,
+                (count, false) => 0.5 / (count - 1) as f32,
+            }
+        }
+    }
+
+    #[test]
+    fn test_puct_selection() {
+        // with every move as likely, PUCT prefers whatever has won more
+        // among moves visited as often, and whatever has been visited less
+        // among moves that have won as often, like UCT
+        let g = Gamestate::new();
+        let moves = g.get_moves();
+        let mut tree = McstTree::new(g.clone());
+        tree.set_stats(&[], 40.0, 40.0, 80);
+        for (turn, wins, total) in [(moves[0], 12.0, 20), (moves[1], 8.0, 20), (moves[2], 5.0, 10), (moves[3], 15.0, 30)] {
+            tree.add_child(&[], turn);
+            tree.set_stats(&[turn], wins, wins, total);
+        }
+        assert!(tree.root().children().values().all(|child| child.prior() == 0.25));
+        for c in [0.25, 0.5] {
+            let mut puct = PuctSelection::new(c);
+            assert_eq!(puct.select(&tree), Some(vec![moves[0]]));
+        }
+        tree.set_stats(&[moves[0]], 6.0, 6.0, 20);
+        tree.set_stats(&[moves[1]], 6.0, 6.0, 20);
+        tree.set_stats(&[moves[2]], 3.0, 3.0, 10);
+        tree.set_stats(&[moves[3]], 9.0, 9.0, 30);
+        assert_eq!(PuctSelection::new(1.0).select(&tree), Some(vec![moves[2]]));
+        assert_eq!(UctSelection::new(1.0).select(&tree), Some(vec![moves[2]]));
+
+        // a node with unexpanded moves is descended through once a child
+        // outscores what the rest of the prior could: with one move of four
+        // expanded and winning 9 of 10, it scores 0.9 + c · 1/4 · √10 / 11
+        // against c · 3/4 · √10 for an unexpanded move
+        let mut tree = McstTree::new(g.clone());
+        tree.set_stats(&[], 9.0, 9.0, 10);
+        tree.add_child(&[], moves[0]);
+        tree.set_stats(&[moves[0]], 9.0, 9.0, 10);
+        assert_eq!(PuctSelection::new(0.25).select(&tree), Some(vec![moves[0]]));
+        assert_eq!(PuctSelection::new(1.0).select(&tree), Some(vec![]));
+
+        // and otherwise explores the likelier moves more
+        let mut agent = McstAgent::new(
+            PuctSelection::new(1.5),
+            SkewedExpansion {},
+            UctDecision {},
+            RandomAgent::seeded(1),
+            RandomAgent::seeded(2),
+            g.clone(),
+        );
+        // the seeds were picked for boxed nodes, which break ties between
+        // children differently than an arena does
+        agent.set_node_storage(NodeStorage::Boxed);
+        agent.cycle_n(400).unwrap();
+        let root = agent.tree().root();
+        let priors: f32 = root.children().values().map(McstNode::prior).sum();
+        assert!((priors - 1.0).abs() < 1e-6);
+        assert_eq!(root.children().get(&moves[0]).unwrap().prior(), 0.5);
+        let favored = *root.children().get(&moves[0]).unwrap().total();
+        assert!(root.children().values().all(|child| *child.total() <= favored));
+        // every fully expanded node's priors add up to 1
+        fn check_priors(node: McstNode) {
+            if node.children().len() == node.game().move_count() && !node.children().is_empty() {
+                let priors: f32 = node.children().values().map(McstNode::prior).sum();
+                assert!((priors - 1.0).abs() < 1e-5, "{priors}");
+            }
+            node.children().values().for_each(check_priors);
+        }
+        check_priors(root);
+    }
+
+    /// Scores a game by the share of the discs on the board that are `me`'s.
+    struct DiscRollout {}
+
+    impl RolloutPolicy for DiscRollout {
+        fn rollout(&self, game: Gamestate, me: Players) -> Result<f64, RolloutError> {
+            let board = game.board();
+            Ok(f64::from(board.count(me)) / f64::from(board.count(Players::Black) + board.count(Players::White)))
+        }
+    }
+
+    #[test]
+    fn test_rollout_policy() {
+        // agents given their rollout agents search just as those given the
+        // same agents as a rollout policy
+        let new_agent = |policy: bool| {
+            let (selector, expander, decider) = (UctSelection::new(2_f64.sqrt()), BfsExpansion {}, UctDecision {});
+            let (rollout, opponent) = (RandomAgent::seeded(1), RandomAgent::seeded(2));
+            match policy {
+                true => McstAgent::with_rollout(selector, expander, decider, AgentRollout::new(rollout, opponent), Gamestate::new()),
+                false => McstAgent::new(selector, expander, decider, rollout, opponent, Gamestate::new()),
+            }
+        };
+        let (mut agents, mut policy) = (new_agent(false), new_agent(true));
+        agents.cycle_n(500).unwrap();
+        policy.cycle_n(500).unwrap();
+        assert!(same_tree(agents.tree().root(), policy.tree().root()));
+
+        // and other policies needn't play the game out at all
+        let mut agent = McstAgent::with_rollout(
+            UctSelection::new(2_f64.sqrt()),
+            BfsExpansion {},
+            UctDecision {},
+            DiscRollout {},
+            Gamestate::new(),
+        );
+        agent.cycle_n(500).unwrap();
+        check_visits(agent.tree().root(), true);
+        // leaves visited once were scored by their own position
+        fn check_leaves(node: McstNode) {
+            if node.children().is_empty() && *node.total() == 1 {
+                assert_eq!(*node.wins(), DiscRollout {}.rollout(node.game().clone(), Players::Black).unwrap());
+            }
+            node.children().values().for_each(check_leaves);
+        }
+        check_leaves(agent.tree().root());
+    }
+
+    /// Rewards a game as won by whoever a [WeightedSquareEvaluator] of disc
+    /// and mobility differences favors.
+    struct StaticRollout {}
+
+    impl RolloutPolicy for StaticRollout {
+        fn rollout(&self, game: Gamestate, me: Players) -> Result<f64, RolloutError> {
+            let lead = match game.is_terminal() {
+                true => f64::from(game.score()),
+                false => WeightedSquareEvaluator::discs_and_mobility().evaluate(&game),
+            };
+            let lead = if me == Players::Black { lead } else { -lead };
+            Ok(if lead > 0.0 { 1.0 } else if lead == 0.0 { 0.5 } else { 0.0 })
+        }
+    }
+
+    #[test]
+    fn test_capped_rollouts() {
+        let new_agent = |rollout: AgentRollout<RandomAgent<StdRng>>, game: Gamestate| {
+            McstAgent::with_rollout(UctSelection::new(2_f64.sqrt()), BfsExpansion {}, UctDecision {}, rollout, game)
+        };
+        let seeded = |seed| (RandomAgent::seeded(seed), RandomAgent::seeded(seed + 1));
+
+        // with no plies, rollouts just judge the game as it is, which for
+        // the opening is a draw
+        let rollout = AgentRollout::capped(seeded(1).0, seeded(1).1, 0);
+        for me in [Players::Black, Players::White] {
+            assert_eq!(rollout.rollout(Gamestate::new(), me).unwrap(), 0.5);
+        }
+        let mut rng = StdRng::seed_from_u64(2);
+        let mut g = Gamestate::new();
+        while !g.is_terminal() {
+            for me in [Players::Black, Players::White] {
+                assert_eq!(rollout.rollout(g.clone(), me).unwrap(), StaticRollout {}.rollout(g.clone(), me).unwrap());
+            }
+            g.make_move_fast(*g.get_moves().choose(&mut rng).unwrap());
+        }
+        let (a, b) = seeded(3);
+        let mut capped = new_agent(AgentRollout::capped(a, b, 0), Gamestate::new());
+        let mut judged = McstAgent::with_rollout(
+            UctSelection::new(2_f64.sqrt()),
+            BfsExpansion {},
+            UctDecision {},
+            StaticRollout {},
+            Gamestate::new(),
+        );
+        capped.cycle_n(500).unwrap();
+        judged.cycle_n(500).unwrap();
+        assert!(same_tree(capped.tree().root(), judged.tree().root()));
+
+        // with more plies than the game can last, they play it out as usual
+        let (a, b) = seeded(5);
+        let mut capped = new_agent(AgentRollout::capped(a, b, 120), Gamestate::new());
+        let (a, b) = seeded(5);
+        let mut uncapped = new_agent(AgentRollout::new(a, b), Gamestate::new());
+        capped.cycle_n(500).unwrap();
+        uncapped.cycle_n(500).unwrap();
+        assert!(same_tree(capped.tree().root(), uncapped.tree().root()));
+
+        // and in between, they stop part of the way
+        let (a, b) = seeded(7);
+        let mut capped = new_agent(AgentRollout::capped(a, b, 10), Gamestate::new());
+        assert_eq!(capped.cycle_n(500).unwrap(), 500);
+        check_visits(capped.tree().root(), true);
+    }
+
+    /// Plays the first legal move, counting its moves and checking that it
+    /// only ever moves for `side`.
+    struct OneSided<'a> {
+        side: Players,
+        moves: &'a Cell<usize>,
+    }
+
+    impl Agent for OneSided<'_> {
+        fn make_move(&self, state: &Gamestate) -> Turn {
+            assert_eq!(state.side_to_move(), self.side);
+            self.moves.set(self.moves.get() + 1);
+            state.get_moves()[0]
+        }
+    }
+
+    #[test]
+    fn test_asymmetric_rollouts() {
+        let g = Gamestate::from_transcript("f5d6c3").unwrap();
+        for me in [Players::Black, Players::White] {
+            // the root player's moves go to one agent and the rest to
+            // another of a different type
+            let (mine, theirs) = (Cell::new(0), Cell::new(0));
+            let random = RandomAgent::seeded(1);
+            let opponent = |state: &Gamestate| {
+                assert_ne!(state.side_to_move(), me);
+                theirs.set(theirs.get() + 1);
+                random.make_move(state)
+            };
+            let rollout = AgentRollout::new(OneSided { side: me, moves: &mine }, opponent);
+            let (reward, plies) = rollout.rollout_plies(g.clone(), me).unwrap();
+            assert!([0.0, 0.5, 1.0].contains(&reward));
+            assert_eq!(plies, Some(mine.get() + theirs.get()));
+            assert!(mine.get() > 0 && theirs.get() > 0);
+        }
+
+        // a random opponent doesn't let a rollout agent which isn't random
+        // leave the whole game to random play
+        let mine = Cell::new(0);
+        let rollout = AgentRollout::new(OneSided { side: Players::Black, moves: &mine }, RandomAgent::seeded(2));
+        assert!(rollout.rollout_plies(Gamestate::new(), Players::Black).unwrap().1.is_some());
+        assert!(mine.get() > 0);
+        // while random ones count the plies of the random playout
+        let rollout = AgentRollout::new(RandomAgent::seeded(3), RandomAgent::seeded(4));
+        assert!(rollout.rollout_plies(Gamestate::new(), Players::Black).unwrap().1.is_some_and(|plies| plies >= 60));
+
+        // and a search can roll out with them
+        let mine = Cell::new(0);
+        let mut agent = McstAgent::new(
+            UctSelection::new(2_f64.sqrt()),
+            BfsExpansion {},
+            UctDecision {},
+            OneSided { side: Players::Black, moves: &mine },
+            RandomAgent::seeded(5),
+            Gamestate::new(),
+        );
+        assert_eq!(agent.cycle_n(200).unwrap(), 200);
+        check_visits(agent.tree().root(), true);
+        assert!(mine.get() > 0);
+    }
+
+    fn new_expanding_agent<E: ExpansionPolicy>(
+        expander: E,
+        seed: u64,
+    ) -> SymmetricMcstAgent<UctSelection, E, UctDecision, RandomAgent<StdRng>> {
+        McstAgent::new(
+            UctSelection::new(2_f64.sqrt()),
+            expander,
+            UctDecision {},
+            RandomAgent::seeded(seed),
+            RandomAgent::seeded(seed + 1),
+            Gamestate::new(),
+        )
+    }
+
+    /// Checks the visits of a tree grown by [FullExpansion]: a node's visits
+    /// are those of its children, plus one if it was rolled out from itself,
+    /// and a node with children has every move as one.
+    fn check_full_visits(node: McstNode, root: bool) {
+        if !node.game().is_terminal() {
+            let below: u32 = node.children().values().map(|child| *child.total()).sum();
+            assert!(*node.total() == below || (!root && *node.total() == below + 1));
+            assert!(node.children().is_empty() || node.children().len() == node.game().move_count());
+        }
+        for child in node.children().values() {
+            check_full_visits(child, false);
+        }
+    }
+
+    #[test]
+    fn test_full_expansion() {
+        let moves = Gamestate::new().get_moves();
+        let mut agent = new_expanding_agent(FullExpansion {}, 1);
+        agent.cycle().unwrap();
+        let root = agent.tree().root();
+        assert_eq!(root.children().len(), moves.len());
+        assert_eq!(agent.tree().size(), moves.len() + 1);
+        for (turn, child) in root.children() {
+            assert_eq!(*child.total(), u32::from(*turn == moves[0]));
+        }
+
+        // every cycle after the first expands one of the unvisited moves
+        assert_eq!(agent.cycle_n(499).unwrap(), 499);
+        assert_eq!(*agent.tree().root().total(), 500);
+        assert_eq!(agent.tree().size(), agent.tree().root().node_count());
+        check_full_visits(agent.tree().root(), true);
+
+        let mut agent = McstAgent::new(
+            PuctSelection::new(1.0),
+            FullExpansion {},
+            UctDecision {},
+            RandomAgent::seeded(3),
+            RandomAgent::seeded(4),
+            Gamestate::new(),
+        );
+        assert_eq!(agent.cycle_n(500).unwrap(), 500);
+        assert_eq!(*agent.tree().root().total(), 500);
+        check_full_visits(agent.tree().root(), true);
+
+        // a limited tree makes room for all of the moves at once
+        let limit = 150;
+        let mut agent = new_expanding_agent(FullExpansion {}, 5);
+        agent.set_node_limit(limit);
+        for _ in 0..1000 {
+            agent.cycle().unwrap();
+            assert!(agent.tree().size() <= limit);
+        }
+        assert_eq!(agent.tree().size(), agent.tree().root().node_count());
+    }
+
+    /// Expands whichever moves it is given for each expansion in turn,
+    /// whether they can be or not.
+    struct ListExpansion {
+        expansions: VecDeque<Vec<Turn>>,
+    }
+
+    impl ExpansionPolicy for ListExpansion {
+        fn expand(&mut self, tree: &McstTree, path: &Vec<Turn>) -> Turn {
+            self.expand_all(tree, path)[0]
+        }
+
+        fn expand_all(&mut self, _tree: &McstTree, _path: &Vec<Turn>) -> Vec<Turn> {
+            self.expansions.pop_front().expect("no more expansions")
+        }
+    }
+
+    #[test]
+    fn test_expansion_errors() {
+        let moves = Gamestate::new().get_moves();
+        // expands `expanded` in one cycle, if there are any, then `moves` in the next
+        let expand = |expanded: &[Turn], moves: &[Turn]| {
+            let mut expansions = VecDeque::from([moves.to_vec()]);
+            if !expanded.is_empty() {
+                expansions.push_front(expanded.to_vec());
+            }
+            let mut agent = new_expanding_agent(ListExpansion { expansions }, 1);
+            agent.cycle_n(usize::from(!expanded.is_empty())).unwrap();
+            let error = agent.cycle().unwrap_err();
+            // nothing is expanded if any move can't be
+            assert_eq!(agent.tree().size(), expanded.len() + 1);
+            assert_eq!(agent.tree().root().children().len(), expanded.len());
+            error
+        };
+
+        assert!(matches!(expand(&[], &[]), CycleError::Expansion(ExpansionError::NoMoves)));
+        assert!(matches!(
+            expand(&[], &[moves[0], Some((0, 0))]),
+            CycleError::Expansion(ExpansionError::IllegalMove(Some((0, 0)), _)),
+        ));
+        assert!(matches!(
+            expand(&[], &[moves[0], moves[1], moves[0]]),
+            CycleError::Expansion(ExpansionError::AlreadyExpanded(turn)) if turn == moves[0],
+        ));
+        assert!(matches!(
+            expand(&[moves[1]], &[moves[0], moves[1]]),
+            CycleError::Expansion(ExpansionError::AlreadyExpanded(turn)) if turn == moves[1],
+        ));
+    }
+
+    #[test]
+    fn test_random_expansion() {
+        let mut a = new_expanding_agent(RandomExpansion::seeded(2), 1);
+        let mut b = new_expanding_agent(RandomExpansion::seeded(2), 1);
+        a.cycle_n(500).unwrap();
+        b.cycle_n(500).unwrap();
+        assert!(same_tree(a.tree().root(), b.tree().root()));
+        check_visits(a.tree().root(), true);
+
+        // the first move expanded isn't always the same one
+        let firsts: HashSet<Turn> = (0..20).map(|seed| {
+            let mut agent = new_expanding_agent(RandomExpansion::seeded(seed), 1);
+            agent.cycle().unwrap();
+            *agent.tree().root().children().keys().next().unwrap()
+        }).collect();
+        assert!(firsts.len() > 1);
+    }
+
+    /// Plays the expansion policies against each other at a few cycle
+    /// budgets, for comparing how they affect strength.
+    #[test]
+    #[ignore = "plays dozens of games; run with --ignored --nocapture to see the results"]
+    fn test_expansion_strength() {
+        for cycles in [200, 1000] {
+            let agents: Vec<(&str, Box<dyn MemoryAgent>)> = vec![
+                ("bfs", Box::new(McstMemoryAgent::with_budget(
+                    new_expanding_agent(BfsExpansion {}, 1),
+                    Budget::Cycles(cycles),
+                ))),
+                ("random", Box::new(McstMemoryAgent::with_budget(
+                    new_expanding_agent(RandomExpansion::seeded(2), 3),
+                    Budget::Cycles(cycles),
+                ))),
+                ("full", Box::new(McstMemoryAgent::with_budget(
+                    new_expanding_agent(FullExpansion {}, 5),
+                    Budget::Cycles(cycles),
+                ))),
+            ];
+            let result = round_robin(agents, 10, true);
+            assert_eq!(result.games().len(), 60);
+            println!("{cycles} cycles:\n{result}");
+        }
+    }
+
+    /// Draws the game `draw` every time and, of every other game, wins two
+    /// in every five for the root player.
+    struct DrawOrGamble {
+        draw: Gamestate,
+        gambles: Cell<u32>,
+    }
+
+    impl RolloutPolicy for DrawOrGamble {
+        fn rollout(&self, game: Gamestate, me: Players) -> Result<f64, RolloutError> {
+            self.rollout_drawn_at(game, me, 0.5).map(|(reward, _)| reward)
+        }
+
+        fn rollout_drawn_at(&self, game: Gamestate, _me: Players, draw: f64) -> Result<(f64, Option<usize>), RolloutError> {
+            if game == self.draw {
+                return Ok((draw, None));
+            }
+            let gamble = self.gambles.get();
+            self.gambles.set(gamble + 1);
+            Ok((if gamble % 5 < 2 { 1.0 } else { 0.0 }, None))
+        }
+    }
+
+    /// Judges every game level without playing it out, so never to be a draw.
+    struct LevelRollout {}
+
+    impl RolloutPolicy for LevelRollout {
+        fn rollout(&self, _game: Gamestate, _me: Players) -> Result<f64, RolloutError> {
+            Ok(0.5)
+        }
+    }
+
+    /// Decides the opening move by `decider` with `contempt`, when the first
+    /// move draws and the others win 40% of the time.
+    fn contempt_decision<D: DecisionPolicy>(decider: D, contempt: f64) -> Turn {
+        let drawing = Gamestate::new().get_moves()[0];
+        let mut draw = Gamestate::new();
+        draw.make_move_fast(drawing);
+        let mut agent = McstAgent::with_rollout(
+            UctSelection::new(2_f64.sqrt()),
+            BfsExpansion {},
+            decider,
+            DrawOrGamble { draw, gambles: Cell::new(0) },
+            Gamestate::new(),
+        );
+        // only the opening moves are ever expanded
+        agent.set_node_limit(1 + Gamestate::new().move_count());
+        agent.set_contempt(contempt);
+        agent.cycle_n(4000).unwrap();
+        agent.decide().unwrap()
+    }
+
+    #[test]
+    fn test_contempt() {
+        let drawing = Gamestate::new().get_moves()[0];
+        assert_eq!(contempt_decision(UctDecision {}, 0.0), drawing);
+        assert_eq!(contempt_decision(WinAverageDecision {}, 0.0), drawing);
+        assert_ne!(contempt_decision(UctDecision {}, 0.2), drawing);
+        assert_ne!(contempt_decision(WinAverageDecision {}, 0.2), drawing);
+        // and negative contempt makes it all the more attractive
+        assert_eq!(contempt_decision(UctDecision {}, -0.2), drawing);
+
+        // games played out to a draw and nodes proven drawn are worth less,
+        // but a game judged level isn't taken for a draw
+        let rollout = AgentRollout::new(RandomAgent::seeded(1), RandomAgent::seeded(2));
+        let drawn = Gamestate::new_from(Board::new(), 0);
+        assert_eq!(rollout.rollout_drawn_at(drawn, Players::Black, 0.3).unwrap().0, 0.3);
+        // Black's only move, h8, flips g8 to draw 32 all
+        let board = Board::from_ascii(
+            " 01234567\n\
+             0WWWWWWWW\n\
+             1WWWWWWWW\n\
+             2WWWWWWWW\n\
+             3WWWWWWWW\n\
+             4BBBBBBBB\n\
+             5BBBBBBBB\n\
+             6BBBBBBBB\n\
+             7BBBBBBW.\n"
+        ).unwrap();
+        let mut agent = McstAgent::new(
+            UctSelection::new(2_f64.sqrt()),
+            BfsExpansion {},
+            UctDecision {},
+            RandomAgent::seeded(1),
+            RandomAgent::seeded(2),
+            Gamestate::new_from(board, 0),
+        );
+        agent.set_contempt(0.2);
+        agent.cycle_n(10).unwrap();
+        let root = agent.tree().root();
+        assert_eq!(root.proof(), Some(Proven::Draw));
+        assert!((*root.wins() - 0.3 * f64::from(*root.total())).abs() < 1e-9);
+        let mut agent = McstAgent::with_rollout(
+            UctSelection::new(2_f64.sqrt()),
+            BfsExpansion {},
+            UctDecision {},
+            LevelRollout {},
+            Gamestate::new(),
+        );
+        agent.set_contempt(0.2);
+        agent.cycle_n(10).unwrap();
+        assert_eq!(*agent.tree().root().wins(), 0.5 * 10.0);
+
+        // and the memory agent takes its contempt when it is made
+        let memory = McstMemoryAgent::new(new_expanding_agent(BfsExpansion {}, 1), 1, 0.2);
+        assert_eq!(memory.agent().contempt(), 0.2);
+    }
+
+    #[test]
+    fn test_observer() {
+        let observe = |agent: &mut McstAgent<_, _, _, _>| {
+            let infos: Arc<Mutex<Vec<CycleInfo>>> = Arc::default();
+            let observed = Arc::clone(&infos);
+            agent.set_observer(move |info| observed.lock().unwrap().push(info.clone()));
+            infos
+        };
+
+        let mut agent = new_expanding_agent(BfsExpansion {}, 1);
+        let infos = observe(&mut agent);
+        assert_eq!(agent.cycle_n(300).unwrap(), 300);
+        let infos = infos.lock().unwrap();
+        assert_eq!(infos.len(), 300);
+        assert!(infos.iter().enumerate().all(|(i, info)| info.cycle == i as u64 && info.depth >= 1));
+        assert_eq!(infos.iter().filter(|info| info.expanded).count(), agent.tree().size() - 1);
+        let reward: f64 = infos.iter().map(|info| info.reward).sum();
+        assert_eq!(reward, *agent.tree().root().wins());
+        // proven nodes aren't rolled out, so their rollouts have no plies
+        assert!(infos.iter().all(|info| info.rollout_plies.is_some_and(|plies| info.proven == (plies == 0))));
+
+        // capped rollouts count them, and parallel cycles are observed too
+        let mut agent = McstAgent::with_rollout(
+            UctSelection::new(2_f64.sqrt()),
+            BfsExpansion {},
+            UctDecision {},
+            AgentRollout::capped(RandomAgent::seeded(2), RandomAgent::seeded(3), 10),
+            Gamestate::new(),
+        );
+        let infos = observe(&mut agent);
+        let rollouts = |worker: usize| -> Box<dyn RolloutPolicy> {
+            let seed = 10 * worker as u64;
+            Box::new(AgentRollout::capped(RandomAgent::seeded(seed), RandomAgent::seeded(seed + 1), 10))
+        };
+        let done = agent.cycle_parallel(3, &rollouts, 500, None).unwrap();
+        let infos = infos.lock().unwrap();
+        assert_eq!(infos.len(), done);
+        assert!(infos.iter().enumerate().all(|(i, info)| info.cycle == i as u64));
+        assert!(infos.iter().all(|info| info.rollout_plies.is_some_and(|plies| plies <= 10)));
+
+        agent.clear_observer();
+        agent.cycle_n(10).unwrap();
+        assert_eq!(infos.len(), done);
+    }
+
+    #[test]
+    fn test_search_summaries() {
+        let mut black = McstMemoryAgent::with_budget(new_expanding_agent(BfsExpansion {}, 1), Budget::Cycles(100));
+        black.summarize_searches();
+        let mut white = MemorifiedAgent::new(RandomAgent::seeded(5));
+        let record = play_game_recorded(&mut black, &mut white, Gamestate::new(), ("mcst", "random"));
+        let searches: Vec<SearchSummary> = record.plies.iter()
+            .filter_map(|ply| ply.analysis.as_ref())
+            .map(|analysis| analysis.search.unwrap())
+            .collect();
+        assert!(searches.iter().all(|search| search.cycles > 0 && search.cycles <= 100 && search.rollouts_per_sec > 0.0));
+        // the opening is far from proven, so every cycle goes past the root
+        assert_eq!(searches[0].cycles, 100);
+        assert!(searches[0].average_depth >= 1.0);
+        assert_eq!(black.search_summary(), searches.last().copied());
+        assert!(record.to_json().contains("\"search\":{\"cycles\":100,"));
+
+        let mut unsummarized = McstMemoryAgent::with_budget(new_expanding_agent(BfsExpansion {}, 1), Budget::Cycles(100));
+        unsummarized.initialize_game(Gamestate::new());
+        unsummarized.make_move();
+        assert!(unsummarized.search_summary().is_none());
+        assert!(unsummarized.analysis().unwrap().search.is_none());
+    }
+
+    #[test]
+    fn test_cycle_error_messages() {
+        let path = vec![Some((3, 2)), None];
+        assert_eq!(SelectionError::NotANode(path.clone()).to_string(), "the selected path d3 pass does not lead to a node");
+        let long: Vec<Turn> = (0..13).map(|y| Some((y % 8, 0))).collect();
+        assert_eq!(
+            SelectionError::NotANode(long.clone()).to_string(),
+            "the selected path a1 b1 c1 d1 e1 f1 g1 h1 a1 b1 and 3 more does not lead to a node",
+        );
+
+        let off_board = Some((9, 9));
+        let error = ExpansionError::IllegalMove(off_board, MoveError::IllegalSquare(off_board));
+        assert_eq!(error.to_string(), "cannot expand (9, 9): (9, 9) is not on the board");
+        assert_eq!(error.source().unwrap().to_string(), "(9, 9) is not on the board");
+        assert_eq!(ExpansionError::AlreadyExpanded(Some((2, 3))).to_string(), "c4 is already expanded or was chosen twice");
+        assert_eq!(ExpansionError::NoMoves.to_string(), "no moves were chosen to expand");
+
+        assert_eq!(RolloutError::IllegalMove(vec![Some((0, 0))]).to_string(), "the first move played, a1, is illegal");
+        assert_eq!(RolloutError::IllegalMove(path).to_string(), "pass is illegal after d3");
+        assert_eq!(
+            RolloutError::IllegalMove(long).to_string(),
+            "e1 is illegal after a1 b1 c1 d1 e1 f1 g1 h1 a1 b1 and 2 more",
+        );
+
+        // cycle errors say which phase failed, and lead back to what went wrong
+        let error = CycleError::Rollout(RolloutError::Agent(AgentError::NoMoves));
+        assert_eq!(
+            error.to_string(),
+            "rollout failed: a rollout agent could not move: asked for a move in a game with no moves",
+        );
+        let rollout = error.source().unwrap();
+        assert_eq!(rollout.to_string(), "a rollout agent could not move: asked for a move in a game with no moves");
+        assert_eq!(rollout.source().unwrap().to_string(), "asked for a move in a game with no moves");
+        let error = CycleError::Expansion(ExpansionError::NoMoves);
+        assert_eq!(error.to_string(), "expansion failed: no moves were chosen to expand");
+        assert!(error.source().unwrap().source().is_none());
+    }
+
+    /// Expands an illegal move the first `failures` times it is asked,
+    /// then expands like [BfsExpansion].
+    struct FailingExpansion {
+        failures: usize,
+    }
+
+    impl ExpansionPolicy for FailingExpansion {
+        fn expand(&mut self, tree: &McstTree, path: &Vec<Turn>) -> Turn {
+            if self.failures == 0 {
+                return BfsExpansion {}.expand(tree, path);
+            }
+            self.failures -= 1;
+            Some((0, 0))
+        }
+    }
+
+    #[test]
+    fn test_search_recovery() {
+        let new_agent = |failures| McstMemoryAgent::with_budget(
+            new_expanding_agent(FailingExpansion { failures }, 1),
+            Budget::Cycles(100),
+        );
+
+        // a search that fails is run again on a new tree
+        let mut agent = new_agent(1);
+        agent.initialize_game(Gamestate::new());
+        let turn = agent.try_make_move().unwrap();
+        assert!(Gamestate::new().check_move(turn).is_ok());
+        assert_eq!(*agent.agent().tree().root().total(), 100);
+
+        // but only once
+        let mut agent = new_agent(2);
+        agent.initialize_game(Gamestate::new());
+        let error = agent.try_make_move().unwrap_err().to_string();
+        assert_eq!(
+            error,
+            "search failed (expansion failed: cannot expand a1: a1 is not a legal move), \
+            and again with a new tree (expansion failed: cannot expand a1: a1 is not a legal move)",
+        );
+
+        // and failing to ponder just throws the tree away
+        let mut agent = McstMemoryAgent::with_pondering(new_expanding_agent(FailingExpansion { failures: 1 }, 1), 1);
+        agent.initialize_game(Gamestate::new());
+        agent.ponder(1);
+        assert_eq!(*agent.agent().tree().root().total(), 0);
+        assert!(agent.try_make_move().is_ok());
+    }
+
+    #[test]
+    fn test_temperature_decision() {
+        let mut agent = McstAgent::new(
+            UctSelection::new(2_f64.sqrt()),
+            BfsExpansion {},
+            UctDecision {},
+            RandomAgent::seeded(4),
+            RandomAgent::seeded(5),
+            Gamestate::new(),
+        );
+        agent.cycle_n(200).unwrap();
+        let tree = agent.tree();
+        let best = UctDecision {}.decide(tree);
+        let visits: HashMap<Turn, f64> = tree.root().children().iter()
+            .map(|(turn, child)| (*turn, f64::from(*child.total())))
+            .collect();
+
+        let mut cold = TemperatureDecision::from_rng(0.0, StdRng::seed_from_u64(1));
+        for _ in 0..20 {
+            assert_eq!(cold.decide(tree), best);
+        }
+
+        // moves are sampled in proportion to their visits to the power of 1/T
+        for (temperature, seed) in [(1.0, 2), (0.5, 3), (2.0, 4)] {
+            let mut decider = TemperatureDecision::from_rng(temperature, StdRng::seed_from_u64(seed));
+            let mut counts: HashMap<Turn, u32> = HashMap::new();
+            for _ in 0..20_000 {
+                *counts.entry(decider.decide(tree)).or_default() += 1;
+            }
+            let weights: HashMap<Turn, f64> = visits.iter()
+                .map(|(turn, visits)| (*turn, visits.powf(1.0 / temperature)))
+                .collect();
+            let total: f64 = weights.values().sum();
+            for (turn, weight) in &weights {
+                let expected = 20_000.0 * weight / total;
+                let count = f64::from(counts.get(turn).copied().unwrap_or(0));
+                // within four standard deviations
+                assert!((count - expected).abs() < 4.0 * expected.sqrt(), "{temperature} {counts:?} {weights:?}");
+            }
+        }
+
+        // the schedule is looked up by the root's ply
+        let opening = TemperatureDecision::opening(1.0, 12, StdRng::seed_from_u64(5));
+        assert_eq!((opening.temperature(0), opening.temperature(11), opening.temperature(12)), (1.0, 1.0, 0.0));
+        let mut late = Gamestate::new();
+        while late.ply() < 12 {
+            late.make_move_fast(late.get_moves()[0]);
+        }
+        let mut agent = McstAgent::new(
+            UctSelection::new(2_f64.sqrt()),
+            BfsExpansion {},
+            TemperatureDecision::opening(1.0, 12, StdRng::seed_from_u64(5)),
+            RandomAgent::seeded(4),
+            RandomAgent::seeded(5),
+            late,
+        );
+        agent.cycle_n(200).unwrap();
+        let best = UctDecision {}.decide(agent.tree());
+        for _ in 0..20 {
+            assert_eq!(agent.decide(), Some(best));
+        }
+
+        // however hot, a move which was never visited is never chosen
+        let mut agent = McstAgent::new(
+            UctSelection::new(2_f64.sqrt()),
+            BfsExpansion {},
+            UctDecision {},
+            RandomAgent::seeded(4),
+            RandomAgent::seeded(5),
+            Gamestate::new(),
+        );
+        agent.cycle_n(2).unwrap();
+        let mut tree = agent.into_tree();
+        let visited: Vec<Turn> = tree.root().children().keys().copied().collect();
+        for turn in Gamestate::new().get_moves() {
+            if !visited.contains(&turn) {
+                tree.add_child(&[], turn);
+            }
+        }
+        let mut hot = TemperatureDecision::from_rng(100.0, StdRng::seed_from_u64(6));
+        for _ in 0..200 {
+            assert!(visited.contains(&hot.decide(&tree)));
+        }
+    }
+
+    #[test]
+    fn test_draws_count_half() {
+        // four empty squares left, and every way of filling them ends 32-32
+        let g = Gamestate::from_transcript(
+            "e6f4d3e7f7c6g4g7f6f5d6c4c5e3c7b7f3b4b5c2e2d7b6b3b1g3b8a7h2d2a8h4h7b2d1e8g5f1a2f8f2g6c3c8a3g2g1a1c1e1a6h6h3h8h5a4"
+        ).unwrap();
+        let mut agent = McstAgent::new(
+            UctSelection::new(2_f64.sqrt()),
+            BfsExpansion {},
+            UctDecision {},
+            RandomAgent::seeded(4),
+            RandomAgent::seeded(5),
+            g,
+        );
+        for _ in 0..50 {
+            agent.cycle().unwrap();
+        }
+        let root = agent.tree().root();
+        assert_eq!(*root.wins(), 0.5 * f64::from(*root.total()));
+    }
+
+    #[test]
+    fn test_uct_perspective_with_passes() {
+        // White wins by taking the tile at 1, which leaves Black to pass,
+        // and loses by taking the one at 3
+        let board = Board::from_ascii(
+            " 01234567\n\
+             0.BWB....\n\
+             1W.......\n\
+             2...W....\n\
+             3..W.....\n\
+             4........\n\
+             5........\n\
+             6........\n\
+             7........\n"
+        ).unwrap();
+        let mut agent = McstAgent::new(
+            UctSelection::new(2_f64.sqrt()),
+            BfsExpansion {},
+            UctDecision {},
+            RandomAgent::seeded(4),
+            RandomAgent::seeded(5),
+            Gamestate::new_from(board, 1),
+        );
+        for _ in 0..300 {
+            agent.cycle().unwrap();
+        }
+        let root = agent.tree().root();
+        let win = root.children().get(&Some((0, 0))).unwrap();
+        let loss = root.children().get(&Some((4, 0))).unwrap();
+        assert!(win.children().contains_key(&None));
+        assert_eq!(*win.wins(), f64::from(*win.total()));
+        assert_eq!(*loss.wins(), 0.0);
+        // once proven, the root is won without going any further
+        assert_eq!(win.proof(), Some(Proven::Win));
+        assert_eq!(root.proof(), Some(Proven::Win));
+        assert_eq!(*root.wins(), f64::from(root.total() - loss.total()));
+        assert_eq!(agent.decide(), Some(Some((0, 0))));
+    }
+
+    #[test]
+    fn test_pondering() {
+        let new_agent = |seed| McstAgent::new(
+            UctSelection::new(2_f64.sqrt()),
+            BfsExpansion {},
+            UctDecision {},
+            RandomAgent::seeded(seed),
+            RandomAgent::seeded(seed + 1),
+            Gamestate::new(),
+        );
+        // with no time to spare, every search and every ponder runs one
+        // batch of cycles before looking at the clock, however fast they are
+        let batch = Budget::CLOCK_CHECK_CYCLES as u64;
+        let mut pondering = McstMemoryAgent::with_pondering(new_agent(0), 0);
+        let mut plain = McstMemoryAgent::new(new_agent(0), 0, 0.0);
+        for (agent, pondered) in [(&mut pondering, batch), (&mut plain, 0)] {
+            let mut game = Gamestate::new();
+            agent.initialize_game(game.clone());
+            for moves in 1..=5 {
+                game.make_move_fast(agent.make_move());
+                agent.ponder(0);
+                let reply = game.get_moves()[0];
+                game.make_move_fast(reply);
+                agent.opponent_move(&reply);
+                assert_eq!(agent.cycles(), moves * (batch + pondered));
+            }
+        }
+    }
+
+    #[test]
+    fn test_iterative_deepening_budget() {
+        let mut agent = IterativeDeepeningAgent::new(WeightedSquareEvaluator::default(), 0);
+        let mut g = Gamestate::new();
+        g.make_moves_fast(&[Some((5, 4)), Some((3, 5))]);
+        agent.initialize_game(g.clone());
+        let turn = agent.make_move();
+        assert!(g.valid_move(turn));
+        assert_eq!(agent.state.board().counts().2, 57);
+    }
+
+    #[test]
+    fn test_iterative_deepening_solves() {
+        // White to move with nine empty tiles, where only h6 wins
+        // and a two ply search plays h5 and loses by 26
+        let g = Gamestate::from_transcript(
+            "f5d6c6f4d3d2c5f6d7c7c8e3f2b4g3g1c1g4e1e7g7d8b5b8e8f3e6a5c4h3\
+             b7a8c3f1b6d1g2a7c2b1g5g6a4h8h1f8h4b3a3h2h7"
+        ).unwrap();
+        let exhaustive = AlphaBetaAgent::new(WeightedSquareEvaluator::default(), 0);
+        for (turn, child) in g.children() {
+            let wins = -minimax(&exhaustive, &child, 64) > 0.0;
+            assert_eq!(wins, turn == Some((7, 5)));
+        }
+        assert_eq!(AlphaBetaAgent::new(WeightedSquareEvaluator::default(), 2).make_move(&g), Some((7, 4)));
+
+        let agent = IterativeDeepeningAgent::new(WeightedSquareEvaluator::default(), 6000);
+        let (turn, depth) = agent.deepen(&g);
+        assert_eq!(turn, Some((7, 5)));
+        assert!(depth > 2);
+    }
+
+    #[test]
+    fn test_endgame_solver() {
+        // the position of test_iterative_deepening_solves, where only h6 wins
+        let g = Gamestate::from_transcript(
+            "f5d6c6f4d3d2c5f6d7c7c8e3f2b4g3g1c1g4e1e7g7d8b5b8e8f3e6a5c4h3\
+             b7a8c3f1b6d1g2a7c2b1g5g6a4h8h1f8h4b3a3h2h7"
+        ).unwrap();
+        let mover = g.side_to_move();
+        let won = |result: GameResult| result.winner() == Some(mover);
+
+        let solver = EndgameSolver::new();
+        let (score, turn) = solver.solve(&g);
+        assert_eq!(turn, Some((7, 5)));
+
+        // against perfect play, greedy play loses
+        assert_ne!(GreedyAgent::new().make_move(&g), Some((7, 5)));
+        let mut greedy = MemorifiedAgent::new(GreedyAgent::new());
+        let mut opponent = MemorifiedAgent::new(SolverAgent::new(12, GreedyAgent::new()));
+        let (result, _) = match mover {
+            Players::Black => play_game_from(&mut greedy, &mut opponent, g.clone()),
+            Players::White => play_game_from(&mut opponent, &mut greedy, g.clone()),
+        };
+        assert!(!won(result));
+
+        let mut solving = MemorifiedAgent::new(SolverAgent::new(12, GreedyAgent::new()));
+        let (result, _) = match mover {
+            Players::Black => play_game_from(&mut solving, &mut opponent, g.clone()),
+            Players::White => play_game_from(&mut opponent, &mut solving, g.clone()),
+        };
+        assert!(won(result));
+        assert_eq!(result.score(), score);
+    }
+
+    #[test]
+    fn test_endgame_solver_consistent() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let solver = EndgameSolver::new();
+        for _ in 0..20 {
+            let mut g = Gamestate::new();
+            while g.board().counts().2 > 8 {
+                g.make_move_fast(*g.get_moves().choose(&mut rng).unwrap());
+            }
+            // the solver's move leads to a position with the same value
+            let (score, turn) = solver.solve(&g);
+            if g.is_terminal() {
+                assert_eq!((score, turn), (g.score(), None));
+                continue;
+            }
+            if g.get_moves()[..] == [None] {
+                assert_eq!(turn, None);
+            }
+            let mut child = g.clone();
+            child.make_move_fast(turn);
+            assert_eq!(solver.solve(&child).0, score);
+        }
+    }
+
+    #[test]
+    fn test_table_keeps_choices() {
+        let plain = AlphaBetaAgent::new(WeightedSquareEvaluator::default(), 4);
+        let cached = AlphaBetaAgent::with_table(
+            WeightedSquareEvaluator::default(), 4, TranspositionTable::new(1 << 16)
+        );
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..3 {
+            let mut g = Gamestate::new();
+            while !g.is_terminal() {
+                assert_eq!(cached.make_move(&g), plain.make_move(&g));
+                g.make_move_fast(*g.get_moves().choose(&mut rng).unwrap());
+            }
+        }
+        assert!(cached.nodes_searched() < plain.nodes_searched());
+        assert!(cached.table().unwrap().hit_rate() > 0.0);
+        assert!(plain.table().is_none());
+    }
+
+    #[test]
+    fn test_pruning_keeps_value() {
+        let agent = AlphaBetaAgent::new(WeightedSquareEvaluator::default(), 3);
+        let mut rng = StdRng::seed_from_u64(3);
+        for _ in 0..3 {
+            let mut g = Gamestate::new();
+            while !g.is_terminal() {
+                assert_eq!(
+                    agent.negamax(&g, 3, f64::NEG_INFINITY, f64::INFINITY, None).unwrap(),
+                    minimax(&agent, &g, 3),
+                );
+                g.make_move_fast(*g.get_moves().choose(&mut rng).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn test_benchmark_report() {
+        let agent = McstAgent::new(
+            UctSelection::new(2_f64.sqrt()),
+            BfsExpansion {},
+            UctDecision {},
+            GreedyAgent::new(),
+            RandomAgent::seeded(1),
+            Gamestate::new(),
+        );
+        let report = benchmark_with(agent, Duration::from_millis(100));
+        assert!(report.elapsed >= Duration::from_millis(100));
+        assert!(report.cycles > 0 && report.cycles >= report.rollouts);
+        assert!(report.nodes_created > 0 && report.nodes_created as u64 <= report.cycles + 1);
+        assert_eq!(report.peak_nodes, report.nodes_created + 1);
+        assert!(report.average_depth >= 1.0);
+        assert!(report.average_rollout_plies.is_some_and(|plies| plies > 0.0 && plies <= 120.0));
+        assert_eq!(report.errors, 0);
+        assert!(report.nodes_per_sec() > 0);
+
+        // failed cycles are counted rather than ending the benchmark
+        let agent = new_expanding_agent(FailingExpansion { failures: 3 }, 2);
+        let report = benchmark_with(agent, Duration::from_millis(100));
+        assert_eq!(report.errors, 3);
+        assert!(report.cycles > 0);
+        assert!(report.average_rollout_plies.is_some_and(|plies| plies > 0.0));
+    }
+
+    #[test]
+    fn test_minimum_visits() {
+        // with no time to speak of, the search goes on until every legal
+        // move at the root has been visited enough
+        let mut agent = McstMemoryAgent::with_budget(new_expanding_agent(BfsExpansion {}, 1), Budget::Millis(0));
+        agent.set_minimums(20, 0, 100_000);
+        let mut g = Gamestate::new();
+        agent.initialize_game(g.clone());
+        for _ in 0..6 {
+            let turn = agent.make_move();
+            let root = agent.agent().tree().root();
+            for turn in root.game().get_moves() {
+                let child = root.children().get(&turn).unwrap();
+                assert!(*child.total() >= 20 || child.proof().is_some());
+            }
+            assert_eq!(agent.last_decision(), Some(Decision { turn, minimums_met: true }));
+            g.make_move_fast(turn);
+            let reply = g.get_moves()[0];
+            g.make_move_fast(reply);
+            agent.opponent_move(&reply);
+        }
+
+        // and until enough cycles have been run
+        let mut agent = McstMemoryAgent::with_budget(new_expanding_agent(BfsExpansion {}, 2), Budget::Cycles(0));
+        agent.set_minimums(0, 300, 100_000);
+        agent.initialize_game(Gamestate::new());
+        agent.make_move();
+        assert_eq!(agent.cycles(), 300);
+        assert!(agent.last_decision().unwrap().minimums_met);
+
+        // but no further than the most extra cycles allowed, which is
+        // reported as a decision made on too little
+        let mut agent = McstMemoryAgent::with_budget(new_expanding_agent(BfsExpansion {}, 3), Budget::Cycles(0));
+        agent.set_minimums(1000, 0, 10);
+        agent.initialize_game(Gamestate::new());
+        agent.make_move();
+        assert_eq!(agent.cycles(), 10);
+        assert!(!agent.last_decision().unwrap().minimums_met);
+        assert!(!agent.decide().unwrap().minimums_met);
+    }
+
+    /// A [Clock] which only moves on when told to.
+    #[derive(Clone, Default)]
+    struct ManualClock(Arc<Mutex<Duration>>);
+
+    impl ManualClock {
+        fn advance(&self, by: Duration) {
+            *self.0.lock().unwrap() += by;
+        }
+    }
+
+    impl Clock for ManualClock {
+        fn now(&self) -> Duration {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    #[test]
+    fn test_time_manager() {
+        let total = Duration::from_secs(60);
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut forced = 0;
+        for overrun in [0, 50, 0, 50] {
+            let clock = ManualClock::default();
+            let mut manager = TimeManager::with_clock(total, clock.clone());
+            let (mut used, mut g) = (Duration::ZERO, Gamestate::new());
+            while !g.is_terminal() {
+                if g.side_to_move() == Players::Black {
+                    let remaining = manager.remaining();
+                    let allotted = manager.start_move(&g);
+                    assert!(allotted <= remaining / 2);
+                    if g.move_count() == 1 {
+                        assert_eq!(allotted, Duration::ZERO);
+                        forced += 1;
+                    }
+                    // moves may overrun their allotment a little
+                    let took = allotted + Duration::from_millis(rng.random_range(0..=overrun));
+                    clock.advance(took);
+                    assert_eq!(manager.finish_move(), Some(TimeAllotment { remaining, allotted, used: took }));
+                    used += took;
+                    assert_eq!(manager.remaining(), total.saturating_sub(used));
+                }
+                g.make_move_fast(*g.get_moves().choose(&mut rng).unwrap());
+            }
+            // moves which keep to their allotments keep to the game's time
+            if overrun == 0 {
+                assert!(used < total);
+            }
+        }
+        assert!(forced > 0);
+
+        // the midgame is given more than the opening
+        let manager = TimeManager::with_clock(total, ManualClock::default());
+        let mut midgame = Gamestate::new();
+        while midgame.board().counts().2 > 30 || midgame.move_count() < TimeManager::FULL_CHOICE {
+            if midgame.is_terminal() {
+                midgame = Gamestate::new();
+            }
+            midgame.make_move_fast(*midgame.get_moves().choose(&mut rng).unwrap());
+        }
+        assert!(manager.allot(&Gamestate::new()) < manager.allot(&midgame));
+    }
+
+    #[test]
+    fn test_time_managed_agent() {
+        let total = Duration::from_millis(500);
+        let mut agent = McstMemoryAgent::with_time_manager(
+            new_expanding_agent(BfsExpansion {}, 1),
+            TimeManager::new(total),
+        );
+        let mut opponent = MemorifiedAgent::new(RandomAgent::seeded(3));
+        play_game(&mut agent, &mut opponent);
+        let clock = agent.analysis().unwrap().clock.unwrap();
+        assert!(clock.allotted <= clock.remaining / 2);
+        let remaining = agent.time_manager().unwrap().remaining();
+        assert_eq!(remaining, clock.remaining.saturating_sub(clock.used));
+        assert!(remaining < total);
+
+        // a new game has all the time again
+        agent.initialize_game(Gamestate::new());
+        assert_eq!(agent.time_manager().unwrap().remaining(), total);
+        assert_eq!(agent.analysis().unwrap().clock, None);
     }
 }
@@ -0,0 +1,301 @@
+use std::cell::RefCell;
+use std::io::{self, Sink, Write};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::agent::{Agent, MemoryAgent};
+use crate::agent::tournament::{to_tile, CompactString, Seconds, Tile};
+use crate::gameplay::{Gamestate, MoveList, Turn};
+use crate::mcst::Analysis;
+
+/// What a [LoggingAgent] or [LoggingMemoryAgent] records about one move.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MoveLog {
+    /// How many turns had been taken before the move (see [Gamestate::ply]).
+    pub ply: u8,
+    /// The [compact form](crate::mechanics::Board::to_compact) of the board.
+    pub board: u128,
+    /// Every legal move.
+    pub moves: Vec<Turn>,
+    pub chosen: Turn,
+    /// When the agent was asked for the move, counted from when the wrapper
+    /// was created.
+    pub asked: Duration,
+    /// How long the agent took to choose the move.
+    pub elapsed: Duration,
+    /// What the agent thought of the position, if it could say
+    /// (see [MemoryAgent::analysis]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub analysis: Option<Analysis>,
+}
+
+impl MoveLog {
+    /// Formats the log as a single line of JSON, with moves in algebraic
+    /// notation (a pass is `null`), times in seconds, and the board as a
+    /// string since it is too large for most JSON readers' numbers. The
+    /// principal variation of the analysis, if there is one, is given as `pv`.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&JsonLog::from(self)).expect("a log is always valid JSON")
+    }
+}
+
+/// A [MoveLog] as [MoveLog::to_json] writes it.
+#[derive(Serialize)]
+struct JsonLog {
+    ply: u8,
+    board: CompactString,
+    moves: Vec<Option<Tile>>,
+    chosen: Option<Tile>,
+    asked: Seconds,
+    elapsed: Seconds,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pv: Option<Vec<Option<Tile>>>,
+}
+
+impl From<&MoveLog> for JsonLog {
+    fn from(log: &MoveLog) -> Self {
+        JsonLog {
+            ply: log.ply,
+            board: CompactString(log.board),
+            moves: log.moves.iter().copied().map(to_tile).collect(),
+            chosen: to_tile(log.chosen),
+            asked: Seconds(log.asked),
+            elapsed: Seconds(log.elapsed),
+            pv: log.analysis.as_ref().map(|analysis| analysis.pv.iter().copied().map(to_tile).collect()),
+        }
+    }
+}
+
+/// Records the moves made by the agent it wraps, along with how long they
+/// took, and writes each record to a sink as a line of JSON.
+struct MoveLogger<W: Write> {
+    logs: RefCell<Vec<MoveLog>>,
+    sink: RefCell<W>,
+    created: Instant,
+}
+
+impl<W: Write> MoveLogger<W> {
+    fn new(sink: W) -> Self {
+        MoveLogger { logs: RefCell::new(Vec::new()), sink: RefCell::new(sink), created: Instant::now() }
+    }
+
+    /// Asks `choose` for a move in `state`, along with any analysis of it,
+    /// and records them.
+    fn log(&self, state: &Gamestate, choose: impl FnOnce() -> (Turn, Option<Analysis>)) -> Turn {
+        let moves: MoveList = state.get_moves();
+        let asked = Instant::now();
+        let (chosen, analysis) = choose();
+        let log = MoveLog {
+            ply: state.ply(),
+            board: state.board().to_compact(),
+            moves: moves.to_vec(),
+            chosen,
+            asked: asked - self.created,
+            elapsed: asked.elapsed(),
+            analysis,
+        };
+        writeln!(self.sink.borrow_mut(), "{}", log.to_json()).expect("move log could not be written");
+        self.logs.borrow_mut().push(log);
+        chosen
+    }
+}
+
+/// Wraps an [Agent], recording a [MoveLog] for every move it makes.
+///
+/// Logs are kept in memory and, if given a sink, also written to it as
+/// they are made, one [line of JSON](MoveLog::to_json) per move.
+pub struct LoggingAgent<A: Agent, W: Write = Sink> {
+    agent: A,
+    logger: MoveLogger<W>,
+}
+
+impl<A: Agent> LoggingAgent<A> {
+    pub fn new(agent: A) -> Self {
+        LoggingAgent { agent, logger: MoveLogger::new(io::sink()) }
+    }
+}
+
+impl<A: Agent, W: Write> LoggingAgent<A, W> {
+    /// Like [LoggingAgent::new], but also writes the logs to `sink`.
+    pub fn with_sink(agent: A, sink: W) -> Self {
+        LoggingAgent { agent, logger: MoveLogger::new(sink) }
+    }
+
+    /// Returns a copy of the logs of every move made so far.
+    pub fn logs(&self) -> Vec<MoveLog> {
+        self.logger.logs.borrow().clone()
+    }
+
+    /// Removes and returns the logs of every move made so far.
+    pub fn take_logs(&self) -> Vec<MoveLog> {
+        self.logger.logs.take()
+    }
+
+    pub fn agent(&self) -> &A {
+        &self.agent
+    }
+}
+
+impl<A: Agent, W: Write> Agent for LoggingAgent<A, W> {
+    fn make_move(&self, state: &Gamestate) -> Turn {
+        self.logger.log(state, || (self.agent.make_move(state), None))
+    }
+}
+
+/// Wraps a [MemoryAgent], recording a [MoveLog] for every move it makes.
+/// See [LoggingAgent].
+pub struct LoggingMemoryAgent<A: MemoryAgent, W: Write = Sink> {
+    agent: A,
+    logger: MoveLogger<W>,
+    /// The game as the wrapped agent sees it.
+    state: Gamestate,
+}
+
+impl<A: MemoryAgent> LoggingMemoryAgent<A> {
+    pub fn new(agent: A) -> Self {
+        LoggingMemoryAgent { agent, logger: MoveLogger::new(io::sink()), state: Gamestate::new() }
+    }
+}
+
+impl<A: MemoryAgent, W: Write> LoggingMemoryAgent<A, W> {
+    /// Like [LoggingMemoryAgent::new], but also writes the logs to `sink`.
+    pub fn with_sink(agent: A, sink: W) -> Self {
+        LoggingMemoryAgent { agent, logger: MoveLogger::new(sink), state: Gamestate::new() }
+    }
+
+    /// Returns a copy of the logs of every move made so far.
+    pub fn logs(&self) -> Vec<MoveLog> {
+        self.logger.logs.borrow().clone()
+    }
+
+    /// Removes and returns the logs of every move made so far.
+    pub fn take_logs(&self) -> Vec<MoveLog> {
+        self.logger.logs.take()
+    }
+
+    pub fn agent(&self) -> &A {
+        &self.agent
+    }
+}
+
+impl<A: MemoryAgent, W: Write> MemoryAgent for LoggingMemoryAgent<A, W> {
+    fn initialize_game(&mut self, state: Gamestate) {
+        self.state = state.clone();
+        self.agent.initialize_game(state);
+    }
+
+    fn opponent_move(&mut self, op: &Turn) {
+        self.state.make_move_fast(*op);
+        self.agent.opponent_move(op);
+    }
+
+    fn make_move(&mut self) -> Turn {
+        let agent = &mut self.agent;
+        let turn = self.logger.log(&self.state, || (agent.make_move(), agent.analysis()));
+        self.state.make_move_fast(turn);
+        turn
+    }
+
+    fn ponder(&mut self, budget: u128) {
+        self.agent.ponder(budget);
+    }
+
+    fn evaluate(&self) -> Option<f64> {
+        self.agent.evaluate()
+    }
+
+    fn analysis(&self) -> Option<Analysis> {
+        self.agent.analysis()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{play_game, MemorifiedAgent};
+    use crate::agent::implementations::{GreedyAgent, RandomAgent};
+    use crate::gameplay::loc_to_alg;
+
+    /// Checks that `logs` are the moves of a game in order,
+    /// asked for one after another.
+    fn check_logs(logs: &[MoveLog], turns: &[Turn]) {
+        let mut g = Gamestate::new();
+        let mut previous: Option<&MoveLog> = None;
+        for log in logs {
+            while g.ply() < log.ply {
+                g.make_move_fast(turns[usize::from(g.ply())]);
+            }
+            assert_eq!(log.board, g.board().to_compact());
+            assert_eq!(log.moves, g.get_moves().to_vec());
+            assert_eq!(log.chosen, turns[usize::from(log.ply)]);
+            if let Some(previous) = previous {
+                assert!(previous.ply < log.ply);
+                assert!(previous.asked + previous.elapsed <= log.asked);
+            }
+            previous = Some(log);
+        }
+    }
+
+    #[test]
+    fn test_logging_agent() {
+        let agent = LoggingAgent::new(GreedyAgent::new());
+        let mut g = Gamestate::new();
+        let mut turns = Vec::new();
+        while !g.is_terminal() {
+            let turn = agent.make_move(&g);
+            turns.push(turn);
+            g.make_move_fast(turn);
+        }
+        let logs = agent.take_logs();
+        assert_eq!(logs.len(), turns.len());
+        check_logs(&logs, &turns);
+        assert!(agent.logs().is_empty());
+    }
+
+    #[test]
+    fn test_to_json() {
+        let mut log = MoveLog {
+            ply: 3,
+            board: 12345,
+            moves: vec![Some((3, 2)), None],
+            chosen: Some((3, 2)),
+            asked: Duration::from_millis(1500),
+            elapsed: Duration::from_millis(250),
+            analysis: None,
+        };
+        assert_eq!(
+            log.to_json(),
+            r#"{"ply":3,"board":"12345","moves":["d3",null],"chosen":"d3","asked":1.5,"elapsed":0.25}"#,
+        );
+        log.analysis = Some(Analysis { moves: Vec::new(), pv: vec![Some((3, 2)), None], search: None, clock: None });
+        assert!(log.to_json().ends_with(r#""elapsed":0.25,"pv":["d3",null]}"#));
+    }
+
+    #[test]
+    fn test_logging_memory_agent() {
+        let mut sink = Vec::new();
+        let mut black = LoggingMemoryAgent::with_sink(MemorifiedAgent::new(GreedyAgent::new()), &mut sink);
+        let mut white = MemorifiedAgent::new(RandomAgent::seeded(5));
+        let (_, turns) = play_game(&mut black, &mut white);
+        let logs = black.logs();
+        drop(black);
+
+        assert_eq!(logs.len(), turns.len().div_ceil(2));
+        check_logs(&logs, &turns);
+        assert!(logs.iter().all(|log| log.ply % 2 == 0));
+
+        let lines: Vec<&str> = std::str::from_utf8(&sink).unwrap().lines().collect();
+        assert_eq!(lines.len(), logs.len());
+        for (line, log) in lines.iter().zip(&logs) {
+            let json: serde_json::Value = serde_json::from_str(line).unwrap();
+            assert_eq!(json["ply"], u64::from(log.ply));
+            assert_eq!(json["board"], log.board.to_string());
+            assert_eq!(json["moves"].as_array().unwrap().len(), log.moves.len());
+            match log.chosen {
+                Some(loc) => assert_eq!(json["chosen"], loc_to_alg(loc)),
+                None => assert!(json["chosen"].is_null()),
+            }
+        }
+    }
+}
@@ -0,0 +1,383 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::str::FromStr;
+use std::sync::{LazyLock, Mutex};
+
+use crate::agent::implementations::{
+    AlphaBetaAgent, BfsExpansion, Budget, GreedyAgent, IterativeDeepeningAgent, McstMemoryAgent,
+    MobilityAgent, PhasedRankedAgent, RandomAgent, RankedCellAgent, RankedTables, SolverAgent,
+    TieBreak, UctDecision, UctOptions, UctSelection, WeightedSquareEvaluator,
+};
+use crate::agent::{MemorifiedAgent, MemoryAgent};
+use crate::gameplay::Gamestate;
+use crate::mcst::{AgentRollout, McstAgent};
+
+/// Errors that can occur when building an agent from a spec with [create].
+#[derive(Debug, PartialEq)]
+pub enum SpecError {
+    /// No agent is registered under the name.
+    UnknownAgent(String),
+    /// A parameter is not of the form `name=value`, or is given twice.
+    BadSyntax(String),
+    /// The agent does not take a parameter with this name.
+    UnknownParameter { agent: String, name: String },
+    /// The value of a parameter could not be understood.
+    BadParameter { agent: String, name: String, value: String },
+    /// The agent needs a parameter which was not given.
+    MissingParameter { agent: String, name: String },
+    /// A file named by a parameter could not be read.
+    File { path: String, error: String },
+}
+
+impl fmt::Display for SpecError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SpecError::UnknownAgent(name) => write!(f, "there is no agent called {name:?}"),
+            SpecError::BadSyntax(param) => write!(f, "parameter {param:?} is not `name=value` or is repeated"),
+            SpecError::UnknownParameter { agent, name } => write!(f, "{agent} does not take a parameter {name:?}"),
+            SpecError::BadParameter { agent, name, value } => {
+                write!(f, "{value:?} is not a valid value of {name} for {agent}")
+            }
+            SpecError::MissingParameter { agent, name } => write!(f, "{agent} needs a parameter {name:?}"),
+            SpecError::File { path, error } => write!(f, "could not read {path}: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for SpecError {}
+
+/// The parameters of a spec, as given to a [Constructor].
+pub struct Params {
+    agent: String,
+    values: HashMap<String, String>,
+    /// The names of the parameters the constructor has asked for, so that
+    /// the rest can be reported as unknown.
+    read: RefCell<HashSet<String>>,
+}
+
+impl Params {
+    /// Returns the raw value of the parameter `name`, if it was given.
+    pub fn str(&self, name: &str) -> Option<&str> {
+        self.read.borrow_mut().insert(name.to_string());
+        self.values.get(name).map(String::as_str)
+    }
+
+    /// Parses the parameter `name`, or returns `default` if it was not given.
+    pub fn get<T: FromStr>(&self, name: &str, default: T) -> Result<T, SpecError> {
+        match self.str(name) {
+            Some(_) => self.require(name),
+            None => Ok(default),
+        }
+    }
+
+    /// Parses the parameter `name`, which must have been given.
+    pub fn require<T: FromStr>(&self, name: &str) -> Result<T, SpecError> {
+        let value = self.str(name).ok_or_else(|| SpecError::MissingParameter {
+            agent: self.agent.clone(),
+            name: name.to_string(),
+        })?;
+        value.parse().map_err(|_| SpecError::BadParameter {
+            agent: self.agent.clone(),
+            name: name.to_string(),
+            value: value.to_string(),
+        })
+    }
+}
+
+/// Builds an agent from the parameters of a spec.
+pub type Constructor = Box<dyn Fn(&Params) -> Result<Box<dyn MemoryAgent>, SpecError> + Send + Sync>;
+
+/// A set of agents that can be built by name.
+///
+/// A spec is an agent's name, optionally followed by a colon and a
+/// comma-separated list of `name=value` parameters, like
+/// `mcst:c=1.41,time=500`. Times are in milliseconds, as in [Budget::Millis].
+pub struct Registry {
+    constructors: HashMap<String, Constructor>,
+}
+
+impl Registry {
+    /// Creates a registry with no agents in it.
+    pub fn new() -> Self {
+        Registry { constructors: HashMap::new() }
+    }
+
+    /// Creates a registry with the agents in
+    /// [implementations](crate::agent::implementations) that don't need a
+    /// person at the keyboard:
+    ///
+    /// - `random` (`seed`)
+    /// - `greedy` (`tie`: `first`, the default, `random` or `corners`)
+    /// - `mobility`
+    /// - `ranked` (`file`: 8 rows of 8 tile values; defaults to
+    ///   [WeightedSquareEvaluator::STANDARD_WEIGHTS])
+    /// - `phased` (`file`: tables as read by [RankedTables::load])
+    /// - `alphabeta` (`depth`, default 4)
+    /// - `deepening` (`time`, default 1000)
+    /// - `mcst` (`c`, default √2; `time`, default 1000; `ponder`, default false;
+    ///   `threads`, default 1; `nodes`, the most nodes its tree may have;
+    ///   `tuned`, default false, and `fpu`, see [UctOptions]; `plies`, how
+    ///   long rollouts may go on, see [AgentRollout::capped]; `contempt`,
+    ///   default 0, see [McstAgent::set_contempt])
+    /// - `solver` (`empties`, default 12), playing greedily before then
+    pub fn with_builtins() -> Self {
+        let mut registry = Registry::new();
+        registry.register("random", |params| Ok(match params.str("seed") {
+            Some(_) => Box::new(MemorifiedAgent::new(RandomAgent::seeded(params.require("seed")?))),
+            None => Box::new(MemorifiedAgent::new(RandomAgent::new())),
+        }));
+        registry.register("greedy", |params| {
+            let tie_break = match params.str("tie").unwrap_or("first") {
+                "first" => TieBreak::First,
+                "random" => TieBreak::Random,
+                "corners" => TieBreak::PreferCorners,
+                value => return Err(SpecError::BadParameter {
+                    agent: String::from("greedy"),
+                    name: String::from("tie"),
+                    value: value.to_string(),
+                }),
+            };
+            Ok(Box::new(MemorifiedAgent::new(GreedyAgent::with_tie_break(tie_break))))
+        });
+        registry.register("mobility", |_| Ok(Box::new(MemorifiedAgent::new(MobilityAgent {}))));
+        registry.register("ranked", |params| {
+            let ranking = match params.str("file") {
+                Some(path) => load_ranking(path)?,
+                None => WeightedSquareEvaluator::STANDARD_WEIGHTS,
+            };
+            Ok(Box::new(MemorifiedAgent::new(RankedCellAgent::new(ranking))))
+        });
+        registry.register("phased", |params| {
+            let tables = match params.str("file") {
+                Some(path) => RankedTables::load(path).map_err(|e| SpecError::File {
+                    path: path.to_string(),
+                    error: e.to_string(),
+                })?,
+                None => RankedTables::default(),
+            };
+            Ok(Box::new(MemorifiedAgent::new(PhasedRankedAgent::new(tables))))
+        });
+        registry.register("alphabeta", |params| Ok(Box::new(MemorifiedAgent::new(
+            AlphaBetaAgent::new(WeightedSquareEvaluator::default(), params.get("depth", 4)?)
+        ))));
+        registry.register("deepening", |params| Ok(Box::new(
+            IterativeDeepeningAgent::with_millis(WeightedSquareEvaluator::default(), params.get("time", 1000)?)
+        )));
+        registry.register("mcst", |params| {
+            let plies: Option<usize> = match params.str("plies") {
+                Some(_) => Some(params.require("plies")?),
+                None => None,
+            };
+            let rollout = move || match plies {
+                Some(plies) => AgentRollout::capped(RandomAgent::new(), RandomAgent::new(), plies),
+                None => AgentRollout::new(RandomAgent::new(), RandomAgent::new()),
+            };
+            let mut agent = McstAgent::with_rollout(
+                UctSelection::with_options(params.get("c", 2_f64.sqrt())?, UctOptions {
+                    tuned: params.get("tuned", false)?,
+                    first_play_urgency: match params.str("fpu") {
+                        Some(_) => Some(params.require("fpu")?),
+                        None => None,
+                    },
+                }),
+                BfsExpansion {},
+                UctDecision {},
+                rollout(),
+                Gamestate::new(),
+            );
+            if params.str("nodes").is_some() {
+                agent.set_node_limit(params.require("nodes")?);
+            }
+            let contempt: f64 = params.get("contempt", 0.0)?;
+            if !(-0.5..=0.5).contains(&contempt) {
+                return Err(SpecError::BadParameter {
+                    agent: String::from("mcst"),
+                    name: String::from("contempt"),
+                    value: params.str("contempt").unwrap().to_string(),
+                });
+            }
+            agent.set_contempt(contempt);
+            let mut agent = McstMemoryAgent::with_budget(agent, Budget::Millis(params.get("time", 1000)?));
+            agent.set_pondering(params.get("ponder", false)?);
+            agent.set_threads(params.get("threads", 1)?, move |_| Box::new(rollout()));
+            Ok(Box::new(agent))
+        });
+        registry.register("solver", |params| Ok(Box::new(MemorifiedAgent::new(
+            SolverAgent::new(params.get("empties", 12)?, GreedyAgent::new())
+        ))));
+        registry
+    }
+
+    /// Makes `constructor` build the agent called `name`,
+    /// replacing any agent already registered under it.
+    pub fn register<F>(&mut self, name: &str, constructor: F)
+    where
+        F: Fn(&Params) -> Result<Box<dyn MemoryAgent>, SpecError> + Send + Sync + 'static,
+    {
+        self.constructors.insert(name.to_string(), Box::new(constructor));
+    }
+
+    /// Returns the names of the registered agents, in alphabetical order.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.constructors.keys().map(String::as_str).collect();
+        names.sort();
+        names
+    }
+
+    /// Builds the agent described by `spec`.
+    pub fn create(&self, spec: &str) -> Result<Box<dyn MemoryAgent>, SpecError> {
+        let (name, list) = spec.trim().split_once(':').unwrap_or((spec.trim(), ""));
+        let constructor = self.constructors.get(name)
+            .ok_or_else(|| SpecError::UnknownAgent(name.to_string()))?;
+
+        let mut values = HashMap::new();
+        for param in list.split(',').map(str::trim).filter(|param| !param.is_empty()) {
+            let Some((key, value)) = param.split_once('=') else {
+                return Err(SpecError::BadSyntax(param.to_string()));
+            };
+            if values.insert(key.trim().to_string(), value.trim().to_string()).is_some() {
+                return Err(SpecError::BadSyntax(param.to_string()));
+            }
+        }
+        let params = Params { agent: name.to_string(), values, read: RefCell::new(HashSet::new()) };
+
+        let agent = constructor(&params)?;
+        let read = params.read.borrow();
+        let mut unknown: Vec<&String> = params.values.keys().filter(|key| !read.contains(*key)).collect();
+        unknown.sort();
+        match unknown.first() {
+            Some(key) => Err(SpecError::UnknownParameter { agent: name.to_string(), name: key.to_string() }),
+            None => Ok(agent),
+        }
+    }
+}
+
+impl Default for Registry {
+    /// Same as [Registry::with_builtins].
+    fn default() -> Self {
+        Registry::with_builtins()
+    }
+}
+
+/// Reads a [RankedCellAgent] ranking: 8 lines of 8 numbers separated by
+/// spaces, one line per row. Blank lines and lines starting with `#` are skipped.
+fn load_ranking(path: &str) -> Result<[[f64; 8]; 8], SpecError> {
+    let error = |error: String| SpecError::File { path: path.to_string(), error };
+    let contents = fs::read_to_string(path).map_err(|e| error(e.to_string()))?;
+    let rows: Vec<[f64; 8]> = contents.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let values: Vec<f64> = line.split_whitespace()
+                .map(str::parse)
+                .collect::<Result<_, _>>()
+                .map_err(|_| error(format!("{line:?} is not a row of numbers")))?;
+            values.try_into().map_err(|_| error(format!("{line:?} is not 8 numbers")))
+        })
+        .collect::<Result<_, _>>()?;
+    rows.try_into().map_err(|rows: Vec<_>| error(format!("there are {} rows, not 8", rows.len())))
+}
+
+/// The registry used by [create] and [register].
+static REGISTRY: LazyLock<Mutex<Registry>> = LazyLock::new(|| Mutex::new(Registry::with_builtins()));
+
+/// Builds the agent described by `spec` from the global registry, which starts
+/// with the agents listed in [Registry::with_builtins].
+pub fn create(spec: &str) -> Result<Box<dyn MemoryAgent>, SpecError> {
+    REGISTRY.lock().unwrap().create(spec)
+}
+
+/// Adds an agent to the global registry used by [create].
+/// See [Registry::register].
+pub fn register<F>(name: &str, constructor: F)
+where
+    F: Fn(&Params) -> Result<Box<dyn MemoryAgent>, SpecError> + Send + Sync + 'static,
+{
+    REGISTRY.lock().unwrap().register(name, constructor);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::play_game;
+
+    #[test]
+    fn test_create() {
+        for spec in [
+            "random", "random:seed=3", "greedy:tie=corners", "mobility", "ranked", "phased",
+            "alphabeta:depth=2", "deepening:time=1", "mcst:c=1.41,time=1", "mcst: time = 1 , ponder=true",
+            "mcst:time=1,threads=2", "mcst:time=1,nodes=100", "mcst:time=1,tuned=true,fpu=0.5",
+            "mcst:time=1,plies=10,threads=2", "mcst:time=1,contempt=-0.1",
+            "solver:empties=8",
+        ] {
+            assert!(create(spec).is_ok(), "{spec}");
+        }
+
+        // seeded agents are built the same way every time
+        let (_, first) = play_game(&mut *create("random:seed=3").unwrap(), &mut *create("random:seed=4").unwrap());
+        let (_, second) = play_game(&mut *create("random:seed=3").unwrap(), &mut *create("random:seed=4").unwrap());
+        assert_eq!(first, second);
+        // as is greedy, which plays the first of its best moves like GreedyAgent::new
+        let (_, greedy) = play_game(&mut *create("greedy").unwrap(), &mut *create("greedy:tie=first").unwrap());
+        let (_, plain) = play_game(&mut MemorifiedAgent::new(GreedyAgent::new()), &mut MemorifiedAgent::new(GreedyAgent::new()));
+        assert_eq!(greedy, plain);
+    }
+
+    #[test]
+    fn test_create_errors() {
+        let unknown = |agent: &str, name: &str| SpecError::UnknownParameter {
+            agent: agent.to_string(),
+            name: name.to_string(),
+        };
+        let bad = |agent: &str, name: &str, value: &str| SpecError::BadParameter {
+            agent: agent.to_string(),
+            name: name.to_string(),
+            value: value.to_string(),
+        };
+        assert_eq!(create("minimax").err(), Some(SpecError::UnknownAgent(String::from("minimax"))));
+        assert_eq!(create("mcst:c").err(), Some(SpecError::BadSyntax(String::from("c"))));
+        assert_eq!(create("mcst:c=1,c=2").err(), Some(SpecError::BadSyntax(String::from("c=2"))));
+        assert_eq!(create("mobility:depth=3").err(), Some(unknown("mobility", "depth")));
+        assert_eq!(create("mcst:time=1,cc=2").err(), Some(unknown("mcst", "cc")));
+        assert_eq!(create("mcst:c=big").err(), Some(bad("mcst", "c", "big")));
+        assert_eq!(create("mcst:contempt=0.6").err(), Some(bad("mcst", "contempt", "0.6")));
+        assert_eq!(create("alphabeta:depth=-1").err(), Some(bad("alphabeta", "depth", "-1")));
+        assert_eq!(create("greedy:tie=last").err(), Some(bad("greedy", "tie", "last")));
+        assert!(matches!(create("ranked:file=/nonexistent/weights.txt"), Err(SpecError::File { .. })));
+    }
+
+    #[test]
+    fn test_ranked_file() {
+        let path = std::env::temp_dir().join(format!("othello-ranking-{}.txt", std::process::id()));
+        let row = "1 2 3 4 5 6 7 8\n";
+        fs::write(&path, format!("# a ranking\n{}", row.repeat(8))).unwrap();
+        let good = create(&format!("ranked:file={}", path.display()));
+        fs::write(&path, row.repeat(7)).unwrap();
+        let short = load_ranking(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+
+        assert!(good.is_ok());
+        assert!(matches!(short, Err(SpecError::File { .. })));
+    }
+
+    #[test]
+    fn test_register() {
+        let mut registry = Registry::new();
+        assert!(registry.names().is_empty());
+        registry.register("seeded", |params| Ok(Box::new(MemorifiedAgent::new(
+            RandomAgent::seeded(params.require("seed")?)
+        ))));
+        assert_eq!(registry.names(), vec!["seeded"]);
+        assert!(registry.create("seeded:seed=1").is_ok());
+        assert_eq!(registry.create("seeded").err(), Some(SpecError::MissingParameter {
+            agent: String::from("seeded"),
+            name: String::from("seed"),
+        }));
+        assert!(registry.create("random").is_err());
+
+        register("greedier", |_| Ok(Box::new(MemorifiedAgent::new(GreedyAgent::new()))));
+        assert!(create("greedier").is_ok());
+    }
+}
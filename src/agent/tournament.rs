@@ -0,0 +1,839 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::time::Duration;
+
+use serde::de::{Error as _, Unexpected};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::agent::{play_game_recorded, MemoryAgent};
+use crate::gameplay::{alg_to_loc, loc_to_alg, GameResult, Gamestate, Players, Turn};
+use crate::mcst::{Analysis, MoveStats, SearchSummary, TimeAllotment};
+
+/// One turn of a [GameRecord].
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlyRecord {
+    pub turn: Turn,
+    /// The tiles the turn flipped, not including the one placed.
+    pub flips: Vec<(u8, u8)>,
+    /// How long the agent took to choose the turn.
+    pub time: Duration,
+    /// The score (Black's tiles minus White's) after the turn.
+    pub score: i8,
+    /// What the agent thought of the position, if it could say
+    /// (see [MemoryAgent::analysis]).
+    pub analysis: Option<Analysis>,
+}
+
+/// A game played between two agents, as recorded by [play_game_recorded].
+///
+/// It is serialized as the JSON [GameRecord::to_json] writes.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(into = "JsonRecord", try_from = "JsonRecord")]
+pub struct GameRecord {
+    /// The name of the agent which played Black.
+    pub black: String,
+    /// The name of the agent which played White.
+    pub white: String,
+    /// The [compact form](crate::mechanics::Board::to_compact) of the starting board.
+    pub start: u128,
+    pub plies: Vec<PlyRecord>,
+    pub result: GameResult,
+}
+
+impl GameRecord {
+    /// Creates a record of a game without any plies, starting from the
+    /// standard position, for when only its result is known.
+    pub fn from_result(black: &str, white: &str, result: GameResult) -> Self {
+        GameRecord {
+            black: black.to_string(),
+            white: white.to_string(),
+            start: Gamestate::new().board().to_compact(),
+            plies: Vec::new(),
+            result,
+        }
+    }
+
+    /// Returns every turn taken in the game.
+    pub fn turns(&self) -> Vec<Turn> {
+        self.plies.iter().map(|ply| ply.turn).collect()
+    }
+
+    /// Formats the record as a single line of JSON, with tiles in algebraic
+    /// notation (a pass is `null`), times in seconds, the result as the final
+    /// score (Black's tiles minus White's), and the starting board as a string
+    /// since it is too large for most JSON readers' numbers. A ply's analysis,
+    /// if it has one, is given as its `pv` and its `moves` with their
+    /// `visits` and `win_rate`, and the summary of its search, if it has
+    /// one, as `search` with its `cycles`, `average_depth` and `rollouts_per_sec`,
+    /// and the time it was allotted, if it was, as `clock` with its
+    /// `remaining`, `allotted` and `used`.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&JsonRecord::from(self)).expect("a record is always valid JSON")
+    }
+
+    /// Reads back a record written by [to_json](GameRecord::to_json).
+    ///
+    /// Times are only as exact as their seconds were written, and fields
+    /// [to_json](GameRecord::to_json) doesn't write are ignored.
+    pub fn from_json(line: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(line)
+    }
+}
+
+/// A tile in algebraic notation, such as `"d3"`.
+pub(super) struct Tile(pub(super) (u8, u8));
+
+impl Serialize for Tile {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        loc_to_alg(self.0).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Tile {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let alg = String::deserialize(deserializer)?;
+        alg_to_loc(&alg).map(Tile).ok_or_else(|| D::Error::invalid_value(Unexpected::Str(&alg), &"a tile such as \"d3\""))
+    }
+}
+
+/// A length of time in seconds, which can't be negative.
+pub(super) struct Seconds(pub(super) Duration);
+
+impl Serialize for Seconds {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.as_secs_f64().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Seconds {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let seconds = f64::deserialize(deserializer)?;
+        Duration::try_from_secs_f64(seconds)
+            .map(Seconds)
+            .map_err(|_| D::Error::invalid_value(Unexpected::Float(seconds), &"a number of seconds"))
+    }
+}
+
+/// A [compact board](crate::mechanics::Board::to_compact) written as a string.
+pub(super) struct CompactString(pub(super) u128);
+
+impl Serialize for CompactString {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.to_string().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for CompactString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let compact = String::deserialize(deserializer)?;
+        compact.parse().map(CompactString)
+            .map_err(|_| D::Error::invalid_value(Unexpected::Str(&compact), &"a compact board"))
+    }
+}
+
+/// Converts a turn to the tile it is on, if it isn't a pass.
+pub(super) fn to_tile(turn: Turn) -> Option<Tile> {
+    turn.map(Tile)
+}
+
+/// Converts a tile, or `None` for a pass, back to a turn.
+fn from_tile(tile: Option<Tile>) -> Turn {
+    tile.map(|Tile(loc)| loc)
+}
+
+// Turns are read with `Option::deserialize` so that a missing turn is an
+// error rather than a pass.
+
+/// A [GameRecord] as [GameRecord::to_json] writes it.
+#[derive(Serialize, Deserialize)]
+struct JsonRecord {
+    black: String,
+    white: String,
+    start: CompactString,
+    plies: Vec<JsonPly>,
+    result: i8,
+}
+
+/// A [PlyRecord] as [GameRecord::to_json] writes it, with its analysis
+/// spread over its own fields.
+#[derive(Serialize, Deserialize)]
+struct JsonPly {
+    #[serde(deserialize_with = "Option::deserialize")]
+    turn: Option<Tile>,
+    flips: Vec<Tile>,
+    time: Seconds,
+    score: i8,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pv: Option<Vec<Option<Tile>>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    moves: Option<Vec<JsonMoveStats>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    search: Option<JsonSearch>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    clock: Option<JsonClock>,
+}
+
+/// A [MoveStats] as [GameRecord::to_json] writes it.
+#[derive(Serialize, Deserialize)]
+struct JsonMoveStats {
+    #[serde(deserialize_with = "Option::deserialize")]
+    turn: Option<Tile>,
+    visits: u32,
+    win_rate: f64,
+}
+
+/// A [SearchSummary] as [GameRecord::to_json] writes it.
+#[derive(Serialize, Deserialize)]
+struct JsonSearch {
+    cycles: u64,
+    average_depth: f64,
+    rollouts_per_sec: f64,
+}
+
+/// A [TimeAllotment] as [GameRecord::to_json] writes it.
+#[derive(Serialize, Deserialize)]
+struct JsonClock {
+    remaining: Seconds,
+    allotted: Seconds,
+    used: Seconds,
+}
+
+impl From<&GameRecord> for JsonRecord {
+    fn from(record: &GameRecord) -> Self {
+        let plies = record.plies.iter()
+            .map(|ply| {
+                let analysis = ply.analysis.as_ref();
+                JsonPly {
+                    turn: to_tile(ply.turn),
+                    flips: ply.flips.iter().copied().map(Tile).collect(),
+                    time: Seconds(ply.time),
+                    score: ply.score,
+                    pv: analysis.map(|analysis| analysis.pv.iter().copied().map(to_tile).collect()),
+                    moves: analysis.map(|analysis| {
+                        analysis.moves.iter()
+                            .map(|stats| JsonMoveStats {
+                                turn: to_tile(stats.turn),
+                                visits: stats.visits,
+                                win_rate: stats.win_rate,
+                            })
+                            .collect()
+                    }),
+                    search: analysis.and_then(|analysis| analysis.search).map(|search| JsonSearch {
+                        cycles: search.cycles,
+                        average_depth: search.average_depth,
+                        rollouts_per_sec: search.rollouts_per_sec,
+                    }),
+                    clock: analysis.and_then(|analysis| analysis.clock).map(|clock| JsonClock {
+                        remaining: Seconds(clock.remaining),
+                        allotted: Seconds(clock.allotted),
+                        used: Seconds(clock.used),
+                    }),
+                }
+            })
+            .collect();
+        JsonRecord {
+            black: record.black.clone(),
+            white: record.white.clone(),
+            start: CompactString(record.start),
+            plies,
+            result: record.result.score(),
+        }
+    }
+}
+
+impl From<GameRecord> for JsonRecord {
+    fn from(record: GameRecord) -> Self {
+        JsonRecord::from(&record)
+    }
+}
+
+impl TryFrom<JsonRecord> for GameRecord {
+    type Error = serde_json::Error;
+
+    /// Returns the record `json` is of, failing if a ply has `moves` without
+    /// a `pv`, as [GameRecord::to_json] never writes.
+    fn try_from(json: JsonRecord) -> Result<Self, Self::Error> {
+        let mut plies = Vec::with_capacity(json.plies.len());
+        for ply in json.plies {
+            let analysis = match (ply.moves, ply.pv) {
+                (Some(moves), Some(pv)) => Some(Analysis {
+                    moves: moves.into_iter()
+                        .map(|stats| MoveStats { turn: from_tile(stats.turn), visits: stats.visits, win_rate: stats.win_rate })
+                        .collect(),
+                    pv: pv.into_iter().map(from_tile).collect(),
+                    search: ply.search.map(|search| SearchSummary {
+                        cycles: search.cycles,
+                        average_depth: search.average_depth,
+                        rollouts_per_sec: search.rollouts_per_sec,
+                    }),
+                    clock: ply.clock.map(|clock| TimeAllotment {
+                        remaining: clock.remaining.0,
+                        allotted: clock.allotted.0,
+                        used: clock.used.0,
+                    }),
+                }),
+                (Some(_), None) => return Err(serde_json::Error::missing_field("pv")),
+                (None, _) => None,
+            };
+            plies.push(PlyRecord {
+                turn: from_tile(ply.turn),
+                flips: ply.flips.into_iter().map(|Tile(loc)| loc).collect(),
+                time: ply.time.0,
+                score: ply.score,
+                analysis,
+            });
+        }
+        Ok(GameRecord {
+            black: json.black,
+            white: json.white,
+            start: json.start.0,
+            plies,
+            result: GameResult::from_score(json.result),
+        })
+    }
+}
+
+/// Wins, draws and losses from the point of view of one agent.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Record {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl Record {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Returns one point per win and half a point per draw.
+    pub fn points(&self) -> f64 {
+        f64::from(self.wins) + f64::from(self.draws) / 2_f64
+    }
+
+    fn add(&mut self, other: Record) {
+        self.wins += other.wins;
+        self.draws += other.draws;
+        self.losses += other.losses;
+    }
+}
+
+/// The outcome of a [round_robin] or [swiss] tournament.
+///
+/// Printing it shows a matrix of every agent's record against every other
+/// agent, followed by the overall standings.
+#[derive(Clone, Debug)]
+pub struct TournamentResult {
+    names: Vec<String>,
+    games: Vec<GameRecord>,
+}
+
+impl TournamentResult {
+    /// Returns the agents' names, in the order they were entered.
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Returns every game played, in the order they were played.
+    pub fn games(&self) -> &[GameRecord] {
+        &self.games
+    }
+
+    /// Returns the record of agent `a` in its games against agent `b`.
+    pub fn record(&self, a: usize, b: usize) -> Record {
+        let (a, b) = (&self.names[a], &self.names[b]);
+        let mut record = Record::default();
+        for game in &self.games {
+            let side = if (&game.black, &game.white) == (a, b) {
+                Players::Black
+            } else if (&game.black, &game.white) == (b, a) {
+                Players::White
+            } else {
+                continue;
+            };
+            match game.result.winner() {
+                None => record.draws += 1,
+                Some(winner) if winner == side => record.wins += 1,
+                Some(_) => record.losses += 1,
+            }
+        }
+        record
+    }
+
+    /// Returns the total record of agent `a` against every other agent.
+    pub fn total(&self, a: usize) -> Record {
+        let mut total = Record::default();
+        for b in (0..self.names.len()).filter(|&b| b != a) {
+            total.add(self.record(a, b));
+        }
+        total
+    }
+
+    /// Returns the Buchholz score of agent `a`: the sum of the total
+    /// [points](Record::points) of its opponent in every game it played,
+    /// which is higher for agents that faced stronger opposition.
+    pub fn buchholz(&self, a: usize) -> f64 {
+        let points: HashMap<&String, f64> = self.names.iter()
+            .enumerate()
+            .map(|(b, name)| (name, self.total(b).points()))
+            .collect();
+        let name = &self.names[a];
+        self.games.iter()
+            .filter_map(|game| match (&game.black, &game.white) {
+                (black, white) if black == name => Some(points[white]),
+                (black, white) if white == name => Some(points[black]),
+                _ => None,
+            })
+            .sum()
+    }
+
+    /// Returns the index and total record of every agent,
+    /// from most [points](Record::points) to fewest. Agents with as many
+    /// points as each other are ordered by their [buchholz](TournamentResult::buchholz)
+    /// score, and then by the order they were entered in.
+    pub fn standings(&self) -> Vec<(usize, Record)> {
+        let mut standings: Vec<(usize, Record, f64)> = (0..self.names.len())
+            .map(|a| (a, self.total(a), self.buchholz(a)))
+            .collect();
+        standings.sort_by(|(_, r1, b1), (_, r2, b2)| {
+            r2.points().total_cmp(&r1.points()).then(b2.total_cmp(b1))
+        });
+        standings.into_iter().map(|(a, record, _)| (a, record)).collect()
+    }
+}
+
+impl fmt::Display for TournamentResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let width = self.names.iter()
+            .map(|name| name.len())
+            .max()
+            .unwrap_or(0)
+            .max(8);
+
+        write!(f, "{:width$}", "")?;
+        for name in &self.names {
+            write!(f, " {name:>width$}")?;
+        }
+        writeln!(f)?;
+        for (a, name) in self.names.iter().enumerate() {
+            write!(f, "{name:width$}")?;
+            for b in 0..self.names.len() {
+                if a == b {
+                    write!(f, " {:>width$}", "-")?;
+                } else {
+                    let r = self.record(a, b);
+                    write!(f, " {:>width$}", format!("{}/{}/{}", r.wins, r.draws, r.losses))?;
+                }
+            }
+            writeln!(f)?;
+        }
+
+        writeln!(f)?;
+        writeln!(f, "{:width$} {:>6} {:>6} {:>6} {:>6}", "", "W", "D", "L", "Pts")?;
+        for (a, r) in self.standings() {
+            writeln!(
+                f, "{:width$} {:>6} {:>6} {:>6} {:>6.1}",
+                self.names[a], r.wins, r.draws, r.losses, r.points(),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Plays `games_per_pair` games between every pair of agents.
+///
+/// If `swap_colors` is set, every one of those games is played twice, once with
+/// each agent as Black; otherwise the agent entered first always plays Black.
+/// Every game starts from the standard position, and each agent is
+/// [initialized](MemoryAgent::initialize_game) before it, so agents which
+/// keep state between moves start every game afresh.
+pub fn round_robin(
+    mut agents: Vec<(&str, Box<dyn MemoryAgent>)>,
+    games_per_pair: u32,
+    swap_colors: bool,
+) -> TournamentResult {
+    let mut result = TournamentResult {
+        names: agents.iter().map(|(name, _)| name.to_string()).collect(),
+        games: Vec::new(),
+    };
+    for j in 1..agents.len() {
+        for i in 0..j {
+            for _ in 0..games_per_pair {
+                result.games.push(play_pairing_game(&mut agents, i, j));
+                if swap_colors {
+                    result.games.push(play_pairing_game(&mut agents, j, i));
+                }
+            }
+        }
+    }
+    result
+}
+
+/// Plays a game between `agents[black]` as Black and `agents[white]` as White
+/// from the standard position.
+fn play_pairing_game(agents: &mut [(&str, Box<dyn MemoryAgent>)], black: usize, white: usize) -> GameRecord {
+    let (black, white) = if black < white {
+        let (before, after) = agents.split_at_mut(white);
+        (&mut before[black], &mut after[0])
+    } else {
+        let (before, after) = agents.split_at_mut(black);
+        (&mut after[0], &mut before[white])
+    };
+    play_game_recorded(black.1.as_mut(), white.1.as_mut(), Gamestate::new(), (black.0, white.0))
+}
+
+/// Plays a Swiss-system tournament of `rounds` rounds, in which every agent
+/// plays `games_per_pairing` games against one other agent each round.
+///
+/// Each round the agents are ordered by their [standings](TournamentResult::standings)
+/// so far, and paired with the agents closest to them in that order that they
+/// have not played yet. They only play again if there is no other way to pair
+/// everyone. With an odd number of agents, the lowest placed agent which hasn't
+/// yet sat out a round sits this one out, with no games and no points.
+///
+/// Within a pairing, the agent which has played Black fewer times so far
+/// starts as Black, and the agents swap colors every game.
+///
+/// Far fewer games are played than in a [round_robin] with as many agents,
+/// while the strongest agents still meet each other in the later rounds.
+pub fn swiss(
+    mut agents: Vec<(&str, Box<dyn MemoryAgent>)>,
+    rounds: u32,
+    games_per_pairing: u32,
+) -> TournamentResult {
+    let mut result = TournamentResult {
+        names: agents.iter().map(|(name, _)| name.to_string()).collect(),
+        games: Vec::new(),
+    };
+    let mut met: HashSet<(usize, usize)> = HashSet::new();
+    let mut sat_out: HashSet<usize> = HashSet::new();
+    let mut blacks = vec![0_u32; agents.len()];
+
+    for _ in 0..rounds {
+        let mut order: Vec<usize> = result.standings().into_iter().map(|(a, _)| a).collect();
+        if order.len() % 2 == 1 {
+            let bye = order.iter().rposition(|a| !sat_out.contains(a)).unwrap_or(order.len() - 1);
+            sat_out.insert(order.remove(bye));
+        }
+        let pairings = pair_without_rematches(&order, &met)
+            .unwrap_or_else(|| order.chunks(2).map(|pair| (pair[0], pair[1])).collect());
+
+        for (a, b) in pairings {
+            met.insert((a.min(b), a.max(b)));
+            let (mut black, mut white) = if blacks[b] < blacks[a] { (b, a) } else { (a, b) };
+            for _ in 0..games_per_pairing {
+                result.games.push(play_pairing_game(&mut agents, black, white));
+                blacks[black] += 1;
+                (black, white) = (white, black);
+            }
+        }
+    }
+    result
+}
+
+/// Pairs up the agents in `order` (of which there must be an even number)
+/// so that no pair is in `met`, preferring to pair agents close together in
+/// `order`. Returns [None] if that is impossible.
+fn pair_without_rematches(order: &[usize], met: &HashSet<(usize, usize)>) -> Option<Vec<(usize, usize)>> {
+    let Some((&a, rest)) = order.split_first() else {
+        return Some(Vec::new());
+    };
+    for (index, &b) in rest.iter().enumerate() {
+        if met.contains(&(a.min(b), a.max(b))) {
+            continue;
+        }
+        let mut others = rest.to_vec();
+        others.remove(index);
+        if let Some(mut pairings) = pair_without_rematches(&others, met) {
+            pairings.insert(0, (a, b));
+            return Some(pairings);
+        }
+    }
+    None
+}
+
+/// An Elo rating as computed by [compute_elo].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rating {
+    pub rating: f64,
+    /// The standard error of the rating, which shrinks as more games are played.
+    /// Ratings within about two errors of each other are not clearly different.
+    pub error: f64,
+}
+
+/// How far a rating may drift from the anchor, which bounds the ratings of
+/// agents that won (or lost) every game and so have no best fit.
+const MAX_RATING_DIFFERENCE: f64 = 1000_f64;
+
+/// Computes an Elo rating for every agent that appears in `results`.
+///
+/// Rather than updating ratings once per game, in an order-dependent way,
+/// every pass applies the Elo update with factor `k` to each agent's total
+/// over all its games (divided by how many games it played), until the ratings
+/// settle on the ones which best explain the results. After every pass all
+/// ratings are shifted so that the agent named by `anchor` keeps its rating,
+/// which makes the ratings from different runs comparable as long as they share
+/// an anchor. Agents with no connection to the anchor through the games played
+/// keep their starting rating, that of the anchor.
+///
+/// A `k` of `32` converges quickly; much larger values may not converge at all.
+pub fn compute_elo(results: &[GameRecord], k: f64, anchor: (&str, f64)) -> HashMap<String, Rating> {
+    const SCALE: f64 = 400_f64;
+    let expected = |r1: f64, r2: f64| 1_f64 / (1_f64 + 10_f64.powf((r2 - r1) / SCALE));
+
+    let mut ratings: HashMap<String, f64> = HashMap::new();
+    ratings.insert(anchor.0.to_string(), anchor.1);
+    let mut games: HashMap<&str, u32> = HashMap::new();
+    for game in results {
+        for name in [&game.black, &game.white] {
+            ratings.entry(name.clone()).or_insert(anchor.1);
+            *games.entry(name).or_default() += 1;
+        }
+    }
+
+    for _ in 0..10_000 {
+        let mut deltas: HashMap<&str, f64> = HashMap::new();
+        for game in results {
+            let black_expected = expected(ratings[&game.black], ratings[&game.white]);
+            let black_score = match game.result.winner() {
+                Some(Players::Black) => 1_f64,
+                Some(Players::White) => 0_f64,
+                None => 0.5_f64,
+            };
+            *deltas.entry(&game.black).or_default() += black_score - black_expected;
+            *deltas.entry(&game.white).or_default() += black_expected - black_score;
+        }
+
+        let mut change = 0_f64;
+        for (name, delta) in deltas {
+            let delta = k * delta / f64::from(games[name]);
+            *ratings.get_mut(name).unwrap() += delta;
+            change = change.max(delta.abs());
+        }
+        let shift = anchor.1 - ratings[anchor.0];
+        for rating in ratings.values_mut() {
+            *rating = (*rating + shift)
+                .clamp(anchor.1 - MAX_RATING_DIFFERENCE, anchor.1 + MAX_RATING_DIFFERENCE);
+        }
+        if change < 1e-6 {
+            break;
+        }
+    }
+
+    // the error comes from the curvature of the likelihood around each rating
+    let mut information: HashMap<&str, f64> = HashMap::new();
+    for game in results {
+        let p = expected(ratings[&game.black], ratings[&game.white]);
+        let info = (10_f64.ln() / SCALE).powi(2) * p * (1_f64 - p);
+        *information.entry(&game.black).or_default() += info;
+        *information.entry(&game.white).or_default() += info;
+    }
+    ratings.iter()
+        .map(|(name, &rating)| {
+            let error = if name == anchor.0 {
+                0_f64
+            } else {
+                information.get(name.as_str()).map_or(f64::INFINITY, |info| info.sqrt().recip())
+            };
+            (name.clone(), Rating { rating, error })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::error::Category;
+
+    use super::*;
+    use crate::agent::MemorifiedAgent;
+    use crate::agent::implementations::{
+        BfsExpansion, GreedyAgent, RandomAgent, RankedCellAgent, UctDecision, UctSelection,
+        WeightedSquareEvaluator,
+    };
+    use crate::mcst::McstAgent;
+
+    #[test]
+    fn test_round_robin() {
+        let ranked = RankedCellAgent::new(WeightedSquareEvaluator::STANDARD_WEIGHTS);
+        let agents: Vec<(&str, Box<dyn MemoryAgent>)> = vec![
+            ("random", Box::new(MemorifiedAgent::new(RandomAgent::seeded(1)))),
+            ("greedy", Box::new(MemorifiedAgent::new(GreedyAgent::new()))),
+            ("ranked", Box::new(MemorifiedAgent::new(ranked))),
+        ];
+        let result = round_robin(agents, 3, true);
+
+        assert_eq!(result.names(), ["random", "greedy", "ranked"]);
+        assert_eq!(result.games().len(), 3 * 3 * 2);
+        for game in result.games() {
+            assert_ne!(game.black, game.white);
+            assert!(!game.plies.is_empty());
+        }
+        for a in 0..3 {
+            assert_eq!(result.total(a).games(), 12);
+            for b in (0..3).filter(|&b| b != a) {
+                let (r1, r2) = (result.record(a, b), result.record(b, a));
+                assert_eq!(r1.games(), 6);
+                assert_eq!((r1.wins, r1.draws, r1.losses), (r2.losses, r2.draws, r2.wins));
+            }
+        }
+        let standings = result.standings();
+        let points: f64 = standings.iter().map(|(_, r)| r.points()).sum();
+        assert_eq!(points, 18_f64);
+        assert!(standings.windows(2).all(|w| w[0].1.points() >= w[1].1.points()));
+        assert!(result.to_string().contains("ranked"));
+    }
+
+    /// An agent which runs `cycles` cycles of MCTS with seeded playouts for
+    /// every move, so that more cycles make for a stronger agent.
+    fn mcst(cycles: u32) -> Box<dyn MemoryAgent> {
+        Box::new(MemorifiedAgent::new(move |state: &Gamestate| {
+            let seed = u64::from(state.ply());
+            let mut agent = McstAgent::new(
+                UctSelection::new(2_f64.sqrt()),
+                BfsExpansion {},
+                UctDecision {},
+                RandomAgent::seeded(seed),
+                RandomAgent::seeded(seed + 1000),
+                state.clone(),
+            );
+            for _ in 0..cycles {
+                if !agent.cycle().unwrap() {
+                    break;
+                }
+            }
+            agent.decide().unwrap()
+        }))
+    }
+
+    #[test]
+    fn test_swiss() {
+        let agents: Vec<(&str, Box<dyn MemoryAgent>)> = vec![
+            ("1x", mcst(30)),
+            ("10x", mcst(300)),
+            ("random", Box::new(MemorifiedAgent::new(RandomAgent::seeded(1)))),
+            ("3x", mcst(90)),
+        ];
+        let result = swiss(agents, 2, 2);
+
+        // four pairings rather than the six of a round robin, with no rematches
+        assert_eq!(result.games().len(), 2 * 2 * 2);
+        let mut pairings: Vec<(&String, &String)> = result.games().iter()
+            .map(|game| ((&game.black).min(&game.white), (&game.black).max(&game.white)))
+            .collect();
+        pairings.dedup();
+        assert_eq!(pairings.len(), 4);
+        for pairing in result.games().chunks(2) {
+            let blacks = pairing.iter().filter(|game| game.black == pairing[0].black).count();
+            assert_eq!(blacks, 1);
+        }
+
+        let ranking: Vec<&str> = result.standings().iter().map(|&(a, _)| result.names()[a].as_str()).collect();
+        assert_eq!(ranking, ["10x", "3x", "1x", "random"], "{result}");
+    }
+
+    #[test]
+    fn test_swiss_pairings() {
+        let met = HashSet::from([(0, 1), (2, 3), (0, 2)]);
+        assert_eq!(pair_without_rematches(&[0, 1, 2, 3], &met), Some(vec![(0, 3), (1, 2)]));
+        assert_eq!(pair_without_rematches(&[0, 1, 2, 3], &HashSet::new()), Some(vec![(0, 1), (2, 3)]));
+        let met = HashSet::from([(0, 1), (2, 3), (0, 2), (0, 3)]);
+        assert_eq!(pair_without_rematches(&[0, 1, 2, 3], &met), None);
+
+        // every agent sits out once in three rounds
+        let agents: Vec<(&str, Box<dyn MemoryAgent>)> = (0..3)
+            .map(|i| (["a", "b", "c"][i], Box::new(MemorifiedAgent::new(GreedyAgent::new())) as Box<dyn MemoryAgent>))
+            .collect();
+        let result = swiss(agents, 3, 1);
+        assert_eq!(result.games().len(), 3);
+        for a in 0..3 {
+            assert_eq!(result.total(a).games(), 2);
+        }
+    }
+
+    fn record(black: &str, white: &str, result: GameResult) -> GameRecord {
+        GameRecord::from_result(black, white, result)
+    }
+
+    #[test]
+    fn test_compute_elo() {
+        // new scores 75% against the anchor, half of the time as each color
+        let mut results = Vec::new();
+        for i in 0..100 {
+            let result = if i % 4 == 0 { GameResult::WhiteWin(2) } else { GameResult::BlackWin(2) };
+            results.push(match i % 2 {
+                0 => record("new", "anchor", result),
+                _ => record("anchor", "new", GameResult::from_score(-result.score())),
+            });
+        }
+        let ratings = compute_elo(&results, 32_f64, ("anchor", 1500_f64));
+        assert_eq!(ratings["anchor"], Rating { rating: 1500_f64, error: 0_f64 });
+        assert!((ratings["new"].rating - 1693_f64).abs() < 5_f64, "{:?}", ratings["new"]);
+
+        // a quarter as many games give twice the error
+        let few = compute_elo(&results[..25], 32_f64, ("anchor", 1500_f64));
+        let ratio = few["new"].error / ratings["new"].error;
+        assert!((ratio - 2_f64).abs() < 0.2, "{ratio}");
+
+        // draws count for half
+        let draw = [record("anchor", "drawer", GameResult::Draw)];
+        let ratings = compute_elo(&draw, 32_f64, ("anchor", 0_f64));
+        assert!(ratings["drawer"].rating.abs() < 1e-3);
+
+        // perfect scores stay finite
+        let win = [record("winner", "anchor", GameResult::BlackWin(64))];
+        let ratings = compute_elo(&win, 32_f64, ("anchor", 0_f64));
+        assert_eq!(ratings["winner"].rating, MAX_RATING_DIFFERENCE);
+    }
+
+    #[test]
+    fn test_game_record_from_json() {
+        let mut game = play_game_recorded(
+            &mut MemorifiedAgent::new(RandomAgent::seeded(1)),
+            &mut MemorifiedAgent::new(GreedyAgent::new()),
+            Gamestate::new(),
+            ("random \"one\"\\\n", "greedy \u{1f600}"),
+        );
+        game.plies[0].time = Duration::from_millis(1_250);
+        game.plies[1].analysis = Some(Analysis {
+            moves: vec![
+                MoveStats { turn: Some((2, 3)), visits: 40, win_rate: 0.625 },
+                MoveStats { turn: None, visits: 0, win_rate: 0.0 },
+            ],
+            pv: vec![Some((2, 3)), None, Some((7, 7))],
+            search: Some(SearchSummary { cycles: 40, average_depth: 2.5, rollouts_per_sec: 1e6 }),
+            clock: Some(TimeAllotment {
+                remaining: Duration::from_secs(60),
+                allotted: Duration::from_millis(500),
+                used: Duration::from_micros(499_875),
+            }),
+        });
+        game.plies[2].analysis = Some(Analysis { moves: Vec::new(), pv: Vec::new(), search: None, clock: None });
+        assert_eq!(GameRecord::from_json(&game.to_json()).unwrap(), game);
+        // which is the one way a record is serialized
+        assert_eq!(serde_json::to_string(&game).unwrap(), game.to_json());
+
+        // other ways of writing the same JSON
+        let spaced = r#" { "black" : "\u0062\ud83d\ude00", "white":"w", "start" : "1", "plies" : [ ],
+            "result" : -4, "comment": [true, false, null, {}, 1.5e3] } "#;
+        let spaced = GameRecord::from_json(spaced).unwrap();
+        assert_eq!(spaced.black, "b\u{1f600}");
+        assert_eq!((spaced.start, spaced.result), (1, GameResult::WhiteWin(4)));
+
+        let json = game.to_json();
+        for (line, category) in [
+            (&json[..json.len() - 1], Category::Eof),
+            ("{\"black\":\"\\ud83d\"}", Category::Syntax),
+            (&format!("{json} {{}}"), Category::Syntax),
+            ("{\"black\":1}", Category::Data),
+            (&json.replace("\"start\":\"", "\"start\":\"x"), Category::Data),
+            (&json.replacen("\"time\":1.25", "\"time\":-1", 1), Category::Data),
+            (&json.replacen("\"visits\":40", "\"visits\":4.5", 1), Category::Data),
+            (&json.replacen("\"turn\":\"", "\"turn\":\"z", 1), Category::Data),
+            (&json.replacen("\"turn\":", "\"move\":", 1), Category::Data),
+            (&json.replacen("\"pv\":", "\"line\":", 1), Category::Data),
+            (&json.replacen("\"result\":", "\"score\":", 1), Category::Data),
+        ] {
+            assert_eq!(GameRecord::from_json(line).unwrap_err().classify(), category, "{line}");
+        }
+    }
+}
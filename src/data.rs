@@ -1,12 +1,36 @@
-use std::collections::{HashMap, VecDeque};
+pub mod wthor;
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Cursor, Write};
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
 
 use magpie::othello::Game;
+use rand::distr::Distribution;
+use rand::distr::weighted::WeightedIndex;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
 
-use crate::agent::implementations::{BfsExpansion, McstMemoryAgent, RandomAgent, UctDecision, UctSelection};
-use crate::agent::{Agent, MemoryAgent};
-use crate::gameplay::{str_to_loc, Gamestate, Players, States, Turn};
+use crate::agent::implementations::{
+    BfsExpansion, Evaluator, GreedyAgent, McstMemoryAgent, MixtureAgent, RandomAgent, TieBreak, UctDecision,
+    UctSelection, WeightedSquareEvaluator,
+};
+use crate::agent::registry;
+use crate::agent::tournament::{GameRecord, PlyRecord};
+use crate::agent::{play_game, play_game_recorded, Agent, MemoryAgent};
+use crate::gameplay::{
+    str_to_loc, turn_to_index, GameResult, Gamestate, Players, Turn,
+};
+pub use crate::gameplay::{transcript_to_turns, turns_to_transcript, TranscriptError};
 use crate::mcst::{McstAgent, McstNode, McstTree};
-use crate::mechanics::Board;
+use crate::mechanics::{Board, Symmetry};
 
 #[derive(PartialEq)]
 enum BAGState {
@@ -85,14 +109,11 @@ impl BfsAllGamestates {
     fn go_back(&mut self) {
         if let Some(turn) = self.turns.pop() {
             // undo a turn - unflip pieces and remove placed piece if not pass
-            let flipped_color = if self.turns.len() % 2 == 0 { Players::White } else { Players::Black };
-            self.board = self.state.board().clone();
-
-            for (x, y) in self.flips.pop().unwrap() {
-                self.board.change(x, y, States::Taken(flipped_color));
-            }
+            let flips = self.flips.pop().unwrap();
+            self.board = *self.state.board();
             if let Some((x, y)) = turn {
-                self.board.change(x, y, States::Empty);
+                let mover = if self.turns.len() % 2 == 0 { Players::Black } else { Players::White };
+                self.board.unapply_move(x, y, mover, &flips);
             }
             self.state = Gamestate::new_from(self.board, u8::try_from(self.turns.len()).unwrap());
             assert!(self.state.get_moves().contains(&turn));
@@ -144,33 +165,270 @@ impl Iterator for BfsAllGamestates {
     }
 }
 
-/// Converts a list of turns to a String representing them.
+/// Iterator over every position reachable from the starting position, level
+/// by level like [BfsAllGamestates], but yielding each position only once,
+/// however many orders of moves reach it, along with one sequence of turns
+/// which does.
+///
+/// By default positions which are rotations or reflections of each other
+/// (which have the same [canonical](Board::canonical) board) are the same
+/// position, and only the first found is yielded; [UniqueGamestates::exact]
+/// tells them apart. Positions at the same level with the same board have
+/// had the same number of passes, so the same player to move, and a board is
+/// only compared with the others of its level. Only the keys of the level
+/// being found are kept, though the positions of the level being yielded
+/// and the level being found are kept too.
+pub struct UniqueGamestates {
+    /// The positions of the current level which haven't been yielded yet.
+    level: std::vec::IntoIter<(Gamestate, Vec<Turn>)>,
+    /// The positions of the next level found so far.
+    next: Vec<(Gamestate, Vec<Turn>)>,
+    /// The keys of the positions in `next`.
+    seen: HashSet<u128>,
+    canonical: bool,
+}
+
+impl UniqueGamestates {
+    /// Creates an iterator yielding each position once up to symmetry.
+    pub fn new() -> Self {
+        UniqueGamestates::with_canonical(true)
+    }
+
+    /// Creates an iterator yielding each position once, telling apart
+    /// positions which are rotations or reflections of each other.
+    pub fn exact() -> Self {
+        UniqueGamestates::with_canonical(false)
+    }
+
+    fn with_canonical(canonical: bool) -> Self {
+        UniqueGamestates {
+            level: vec![(Gamestate::new(), Vec::new())].into_iter(),
+            next: Vec::new(),
+            seen: HashSet::new(),
+            canonical,
+        }
+    }
+}
+
+impl Default for UniqueGamestates {
+    fn default() -> Self {
+        UniqueGamestates::new()
+    }
+}
+
+impl Iterator for UniqueGamestates {
+    type Item = (Gamestate, Vec<Turn>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((state, turns)) = self.level.next() {
+                for (turn, child) in state.children() {
+                    let key = if self.canonical { child.board().canonical().0 } else { child.board().to_compact() };
+                    if self.seen.insert(key) {
+                        let mut child_turns = turns.clone();
+                        child_turns.push(turn);
+                        self.next.push((child, child_turns));
+                    }
+                }
+                return Some((state, turns));
+            }
+            if self.next.is_empty() {
+                return None;
+            }
+            // every position of the next level has been found
+            self.level = std::mem::take(&mut self.next).into_iter();
+            self.seen = HashSet::new();
+        }
+    }
+}
+
+/// A filter on the positions a [RandomPositionSampler] yields.
+pub type PositionFilter = dyn FnMut(&Gamestate) -> bool;
+
+/// Infinite iterator over positions from all through the game, each from a
+/// fresh game played by an agent against itself, for positions deeper than
+/// [UniqueGamestates] can reach.
+///
+/// Each position is one of the game's, the starting and final positions
+/// included, chosen uniformly, unless the sampler is given a
+/// [distribution of plies](RandomPositionSampler::with_ply_weights).
+pub struct RandomPositionSampler<A: Agent = RandomAgent<StdRng>> {
+    agent: A,
+    rng: StdRng,
+    ply_weights: Option<WeightedIndex<f64>>,
+    filter: Option<Box<PositionFilter>>,
+    /// The turns to the last position yielded.
+    turns: Vec<Turn>,
+}
+
+impl RandomPositionSampler {
+    /// Creates a sampler of positions from random games, determined by
+    /// `seed`.
+    pub fn new(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        RandomPositionSampler::with_agent(RandomAgent::seeded(rng.random()), rng.random())
+    }
+}
+
+impl RandomPositionSampler<MixtureAgent<GreedyAgent<StdRng>, RandomAgent<StdRng>, StdRng>> {
+    /// Creates a sampler of positions from games in which each move is
+    /// random with probability `epsilon`, and [greedy](GreedyAgent)
+    /// otherwise, determined by `seed`.
+    ///
+    /// # Panics
+    /// If `epsilon` is not between 0 and 1.
+    pub fn epsilon_greedy(seed: u64, epsilon: f64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let agent = MixtureAgent::from_rng(
+            GreedyAgent::from_rng(TieBreak::Random, StdRng::seed_from_u64(rng.random())),
+            RandomAgent::seeded(rng.random()),
+            epsilon,
+            StdRng::seed_from_u64(rng.random()),
+        );
+        RandomPositionSampler::with_agent(agent, rng.random())
+    }
+}
+
+impl<A: Agent> RandomPositionSampler<A> {
+    /// Creates a sampler of positions from games `agent` plays against
+    /// itself, choosing positions with an RNG seeded with `seed`.
+    pub fn with_agent(agent: A, seed: u64) -> Self {
+        RandomPositionSampler {
+            agent,
+            rng: StdRng::seed_from_u64(seed),
+            ply_weights: None,
+            filter: None,
+            turns: Vec::new(),
+        }
+    }
+
+    /// Chooses the ply of each position with chance in proportion to its
+    /// weight in `weights`, where the first weight is for the starting
+    /// position. A game which ends before the chosen ply is thrown away.
+    ///
+    /// # Panics
+    /// If a weight is negative or not finite, or every weight is 0.
+    pub fn with_ply_weights(mut self, weights: &[f64]) -> Self {
+        self.ply_weights = Some(WeightedIndex::new(weights).expect("ply weights are not a distribution"));
+        self
+    }
+
+    /// Only yields positions `filter` accepts, playing new games until it
+    /// does, so it has to accept some of the positions on offer.
+    pub fn with_filter(mut self, filter: impl FnMut(&Gamestate) -> bool + 'static) -> Self {
+        self.filter = Some(Box::new(filter));
+        self
+    }
+
+    /// Returns the turns from the starting position to the last position
+    /// yielded.
+    pub fn last_turns(&self) -> &[Turn] {
+        &self.turns
+    }
+
+    /// Plays a game up to ply `target`, or to its end if it is [None],
+    /// leaving its turns in `self.turns`, and returns whether it got there.
+    fn play_to(&mut self, target: Option<usize>) -> bool {
+        self.turns.clear();
+        let mut state = Gamestate::new();
+        while !state.is_terminal() && target.is_none_or(|target| self.turns.len() < target) {
+            let turn = self.agent.make_move(&state);
+            state.make_move_fast(turn);
+            self.turns.push(turn);
+        }
+        target.is_none_or(|target| self.turns.len() == target)
+    }
+}
+
+impl<A: Agent> Iterator for RandomPositionSampler<A> {
+    type Item = Gamestate;
+
+    fn next(&mut self) -> Option<Gamestate> {
+        loop {
+            let target = self.ply_weights.as_ref().map(|weights| weights.sample(&mut self.rng));
+            if !self.play_to(target) {
+                continue;
+            }
+            if target.is_none() {
+                let ply = self.rng.random_range(0..=self.turns.len());
+                self.turns.truncate(ply);
+            }
+            let mut state = Gamestate::new();
+            state.make_moves_fast(&self.turns);
+            if self.filter.as_mut().is_none_or(|filter| filter(&state)) {
+                return Some(state);
+            }
+        }
+    }
+}
+
+/// Returns the share of a game from `state` that Black wins, counting a draw
+/// as half a win, when `agent` plays it out against itself: a label of a
+/// position by its outcome, for [sample_labeled].
+pub fn playout_outcome<A: Agent>(agent: &A, state: &Gamestate) -> f32 {
+    let mut state = state.clone();
+    while !state.is_terminal() {
+        state.make_move_fast(agent.make_move(&state));
+    }
+    match GameResult::from_score(state.score()) {
+        GameResult::BlackWin(_) => 1.0,
+        GameResult::Draw => 0.5,
+        GameResult::WhiteWin(_) => 0.0,
+    }
+}
+
+/// Takes `n` positions from `sampler`, such as a [RandomPositionSampler],
+/// and returns each as a [compact](Board::to_compact) board with the label
+/// `labeler` gives it, such as [playout_outcome] or a search's value.
+/// Positions sampled more than once appear more than once.
+pub fn sample_labeled(
+    n: usize,
+    sampler: impl Iterator<Item = Gamestate>,
+    mut labeler: impl FnMut(&Gamestate) -> f32,
+) -> Vec<(u128, f32)> {
+    sampler.take(n).map(|state| (state.board().to_compact(), labeler(&state))).collect()
+}
+
+/// The token for a pass in the strings of [turns_to_str].
+const PASS_TOKEN: &str = "P";
+
+/// Converts a list of turns to a String representing them, such as
+/// `1,2;P;3,4`: each move as `x,y` and each pass as `P`, separated by `;`.
+/// No turns at all are the empty string.
 pub fn turns_to_str(turns: &[Turn]) -> String {
     turns.iter().map(
         |t: &Turn| -> String {
             if let Some((x, y)) = t {
                 format!("{x},{y}")
             } else {
-                String::from("")
+                String::from(PASS_TOKEN)
             }
         }
     ).collect::<Vec<String>>().join(";")
 }
 
+/// Parses a list of turns written by [turns_to_str], or [None] if any of
+/// them isn't a move or a pass.
+///
+/// Strings written before passes were given a token, with a pass as an
+/// empty segment (such as `1,2;;3,4`), are still read, so an empty segment
+/// is a pass too. The empty string is no turns, though in the old format it
+/// was a single pass.
 pub fn str_to_turns(string: &str) -> Option<Vec<Turn>> {
-    let mut turns: Vec<Turn> = Vec::new();
-    for trial in string.split(";") {
-        if trial == "" {
-            turns.push(None);
-        } else {
-            if let Some(loc) = str_to_loc(trial) {
-                turns.push(Some(loc))
-            } else {
-                return None;
-            }
-        }
+    if string.is_empty() {
+        return Some(Vec::new());
+    }
+    string.split(";").map(str_to_turn).collect()
+}
+
+/// Parses one turn of a string of [str_to_turns], or [None] if it isn't a
+/// move or a pass.
+fn str_to_turn(segment: &str) -> Option<Turn> {
+    if segment == "" || segment.trim() == PASS_TOKEN {
+        Some(None)
+    } else {
+        str_to_loc(segment).map(Some)
     }
-    Some(turns)
 }
 
 pub fn turns_to_game_seeded(turns: &[Turn], mut g: Gamestate) -> Option<Vec<Gamestate>> {
@@ -191,11 +449,79 @@ pub fn turns_to_game(turns: &[Turn]) -> Option<Vec<Gamestate>> {
     turns_to_game_seeded(turns, Gamestate::new())
 }
 
-pub fn str_to_states(line: &str) -> (f32, Vec<Board>, Vec<Board>) {
-    let record: Vec<&str> = line.split(":").collect();
-    let score: f32 = record[0].parse().unwrap();
-    // you will probably have to do better error handling here one day
-    let games = turns_to_game(&str_to_turns(record[1]).unwrap()).unwrap();
+/// Why a line of a record file couldn't be read (see [DataParseError]).
+#[derive(Clone, Debug, PartialEq)]
+pub enum DataParseReason {
+    /// There is no `:` between the score and the turns.
+    NoTurns,
+    /// The score isn't a number.
+    BadScore,
+    /// A turn is neither a move (see [str_to_loc]) nor a pass.
+    BadTurn,
+    /// A turn can't be played after the `ply` turns before it.
+    IllegalMove { ply: usize },
+    /// The line isn't a [GameRecord] (see [GameRecordReader]), for the
+    /// reason given.
+    BadRecord(String),
+    /// A [GameRecord]'s starting board isn't a
+    /// [compact board](Board::to_compact).
+    BadStart,
+}
+
+/// Errors that can occur when reading the records of games with
+/// [str_to_states], [game_states_records] or [GameRecordReader].
+#[derive(Clone, Debug, PartialEq)]
+pub struct DataParseError {
+    /// The (1-based) number of the line, which is always 1 from
+    /// [str_to_states], as it reads a single line.
+    pub line: usize,
+    /// The part of the line at fault: the score, the turn, or the whole line
+    /// if it has no turns.
+    pub segment: String,
+    pub reason: DataParseReason,
+}
+
+impl fmt::Display for DataParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let DataParseError { line, segment, reason } = self;
+        match reason {
+            DataParseReason::NoTurns => write!(f, "line {line} is not `score:turns`: {segment:?}"),
+            DataParseReason::BadScore => write!(f, "score on line {line} is not a number: {segment:?}"),
+            DataParseReason::BadTurn => write!(f, "turn on line {line} is not a move or a pass: {segment:?}"),
+            DataParseReason::IllegalMove { ply } => {
+                write!(f, "turn {segment:?} on line {line} is illegal after {ply} turns")
+            }
+            DataParseReason::BadRecord(e) => write!(f, "line {line} is not a game record: {e}"),
+            DataParseReason::BadStart => write!(f, "starting board on line {line} is not a board: {segment:?}"),
+        }
+    }
+}
+
+impl std::error::Error for DataParseError {}
+
+/// Reads a record of a game as `score:turns`, where `score` is the share of
+/// the game White won and `turns` are as written by [turns_to_str], and
+/// returns the score, the positions with Black to move, and the positions
+/// with White to move turned and with their colors flipped, so that the
+/// player to move is always Black.
+pub fn str_to_states(line: &str) -> Result<(f32, Vec<Board>, Vec<Board>), DataParseError> {
+    let error = |segment: &str, reason| DataParseError { line: 1, segment: String::from(segment), reason };
+    let mut record = line.split(":");
+    let score_text = record.next().unwrap();
+    let turns_text = record.next().ok_or_else(|| error(line, DataParseReason::NoTurns))?;
+    let score: f32 = score_text.trim().parse().map_err(|_| error(score_text, DataParseReason::BadScore))?;
+
+    let mut game = Gamestate::new();
+    let mut games = vec![game.clone()];
+    if !turns_text.is_empty() {
+        for (ply, segment) in turns_text.split(";").enumerate() {
+            let turn = str_to_turn(segment).ok_or_else(|| error(segment, DataParseReason::BadTurn))?;
+            if !game.make_move_fast(turn) {
+                return Err(error(segment, DataParseReason::IllegalMove { ply }));
+            }
+            games.push(game.clone());
+        }
+    }
     let mut boards: Vec<Board> = Vec::new();
     let mut rot_boards: Vec<Board> = Vec::new();
 
@@ -211,85 +537,1443 @@ pub fn str_to_states(line: &str) -> (f32, Vec<Board>, Vec<Board>) {
         }
     };
 
-    (score, boards, rot_boards)
+    Ok((score, boards, rot_boards))
+}
+
+/// How [game_states_records] turns positions into keys, all off by default.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RecordOptions {
+    /// Count every position in all its [orientations](augment_symmetries)
+    /// too.
+    pub augment: bool,
+    /// Key every position by its [canonical](Board::canonical) board, so
+    /// that positions which are rotations or reflections of each other
+    /// share one record, with their counts merged. This makes `augment`
+    /// redundant, as every orientation of a position has the same key.
+    ///
+    /// A network trained on records keyed this way has only seen canonical
+    /// boards, so it should be given canonical boards to evaluate too, by
+    /// setting [ModelConfig::canonical](crate::neural::model_a::ModelConfig::canonical)
+    /// for both training and evaluation.
+    pub dedup_canonical: bool,
+    /// Leave out lines which can't be read, and count them, rather than
+    /// failing on the first.
+    pub skip_bad_lines: bool,
+}
+
+/// Returns every position of the games recorded in `contents` (as read by
+/// [str_to_states]) with the share of the games through it won by the
+/// player to move, keyed as `options` says, and how many lines were
+/// [skipped](RecordOptions::skip_bad_lines).
+///
+/// See [game_states_records_from] to read records without holding them all
+/// in memory.
+pub fn game_states_records(
+    contents: &str,
+    options: RecordOptions,
+) -> Result<(HashMap<u128, f32>, usize), DataParseError> {
+    game_states_records_weighted(contents, 1.0, options)
+}
+
+/// Returns `board` in each of its eight orientations (see [Symmetry]), as
+/// compact boards, each with `label`.
+///
+/// The boards of [str_to_states] are always from the point of view of the
+/// player to move, as Black, which no orientation changes, so neither do the
+/// colors or the label. A board with symmetries of its own appears more than
+/// once.
+pub fn augment_symmetries(board: &Board, label: f32) -> [(u128, f32); 8] {
+    Symmetry::ALL.map(|symmetry| {
+        let mut board = *board;
+        symmetry.apply(&mut board);
+        (board.to_compact(), label)
+    })
+}
+
+/// Returns whether a record line (as read by [str_to_states]) is for a game
+/// that was [adjudicated](crate::agent::play_game_adjudicated), which is
+/// marked by a trailing `:adjudicated`.
+pub fn str_is_adjudicated(line: &str) -> bool {
+    line.split(":").nth(2) == Some("adjudicated")
+}
+
+/// Writes a record of a game which went `turns` and ended in `result`, as
+/// read by [str_to_states], marking it as [adjudicated](str_is_adjudicated)
+/// if it was, as [play_game_adjudicated](crate::agent::play_game_adjudicated)
+/// says.
+pub fn game_to_str(result: GameResult, turns: &[Turn], adjudicated: bool) -> String {
+    // the share of the game White won
+    let score = match result.winner() {
+        Some(Players::Black) => "0.0",
+        Some(Players::White) => "1.0",
+        None => "0.5",
+    };
+    let marker = if adjudicated { ":adjudicated" } else { "" };
+    format!("{score}:{}{marker}", turns_to_str(turns))
+}
+
+/// Like [game_states_records], but games that were [adjudicated](str_is_adjudicated)
+/// only count `adjudicated_weight` times as much as games played to the end.
+/// A weight of `0` leaves them out.
+pub fn game_states_records_weighted(
+    contents: &str,
+    adjudicated_weight: f32,
+    options: RecordOptions,
+) -> Result<(HashMap<u128, f32>, usize), DataParseError> {
+    match game_states_records_weighted_from(Cursor::new(contents), adjudicated_weight, options, None) {
+        Ok(records) => Ok(records),
+        Err(DataError::Parse(e)) => Err(e),
+        Err(DataError::Io(e)) => unreachable!("reading a string failed: {e}"),
+    }
+}
+
+/// Errors that can occur when reading records of games with
+/// [game_states_records_from].
+#[derive(Debug)]
+pub enum DataError {
+    Io(io::Error),
+    Parse(DataParseError),
+}
+
+impl fmt::Display for DataError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DataError::Io(e) => write!(f, "could not read records: {e}"),
+            DataError::Parse(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for DataError {}
+
+impl From<io::Error> for DataError {
+    fn from(e: io::Error) -> Self {
+        DataError::Io(e)
+    }
+}
+
+impl From<DataParseError> for DataError {
+    fn from(e: DataParseError) -> Self {
+        DataError::Parse(e)
+    }
+}
+
+/// Like [game_states_records], but reads the records a line at a time from
+/// `reader`, so that only the positions are held in memory, and calls the
+/// callback of `progress` with the number of lines read after every so many
+/// lines.
+pub fn game_states_records_from(
+    reader: impl BufRead,
+    options: RecordOptions,
+    progress: Option<(usize, &mut dyn FnMut(usize))>,
+) -> Result<(HashMap<u128, f32>, usize), DataError> {
+    game_states_records_weighted_from(reader, 1.0, options, progress)
+}
+
+/// Like [game_states_records_from], but with adjudicated games weighted as
+/// by [game_states_records_weighted].
+pub fn game_states_records_weighted_from(
+    reader: impl BufRead,
+    adjudicated_weight: f32,
+    options: RecordOptions,
+    progress: Option<(usize, &mut dyn FnMut(usize))>,
+) -> Result<(HashMap<u128, f32>, usize), DataError> {
+    let (totals, skipped) = record_totals_from(reader, adjudicated_weight, options, progress)?;
+    Ok((totals.records(), skipped))
 }
 
-pub fn game_states_records(contents: &str) -> HashMap<u128, f32> {
-    let mut all_games = HashMap::<u128, (f32, f32)>::new();
-    for line in contents.split("\n") {
-        if line == "" {
+/// Like [game_states_records], but every record also has its weight: how
+/// many games went through its position, with adjudicated games counting
+/// `adjudicated_weight` times as in [game_states_records_weighted]. The
+/// records are in order of their keys.
+///
+/// Training on these weights, rather than on every position once, gives
+/// positions as much say as they have games behind them (see
+/// [write_weighted_csv]).
+pub fn game_states_records_counted(
+    contents: &str,
+    adjudicated_weight: f32,
+    options: RecordOptions,
+) -> Result<(WeightedBoards, usize), DataParseError> {
+    match record_totals_from(Cursor::new(contents), adjudicated_weight, options, None) {
+        Ok((totals, skipped)) => Ok((totals.weighted_records(), skipped)),
+        Err(DataError::Parse(e)) => Err(e),
+        Err(DataError::Io(e)) => unreachable!("reading a string failed: {e}"),
+    }
+}
+
+/// Adds up the positions of every game in `reader` for
+/// [game_states_records_weighted_from].
+fn record_totals_from(
+    mut reader: impl BufRead,
+    adjudicated_weight: f32,
+    options: RecordOptions,
+    mut progress: Option<(usize, &mut dyn FnMut(usize))>,
+) -> Result<(RecordTotals, usize), DataError> {
+    let mut totals = RecordTotals { options, totals: HashMap::new() };
+    let mut skipped = 0;
+    let mut buffer = String::new();
+    let mut number = 0;
+    loop {
+        buffer.clear();
+        if reader.read_line(&mut buffer)? == 0 {
+            break;
+        }
+        number += 1;
+        match progress.as_mut() {
+            Some((every, callback)) if number % *every == 0 => callback(number),
+            _ => {}
+        }
+        let line = buffer.strip_suffix('\n').unwrap_or(&buffer);
+        if line.is_empty() {
+            continue;
+        }
+        let weight = if str_is_adjudicated(line) { adjudicated_weight } else { 1.0 };
+        if weight == 0.0 {
             continue;
         }
-        let (score, first, second) = str_to_states(line);
+        let (score, first, second) = match str_to_states(line) {
+            Ok(states) => states,
+            Err(_) if options.skip_bad_lines => {
+                skipped += 1;
+                continue;
+            }
+            Err(e) => return Err(DataParseError { line: number, ..e }.into()),
+        };
         for game in &first {
-            let entry = all_games.entry(game.to_compact()).or_insert((0.0, 0.0));
-            entry.0 += 1.0 - score;
-            entry.1 += 1.0; // total
+            totals.add(game, 1.0 - score, weight);
         }
         for game in &second {
-            let entry = all_games.entry(game.to_compact()).or_insert((0.0, 0.0));
-            entry.0 += score;
-            entry.1 += 1.0; // total
+            totals.add(game, score, weight);
         }
     }
+    Ok((totals, skipped))
+}
+
+/// Like [game_states_records], but for games as [GameRecord]s, such as those
+/// read by a [GameRecordReader]. Each game is replayed from its starting
+/// board, with Black to move first unless its turns are only legal with
+/// White to move first.
+///
+/// The [line](DataParseError::line) of an error is the (1-based) number of
+/// the game, and an illegal turn is the first of whichever replay got
+/// further. Games that can't be replayed are skipped and counted instead if
+/// the options [say to](RecordOptions::skip_bad_lines).
+pub fn game_states_records_from_games<'a>(
+    games: impl IntoIterator<Item = &'a GameRecord>,
+    options: RecordOptions,
+) -> Result<(HashMap<u128, f32>, usize), DataParseError> {
+    let mut totals = RecordTotals { options, totals: HashMap::new() };
+    let mut skipped = 0;
+    for (number, game) in games.into_iter().enumerate() {
+        let turns = game.turns();
+        // the states of the game from its start, or the ply it goes wrong at
+        let replay = |board: Board, turn: u8| {
+            let mut state = Gamestate::new_from(board, turn);
+            let mut states = vec![state.clone()];
+            for (ply, &turn) in turns.iter().enumerate() {
+                if !state.make_move_fast(turn) {
+                    return Err(ply);
+                }
+                states.push(state.clone());
+            }
+            Ok(states)
+        };
+        let replayed = match Board::from_compact(game.start) {
+            Some(board) => replay(board, 0).or_else(|black| replay(board, 1).map_err(|white| black.max(white))),
+            None => Err(usize::MAX),
+        };
+        let states = match replayed {
+            Ok(states) => states,
+            Err(_) if options.skip_bad_lines => {
+                skipped += 1;
+                continue;
+            }
+            Err(usize::MAX) => return Err(DataParseError {
+                line: number + 1,
+                segment: game.start.to_string(),
+                reason: DataParseReason::BadStart,
+            }),
+            Err(ply) => return Err(DataParseError {
+                line: number + 1,
+                segment: turns_to_transcript(&turns[ply..=ply]),
+                reason: DataParseReason::IllegalMove { ply },
+            }),
+        };
+
+        // the share of the game White won, as on a line of str_to_states
+        let score = match game.result {
+            GameResult::BlackWin(_) => 0.0,
+            GameResult::WhiteWin(_) => 1.0,
+            GameResult::Draw => 0.5,
+        };
+        totals.add_game(&states, score);
+    }
+    Ok((totals.records(), skipped))
+}
 
-    all_games.into_iter()
-        .map(|(k, (numerator, denominator))| (k, numerator / denominator))
-        .collect()
+/// The running totals of the positions of games being read into records,
+/// keyed as the options say.
+struct RecordTotals {
+    options: RecordOptions,
+    /// The total label and weight of each key.
+    totals: HashMap<u128, (f32, f32)>,
+}
+
+impl RecordTotals {
+    /// Adds `board` (from the point of view of the player to move, as Black)
+    /// with `label`, counting `weight` times.
+    fn add(&mut self, board: &Board, label: f32, weight: f32) {
+        let all = if self.options.dedup_canonical {
+            [(board.canonical().0, label); 8]
+        } else {
+            augment_symmetries(board, label)
+        };
+        // the first orientation is the board as it is
+        let orientations = if self.options.augment { &all[..] } else { &all[..1] };
+        // a symmetric board is the same in some orientations, and counts once
+        for (i, (compact, label)) in orientations.iter().enumerate() {
+            if orientations[..i].iter().any(|(seen, _)| seen == compact) {
+                continue;
+            }
+            let entry = self.totals.entry(*compact).or_insert((0.0, 0.0));
+            entry.0 += weight * label;
+            entry.1 += weight; // total
+        }
+    }
+
+    /// Adds every one of `states`, the positions of a game of which White
+    /// won `score`, as [str_to_states] gives them: positions with White to
+    /// move are turned and have their colors flipped, so that the player to
+    /// move is always Black.
+    fn add_game(&mut self, states: &[Gamestate], score: f32) {
+        for state in states {
+            match state.side_to_move() {
+                Players::Black => self.add(state.board(), 1.0 - score, 1.0),
+                Players::White => {
+                    let mut board = *state.board();
+                    board.rotate_90();
+                    board.flip_colors();
+                    self.add(&board, score, 1.0);
+                }
+            }
+        }
+    }
+
+    /// Returns the mean label of each key.
+    fn records(self) -> HashMap<u128, f32> {
+        self.totals.into_iter()
+            .map(|(k, (numerator, denominator))| (k, numerator / denominator))
+            .collect()
+    }
+
+    /// Returns the mean label and total weight of each key, in order of
+    /// their keys.
+    fn weighted_records(self) -> WeightedBoards {
+        let mut records: WeightedBoards = self.totals.into_iter()
+            .map(|(k, (numerator, denominator))| (k, numerator / denominator, denominator))
+            .collect();
+        records.sort_unstable_by_key(|(k, _, _)| *k);
+        records
+    }
 }
 
-pub fn collect_mcst_data() {
+/// Appends [GameRecord]s to a file as lines of [JSON](GameRecord::to_json),
+/// to be read back by a [GameRecordReader], so that games played with
+/// [play_game_recorded](crate::agent::play_game_recorded) keep who played
+/// them, how long each move took and what the agents thought of it.
+pub struct GameRecordWriter<W: Write = File> {
+    out: W,
+}
+
+impl GameRecordWriter {
+    /// Opens the file at `path` to append records to, creating it if it
+    /// doesn't exist.
+    pub fn append(path: &Path) -> io::Result<Self> {
+        Ok(GameRecordWriter::new(OpenOptions::new().create(true).append(true).open(path)?))
+    }
+}
+
+impl<W: Write> GameRecordWriter<W> {
+    pub fn new(out: W) -> Self {
+        GameRecordWriter { out }
+    }
+
+    /// Writes `record` as a line, all at once, and flushes it, so that a run
+    /// which is stopped leaves at most its last line unfinished.
+    pub fn write(&mut self, record: &GameRecord) -> io::Result<()> {
+        let mut line = record.to_json();
+        line.push('\n');
+        self.out.write_all(line.as_bytes())?;
+        self.out.flush()
+    }
+}
+
+/// Reads back the [GameRecord]s a [GameRecordWriter] wrote, a line at a
+/// time, as an iterator.
+///
+/// A line which isn't a record, such as one left unfinished, is an error
+/// of its own, after which reading carries on with the next line. Blank
+/// lines are skipped. Reading stops after an error reading the file itself.
+pub struct GameRecordReader<R: BufRead = BufReader<File>> {
+    reader: R,
+    buffer: String,
+    /// How many lines have been read.
+    line: usize,
+    /// Whether reading has failed.
+    failed: bool,
+}
+
+impl GameRecordReader {
+    /// Opens the file at `path` to read records from.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Ok(GameRecordReader::new(BufReader::new(File::open(path)?)))
+    }
+}
+
+impl<R: BufRead> GameRecordReader<R> {
+    pub fn new(reader: R) -> Self {
+        GameRecordReader { reader, buffer: String::new(), line: 0, failed: false }
+    }
+}
+
+impl<R: BufRead> Iterator for GameRecordReader<R> {
+    type Item = Result<GameRecord, DataError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.failed {
+            self.buffer.clear();
+            match self.reader.read_line(&mut self.buffer) {
+                Ok(0) => return None,
+                Ok(_) => self.line += 1,
+                Err(e) => {
+                    self.failed = true;
+                    return Some(Err(e.into()));
+                }
+            }
+            let line = self.buffer.trim_end();
+            if line.is_empty() {
+                continue;
+            }
+            return Some(GameRecord::from_json(line).map_err(|e| {
+                DataError::Parse(DataParseError {
+                    line: self.line,
+                    segment: line.to_string(),
+                    reason: DataParseReason::BadRecord(e.to_string()),
+                })
+            }));
+        }
+        None
+    }
+}
+
+/// How much [dedup_file] merged.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DedupReport {
+    /// How many rows were read.
+    pub rows_in: usize,
+    /// How many rows were written, one for each canonical board.
+    pub rows_out: usize,
+}
+
+impl DedupReport {
+    /// How many rows were merged into another row for the same canonical
+    /// board.
+    pub fn collapsed(&self) -> usize {
+        self.rows_in - self.rows_out
+    }
+}
+
+/// Rewrites a dataset of `compact,label` rows, such as `train.csv`, from
+/// `in_path` to `out_path` with every board replaced by its
+/// [canonical](Board::canonical) board, and the rows for each canonical
+/// board merged into one labelled with the mean of their labels, as
+/// [RecordOptions::dedup_canonical] would have keyed them. A first line which
+/// isn't a row is kept as the header. The rows are written in order of their
+/// boards.
+pub fn dedup_file(in_path: &Path, out_path: &Path) -> io::Result<DedupReport> {
+    let contents = fs::read_to_string(in_path)?;
+    let mut lines = contents.lines().filter(|line| !line.is_empty()).peekable();
+    let parse = |line: &str| -> Option<(u128, f32)> {
+        let (compact, label) = line.split_once(',')?;
+        Some((compact.trim().parse().ok()?, label.trim().parse().ok()?))
+    };
+    let header = match lines.peek() {
+        Some(line) if parse(line).is_none() => lines.next(),
+        _ => None,
+    };
+
+    let mut report = DedupReport::default();
+    let mut merged = HashMap::<u128, (f32, f32)>::new();
+    for line in lines {
+        let (compact, label) = parse(line).ok_or_else(|| io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("bad row {line:?} in {}", in_path.display()),
+        ))?;
+        let board = Board::from_compact(compact).ok_or_else(|| io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{compact} in {} is not a board", in_path.display()),
+        ))?;
+        let entry = merged.entry(board.canonical().0).or_insert((0.0, 0.0));
+        entry.0 += label;
+        entry.1 += 1.0;
+        report.rows_in += 1;
+    }
+
+    let mut rows: Vec<(u128, f32)> = merged.into_iter()
+        .map(|(compact, (total, count))| (compact, total / count))
+        .collect();
+    rows.sort_by_key(|(compact, _)| *compact);
+    let mut out = BufWriter::new(File::create(out_path)?);
+    if let Some(header) = header {
+        writeln!(out, "{header}")?;
+    }
+    for (compact, label) in &rows {
+        writeln!(out, "{compact},{label}")?;
+    }
+    out.flush()?;
+    report.rows_out = rows.len();
+    Ok(report)
+}
+
+/// Compact boards with their labels, as in `train.csv`.
+pub type LabeledBoards = Vec<(u128, f32)>;
+
+/// Compact boards with their labels and weights, as written by
+/// [write_weighted_csv] and [write_weighted_records].
+pub type WeightedBoards = Vec<(u128, f32, f32)>;
+
+/// Splits `records` into a training set and a validation set of about
+/// `valid_fraction` of them, at random as `seed` determines, keeping the
+/// records in the order they were given in.
+///
+/// Records of boards with the same [canonical](Board::canonical) board
+/// always go to the same set, so that the validation set can't be learned
+/// from a rotation or reflection of it in the training set. With
+/// `stratify_by_ply`, each number of discs on the board (which is the ply,
+/// but for passes) is split on its own, so that both sets have positions
+/// from the same parts of the game.
+///
+/// # Panics
+/// If `valid_fraction` is not between 0 and 1.
+pub fn split_dataset(
+    records: &[(u128, f32)],
+    valid_fraction: f64,
+    seed: u64,
+    stratify_by_ply: bool,
+) -> (LabeledBoards, LabeledBoards) {
+    assert!((0.0..=1.0).contains(&valid_fraction), "valid_fraction {valid_fraction} is not a fraction");
+    // how many records each canonical board has, in each stratum
+    let mut strata = HashMap::<usize, HashMap<u128, usize>>::new();
+    let canonical = |compact: u128| Board::from_compact(compact).map(|board| {
+        let (black, white, _) = board.counts();
+        (board.canonical().0, usize::from(black + white))
+    });
+    for &(compact, _) in records {
+        let (key, discs) = canonical(compact).unwrap_or((compact, 0));
+        let stratum = if stratify_by_ply { discs } else { 0 };
+        *strata.entry(stratum).or_default().entry(key).or_default() += 1;
+    }
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut strata: Vec<(usize, HashMap<u128, usize>)> = strata.into_iter().collect();
+    strata.sort_unstable_by_key(|(stratum, _)| *stratum);
+    let mut valid_keys = HashSet::new();
+    for (_, groups) in strata {
+        let total: usize = groups.values().sum();
+        let target = valid_fraction * total as f64;
+        let mut groups: Vec<(u128, usize)> = groups.into_iter().collect();
+        groups.sort_unstable();
+        groups.shuffle(&mut rng);
+        let mut valid = 0;
+        for (key, count) in groups {
+            // a group goes to the validation set while it brings the set
+            // closer to its target
+            if (valid + count) as f64 - target <= target - valid as f64 {
+                valid += count;
+                valid_keys.insert(key);
+            }
+        }
+    }
+
+    records.iter().partition(|&&(compact, _)| {
+        let key = canonical(compact).map_or(compact, |(key, _)| key);
+        !valid_keys.contains(&key)
+    })
+}
+
+/// What [blend_labels] does with a position only one of its sources has.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UnmatchedLabels {
+    /// Leave it out.
+    #[default]
+    Drop,
+    /// Keep it with its outcome label if it only has one of those, and
+    /// leave it out otherwise.
+    KeepOutcomes,
+    /// Keep it with whichever label it has.
+    KeepAll,
+}
+
+/// Blends the labels of positions by how their games ended,
+/// `outcome_records`, such as from [game_states_records], with their values
+/// from searches, `mcst_value_records`, such as the share of wins of a
+/// [NodeReport], as `lambda * outcome + (1 - lambda) * value`.
+///
+/// Outcomes are unbiased but noisy, as one game says little about a
+/// position, while the values of shallow searches are steadier but can be
+/// wrong. Both sources have to label positions for the same player, such
+/// as the player to move.
+///
+/// Positions are matched by their [canonical](Board::canonical) boards, and
+/// a position a source has more than once, in any orientation, has the
+/// mean of its labels. Positions only one source has are kept or not as
+/// `unmatched` says. The blended records are keyed by canonical board, in
+/// order.
+///
+/// # Panics
+/// If `lambda` isn't between 0 and 1.
+pub fn blend_labels(
+    outcome_records: &[(u128, f32)],
+    mcst_value_records: &[(u128, f32)],
+    lambda: f32,
+    unmatched: UnmatchedLabels,
+) -> LabeledBoards {
+    blend_labels_by_ply(outcome_records, mcst_value_records, |_| lambda, unmatched)
+}
+
+/// Like [blend_labels], but with `lambda(ply)` weighting the outcome of a
+/// position `ply` plies into the game, counted by its discs, so that
+/// outcomes can be trusted more near the end of the game, where fewer
+/// moves stand between a position and how its game ended. For example,
+/// `|ply| ply as f32 / 60.0` trusts searches entirely at the start and
+/// outcomes entirely at the end.
+///
+/// # Panics
+/// If `lambda` gives a weight that isn't between 0 and 1.
+pub fn blend_labels_by_ply(
+    outcome_records: &[(u128, f32)],
+    mcst_value_records: &[(u128, f32)],
+    lambda: impl Fn(usize) -> f32,
+    unmatched: UnmatchedLabels,
+) -> LabeledBoards {
+    let means = |records: &[(u128, f32)]| {
+        let mut totals = HashMap::<u128, (f32, f32)>::new();
+        for &(compact, label) in records {
+            let board = Board::from_compact(compact).expect("not a compact board");
+            let entry = totals.entry(board.canonical().0).or_insert((0.0, 0.0));
+            entry.0 += label;
+            entry.1 += 1.0;
+        }
+        totals.into_iter()
+            .map(|(key, (total, count))| (key, total / count))
+            .collect::<HashMap<u128, f32>>()
+    };
+    let outcomes = means(outcome_records);
+    let mut values = means(mcst_value_records);
+
+    let mut blended = Vec::with_capacity(outcomes.len());
+    for (key, outcome) in outcomes {
+        match values.remove(&key) {
+            Some(value) => {
+                let (black, white, _) = Board::from_compact(key).unwrap().counts();
+                let ply = usize::from(black + white).saturating_sub(4);
+                let lambda = lambda(ply);
+                assert!((0.0..=1.0).contains(&lambda), "lambda {lambda} at ply {ply} is not between 0 and 1");
+                blended.push((key, lambda * outcome + (1.0 - lambda) * value));
+            }
+            None if unmatched != UnmatchedLabels::Drop => blended.push((key, outcome)),
+            None => {}
+        }
+    }
+    if unmatched == UnmatchedLabels::KeepAll {
+        blended.extend(values);
+    }
+    blended.sort_unstable_by_key(|(key, _)| *key);
+    blended
+}
+
+/// Writes `records` to `path` as CSV, with a `board,label` header and then a
+/// `compact,label` row for each record, as read as `train.csv` and
+/// `valid.csv` for training.
+pub fn write_csv(path: &Path, records: &[(u128, f32)]) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(out, "board,label")?;
+    for (compact, label) in records {
+        writeln!(out, "{compact},{label}")?;
+    }
+    out.flush()
+}
+
+/// Like [write_csv], but with a `board,label,weight` header and a weight
+/// after every record's label, such as how many games went through its
+/// position.
+pub fn write_weighted_csv(path: &Path, records: &[(u128, f32, f32)]) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(out, "board,label,weight")?;
+    for (compact, label, weight) in records {
+        writeln!(out, "{compact},{label},{weight}")?;
+    }
+    out.flush()
+}
+
+/// Reads the records of a CSV file written by [write_csv] or
+/// [write_weighted_csv], giving records without a weight a weight of `1`.
+///
+/// The header, if there is one, is skipped, and a row which can't be read
+/// is an [InvalidData](io::ErrorKind::InvalidData) error.
+pub fn read_csv(path: &Path) -> io::Result<WeightedBoards> {
+    let contents = fs::read_to_string(path)?;
+    let parse = |line: &str| -> Option<(u128, f32, f32)> {
+        let mut fields = line.split(',').map(str::trim);
+        let compact = fields.next()?.parse().ok()?;
+        let label = fields.next()?.parse().ok()?;
+        let weight = match fields.next() {
+            Some(weight) => weight.parse().ok()?,
+            None => 1.0,
+        };
+        match fields.next() {
+            Some(_) => None,
+            None => Some((compact, label, weight)),
+        }
+    };
+    let mut records = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() || (i == 0 && line.starts_with("board")) {
+            continue;
+        }
+        records.push(parse(line).ok_or_else(|| io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} line {}: {line:?} is not a record", path.display(), i + 1),
+        ))?);
+    }
+    Ok(records)
+}
+
+/// The first bytes of a [binary record file](write_records).
+const RECORDS_MAGIC: &[u8; 4] = b"OTHR";
+/// The version of the binary record format [write_records] writes.
+const RECORDS_VERSION: u16 = 1;
+/// The header flag for record files whose records each have a weight.
+const RECORDS_WEIGHTED: u16 = 1;
+/// The length of a record file's header.
+const RECORDS_HEADER_LEN: usize = 16;
+
+/// Writes `records` of compact boards and their labels to `path` in a binary
+/// format which is much smaller and faster to read than CSV.
+///
+/// The file starts with a 16 byte header: the magic bytes `OTHR`, the
+/// format version and flags as little-endian `u16`s, and the number of
+/// records as a little-endian `u64`. Each record follows as its board as a
+/// little-endian `u128` and its label as a little-endian `f32`, then its
+/// weight as another `f32` if the weighted flag (`1`) is set, as
+/// [write_weighted_records] sets it.
+pub fn write_records(path: &Path, records: &[(u128, f32)]) -> io::Result<()> {
+    let records = records.iter().map(|&(compact, label)| (compact, label, None));
+    write_record_file(path, records, 0)
+}
+
+/// Like [write_records], but every record also has a weight, such as how
+/// many games went through its position.
+pub fn write_weighted_records(path: &Path, records: &[(u128, f32, f32)]) -> io::Result<()> {
+    let records = records.iter().map(|&(compact, label, weight)| (compact, label, Some(weight)));
+    write_record_file(path, records, RECORDS_WEIGHTED)
+}
+
+fn write_record_file(
+    path: &Path,
+    records: impl ExactSizeIterator<Item = (u128, f32, Option<f32>)>,
+    flags: u16,
+) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    out.write_all(RECORDS_MAGIC)?;
+    out.write_all(&RECORDS_VERSION.to_le_bytes())?;
+    out.write_all(&flags.to_le_bytes())?;
+    out.write_all(&(records.len() as u64).to_le_bytes())?;
+    for (compact, label, weight) in records {
+        out.write_all(&compact.to_le_bytes())?;
+        out.write_all(&label.to_le_bytes())?;
+        if let Some(weight) = weight {
+            out.write_all(&weight.to_le_bytes())?;
+        }
+    }
+    out.flush()
+}
+
+/// Reads the records of a file written by [write_records] or
+/// [write_weighted_records], leaving out any weights.
+///
+/// A file which isn't a record file, or is of a newer version, is an
+/// [InvalidData](io::ErrorKind::InvalidData) error, and one which ends
+/// before its last record an [UnexpectedEof](io::ErrorKind::UnexpectedEof)
+/// error.
+pub fn read_records(path: &Path) -> io::Result<Vec<(u128, f32)>> {
+    Ok(read_weighted_records(path)?.into_iter().map(|(compact, label, _)| (compact, label)).collect())
+}
+
+/// Like [read_records], but keeps every record's weight, which is `1` for
+/// files written without weights.
+pub fn read_weighted_records(path: &Path) -> io::Result<Vec<(u128, f32, f32)>> {
+    let bytes = fs::read(path)?;
+    let invalid = |message: &str| io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{} {message}", path.display()),
+    );
+    if bytes.len() < RECORDS_HEADER_LEN {
+        return Err(if RECORDS_MAGIC.starts_with(&bytes[..bytes.len().min(4)]) {
+            io::Error::new(io::ErrorKind::UnexpectedEof, format!("{} is truncated", path.display()))
+        } else {
+            invalid("is not a record file")
+        });
+    }
+    let (header, body) = bytes.split_at(RECORDS_HEADER_LEN);
+    if &header[..4] != RECORDS_MAGIC {
+        return Err(invalid("is not a record file"));
+    }
+    let version = u16::from_le_bytes([header[4], header[5]]);
+    if version != RECORDS_VERSION {
+        return Err(invalid(&format!("is of unknown version {version}")));
+    }
+    let flags = u16::from_le_bytes([header[6], header[7]]);
+    if flags & !RECORDS_WEIGHTED != 0 {
+        return Err(invalid(&format!("has unknown flags {flags:#x}")));
+    }
+    let count = u64::from_le_bytes(header[8..].try_into().unwrap());
+    let weighted = flags & RECORDS_WEIGHTED != 0;
+    let record_len = if weighted { 24 } else { 20 };
+    let expected = count.checked_mul(record_len as u64);
+    if expected.is_none_or(|expected| expected > body.len() as u64) {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!("{} is truncated: it should hold {count} records", path.display()),
+        ));
+    }
+    if expected != Some(body.len() as u64) {
+        return Err(invalid(&format!("has data after its {count} records")));
+    }
+
+    Ok(body.chunks_exact(record_len).map(|record| {
+        let compact = u128::from_le_bytes(record[..16].try_into().unwrap());
+        let label = f32::from_le_bytes(record[16..20].try_into().unwrap());
+        let weight = if weighted { f32::from_le_bytes(record[20..].try_into().unwrap()) } else { 1.0 };
+        (compact, label, weight)
+    }).collect())
+}
+
+/// How much [collect_mcst_data] wrote.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CollectStats {
+    /// How many games were played.
+    pub games: usize,
+    /// How many positions were written, each as a row of its compact board,
+    /// its wins and its visits.
+    pub positions: usize,
+}
+
+/// Returns the path of the state file kept alongside the data collected at
+/// `out_path` (see [collect_mcst_data]).
+fn collect_state_path(out_path: &Path) -> PathBuf {
+    let mut path = out_path.as_os_str().to_owned();
+    path.push(".state");
+    PathBuf::from(path)
+}
+
+/// Plays `games` random games and, every other ply, searches the position
+/// with `cycles_per_position` cycles of MCTS, writing each well visited
+/// position of the tree (see [NodeReporter]) to `out_path` as a line
+/// `compact,wins,visits`, sorted by compact board. Every random choice is
+/// determined by `seed` and the game's index, so the same data is collected
+/// each time.
+///
+/// Lines are appended if the file already exists. After each game the file
+/// is flushed, and the index of the next game is written to a state file
+/// next to it (`out_path` with `.state` appended), along with how long the
+/// file was. A run which finds a state file resumes from that game, first
+/// cutting off anything written after it, so that an interrupted run can be
+/// picked up without writing any game twice. It fails if the state file was
+/// written with another seed or number of cycles.
+pub fn collect_mcst_data(
+    out_path: &Path,
+    games: usize,
+    cycles_per_position: usize,
+    seed: u64,
+) -> io::Result<CollectStats> {
+    let state_path = collect_state_path(out_path);
+    let file = OpenOptions::new().create(true).append(true).open(out_path)?;
+    let mut next_game = 0;
+    match fs::read_to_string(&state_path) {
+        Ok(state) => {
+            let fields: Vec<u64> = state.split_whitespace().map(str::parse).collect::<Result<_, _>>()
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            let [next, length, state_seed, state_cycles] = fields[..] else {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!("bad state file {state:?}")));
+            };
+            if (state_seed, state_cycles) != (seed, cycles_per_position as u64) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{} was collected with seed {state_seed} and {state_cycles} cycles", out_path.display()),
+                ));
+            }
+            if file.metadata()?.len() < length {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{} is shorter than its state file says", out_path.display()),
+                ));
+            }
+            file.set_len(length)?;
+            next_game = next as usize;
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+
+    let mut out = BufWriter::new(file);
+    let mut stats = CollectStats::default();
+    for index in next_game..games {
+        stats.positions += collect_mcst_game(&mut out, cycles_per_position, game_seed(seed, index))?;
+        stats.games += 1;
+        out.flush()?;
+
+        let length = out.get_ref().metadata()?.len();
+        let temporary = state_path.with_extension("state.tmp");
+        fs::write(&temporary, format!("{} {length} {seed} {cycles_per_position}\n", index + 1))?;
+        fs::rename(&temporary, &state_path)?;
+    }
+    Ok(stats)
+}
+
+/// Returns the seed for game `index` of a collection seeded with `seed`,
+/// spread out so that nearby games' seeds aren't related.
+fn game_seed(seed: u64, index: usize) -> u64 {
+    seed.wrapping_add((index as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15))
+}
+
+/// Makes the Black and White agents for a game of self-play collected by
+/// [collect_parallel], given the game's seed. The agents are made on the
+/// thread that plays the game, so they don't need to be [Send].
+pub type AgentPairFactory = dyn Fn(u64) -> (Box<dyn MemoryAgent>, Box<dyn MemoryAgent>) + Send + Sync;
+
+/// How far [collect_parallel] has got, as told to its progress callback
+/// after each game.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CollectProgress {
+    /// How many games have been played.
+    pub games: usize,
+    /// How many positions have been collected.
+    pub positions: usize,
+    /// How many positions have been collected per second so far.
+    pub positions_per_sec: f64,
+}
+
+/// Told how far [collect_parallel] has got after each game.
+pub type ProgressCallback = dyn FnMut(&CollectProgress) + Send;
+
+/// What [collect_parallel] should collect, and how.
+pub struct CollectConfig {
+    /// How many games to play.
+    pub games: usize,
+    /// How many threads to play them on.
+    pub workers: usize,
+    /// The seed every game's seed is derived from.
+    pub seed: u64,
+    /// Makes the agents for each game.
+    pub agents: Box<AgentPairFactory>,
+    /// The file to write the positions to, if any, as lines
+    /// `game,ply,compact,result`.
+    pub out_path: Option<PathBuf>,
+    /// Told how far collection has got after each game.
+    pub progress: Option<Box<ProgressCallback>>,
+}
+
+impl CollectConfig {
+    /// Constructs a config playing `games` games on `workers` threads with
+    /// agents made by `agents`, seeded from `seed`, which writes nothing
+    /// and reports no progress.
+    pub fn new(
+        games: usize,
+        workers: usize,
+        seed: u64,
+        agents: impl Fn(u64) -> (Box<dyn MemoryAgent>, Box<dyn MemoryAgent>) + Send + Sync + 'static,
+    ) -> Self {
+        CollectConfig { games, workers, seed, agents: Box::new(agents), out_path: None, progress: None }
+    }
+}
+
+/// A position from a game of self-play, with how the game ended for the
+/// player to move.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PositionRecord {
+    /// The index of the game among those collected.
+    pub game: usize,
+    /// How many plies into the game the position is.
+    pub ply: usize,
+    /// The [compact](Board::to_compact) board.
+    pub board: u128,
+    pub side_to_move: Players,
+    /// 1 if the player to move went on to win, 0.5 for a draw and 0 for a loss.
+    pub result: f32,
+}
+
+/// Plays [`config.games`](CollectConfig::games) games of self-play on
+/// [`config.workers`](CollectConfig::workers) threads and returns every
+/// position they pass through, in the order their games finish.
+///
+/// Game `i` is played by the agents made from a seed derived from `i` and
+/// [`config.seed`](CollectConfig::seed), so with seeded agents which thread
+/// plays it makes no difference: the positions are the same as with one
+/// thread, if perhaps in another order. Each game's positions are sent to a
+/// single writer thread, which writes them to the output file, if there is
+/// one, and reports progress.
+///
+/// # Panics
+/// If an agent panics, once every thread has stopped.
+pub fn collect_parallel(config: CollectConfig) -> io::Result<Vec<PositionRecord>> {
+    let CollectConfig { games, workers, seed, agents, out_path, mut progress } = config;
+    let mut out = match out_path {
+        Some(path) => Some(BufWriter::new(File::create(path)?)),
+        None => None,
+    };
+    let next_game = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel::<Vec<PositionRecord>>();
+
+    thread::scope(|scope| {
+        for _ in 0..workers.clamp(1, games.max(1)) {
+            let sender = sender.clone();
+            let (next_game, agents) = (&next_game, &agents);
+            scope.spawn(move || loop {
+                let game = next_game.fetch_add(1, AtomicOrdering::Relaxed);
+                if game >= games {
+                    break;
+                }
+                let (mut black, mut white) = agents(game_seed(seed, game));
+                let (result, turns) = play_game(&mut *black, &mut *white);
+                if sender.send(game_positions(game, result, &turns)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        let writer = scope.spawn(move || -> io::Result<Vec<PositionRecord>> {
+            let start = Instant::now();
+            let mut records = Vec::new();
+            for (played, positions) in receiver.into_iter().enumerate() {
+                if let Some(out) = &mut out {
+                    for record in &positions {
+                        writeln!(out, "{},{},{},{}", record.game, record.ply, record.board, record.result)?;
+                    }
+                    out.flush()?;
+                }
+                records.extend(positions);
+                if let Some(progress) = &mut progress {
+                    progress(&CollectProgress {
+                        games: played + 1,
+                        positions: records.len(),
+                        positions_per_sec: records.len() as f64 / start.elapsed().as_secs_f64(),
+                    });
+                }
+            }
+            Ok(records)
+        });
+        writer.join().unwrap_or_else(|payload| panic::resume_unwind(payload))
+    })
+}
+
+/// Returns the positions of game `game` of [collect_parallel], which went
+/// `turns` and ended in `result`.
+fn game_positions(game: usize, result: GameResult, turns: &[Turn]) -> Vec<PositionRecord> {
+    let mut state = Gamestate::new();
+    let mut positions = Vec::with_capacity(turns.len());
+    for (ply, turn) in turns.iter().enumerate() {
+        let side_to_move = state.side_to_move();
+        let result = match result.winner() {
+            Some(winner) if winner == side_to_move => 1.0,
+            Some(_) => 0.0,
+            None => 0.5,
+        };
+        positions.push(PositionRecord { game, ply, board: state.board().to_compact(), side_to_move, result });
+        state.make_move_fast(*turn);
+    }
+    positions
+}
+
+/// Plays one game for [collect_mcst_data], writing its positions to `out`
+/// and returning how many there were.
+fn collect_mcst_game(out: &mut impl Write, cycles: usize, seed: u64) -> io::Result<usize> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let r = RandomAgent::seeded(rng.random());
     let mut g = Gamestate::new();
-    let r = RandomAgent::new();
+    let mut positions = 0;
 
-    while !g.get_moves().is_empty() {
+    while !g.is_terminal() {
         let mut a = McstAgent::new(
             UctSelection::new(2_f64.sqrt()),
             BfsExpansion {},
             UctDecision {},
-            RandomAgent::new(),
-            RandomAgent::new(),
+            RandomAgent::seeded(rng.random()),
+            RandomAgent::seeded(rng.random()),
             g.clone(),
         );
-        for _ in 0..100000 {
+        for _ in 0..cycles {
             let _ = a.cycle();
         }
 
-        let mut data = HashMap::<u128, (u64, u64)>::new();
-        mcst_node_report(a.tree().root(), &mut data);
-        for (compact, (win, total)) in data.iter() {
-            println!("{},{},{}", compact, win, total);
+        let mut data = HashMap::new();
+        NodeReporter::default().report(a.tree().root(), usize::from(g.ply()), &mut data);
+        let mut rows: Vec<_> = data.into_iter().collect();
+        rows.sort_unstable_by_key(|(compact, _)| *compact);
+        for (compact, report) in &rows {
+            writeln!(out, "{},{},{}", compact, report.wins, report.total)?;
         }
+        positions += rows.len();
 
         g.make_move_fast(r.make_move(&g));
-        if !g.get_moves().is_empty() {
+        if !g.is_terminal() {
             g.make_move_fast(r.make_move(&g));
         }
     }
+    Ok(positions)
+}
+
+/// What a [NodeReporter] found of a position.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NodeReport {
+    /// How many of the rollouts through the position the player to move won,
+    /// counting draws as half a win.
+    pub wins: f64,
+    /// How many rollouts went through the position.
+    pub total: u64,
+    /// The smallest ply the position was found at, if the reporter
+    /// [includes plies](NodeReporter::include_ply).
+    pub ply: Option<usize>,
+}
+
+/// Collects the statistics of the positions in a search tree with enough
+/// visits, as training data.
+///
+/// Every node of a tree keeps its wins for the player to move at the root,
+/// so only the nodes with that player to move are reported, as their wins
+/// are then the wins of the player to move. Without passes those are the
+/// nodes an even number of plies below the root.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NodeReporter {
+    /// Nodes with fewer visits than this aren't reported, and nor is
+    /// anything below them.
+    pub min_visits: u32,
+    /// Report the ply of each position.
+    pub include_ply: bool,
+    /// Key positions by their [canonical](Board::canonical) board, merging
+    /// the statistics of positions which are rotations or reflections of each
+    /// other.
+    pub dedup_canonical: bool,
 }
 
-pub fn mcst_node_report(node: &McstNode, data: &mut HashMap<u128, (u64, u64)>) {
-    if node.total() >= &64 {
-        let entry = data.entry(node.game().board().to_compact()).or_insert((0, 0));
-        entry.0 += u64::from(*node.wins());
-        entry.1 += u64::from(*node.total());
+impl Default for NodeReporter {
+    /// Reports nodes with at least 64 visits, without plies, by their
+    /// boards as they are.
+    fn default() -> Self {
+        NodeReporter { min_visits: 64, include_ply: false, dedup_canonical: false }
+    }
+}
+
+impl NodeReporter {
+    /// Adds the statistics of `node` and the nodes below it to `out`, for the
+    /// positions with the player to move at `node` to move, where `node` is
+    /// `ply` plies into the game. A position found more than once has its
+    /// statistics added up.
+    pub fn report(&self, node: McstNode, ply: usize, out: &mut HashMap<u128, NodeReport>) {
+        self.report_for(node.game().side_to_move(), node, ply, out);
+    }
+
+    fn report_for(&self, me: Players, node: McstNode, ply: usize, out: &mut HashMap<u128, NodeReport>) {
+        if *node.total() < self.min_visits {
+            return;
+        }
+        let game = node.game();
+        if game.side_to_move() == me {
+            let board = game.board();
+            let key = if self.dedup_canonical { board.canonical().0 } else { board.to_compact() };
+            let ply = self.include_ply.then_some(ply);
+            let entry = out.entry(key).or_insert(NodeReport { wins: 0.0, total: 0, ply });
+            entry.wins += node.wins();
+            entry.total += u64::from(*node.total());
+            entry.ply = entry.ply.min(ply);
+        }
         for child in node.children().values() {
-            mcst_node_skip(child, data);
+            self.report_for(me, child, ply + 1, out);
         }
     }
 }
 
-pub fn mcst_node_skip(node: &McstNode, data: &mut HashMap<u128, (u64, u64)>) {
-    if node.total() >= &64 {
-        for child in node.children().values() {
-            mcst_node_report(child, data);
+/// What [selfplay_pipeline] should play, and what it should write.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PipelineConfig {
+    /// The [spec](registry::Registry) of the agent playing Black.
+    pub black: String,
+    /// The spec of the agent playing White.
+    pub white: String,
+    /// How many games to play.
+    pub games: usize,
+    /// How many threads to play them on.
+    pub threads: usize,
+    /// The seed the openings, and the split into training and validation
+    /// sets, are chosen with.
+    pub seed: u64,
+    /// How positions are turned into records, such as whether they are
+    /// [augmented](RecordOptions::augment) or
+    /// [deduplicated](RecordOptions::dedup_canonical).
+    pub records: RecordOptions,
+    /// The temperature of each ply of the opening, which is played before
+    /// the agents take over, so that games between the same agents don't all
+    /// go the same way.
+    ///
+    /// Each move of the opening is chosen with a chance in proportion to
+    /// `exp(value / temperature)`, where `value` is what the
+    /// [standard evaluator](WeightedSquareEvaluator) makes of the position it
+    /// leads to for the player making it. So a temperature in the tens
+    /// favours good moves a little, and one of 0 plays the best move.
+    pub temperatures: Vec<f64>,
+    /// The share of the records to set aside for validation (see
+    /// [split_dataset]).
+    pub valid_fraction: f64,
+    /// The directory to write the files to, which must exist.
+    pub out_dir: PathBuf,
+    /// Write the records as `train.csv` and `valid.csv` (see [write_csv]).
+    pub csv: bool,
+    /// Write the records as `train.bin` and `valid.bin` (see [write_records]).
+    pub binary: bool,
+    /// Write what the agents chose in each position they played from to
+    /// `policy.csv`, as lines `board,side,moves` of the
+    /// [compact](Board::to_compact) board, `black` or `white` for the player
+    /// to move, and the moves, as `index:share` pairs separated by `;` of
+    /// each move's [index](turn_to_index) and its share of the search's
+    /// visits. An agent which doesn't [analyse](MemoryAgent::analysis) its
+    /// moves gives all of the share to the move it played.
+    pub policy: bool,
+    /// Write the games to `games.jsonl` (see [GameRecordWriter]).
+    pub keep_games: bool,
+}
+
+impl PipelineConfig {
+    /// Constructs a config playing `games` games between the agents of the
+    /// specs `black` and `white` on one thread from seed 0, with no opening
+    /// and a tenth of the records set aside for validation, which writes
+    /// only `train.csv` and `valid.csv` to `out_dir`.
+    pub fn new(black: &str, white: &str, games: usize, out_dir: impl Into<PathBuf>) -> Self {
+        PipelineConfig {
+            black: black.to_string(),
+            white: white.to_string(),
+            games,
+            threads: 1,
+            seed: 0,
+            records: RecordOptions::default(),
+            temperatures: Vec::new(),
+            valid_fraction: 0.1,
+            out_dir: out_dir.into(),
+            csv: true,
+            binary: false,
+            policy: false,
+            keep_games: false,
+        }
+    }
+}
+
+/// What [selfplay_pipeline] did.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PipelineReport {
+    /// How many games were played.
+    pub games: usize,
+    /// How many positions the games went through.
+    pub positions: usize,
+    /// How many records the positions made, once keyed as the
+    /// [options](PipelineConfig::records) say.
+    pub records: usize,
+    /// How many of the records are in the training set.
+    pub train: usize,
+    /// How many of the records are in the validation set.
+    pub valid: usize,
+    /// How many lines were written to `policy.csv`.
+    pub policy: usize,
+    /// How long the games took.
+    pub play_time: Duration,
+    /// How long everything took.
+    pub total_time: Duration,
+}
+
+/// Plays games of self-play between two agents from the
+/// [registry](registry::create) and turns them into a dataset to train
+/// networks on, all as `config` says.
+///
+/// The games are labelled as by [game_states_records_from_games] and split
+/// into training and validation sets by [split_dataset], stratified by
+/// ply, and the sets written to `config.out_dir` under the names
+/// [load_dataset](crate::neural::load_dataset) is given when training.
+///
+/// Fails with [InvalidInput](io::ErrorKind::InvalidInput) if either spec
+/// is bad, before any game is played. `config.out_dir` is made, if it
+/// doesn't exist, before any game is played too.
+///
+/// # Panics
+/// If an agent panics, once every thread has stopped.
+pub fn selfplay_pipeline(config: PipelineConfig) -> io::Result<PipelineReport> {
+    let start = Instant::now();
+    for spec in [&config.black, &config.white] {
+        registry::create(spec).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    }
+    fs::create_dir_all(&config.out_dir)?;
+
+    let next_game = AtomicUsize::new(0);
+    let mut games: Vec<Option<GameRecord>> = vec![None; config.games];
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..config.threads.clamp(1, config.games.max(1)))
+            .map(|_| scope.spawn(|| {
+                let mut played = Vec::new();
+                loop {
+                    let game = next_game.fetch_add(1, AtomicOrdering::Relaxed);
+                    if game >= config.games {
+                        break played;
+                    }
+                    played.push((game, play_pipeline_game(&config, game)));
+                }
+            }))
+            .collect();
+        for worker in workers {
+            match worker.join() {
+                Ok(played) => for (game, record) in played {
+                    games[game] = Some(record);
+                },
+                Err(payload) => {
+                    next_game.store(config.games, AtomicOrdering::Relaxed);
+                    panic::resume_unwind(payload);
+                }
+            }
+        }
+    });
+    let games: Vec<GameRecord> = games.into_iter().map(|game| game.expect("every game is played")).collect();
+    let play_time = start.elapsed();
+
+    let (records, _) = game_states_records_from_games(&games, config.records)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let mut records: LabeledBoards = records.into_iter().collect();
+    records.sort_unstable_by_key(|(compact, _)| *compact);
+    let (train, valid) = split_dataset(&records, config.valid_fraction, config.seed, true);
+    let dir = &config.out_dir;
+    if config.csv {
+        write_csv(&dir.join("train.csv"), &train)?;
+        write_csv(&dir.join("valid.csv"), &valid)?;
+    }
+    if config.binary {
+        write_records(&dir.join("train.bin"), &train)?;
+        write_records(&dir.join("valid.bin"), &valid)?;
+    }
+    let policy = match config.policy {
+        true => write_policy(&dir.join("policy.csv"), &games, config.temperatures.len())?,
+        false => 0,
+    };
+    if config.keep_games {
+        let mut writer = GameRecordWriter::new(BufWriter::new(File::create(dir.join("games.jsonl"))?));
+        for game in &games {
+            writer.write(game)?;
+        }
+    }
+
+    Ok(PipelineReport {
+        games: games.len(),
+        positions: games.iter().map(|game| game.plies.len() + 1).sum(),
+        records: records.len(),
+        train: train.len(),
+        valid: valid.len(),
+        policy,
+        play_time,
+        total_time: start.elapsed(),
+    })
+}
+
+/// Plays game `game` of a [selfplay_pipeline] run: its opening, then the
+/// rest between the agents. The record starts from the standard position,
+/// with the opening's plies taking no time.
+fn play_pipeline_game(config: &PipelineConfig, game: usize) -> GameRecord {
+    let mut rng = StdRng::seed_from_u64(game_seed(config.seed, game));
+    let evaluator = WeightedSquareEvaluator::default();
+    let mut state = Gamestate::new();
+    let mut opening = Vec::new();
+    for &temperature in &config.temperatures {
+        if state.is_terminal() {
+            break;
+        }
+        let sign = if state.side_to_move() == Players::Black { 1.0 } else { -1.0 };
+        let values: Vec<(Turn, f64)> = state.children()
+            .map(|(turn, child)| (turn, sign * evaluator.evaluate(&child)))
+            .collect();
+        let best = values.iter().map(|(_, value)| *value).fold(f64::NEG_INFINITY, f64::max);
+        let turn = match temperature > 0.0 {
+            true => {
+                let weights = values.iter().map(|(_, value)| ((value - best) / temperature).exp());
+                let index = WeightedIndex::new(weights).expect("the best move has a weight of 1");
+                values[index.sample(&mut rng)].0
+            }
+            false => values.iter().find(|(_, value)| *value == best).unwrap().0,
+        };
+        let flips = state.make_move(turn).expect("the move was one of the children");
+        opening.push(PlyRecord { turn, flips, time: Duration::ZERO, score: state.score(), analysis: None });
+    }
+
+    let mut black = registry::create(&config.black).expect("the spec was checked");
+    let mut white = registry::create(&config.white).expect("the spec was checked");
+    let mut record = play_game_recorded(&mut *black, &mut *white, state, (&config.black, &config.white));
+    record.start = Gamestate::new().board().to_compact();
+    record.plies.splice(0..0, opening);
+    record
+}
+
+/// Writes the policy targets of `games` to `path`, as described by
+/// [PipelineConfig::policy], leaving out the first `opening` plies of each
+/// game, and returns how many lines there were.
+fn write_policy(path: &Path, games: &[GameRecord], opening: usize) -> io::Result<usize> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(out, "board,side,moves")?;
+    let mut lines = 0;
+    for game in games {
+        let mut state = Gamestate::new();
+        for (ply, record) in game.plies.iter().enumerate() {
+            if ply >= opening {
+                let visits = |turn| record.analysis.iter()
+                    .flat_map(|analysis| &analysis.moves)
+                    .find(|stats| stats.turn == turn)
+                    .map_or(0, |stats| stats.visits);
+                let moves: Vec<Turn> = state.get_moves().into_iter().collect();
+                let total: u32 = moves.iter().map(|&turn| visits(turn)).sum();
+                let shares: Vec<String> = match total {
+                    0 => vec![format!("{}:1", turn_to_index(record.turn))],
+                    _ => moves.iter()
+                        .filter(|&&turn| visits(turn) > 0)
+                        .map(|&turn| format!("{}:{}", turn_to_index(turn), visits(turn) as f32 / total as f32))
+                        .collect(),
+                };
+                let side = match state.side_to_move() {
+                    Players::Black => "black",
+                    Players::White => "white",
+                };
+                writeln!(out, "{},{side},{}", state.board().to_compact(), shares.join(";"))?;
+                lines += 1;
+            }
+            state.make_move_fast(record.turn);
         }
     }
+    out.flush()?;
+    Ok(lines)
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use rand::seq::IndexedRandom;
+
     use super::*;
+    use crate::agent::implementations::Budget;
+    use crate::agent::{play_game_recorded, MemorifiedAgent};
+    use crate::gameplay::tests::FULL_GAME;
 
     #[test]
     fn test_bfsallgamestates() {
@@ -307,14 +1991,132 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_unique_gamestates() {
+        let counts = |positions: &[(Gamestate, Vec<Turn>)]| {
+            let mut counts = vec![0; positions.last().unwrap().1.len() + 1];
+            for (_, turns) in positions {
+                counts[turns.len()] += 1;
+            }
+            counts
+        };
+
+        // the number of distinct positions after each of the first plies,
+        // which from ply 3 is fewer than the number of orders of moves
+        let exact: Vec<_> = UniqueGamestates::exact().take_while(|(_, turns)| turns.len() <= 6).collect();
+        assert_eq!(counts(&exact), [1, 4, 12, 54, 236, 1288, 7092]);
+        let mut sequences = [0; 7];
+        for g in BfsAllGamestates::new().take_while(|g| g.ply() <= 6) {
+            sequences[g.ply() as usize] += 1;
+        }
+        assert_eq!(sequences, [1, 4, 12, 56, 244, 1396, 8200]);
+        for (state, turns) in &exact {
+            let mut replayed = Gamestate::new();
+            assert!(replayed.make_moves_fast(turns));
+            assert_eq!(replayed.board(), state.board());
+        }
+
+        // all four first moves, and the twelve positions after two plies,
+        // are the same up to symmetry as one and three
+        let unique: Vec<_> = UniqueGamestates::new().take_while(|(_, turns)| turns.len() <= 6).collect();
+        let unique_counts = counts(&unique);
+        assert_eq!(unique_counts[..3], [1, 1, 3]);
+        assert!(unique_counts[2] < sequences[2]);
+        for (ply, count) in unique_counts.iter().enumerate() {
+            let canonical: HashSet<u128> = exact.iter()
+                .filter(|(_, turns)| turns.len() == ply)
+                .map(|(state, _)| state.board().canonical().0)
+                .collect();
+            assert_eq!(*count, canonical.len());
+        }
+        let mut keys: Vec<(usize, u128)> = unique.iter()
+            .map(|(state, turns)| (turns.len(), state.board().canonical().0))
+            .collect();
+        keys.sort_unstable();
+        keys.dedup();
+        assert_eq!(keys.len(), unique.len());
+    }
+
+    #[test]
+    fn test_random_position_sampler() {
+        // positions are reached by the turns recorded for them
+        let mut sampler = RandomPositionSampler::new(101);
+        let mut plies = HashSet::new();
+        for _ in 0..200 {
+            let state = sampler.next().unwrap();
+            let mut replayed = Gamestate::new();
+            assert!(replayed.make_moves_fast(sampler.last_turns()));
+            assert_eq!((replayed.board(), replayed.ply()), (state.board(), state.ply()));
+            plies.insert(state.ply());
+        }
+        assert!(plies.len() > 40, "{plies:?}");
+
+        // and are at plies with the chances asked for
+        let mut weights = vec![0.0; 40];
+        weights[10..20].fill(1.0);
+        weights[30..40].fill(3.0);
+        let mut at = [0; 4];
+        for state in RandomPositionSampler::epsilon_greedy(102, 0.5).with_ply_weights(&weights).take(2000) {
+            assert!((10..20).contains(&state.ply()) || (30..40).contains(&state.ply()), "{}", state.ply());
+            at[usize::from(state.ply() / 10)] += 1;
+        }
+        assert!((at[3] as f64 / 2000.0 - 0.75).abs() < 0.03, "{at:?}");
+
+        // and pass the filter
+        let white = RandomPositionSampler::new(103).with_filter(|state| state.side_to_move() == Players::White);
+        let records = sample_labeled(50, white, |state| state.ply() as f32);
+        assert_eq!(records.len(), 50);
+        for (compact, ply) in records {
+            assert!(Board::from_compact(compact).is_some());
+            assert_eq!(ply % 2.0, 1.0);
+        }
+
+        // the same seed samples the same positions
+        let agent = RandomAgent::seeded(104);
+        let labeled = |seed| sample_labeled(20, RandomPositionSampler::new(seed), |state| playout_outcome(&agent, state));
+        let (first, second) = (labeled(105), labeled(105));
+        assert!(first.iter().zip(&second).all(|((a, _), (b, _))| a == b));
+        assert!(first.iter().all(|(_, label)| [0.0, 0.5, 1.0].contains(label)));
+    }
+
     #[test]
     fn test_turns_to_str() {
-        assert_eq!(turns_to_str(&[Some((1, 2)), Some((3, 4)), None]), "1,2;3,4;");
+        assert_eq!(turns_to_str(&[Some((1, 2)), Some((3, 4)), None]), "1,2;3,4;P");
+        assert_eq!(turns_to_str(&[None, Some((1, 2)), None, None]), "P;1,2;P;P");
+        assert_eq!(turns_to_str(&[]), "");
     }
 
     #[test]
     fn test_str_to_turns() {
         assert_eq!(str_to_turns("1,2;3,4;"), Some(vec![Some((1, 2)), Some((3, 4)), None]));
+        assert_eq!(str_to_turns("1,2;3,4;P"), Some(vec![Some((1, 2)), Some((3, 4)), None]));
+        assert_eq!(str_to_turns("1,2;P;;3,4"), Some(vec![Some((1, 2)), None, None, Some((3, 4))]));
+        assert_eq!(str_to_turns("1,2"), Some(vec![Some((1, 2))]));
+        assert_eq!(str_to_turns(""), Some(vec![]));
+        assert_eq!(str_to_turns("1,2;Q"), None);
+        assert_eq!(str_to_turns("1,2;PP"), None);
+
+        // every list of up to four turns round trips, in both formats, with
+        // passes anywhere, except that one pass on its own used to be written
+        // as the empty string
+        let alphabet = [None, Some((0, 0)), Some((3, 4)), Some((7, 7))];
+        let mut lists: Vec<Vec<Turn>> = vec![vec![]];
+        for length in 1..=4 {
+            let shorter: Vec<Vec<Turn>> = lists.iter().filter(|list| list.len() == length - 1).cloned().collect();
+            for list in shorter {
+                for turn in alphabet {
+                    lists.push([&list[..], &[turn]].concat());
+                }
+            }
+        }
+        assert_eq!(lists.len(), 1 + 4 + 16 + 64 + 256);
+        for list in &lists {
+            assert_eq!(str_to_turns(&turns_to_str(list)).as_ref(), Some(list), "{list:?}");
+            let old = turns_to_str(list).replace(PASS_TOKEN, "");
+            if list[..] != [None] {
+                assert_eq!(str_to_turns(&old).as_ref(), Some(list), "{old:?}");
+            }
+        }
     }
 
     #[test]
@@ -330,7 +2132,7 @@ mod tests {
 
     #[test]
     fn test_str_to_states() {
-        let (score, first, second) = str_to_states("1.0:4,5;5,3;3,2;2,3");
+        let (score, first, second) = str_to_states("1.0:4,5;5,3;3,2;2,3").unwrap();
 
         let moves = [Some((4, 5)), Some((5, 3)), Some((3, 2)), Some((2, 3))];
         let mut g = Gamestate::new();
@@ -357,11 +2159,51 @@ mod tests {
         assert_eq!(score, 1.0);
         assert_eq!(first, first_ex);
         assert_eq!(second, second_ex);
+
+        // records of games with passes read the same in either turn format
+        let turns = turns_to_str(&transcript_to_turns(FULL_GAME).unwrap());
+        assert!(turns.contains(";P;"));
+        let (_, first, second) = str_to_states(&format!("0.0:{turns}")).unwrap();
+        assert_eq!(first.len() + second.len(), 62);
+        let old = str_to_states(&format!("0.0:{}", turns.replace(PASS_TOKEN, ""))).unwrap();
+        assert_eq!(old, (0.0, first, second));
+    }
+
+    #[test]
+    fn test_data_parse_errors() {
+        let error = |line, segment: &str, reason| DataParseError { line, segment: String::from(segment), reason };
+        assert_eq!(str_to_states("1.0"), Err(error(1, "1.0", DataParseReason::NoTurns)));
+        assert_eq!(str_to_states("won:4,5"), Err(error(1, "won", DataParseReason::BadScore)));
+        assert_eq!(str_to_states(":4,5"), Err(error(1, "", DataParseReason::BadScore)));
+        assert_eq!(str_to_states("1.0:4,5;9,9"), Err(error(1, "9,9", DataParseReason::BadTurn)));
+        assert_eq!(str_to_states("1.0:4,5;x"), Err(error(1, "x", DataParseReason::BadTurn)));
+        assert_eq!(str_to_states("1.0:4,5;4,5"), Err(error(1, "4,5", DataParseReason::IllegalMove { ply: 1 })));
+        assert_eq!(str_to_states("1.0:P"), Err(error(1, "P", DataParseReason::IllegalMove { ply: 0 })));
+        // a game with no turns is only its starting position
+        assert_eq!(str_to_states("0.5:").unwrap().1.len(), 1);
+
+        // the records of a file fail on the first bad line, with its number
+        let contents = "0.0:4,5;5,3\n\n1.0:4,5;5,5;0,0\n0.5\n1.0:4,5\n";
+        assert_eq!(
+            game_states_records(contents, RecordOptions::default()),
+            Err(error(3, "0,0", DataParseReason::IllegalMove { ply: 2 })),
+        );
+        assert_eq!(
+            error(3, "0,0", DataParseReason::IllegalMove { ply: 2 }).to_string(),
+            "turn \"0,0\" on line 3 is illegal after 2 turns",
+        );
+        // or leave them out and count them
+        let skipping = RecordOptions { skip_bad_lines: true, ..RecordOptions::default() };
+        let (records, skipped) = game_states_records(contents, skipping).unwrap();
+        assert_eq!(skipped, 2);
+        let good = game_states_records("0.0:4,5;5,3\n1.0:4,5\n", RecordOptions::default()).unwrap();
+        assert_eq!((records, skipped), (good.0, 2));
+        assert_eq!(good.1, 0);
     }
 
     #[test]
     fn test_game_states_record() {
-        let records = game_states_records("0.0:4,5;5,3;3,2;2,3\n1.0:4,5;5,5\n");
+        let records = game_states_records("0.0:4,5;5,3;3,2;2,3\n1.0:4,5;5,5\n", RecordOptions::default()).unwrap().0;
 
         let mut expected = HashMap::<u128, f32>::new();
         let mut g = Gamestate::new();
@@ -397,4 +2239,643 @@ mod tests {
             expected
         );
     }
+
+    /// A reader which gives out at most three bytes at a time.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl io::Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = buf.len().min(self.0.len()).min(3);
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_game_states_records_from() {
+        let contents = "0.0:4,5;5,3;3,2;2,3\n1.0:4,5;5,5\n";
+        for options in [
+            RecordOptions::default(),
+            RecordOptions { augment: true, ..RecordOptions::default() },
+            RecordOptions { dedup_canonical: true, ..RecordOptions::default() },
+        ] {
+            let in_memory = game_states_records(contents, options).unwrap();
+            let streamed = game_states_records_from(Cursor::new(contents), options, None).unwrap();
+            assert_eq!(streamed, in_memory);
+            let trickled = io::BufReader::with_capacity(4, Trickle(contents.as_bytes()));
+            assert_eq!(game_states_records_from(trickled, options, None).unwrap(), in_memory);
+        }
+
+        // progress is reported every so many lines
+        let contents = "0.0:4,5;5,3\n".repeat(7);
+        let mut reported = Vec::new();
+        let mut report = |lines| reported.push(lines);
+        let records = game_states_records_from(
+            io::BufReader::with_capacity(5, Trickle(contents.as_bytes())),
+            RecordOptions::default(),
+            Some((3, &mut report)),
+        );
+        assert_eq!(records.unwrap().0.len(), 3);
+        assert_eq!(reported, [3, 6]);
+
+        // errors reading and parsing are told apart
+        let records = game_states_records_from(Cursor::new(&b"0.0:4,5\n\xff\n"[..]), RecordOptions::default(), None);
+        assert!(matches!(records, Err(DataError::Io(e)) if e.kind() == io::ErrorKind::InvalidData));
+        let records = game_states_records_from(Cursor::new("0.0:4,5\n0.0:4,4\n"), RecordOptions::default(), None);
+        assert!(matches!(records, Err(DataError::Parse(DataParseError { line: 2, .. }))));
+    }
+
+    #[test]
+    fn test_game_states_records_weighted() {
+        let turns = [Some((4, 5)), Some((5, 5))];
+        let contents = format!(
+            "{}\n{}\n",
+            game_to_str(GameResult::WhiteWin(2), &turns, false),
+            game_to_str(GameResult::BlackWin(2), &turns, true),
+        );
+        let contents = contents.as_str();
+        assert_eq!(contents, "1.0:4,5;5,5\n0.0:4,5;5,5:adjudicated\n");
+        assert!(!str_is_adjudicated(contents.lines().next().unwrap()));
+        assert!(str_is_adjudicated(contents.lines().nth(1).unwrap()));
+        assert_eq!(game_to_str(GameResult::Draw, &[], false), "0.5:");
+
+        let mut g = Gamestate::new();
+        g.make_moves_fast(&[Some((4, 5)), Some((5, 5))]);
+        let compact = g.board().to_compact();
+        assert_eq!(game_states_records(contents, RecordOptions::default()).unwrap().0[&compact], 0.5);
+        assert_eq!(game_states_records_weighted(contents, 0.0, RecordOptions::default()).unwrap().0[&compact], 0.0);
+        assert_eq!(game_states_records_weighted(contents, 0.25, RecordOptions::default()).unwrap().0[&compact], 0.2);
+
+        // the counted records have the same labels, weighted by their games
+        let (counted, skipped) = game_states_records_counted(contents, 0.25, RecordOptions::default()).unwrap();
+        assert_eq!(skipped, 0);
+        assert!(counted.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert!(counted.contains(&(compact, 0.2, 1.25)));
+        let labels = game_states_records_weighted(contents, 0.25, RecordOptions::default()).unwrap().0;
+        assert_eq!(counted.len(), labels.len());
+        assert!(counted.iter().all(|(key, label, _)| labels[key] == *label));
+    }
+
+    /// Reads back the rows written by [collect_mcst_data].
+    fn read_collected(path: &Path) -> Vec<(u128, f64, u64)> {
+        fs::read_to_string(path).unwrap().lines()
+            .map(|line| {
+                let fields: Vec<&str> = line.split(',').collect();
+                assert_eq!(fields.len(), 3, "bad row {line}");
+                (fields[0].parse().unwrap(), fields[1].parse().unwrap(), fields[2].parse().unwrap())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_collect_mcst_data() {
+        let dir = std::env::temp_dir().join(format!("othello-collect-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (whole, resumed) = (dir.join("whole.csv"), dir.join("resumed.csv"));
+
+        let stats = collect_mcst_data(&whole, 3, 200, 7).unwrap();
+        let rows = read_collected(&whole);
+        assert_eq!(stats.games, 3);
+        assert_eq!(stats.positions, rows.len());
+        for &(compact, wins, visits) in &rows {
+            assert!(Board::from_compact(compact).is_some());
+            assert!(visits >= 64 && wins >= 0.0 && wins <= visits as f64);
+        }
+
+        // stopping after two games and picking up again collects the same
+        // games, even if something was written after the last one finished
+        assert_eq!(collect_mcst_data(&resumed, 2, 200, 7).unwrap().games, 2);
+        OpenOptions::new().append(true).open(&resumed).unwrap().write_all(b"1,2").unwrap();
+        let stats = collect_mcst_data(&resumed, 3, 200, 7).unwrap();
+        assert_eq!(stats.games, 1);
+        assert_eq!(fs::read_to_string(&resumed).unwrap(), fs::read_to_string(&whole).unwrap());
+        // and once every game is collected, there is nothing left to do
+        assert_eq!(collect_mcst_data(&resumed, 3, 200, 7).unwrap(), CollectStats::default());
+        assert_eq!(
+            collect_mcst_data(&resumed, 3, 100, 7).unwrap_err().kind(),
+            io::ErrorKind::InvalidInput,
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_collect_parallel() {
+        let config = |workers| CollectConfig::new(4, workers, 11, |seed| {
+            let new_agent = |seed| Box::new(McstMemoryAgent::with_budget(
+                McstAgent::seeded_default(seed, Gamestate::new()),
+                Budget::Cycles(50),
+            )) as Box<dyn MemoryAgent>;
+            (new_agent(seed), new_agent(seed.wrapping_add(2)))
+        });
+        let mut serial = collect_parallel(config(1)).unwrap();
+
+        let path = std::env::temp_dir().join(format!("othello-collect-parallel-{}.csv", std::process::id()));
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let mut parallel = config(2);
+        parallel.out_path = Some(path.clone());
+        let reported = Arc::clone(&reports);
+        parallel.progress = Some(Box::new(move |progress| reported.lock().unwrap().push(*progress)));
+        let mut parallel = collect_parallel(parallel).unwrap();
+
+        // the same positions are written as returned, and progress is
+        // reported after every game
+        let written: Vec<String> = fs::read_to_string(&path).unwrap().lines().map(String::from).collect();
+        let returned: Vec<String> = parallel.iter()
+            .map(|record| format!("{},{},{},{}", record.game, record.ply, record.board, record.result))
+            .collect();
+        assert_eq!(written, returned);
+        fs::remove_file(&path).unwrap();
+        let reports = reports.lock().unwrap();
+        assert_eq!(reports.iter().map(|progress| progress.games).collect::<Vec<_>>(), [1, 2, 3, 4]);
+        assert_eq!(reports.last().unwrap().positions, parallel.len());
+
+        // and they are the positions collected on one thread, if perhaps in
+        // another order
+        serial.sort_by_key(|record| (record.game, record.ply));
+        parallel.sort_by_key(|record| (record.game, record.ply));
+        assert_eq!(serial, parallel);
+        assert_eq!(serial.iter().map(|record| record.game).max(), Some(3));
+        for record in &serial {
+            assert!(Board::from_compact(record.board).is_some());
+            assert!([0.0, 0.5, 1.0].contains(&record.result));
+        }
+    }
+
+    #[test]
+    fn test_augment_symmetries() {
+        let distinct = |board: &Board| {
+            let mut keys: Vec<u128> = augment_symmetries(board, 0.25).iter()
+                .map(|&(compact, label)| {
+                    assert_eq!(label, 0.25);
+                    compact
+                })
+                .collect();
+            keys.sort_unstable();
+            keys.dedup();
+            keys.len()
+        };
+        // the opening is the same turned half way round or flipped across
+        // either diagonal, but one move in, no two orientations are the same
+        let mut g = Gamestate::new();
+        assert_eq!(distinct(g.board()), 2);
+        g.make_moves_fast(&[Some((4, 5)), Some((5, 3))]);
+        assert_eq!(distinct(g.board()), 8);
+
+        // a game from the opening contributes each of its orientations once
+        let augmented_options = RecordOptions { augment: true, ..RecordOptions::default() };
+        let contents = "0.0:4,5;5,3\n";
+        let plain = game_states_records(contents, RecordOptions::default()).unwrap().0;
+        let augmented = game_states_records(contents, augmented_options).unwrap().0;
+        assert_eq!(plain.len(), 3);
+        assert_eq!(augmented.len(), 2 + 8 + 8);
+        for (compact, value) in &plain {
+            let board = Board::from_compact(*compact).unwrap();
+            for (orientation, _) in augment_symmetries(&board, *value) {
+                assert_eq!(augmented[&orientation], *value);
+            }
+        }
+
+        // and symmetric positions are weighted like any other
+        let contents = "0.0:4,5\n1.0:4,5;5,5\n1.0:4,5;5,5\n";
+        let opening = Gamestate::new().board().to_compact();
+        assert_eq!(game_states_records(contents, augmented_options).unwrap().0[&opening], 1.0 / 3.0);
+    }
+
+    #[test]
+    fn test_dedup_canonical() {
+        // the four first moves are the same up to symmetry, so the games
+        // through them share their records once keyed canonically
+        let contents = "1.0:4,5\n0.0:5,4\n0.0:2,3\n0.0:3,2;2,2\n";
+        let canonical = RecordOptions { dedup_canonical: true, ..RecordOptions::default() };
+        let plain = game_states_records(contents, RecordOptions::default()).unwrap().0;
+        let deduped = game_states_records(contents, canonical).unwrap().0;
+        assert_eq!(plain.len(), 1 + 4 + 1);
+        assert_eq!(deduped.len(), 1 + 1 + 1);
+        let (_, _, second) = str_to_states("1.0:4,5").unwrap();
+        assert_eq!(deduped[&second[0].canonical().0], 0.25);
+        assert_eq!(deduped[&Gamestate::new().board().canonical().0], 0.75);
+        for compact in deduped.keys() {
+            assert_eq!(Board::from_compact(*compact).unwrap().canonical().0, *compact);
+        }
+        // augmenting adds nothing once every orientation has the same key
+        let both = RecordOptions { augment: true, dedup_canonical: true, ..RecordOptions::default() };
+        assert_eq!(game_states_records(contents, both).unwrap().0, deduped);
+
+        // rewriting a dataset merges the same rows
+        let dir = std::env::temp_dir().join(format!("othello-dedup-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (train, deduped_train) = (dir.join("train.csv"), dir.join("deduped.csv"));
+        let mut rows: Vec<(u128, f32)> = plain.into_iter().collect();
+        rows.sort_by_key(|(compact, _)| *compact);
+        let csv: String = rows.iter().map(|(compact, label)| format!("{compact},{label}\n")).collect();
+        fs::write(&train, format!("board,label\n{csv}")).unwrap();
+        let report = dedup_file(&train, &deduped_train).unwrap();
+        assert_eq!(report, DedupReport { rows_in: 6, rows_out: 3 });
+        assert_eq!(report.collapsed(), 3);
+        let rewritten = fs::read_to_string(&deduped_train).unwrap();
+        let mut lines = rewritten.lines();
+        assert_eq!(lines.next(), Some("board,label"));
+        let merged: HashMap<u128, f32> = lines
+            .map(|line| {
+                let (compact, label) = line.split_once(',').unwrap();
+                (compact.parse().unwrap(), label.parse().unwrap())
+            })
+            .collect();
+        assert_eq!(merged, deduped);
+
+        fs::write(&train, "board,label\n1,2,3\n").unwrap();
+        assert_eq!(dedup_file(&train, &deduped_train).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_split_dataset() {
+        // positions from whole games, some of them more than once and in
+        // other orientations
+        let mut records = sample_labeled(2000, RandomPositionSampler::new(103), |state| state.ply() as f32 / 60.0);
+        let mut rng = StdRng::seed_from_u64(103);
+        for i in 0..500 {
+            let board = Board::from_compact(records[i * 4].0).unwrap();
+            let orientations = augment_symmetries(&board, records[i * 4].1);
+            records.push(*orientations.choose(&mut rng).unwrap());
+        }
+        let key = |compact| Board::from_compact(compact).unwrap().canonical().0;
+        let discs = |compact| {
+            let (black, white, _) = Board::from_compact(compact).unwrap().counts();
+            black + white
+        };
+
+        for stratify in [false, true] {
+            let (train, valid) = split_dataset(&records, 0.2, 7, stratify);
+            assert_eq!(train.len() + valid.len(), records.len());
+            assert!((valid.len() as f64 / records.len() as f64 - 0.2).abs() < 0.02, "{}", valid.len());
+            // no position is in both sets in any orientation
+            let valid_keys: HashSet<u128> = valid.iter().map(|(compact, _)| key(*compact)).collect();
+            assert!(train.iter().all(|(compact, _)| !valid_keys.contains(&key(*compact))));
+            // and the split is the same every time for the same seed
+            assert_eq!(split_dataset(&records, 0.2, 7, stratify), (train.clone(), valid.clone()));
+            assert_ne!(split_dataset(&records, 0.2, 8, stratify).1, valid);
+
+            if stratify {
+                // every part of the game is split the same way
+                for range in [5..20, 20..35, 35..50, 50..65] {
+                    let in_range = |set: &[(u128, f32)]| set.iter().filter(|(c, _)| range.contains(&discs(*c))).count();
+                    let share = in_range(&valid) as f64 / (in_range(&valid) + in_range(&train)) as f64;
+                    assert!((share - 0.2).abs() < 0.03, "{range:?}: {share}");
+                }
+            }
+        }
+        let (train, valid) = split_dataset(&records, 0.0, 7, true);
+        assert_eq!((train.len(), valid.len()), (records.len(), 0));
+
+        let path = std::env::temp_dir().join(format!("othello-split-{}.csv", std::process::id()));
+        write_csv(&path, &valid).unwrap();
+        let written = fs::read_to_string(&path).unwrap();
+        let mut lines = written.lines();
+        assert_eq!(lines.next(), Some("board,label"));
+        let rows: Vec<(u128, f32)> = lines
+            .map(|line| {
+                let (compact, label) = line.split_once(',').unwrap();
+                (compact.parse().unwrap(), label.parse().unwrap())
+            })
+            .collect();
+        assert_eq!(rows, valid);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_blend_labels() {
+        // a position in both sources, an orientation of it, and a position
+        // of each source's own
+        let both = Gamestate::from_transcript("f5d6c3").unwrap().board().to_compact();
+        let turned = {
+            let mut board = Board::from_compact(both).unwrap();
+            board.rotate_90();
+            board.to_compact()
+        };
+        let key = |compact| Board::from_compact(compact).unwrap().canonical().0;
+        let late = Gamestate::from_transcript("f5f6e6f4e3c5c4e7c6e2").unwrap().board().to_compact();
+        let value_only = Gamestate::new().board().to_compact();
+        let outcomes = [(both, 1.0), (turned, 0.0), (late, 1.0)];
+        let values = [(turned, 0.25), (value_only, 0.75)];
+
+        // the two orientations have a mean outcome of 0.5, which is blended
+        // with the value they share
+        assert_eq!(blend_labels(&outcomes, &values, 0.5, UnmatchedLabels::Drop), [(key(both), 0.375)]);
+        assert_eq!(blend_labels(&outcomes, &values, 1.0, UnmatchedLabels::Drop), [(key(both), 0.5)]);
+        assert_eq!(blend_labels(&outcomes, &values, 0.0, UnmatchedLabels::Drop), [(key(both), 0.25)]);
+        let mut kept = vec![(key(both), 0.375), (key(late), 1.0)];
+        kept.sort_by_key(|(key, _)| *key);
+        assert_eq!(blend_labels(&outcomes, &values, 0.5, UnmatchedLabels::KeepOutcomes), kept);
+        kept.push((key(value_only), 0.75));
+        kept.sort_by_key(|(key, _)| *key);
+        assert_eq!(blend_labels(&outcomes, &values, 0.5, UnmatchedLabels::KeepAll), kept);
+        assert_eq!(blend_labels(&[], &[], 0.5, UnmatchedLabels::KeepAll), []);
+
+        // the schedule is given the ply by the discs on the board, and only
+        // used where there are labels to blend
+        let outcomes = [(both, 1.0), (late, 1.0), (value_only, 1.0)];
+        let values = [(both, 0.0), (late, 0.0)];
+        let plies = std::cell::RefCell::new(Vec::new());
+        let blended = blend_labels_by_ply(&outcomes, &values, |ply| {
+            plies.borrow_mut().push(ply);
+            ply as f32 / 60.0
+        }, UnmatchedLabels::Drop);
+        let mut plies = plies.into_inner();
+        plies.sort();
+        assert_eq!(plies, [3, 10]);
+        let label = |compact| blended.iter().find(|(key, _)| *key == compact).unwrap().1;
+        assert_eq!(label(key(both)), 3.0 / 60.0);
+        assert_eq!(label(key(late)), 10.0 / 60.0);
+        assert_eq!(blended.len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "lambda 1.5 at ply 0 is not between 0 and 1")]
+    fn test_blend_labels_bad_lambda() {
+        let start = Gamestate::new().board().to_compact();
+        blend_labels(&[(start, 1.0)], &[(start, 0.0)], 1.5, UnmatchedLabels::Drop);
+    }
+
+    #[test]
+    fn test_game_record_lines() {
+        let path = std::env::temp_dir().join(format!("othello-games-{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let games: Vec<GameRecord> = (0..100)
+            .map(|seed| play_game_recorded(
+                &mut MemorifiedAgent::new(RandomAgent::seeded(seed)),
+                &mut MemorifiedAgent::new(RandomAgent::seeded(seed + 100)),
+                Gamestate::new(),
+                ("random", "random"),
+            ))
+            .collect();
+        // written by two runs, the second appending to the first
+        for run in games.chunks(50) {
+            let mut writer = GameRecordWriter::append(&path).unwrap();
+            for game in run {
+                writer.write(game).unwrap();
+            }
+        }
+
+        // cut one line short, as if a run was stopped while writing it
+        let contents = fs::read_to_string(&path).unwrap();
+        let mut lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 100);
+        lines[41] = &lines[41][..lines[41].len() / 2];
+        fs::write(&path, lines.join("\n") + "\n\n").unwrap();
+        let (read, errors): (Vec<_>, Vec<_>) = GameRecordReader::open(&path).unwrap().partition(Result::is_ok);
+        assert_eq!((read.len(), errors.len()), (99, 1));
+        match &errors[0] {
+            Err(DataError::Parse(DataParseError {
+                line: 42,
+                reason: DataParseReason::BadRecord(_),
+                ..
+            })) => {}
+            other => panic!("{other:?}"),
+        }
+        let read: Vec<GameRecord> = read.into_iter().map(Result::unwrap).collect();
+        let mut expected = games.clone();
+        expected.remove(41);
+        assert_eq!(read, expected);
+
+        // the games give the same records as their lines of turns
+        let lines: String = expected.iter()
+            .map(|game| {
+                let white = match game.result {
+                    GameResult::BlackWin(_) => "0.0",
+                    GameResult::WhiteWin(_) => "1.0",
+                    GameResult::Draw => "0.5",
+                };
+                format!("{white}:{}\n", turns_to_str(&game.turns()))
+            })
+            .collect();
+        for options in [RecordOptions::default(), RecordOptions { augment: true, ..RecordOptions::default() }] {
+            assert_eq!(game_states_records_from_games(&read, options), game_states_records(&lines, options));
+        }
+
+        // a game from a position with White to move, and one which can't be
+        // replayed either way
+        let mut from_white = Gamestate::new();
+        from_white.make_move_fast(Some((4, 5)));
+        let mut white_first = play_game_recorded(
+            &mut MemorifiedAgent::new(RandomAgent::seeded(1)),
+            &mut MemorifiedAgent::new(RandomAgent::seeded(2)),
+            from_white,
+            ("random", "random"),
+        );
+        let (records, skipped) = game_states_records_from_games([&white_first], RecordOptions::default()).unwrap();
+        assert_eq!((records.len(), skipped), (white_first.plies.len() + 1, 0));
+        white_first.plies[3].turn = Some((0, 0));
+        let error = game_states_records_from_games([&read[0], &white_first], RecordOptions::default()).unwrap_err();
+        assert_eq!((error.line, error.segment.as_str(), error.reason), (2, "a1", DataParseReason::IllegalMove { ply: 3 }));
+        let options = RecordOptions { skip_bad_lines: true, ..RecordOptions::default() };
+        assert_eq!(game_states_records_from_games([&white_first], options).unwrap(), (HashMap::new(), 1));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_selfplay_pipeline() {
+        // the pipeline makes the directory itself
+        let dir = std::env::temp_dir().join(format!("othello-pipeline-{}", std::process::id()));
+        // greedy agents which break ties the same way play the same game
+        // every time, so the games only differ by their openings
+        let mut config = PipelineConfig::new("greedy:tie=first", "greedy:tie=first", 12, &dir);
+        config.threads = 3;
+        config.temperatures = vec![20.0; 4];
+        config.valid_fraction = 0.25;
+        config.binary = true;
+        config.policy = true;
+        config.keep_games = true;
+        let report = selfplay_pipeline(config.clone()).unwrap();
+        let games: Vec<GameRecord> = GameRecordReader::open(&dir.join("games.jsonl")).unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!((report.games, games.len()), (12, 12));
+        assert!(games.iter().all(|game| game.start == Gamestate::new().board().to_compact()));
+        let openings: HashSet<Vec<Turn>> = games.iter().map(|game| game.turns()[..4].to_vec()).collect();
+        assert!(openings.len() > 6, "{openings:?}");
+        assert_eq!(report.positions, games.iter().map(|game| game.plies.len() + 1).sum::<usize>());
+        assert_eq!(report.policy, games.iter().map(|game| game.plies.len() - 4).sum::<usize>());
+        assert_eq!(report.records, game_states_records_from_games(&games, config.records).unwrap().0.len());
+        assert_eq!(report.train + report.valid, report.records);
+        assert!(report.valid > 0 && report.train > report.valid);
+
+        // the CSV and binary files hold the same records
+        for (name, count) in [("train", report.train), ("valid", report.valid)] {
+            let records = read_records(&dir.join(format!("{name}.bin"))).unwrap();
+            assert_eq!(records.len(), count);
+            let csv = fs::read_to_string(dir.join(format!("{name}.csv"))).unwrap();
+            let rows: Vec<(u128, f32)> = csv.lines().skip(1)
+                .map(|line| {
+                    let (compact, label) = line.split_once(',').unwrap();
+                    (compact.parse().unwrap(), label.parse().unwrap())
+                })
+                .collect();
+            assert_eq!(rows, records);
+        }
+        // greedy agents don't analyse their moves, so every policy line is
+        // the move played
+        let policy = fs::read_to_string(dir.join("policy.csv")).unwrap();
+        let mut lines = policy.lines();
+        assert_eq!(lines.next(), Some("board,side,moves"));
+        let first: Vec<&str> = lines.next().unwrap().split(',').collect();
+        let mut state = Gamestate::new();
+        state.make_moves_fast(&games[0].turns()[..4]);
+        assert_eq!(first[0], state.board().to_compact().to_string());
+        assert_eq!(first[1], "black");
+        assert_eq!(first[2], format!("{}:1", turn_to_index(games[0].plies[4].turn)));
+
+        // the same seed plays the same games, on however many threads
+        let train = fs::read(dir.join("train.bin")).unwrap();
+        config.threads = 1;
+        assert_eq!(selfplay_pipeline(config.clone()).unwrap().records, report.records);
+        assert_eq!(fs::read(dir.join("train.bin")).unwrap(), train);
+
+        config.white = String::from("nobody");
+        assert_eq!(selfplay_pipeline(config).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// The reporter [NodeReporter] replaced, which reports every other level
+    /// of the tree from the root.
+    fn old_report(node: McstNode, data: &mut HashMap<u128, (f64, u64)>) {
+        if node.total() >= &64 {
+            let entry = data.entry(node.game().board().to_compact()).or_insert((0.0, 0));
+            entry.0 += node.wins();
+            entry.1 += u64::from(*node.total());
+            for child in node.children().values() {
+                old_skip(child, data);
+            }
+        }
+    }
+
+    fn old_skip(node: McstNode, data: &mut HashMap<u128, (f64, u64)>) {
+        if node.total() >= &64 {
+            for child in node.children().values() {
+                old_report(child, data);
+            }
+        }
+    }
+
+    #[test]
+    fn test_node_reporter() {
+        let mut agent = McstAgent::seeded_default(106, Gamestate::new());
+        agent.cycle_n(5000).unwrap();
+        let root = agent.tree().root();
+
+        // without passes, the same positions as before
+        let mut old = HashMap::new();
+        old_report(root, &mut old);
+        let mut new = HashMap::new();
+        NodeReporter::default().report(root, 0, &mut new);
+        assert!(old.len() > 5);
+        assert_eq!(new.len(), old.len());
+        for (compact, (wins, total)) in &old {
+            assert_eq!(new[compact], NodeReport { wins: *wins, total: *total, ply: None });
+        }
+
+        // with plies, and with positions the same up to symmetry merged
+        let reporter = NodeReporter { min_visits: 16, include_ply: true, dedup_canonical: true };
+        let mut merged = HashMap::new();
+        reporter.report(root, 0, &mut merged);
+        let mut exact = HashMap::new();
+        NodeReporter { dedup_canonical: false, ..reporter }.report(root, 0, &mut exact);
+        assert!(merged.len() < exact.len());
+        for (compact, report) in &exact {
+            let ply = report.ply.unwrap();
+            assert_eq!(ply % 2, 0);
+            let board = Board::from_compact(*compact).unwrap();
+            let (black, white, _) = board.counts();
+            assert_eq!(usize::from(black + white), ply + 4);
+            assert!(merged[&board.canonical().0].ply.unwrap() <= ply);
+        }
+        let totals = |reports: &HashMap<u128, NodeReport>| reports.values().map(|report| report.total).sum::<u64>();
+        assert_eq!(totals(&merged), totals(&exact));
+
+        // after a pass, the player to move is the same one level down, which
+        // only the new reporter reports
+        let turns = transcript_to_turns(FULL_GAME).unwrap();
+        let pass = turns.iter().position(Option::is_none).unwrap();
+        let mut before = Gamestate::new();
+        before.make_moves_fast(&turns[..pass - 1]);
+        let mut agent = McstAgent::seeded_default(106, before.clone());
+        agent.cycle_n(2000).unwrap();
+        let reporter = NodeReporter { min_visits: 1, include_ply: true, dedup_canonical: false };
+        let mut reports = HashMap::new();
+        reporter.report(agent.tree().root(), usize::from(before.ply()), &mut reports);
+        let mut after_pass = before.clone();
+        after_pass.make_moves_fast(&turns[pass - 1..=pass]);
+        assert_eq!(after_pass.side_to_move(), before.side_to_move());
+        assert_eq!(reports[&after_pass.board().to_compact()].ply, Some(usize::from(before.ply()) + 2));
+        let mut after_move = after_pass.clone();
+        after_move.make_move_fast(turns[pass + 1]);
+        assert!(!reports.contains_key(&after_move.board().to_compact()));
+    }
+
+    #[test]
+    fn test_binary_records() {
+        let dir = std::env::temp_dir().join(format!("othello-records-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("train.bin");
+        let mut rng = StdRng::seed_from_u64(95);
+        let records: Vec<(u128, f32)> = (0..10_000)
+            .map(|_| (rng.random_range(0..3_u128.pow(64)), rng.random()))
+            .collect();
+        write_records(&path, &records).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().len(), 16 + 20 * 10_000);
+        assert_eq!(read_records(&path).unwrap(), records);
+        assert!(read_weighted_records(&path).unwrap().iter().all(|&(_, _, weight)| weight == 1.0));
+
+        let weighted: Vec<(u128, f32, f32)> = records.iter()
+            .map(|&(compact, label)| (compact, label, rng.random_range(1.0..8.0)))
+            .collect();
+        write_weighted_records(&path, &weighted).unwrap();
+        assert_eq!(read_weighted_records(&path).unwrap(), weighted);
+        assert_eq!(read_records(&path).unwrap(), records);
+        write_records(&path, &[]).unwrap();
+        assert_eq!(read_records(&path).unwrap(), []);
+
+        // damaged files are errors
+        write_records(&path, &records[..3]).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        for (damaged, kind) in [
+            (bytes[..bytes.len() - 1].to_vec(), io::ErrorKind::UnexpectedEof),
+            (bytes[..16 + 20].to_vec(), io::ErrorKind::UnexpectedEof),
+            (bytes[..10].to_vec(), io::ErrorKind::UnexpectedEof),
+            ([&bytes[..], &[0]].concat(), io::ErrorKind::InvalidData),
+            ([b"othr", &bytes[4..]].concat(), io::ErrorKind::InvalidData),
+            ([&bytes[..4], &[2, 0], &bytes[6..]].concat(), io::ErrorKind::InvalidData),
+            ([&bytes[..8], &[u8::MAX; 8], &bytes[16..]].concat(), io::ErrorKind::UnexpectedEof),
+            (b"board,label\n".to_vec(), io::ErrorKind::InvalidData),
+        ] {
+            fs::write(&path, damaged).unwrap();
+            assert_eq!(read_records(&path).unwrap_err().kind(), kind);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_weighted_csv() {
+        let dir = std::env::temp_dir().join(format!("othello-weighted-csv-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("train.csv");
+        let records = vec![(0, 0.5, 1.0), (3_u128.pow(64) - 1, 0.25, 12.0), (42, 1.0, 0.5)];
+        write_weighted_csv(&path, &records).unwrap();
+        assert!(fs::read_to_string(&path).unwrap().starts_with("board,label,weight\n"));
+        assert_eq!(read_csv(&path).unwrap(), records);
+
+        // records without weights count once
+        let unweighted: LabeledBoards = records.iter().map(|&(compact, label, _)| (compact, label)).collect();
+        write_csv(&path, &unweighted).unwrap();
+        assert!(read_csv(&path).unwrap().iter().zip(&records)
+            .all(|(read, written)| *read == (written.0, written.1, 1.0)));
+
+        for bad in ["board,label\n1,0.5,1,2\n", "1\n", "x,0.5\n", "1,0.5,heavy\n"] {
+            fs::write(&path, bad).unwrap();
+            assert_eq!(read_csv(&path).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
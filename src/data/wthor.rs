@@ -0,0 +1,290 @@
+//! Reading games from WTHOR databases, the `.wtb` files in which the French
+//! Othello federation publishes every tournament game it records.
+//!
+//! A `.wtb` file is a 16 byte header followed by a 68 byte record for every
+//! game. The header holds the date the file was made, the number of games as
+//! a little-endian `u32` (bytes 4 to 7), the year the games were played as a
+//! little-endian `u16` (bytes 10 and 11), and the board size (byte 12, where
+//! `0` also means 8x8). Each game record holds the numbers of its tournament
+//! and its Black and White players as little-endian `u16`s, Black's real and
+//! theoretical scores, then its moves, a byte each, with `0` after the last.
+//! A move is its row and column from `1` to `8` as a two digit number, so
+//! `11` is a1, `18` is h1 and `81` is a8. Passes aren't recorded.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::data::{RecordOptions, RecordTotals};
+use crate::gameplay::{Gamestate, Turn};
+
+/// The length of a `.wtb` file's header.
+const HEADER_LEN: usize = 16;
+/// The length of each game's record in a `.wtb` file.
+const GAME_LEN: usize = 68;
+
+/// Errors that can occur when reading a WTHOR database with [read_wtb].
+#[derive(Debug)]
+pub enum WthorError {
+    Io(io::Error),
+    /// The file is shorter than a header.
+    NoHeader { len: usize },
+    /// The games are on a board of the given size, rather than 8x8.
+    BoardSize(u8),
+    /// The file isn't as long as the number of games its header gives.
+    Length { games: usize, len: usize },
+}
+
+impl fmt::Display for WthorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WthorError::Io(e) => write!(f, "could not read WTHOR database: {e}"),
+            WthorError::NoHeader { len } => {
+                write!(f, "WTHOR database of {len} bytes is too short for a header")
+            }
+            WthorError::BoardSize(size) => {
+                write!(f, "WTHOR database is of games on a {size}x{size} board")
+            }
+            WthorError::Length { games, len } => write!(
+                f,
+                "WTHOR database of {len} bytes should hold {games} games of {GAME_LEN} bytes after its header",
+            ),
+        }
+    }
+}
+
+impl std::error::Error for WthorError {}
+
+impl From<io::Error> for WthorError {
+    fn from(e: io::Error) -> Self {
+        WthorError::Io(e)
+    }
+}
+
+/// A game read from a WTHOR database.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WthorGame {
+    /// The year the game was played in.
+    pub year: u16,
+    /// The number of the tournament the game was played in.
+    pub tournament: u16,
+    /// The number of the player who played Black.
+    pub black_player: u16,
+    /// The number of the player who played White.
+    pub white_player: u16,
+    /// How many discs Black ended the game with, counting the empty squares
+    /// for whoever won (or half of them each for a draw).
+    pub score: u8,
+    /// How many discs Black would have ended the game with under perfect
+    /// play from some point near its end.
+    pub theoretical_score: u8,
+    /// Every move of the game, with the passes put back in.
+    pub turns: Vec<Turn>,
+}
+
+/// How many of the games in a WTHOR database [read_wtb_with_report] kept.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WthorReport {
+    /// How many games were read.
+    pub games: usize,
+    /// How many games were left out for a move which couldn't be played.
+    pub illegal: usize,
+    /// How many games were left out because their recorded score isn't the
+    /// score their moves end with, such as games which were abandoned.
+    pub wrong_score: usize,
+}
+
+/// Reads every game of the WTHOR database at `path`, leaving out the games
+/// which can't be replayed or whose replay doesn't end with their recorded
+/// score (see [read_wtb_with_report]).
+pub fn read_wtb(path: &Path) -> Result<Vec<WthorGame>, WthorError> {
+    Ok(read_wtb_with_report(path)?.0)
+}
+
+/// Like [read_wtb], but also reports how many games were left out, and why.
+pub fn read_wtb_with_report(path: &Path) -> Result<(Vec<WthorGame>, WthorReport), WthorError> {
+    let bytes = fs::read(path)?;
+    if bytes.len() < HEADER_LEN {
+        return Err(WthorError::NoHeader { len: bytes.len() });
+    }
+    let (header, body) = bytes.split_at(HEADER_LEN);
+    let games = u32::from_le_bytes(header[4..8].try_into().unwrap()) as usize;
+    let year = u16::from_le_bytes([header[10], header[11]]);
+    if header[12] != 0 && header[12] != 8 {
+        return Err(WthorError::BoardSize(header[12]));
+    }
+    if games.checked_mul(GAME_LEN) != Some(body.len()) {
+        return Err(WthorError::Length { games, len: bytes.len() });
+    }
+
+    let mut report = WthorReport::default();
+    let mut kept = Vec::with_capacity(games);
+    for record in body.chunks_exact(GAME_LEN) {
+        report.games += 1;
+        let field = |i: usize| u16::from_le_bytes([record[i], record[i + 1]]);
+        let Some((turns, final_state)) = replay(&record[8..]) else {
+            report.illegal += 1;
+            continue;
+        };
+        let score = record[6];
+        if score != final_score(&final_state) {
+            report.wrong_score += 1;
+            continue;
+        }
+        kept.push(WthorGame {
+            year,
+            tournament: field(0),
+            black_player: field(2),
+            white_player: field(4),
+            score,
+            theoretical_score: record[7],
+            turns,
+        });
+    }
+    Ok((kept, report))
+}
+
+/// Plays the moves of a game record from the start, passing whenever the
+/// player to move can't do anything else, and returns every turn and the
+/// state they end in, or [None] if a move can't be played.
+fn replay(moves: &[u8]) -> Option<(Vec<Turn>, Gamestate)> {
+    let mut state = Gamestate::new();
+    let mut turns = Vec::with_capacity(moves.len());
+    for &byte in moves.iter().take_while(|&&byte| byte != 0) {
+        let (row, column) = (byte / 10, byte % 10);
+        if !(1..=8).contains(&row) || !(1..=8).contains(&column) {
+            return None;
+        }
+        if state.get_moves()[..] == [None] {
+            state.make_move_fast(None);
+            turns.push(None);
+        }
+        let turn = Some((column - 1, row - 1));
+        if !state.valid_move(turn) {
+            return None;
+        }
+        state.make_move_fast(turn);
+        turns.push(turn);
+    }
+    Some((turns, state))
+}
+
+/// Returns how many discs WTHOR counts for Black at the end of a game, which
+/// gives the empty squares to the winner.
+fn final_score(state: &Gamestate) -> u8 {
+    let (black, white, empty) = state.board().counts();
+    match black.cmp(&white) {
+        Ordering::Greater => black + empty,
+        Ordering::Less => black,
+        Ordering::Equal => black + empty / 2,
+    }
+}
+
+/// Returns every position of `games` with the share of the games through it
+/// won by the player to move, counting draws as half a win, keyed as
+/// [game_states_records](super::game_states_records) keys them with the default options: positions with
+/// White to move are turned and have their colors flipped, so that the
+/// player to move is always Black. The records are in order of their boards.
+pub fn wthor_to_records(games: &[WthorGame]) -> Vec<(u128, f32)> {
+    let mut totals = RecordTotals { options: RecordOptions::default(), totals: HashMap::new() };
+    for game in games {
+        // the share of the game White won
+        let score = match game.score.cmp(&32) {
+            Ordering::Greater => 0.0,
+            Ordering::Less => 1.0,
+            Ordering::Equal => 0.5,
+        };
+        let mut state = Gamestate::new();
+        let mut states = vec![state.clone()];
+        for &turn in &game.turns {
+            state.make_move_fast(turn);
+            states.push(state.clone());
+        }
+        totals.add_game(&states, score);
+    }
+
+    let mut records: Vec<(u128, f32)> = totals.records().into_iter().collect();
+    records.sort_by_key(|(compact, _)| *compact);
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{game_states_records, turns_to_str, turns_to_transcript};
+    use crate::gameplay::GameResult;
+
+    fn fixture() -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/tiny.wtb")
+    }
+
+    #[test]
+    fn test_read_wtb() {
+        // four games: a whole game with a pass, a wipeout, the first game
+        // again with the wrong score, and a game opening on a1
+        let (games, report) = read_wtb_with_report(&fixture()).unwrap();
+        assert_eq!(report, WthorReport { games: 4, illegal: 1, wrong_score: 1 });
+        assert_eq!(games.len(), 2);
+        assert_eq!(
+            (games[0].year, games[0].tournament, games[0].black_player, games[0].white_player),
+            (2025, 1, 2, 3),
+        );
+        assert_eq!((games[0].score, games[0].theoretical_score), (30, 32));
+        assert_eq!(
+            turns_to_transcript(&games[0].turns),
+            "e6f4g3c6c5e7d6g4g5c4e8h4c3g2g1f2b4f7f6c2g8g6b5a5g7h8c7c8f3f5b6f8b3h1c1f1e3a4a3a6h5d2d7\
+             d8a7b7a8h2h3b8e2h6d3d1e1b1b2a1a2--h7",
+        );
+        let mut state = Gamestate::new();
+        state.make_moves_fast(&games[1].turns);
+        assert_eq!(state.winner(), Some(GameResult::BlackWin(13)));
+        assert_eq!(games[1].score, 64);
+        assert_eq!(read_wtb(&fixture()).unwrap(), games);
+
+        // every position of both games, with the opening shared between them
+        let records = wthor_to_records(&games);
+        let opening = Gamestate::new().board().to_compact();
+        let label = |compact| records.iter().find(|(c, _)| *c == compact).unwrap().1;
+        assert_eq!(label(opening), 0.5);
+        assert!(records.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        // which are the records of the games written as lines of turns
+        let lines: String = games.iter()
+            .map(|game| {
+                let white = match game.score.cmp(&32) {
+                    Ordering::Greater => 0.0,
+                    Ordering::Less => 1.0,
+                    Ordering::Equal => 0.5,
+                };
+                format!("{white}:{}\n", turns_to_str(&game.turns))
+            })
+            .collect();
+        let (expected, _) = game_states_records(&lines, RecordOptions::default()).unwrap();
+        assert_eq!(records.len(), expected.len());
+        assert!(records.iter().all(|(compact, label)| expected[compact] == *label));
+    }
+
+    #[test]
+    fn test_wtb_errors() {
+        let bytes = fs::read(fixture()).unwrap();
+        let path = std::env::temp_dir().join(format!("othello-wthor-{}.wtb", std::process::id()));
+        let read = |bytes: &[u8]| {
+            fs::write(&path, bytes).unwrap();
+            read_wtb(&path)
+        };
+        assert!(matches!(read(&bytes[..10]), Err(WthorError::NoHeader { len: 10 })));
+        assert!(matches!(read(&bytes[..bytes.len() - 1]), Err(WthorError::Length { games: 4, .. })));
+        assert!(matches!(read(&bytes[..16]), Err(WthorError::Length { games: 4, len: 16 })));
+        let mut big_board = bytes.clone();
+        big_board[12] = 10;
+        assert!(matches!(read(&big_board), Err(WthorError::BoardSize(10))));
+        // an empty database is fine
+        let mut empty = bytes[..16].to_vec();
+        empty[4..8].copy_from_slice(&0_u32.to_le_bytes());
+        assert_eq!(read(&empty).unwrap(), []);
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(read_wtb(&path), Err(WthorError::Io(_))));
+    }
+}
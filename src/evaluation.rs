@@ -0,0 +1,70 @@
+use crate::gameplay::{Gamestate, Players, States};
+
+/// A way of judging how good a position is without searching any further.
+pub trait Evaluator {
+    /// Scores a game state that is not over.
+    /// Positive means Black is winning, negative means White is winning.
+    fn evaluate(&self, state: &Gamestate) -> f64;
+}
+
+/// Evaluates a position by summing a weight for every tile each player
+/// holds and adding the [mobility difference](Gamestate::mobility_difference)
+/// scaled by its own weight.
+pub struct WeightedSquareEvaluator {
+    /// How much holding each tile is worth, indexed as `weights[y][x]`.
+    weights: [[f64; 8]; 8],
+    /// How much each extra legal move is worth.
+    mobility_weight: f64,
+}
+
+impl WeightedSquareEvaluator {
+    /// The usual hand-tuned weights: corners are very valuable and the
+    /// tiles next to them are dangerous to take.
+    pub const STANDARD_WEIGHTS: [[f64; 8]; 8] = [
+        [100.0, -20.0, 10.0,  5.0,  5.0, 10.0, -20.0, 100.0],
+        [-20.0, -50.0, -2.0, -2.0, -2.0, -2.0, -50.0, -20.0],
+        [ 10.0,  -2.0, -1.0, -1.0, -1.0, -1.0,  -2.0,  10.0],
+        [  5.0,  -2.0, -1.0, -1.0, -1.0, -1.0,  -2.0,   5.0],
+        [  5.0,  -2.0, -1.0, -1.0, -1.0, -1.0,  -2.0,   5.0],
+        [ 10.0,  -2.0, -1.0, -1.0, -1.0, -1.0,  -2.0,  10.0],
+        [-20.0, -50.0, -2.0, -2.0, -2.0, -2.0, -50.0, -20.0],
+        [100.0, -20.0, 10.0,  5.0,  5.0, 10.0, -20.0, 100.0],
+    ];
+
+    /// Creates a new `WeightedSquareEvaluator` with the given tile weights
+    /// (indexed as `weights[y][x]`) and mobility weight.
+    pub fn new(weights: [[f64; 8]; 8], mobility_weight: f64) -> Self {
+        WeightedSquareEvaluator { weights, mobility_weight }
+    }
+
+    /// Creates a `WeightedSquareEvaluator` which weighs every tile and every
+    /// extra legal move as 1, which is to say it adds up the disc difference
+    /// and the mobility difference.
+    pub fn discs_and_mobility() -> Self {
+        WeightedSquareEvaluator::new([[1.0; 8]; 8], 1.0)
+    }
+}
+
+impl Default for WeightedSquareEvaluator {
+    /// Uses [WeightedSquareEvaluator::STANDARD_WEIGHTS] and values each
+    /// extra legal move at 5.
+    fn default() -> Self {
+        WeightedSquareEvaluator::new(WeightedSquareEvaluator::STANDARD_WEIGHTS, 5.0)
+    }
+}
+
+impl Evaluator for WeightedSquareEvaluator {
+    fn evaluate(&self, state: &Gamestate) -> f64 {
+        let mut total = self.mobility_weight * f64::from(state.mobility_difference());
+        for y in 0..8 {
+            for x in 0..8 {
+                total += match state.board().at(x, y) {
+                    Some(States::Taken(Players::Black)) => self.weights[y as usize][x as usize],
+                    Some(States::Taken(Players::White)) => -self.weights[y as usize][x as usize],
+                    _ => 0.0,
+                };
+            }
+        }
+        total
+    }
+}
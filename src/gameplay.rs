@@ -1,37 +1,186 @@
 use std::fmt;
-use std::rc::Rc;
-use std::cell::RefCell;
+use std::cell::{Ref, RefCell};
 
-pub use crate::mechanics::{Players, States};
-use crate::mechanics::Board;
+use serde::{Deserialize, Serialize};
+use smallvec::{smallvec, SmallVec};
+
+pub use crate::mechanics::{FlipLine, Players, RenderStyle, States, Symmetry};
+use crate::mechanics::{Board, ZOBRIST_WHITE_TO_MOVE};
 
 /// A player's move, which may be a board position `(x, y)` or [None] for pass.
 pub type Turn = Option<(u8, u8)>;
 
+/// A list of moves which only allocates when there are more than 16 of them
+/// (which is rare in real games).
+pub type MoveList = SmallVec<[Turn; 16]>;
+
+/// The outcome of a finished game.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameResult {
+    /// Black won by the given (positive) number of tiles.
+    BlackWin(i8),
+    /// White won by the given (positive) number of tiles.
+    WhiteWin(i8),
+    Draw,
+}
+
+impl GameResult {
+    /// Converts a final [score](Gamestate::score) into a result.
+    pub fn from_score(score: i8) -> Self {
+        match score {
+            0 => GameResult::Draw,
+            s if s > 0 => GameResult::BlackWin(s),
+            s => GameResult::WhiteWin(-s),
+        }
+    }
+
+    /// Returns the final score: positive if Black won, negative if White won.
+    pub fn score(&self) -> i8 {
+        match self {
+            GameResult::BlackWin(margin) => *margin,
+            GameResult::WhiteWin(margin) => -*margin,
+            GameResult::Draw => 0,
+        }
+    }
+
+    /// Returns the winning player, or [None] for a draw.
+    pub fn winner(&self) -> Option<Players> {
+        match self {
+            GameResult::BlackWin(_) => Some(Players::Black),
+            GameResult::WhiteWin(_) => Some(Players::White),
+            GameResult::Draw => None,
+        }
+    }
+
+    /// Returns the result once `komi` tiles are added to White's count
+    /// (see [Rules::komi]). Margins beyond 127 tiles are cut down to it.
+    pub fn with_komi(&self, komi: i8) -> Self {
+        GameResult::from_score(self.score().saturating_sub(komi).max(-i8::MAX))
+    }
+}
+
+/// Changes to the standard rules, for evening out games between
+/// agents of different strength.
+///
+/// As in Go, the weaker player takes Black and any handicap, while
+/// White is given the komi.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rules {
+    /// Tiles added to White's count at the end of the game before deciding
+    /// who won. Negative komi is taken away from White instead.
+    pub komi: i8,
+    /// How many of the corners (up to all 4) Black has tiles on before the
+    /// game starts, in the order a1, h8, a8, h1.
+    pub handicap_corners: u8,
+}
+
+impl Rules {
+    /// The corners handicap tiles are placed on, in order.
+    pub const CORNERS: [(u8, u8); 4] = [(0, 0), (7, 7), (0, 7), (7, 0)];
+
+    /// Returns `state` with Black's handicap tiles placed on it.
+    /// Corners that are already taken are left alone.
+    pub fn apply_handicap(&self, state: &Gamestate) -> Gamestate {
+        let mut board = *state.board();
+        for &(x, y) in Rules::CORNERS.iter().take(usize::from(self.handicap_corners)) {
+            if board.at(x, y) == Some(States::Empty) {
+                board.change(x, y, States::Taken(Players::Black));
+            }
+        }
+        Gamestate::new_from(board, state.ply())
+    }
+
+    /// Converts a final [score](Gamestate::score) into a result,
+    /// taking [komi](Rules::komi) into account.
+    pub fn result(&self, score: i8) -> GameResult {
+        GameResult::from_score(score).with_komi(self.komi)
+    }
+}
+
+/// The reasons a move can be rejected by [Gamestate::try_move].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MoveError {
+    /// Neither player can move.
+    GameOver,
+    /// The tile is only a legal move for the player whose turn it isn't.
+    NotYourTurn,
+    /// The move is not legal for anyone: the tile is off the board, taken,
+    /// or flips nothing, or it is a pass while there are tiles to play.
+    IllegalSquare(Turn),
+    /// A tile was played but the only legal move is to pass.
+    MustPass,
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MoveError::GameOver => write!(f, "the game is over"),
+            MoveError::NotYourTurn => write!(f, "it is the other player's move"),
+            MoveError::IllegalSquare(None) => write!(f, "cannot pass while there are legal moves"),
+            MoveError::IllegalSquare(Some((x, y))) if *x < 8 && *y < 8 => {
+                write!(f, "{} is not a legal move", loc_to_alg((*x, *y)))
+            }
+            MoveError::IllegalSquare(Some((x, y))) => write!(f, "({x}, {y}) is not on the board"),
+            MoveError::MustPass => write!(f, "there are no legal moves, so the only move is to pass"),
+        }
+    }
+}
+
+impl std::error::Error for MoveError {}
+
+/// A move recorded in a [Gamestate]'s history, along with the tiles it flipped.
+type HistoryEntry = (Turn, Vec<(u8, u8)>);
+
+/// What a [Gamestate] has worked out about its current position so far.
+///
+/// Every part lives behind one [RefCell] so that making a move clears them
+/// together and they can never disagree.
+#[derive(Clone, Debug, Default)]
+struct MoveCache {
+    /// The valid moves for the side to move, if generated.
+    moves: Option<MoveList>,
+    /// Whether the game is over, if known.
+    terminal: Option<bool>,
+    /// The [move masks](Board::move_mask) of Black and White, if computed.
+    masks: [Option<u64>; 2],
+}
+
+#[cfg(test)]
+thread_local! {
+    /// How many times [Gamestate::gen_moves] has run on this thread.
+    static GEN_MOVES_CALLS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
 /// A representation of the game state, including the board, turn number,
 /// and cached list of valid moves for the current player.
-// TODO: hey make it so that when it clones it keeps the turn list (if it doesn't already?)
-#[derive(Clone, Debug, PartialEq)]
+///
+/// Cloning keeps the cached move list and terminality, and equality only considers the
+/// board and turn number.
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Gamestate {
     board: Board,
     turn: u8,
-    moves: RefCell<Option<Rc<Vec<Turn>>>>,
+    #[serde(skip)]
+    cache: RefCell<MoveCache>,
+    /// Every move made along with the tiles it flipped,
+    /// if history has been [enabled](Gamestate::enable_history).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    history: Option<Vec<HistoryEntry>>,
+}
+
+impl PartialEq for Gamestate {
+    /// Game states are equal when their boards and turn numbers are,
+    /// regardless of what is cached or recorded.
+    fn eq(&self, other: &Self) -> bool {
+        self.board == other.board && self.turn == other.turn
+    }
 }
 
 impl fmt::Display for Gamestate {
     /// Formats the board followed by a message indicating whose turn it is,
     /// or "Game Over" if the game has ended.
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f,
-            "{}\n{}",
-            self.board,
-            match self.whose_turn() {
-                States::Empty => "Game Over",
-                States::Taken(Players::Black) => "Black to play",
-                States::Taken(Players::White) => "White to play",
-            },
-        )
+        write!(f, "{}\n{}", self.board, self.status())
     }
 }
 
@@ -44,12 +193,13 @@ impl Gamestate {
         let mut g = Gamestate {
             board: Board::new(),
             turn: 0,
-            moves: RefCell::new(None),
+            cache: RefCell::default(),
+            history: None,
         };
-        g.board.pieces[3][3] = States::Taken(Players::White);
-        g.board.pieces[4][4] = States::Taken(Players::White);
-        g.board.pieces[4][3] = States::Taken(Players::Black);
-        g.board.pieces[3][4] = States::Taken(Players::Black);
+        g.board.change(3, 3, States::Taken(Players::White));
+        g.board.change(4, 4, States::Taken(Players::White));
+        g.board.change(3, 4, States::Taken(Players::Black));
+        g.board.change(4, 3, States::Taken(Players::Black));
         g
     }
 
@@ -59,73 +209,208 @@ impl Gamestate {
         Gamestate {
             board: board,
             turn: turn,
-            moves: RefCell::new(None),
+            cache: RefCell::default(),
+            history: None,
         }
     }
 
+    /// Describes whose turn it is, or that the game is over.
+    fn status(&self) -> &'static str {
+        match self.whose_turn() {
+            States::Empty => "Game Over",
+            States::Taken(Players::Black) => "Black to play",
+            States::Taken(Players::White) => "White to play",
+        }
+    }
+
+    /// Draws the board in the given [RenderStyle] with the current player's
+    /// legal moves marked as `*` and `last_move` highlighted (see
+    /// [Board::render_marked]), followed by the same status line as the
+    /// [Display](fmt::Display) implementation.
+    ///
+    /// A game state with history [enabled](Gamestate::enable_history) knows
+    /// its own [last move](Gamestate::last_turn).
+    pub fn render_with_moves(&self, style: RenderStyle, last_move: Turn) -> String {
+        let moves: Vec<(u8, u8)> = self.get_moves().iter().flatten().copied().collect();
+        format!("{}\n{}", self.board.render_marked(style, &moves, last_move), self.status())
+    }
+
+    /// Replays a transcript in algebraic notation such as `f5d6c3` from the
+    /// standard starting position and returns the resulting game state.
+    ///
+    /// See [transcript_to_turns] for the accepted format.
+    pub fn from_transcript(transcript: &str) -> Result<Self, TranscriptError> {
+        let mut g = Gamestate::new();
+        // won't fail since transcript_to_turns validated every move
+        g.make_moves_fast(&transcript_to_turns(transcript)?);
+        Ok(g)
+    }
+
     /// Returns whose turn it is.
     /// Returns [empty](States::Empty) if the game is over.
     pub fn whose_turn(&self) -> States {
-        if self.get_moves().is_empty() {
+        if self.is_terminal() {
             States::Empty
         } else {
-            if self.turn & 1 == 0 {
-                States::Taken(Players::Black)
-            } else {
-                States::Taken(Players::White)
-            }
+            States::Taken(self.side_to_move())
         }
     }
 
+    /// Returns the player whose turn it is by turn number alone,
+    /// even if the game is over or that player has to pass.
+    pub fn side_to_move(&self) -> Players {
+        if self.turn & 1 == 0 { Players::Black } else { Players::White }
+    }
+
+    /// Returns how many turns have been taken, passes included.
+    pub fn ply(&self) -> u8 {
+        self.turn
+    }
+
+    /// Returns whether neither player can move.
+    ///
+    /// Only checks the players' move masks rather than generating the
+    /// move list, and remembers the answer until the next move is made.
+    pub fn is_terminal(&self) -> bool {
+        let known = {
+            let cache = self.cache.borrow();
+            cache.terminal.or_else(|| cache.moves.as_ref().map(|moves| moves.is_empty()))
+        };
+        let terminal = known.unwrap_or_else(
+            || self.cached_mask(Players::Black) == 0 && self.cached_mask(Players::White) == 0
+        );
+        self.cache.borrow_mut().terminal = Some(terminal);
+        terminal
+    }
+
+    /// Returns every tile `p` could place a piece on, whether or not it is
+    /// their turn.
+    ///
+    /// Unlike [Gamestate::get_moves] this never contains a pass, so it is
+    /// empty if `p` has no moves. Cached separately for each player.
+    pub fn get_moves_for(&self, p: Players) -> MoveList {
+        let mut mask = self.cached_mask(p);
+        let mut moves = MoveList::new();
+        while mask != 0 {
+            // won't fail since only the 64 lowest bits can be set
+            moves.push(index_to_turn(mask.trailing_zeros() as u8).unwrap());
+            mask &= mask - 1;
+        }
+        moves
+    }
+
+    /// Returns how many more tiles Black could place a piece on than White.
+    /// Positive means Black has more moves, negative means White does.
+    pub fn mobility_difference(&self) -> i8 {
+        self.cached_mask(Players::Black).count_ones() as i8
+            - self.cached_mask(Players::White).count_ones() as i8
+    }
+
+    /// Returns the [move mask](Board::move_mask) of `p`,
+    /// computing it first if necessary.
+    fn cached_mask(&self, p: Players) -> u64 {
+        let index = match p {
+            Players::Black => 0,
+            Players::White => 1,
+        };
+        if let Some(mask) = self.cache.borrow().masks[index] {
+            return mask;
+        }
+        let mask = self.board.move_mask(p);
+        self.cache.borrow_mut().masks[index] = Some(mask);
+        mask
+    }
+
+    /// Returns the Zobrist hash of the game state: the board's
+    /// [hash](Board::zobrist_hash) combined with whose turn it is.
+    pub fn zobrist_hash(&self) -> u64 {
+        if self.turn & 1 == 0 {
+            self.board.zobrist_hash()
+        } else {
+            self.board.zobrist_hash() ^ ZOBRIST_WHITE_TO_MOVE
+        }
+    }
+
+    /// Returns the result of the game, or [None] if the game is not over.
+    pub fn winner(&self) -> Option<GameResult> {
+        if self.is_terminal() {
+            Some(GameResult::from_score(self.score()))
+        } else { None }
+    }
+
     /// Returns the score of the current board.
     /// Positive means Black is winning, negative means White is winning.
     pub fn score(&self) -> i8 {
         self.board.score()
     }
 
-    /// Returns a reference-counted list of all valid moves
-    /// (including [None] for pass).
+    /// Returns a list of all valid moves (including [None] for pass).
     /// Cached after first computation for performance.
-    pub fn get_moves(&self) -> Rc<Vec<Turn>> {
-        if self.moves.borrow().is_none() {
-            *self.moves.borrow_mut() = Some(Rc::new(self.gen_moves()));
+    pub fn get_moves(&self) -> MoveList {
+        self.cached_moves().clone()
+    }
+
+    /// Returns every valid move along with the game state it leads to.
+    ///
+    /// Yields a single `(None, state)` pair if the only move is to pass,
+    /// and nothing if the game is over.
+    pub fn children(&self) -> impl Iterator<Item = (Turn, Gamestate)> + '_ {
+        self.get_moves().into_iter().map(
+            move |turn| {
+                let mut next = self.clone();
+                next.make_move_fast(turn);
+                (turn, next)
+            }
+        )
+    }
+
+    /// Returns the number of valid moves (counting [None] for pass)
+    /// without copying the move list.
+    pub fn move_count(&self) -> usize {
+        self.cached_moves().len()
+    }
+
+    /// Borrows the cached move list, generating it first if necessary.
+    fn cached_moves(&self) -> Ref<'_, MoveList> {
+        if self.cache.borrow().moves.is_none() {
+            let moves = self.gen_moves();
+            let mut cache = self.cache.borrow_mut();
+            cache.terminal = Some(moves.is_empty());
+            cache.moves = Some(moves);
         };
-        self.moves.borrow().as_ref().unwrap().clone()
+        Ref::map(self.cache.borrow(), |cache| cache.moves.as_ref().unwrap())
     }
 
     /// Generates the list of valid moves for the current player.
     /// If no moves are possible, returns a list containing only [None] (pass).
     /// If the game is over, returns an empty list.
-    fn gen_moves(&self) -> Vec<Turn> {
-        let possible_turn = if self.turn & 1 == 0 {
-            Players::Black
-        } else {
-            Players::White
-        };
+    fn gen_moves(&self) -> MoveList {
+        #[cfg(test)]
+        GEN_MOVES_CALLS.with(|calls| calls.set(calls.get() + 1));
 
-        let moves = self.board.get_moves(possible_turn);
-        let is_terminal = match (moves.is_empty(), possible_turn) {
+        let possible_turn = self.side_to_move();
+
+        let moves = self.board.moves_iter(possible_turn);
+        let is_terminal = match (moves.len() == 0, possible_turn) {
             (false, _) => false,
-            (true, Players::Black) => self.board.get_moves(Players::White).is_empty(),
-            (true, Players::White) => self.board.get_moves(Players::Black).is_empty(),
+            (true, Players::Black) => self.board.move_mask(Players::White) == 0,
+            (true, Players::White) => self.board.move_mask(Players::Black) == 0,
         };
 
         if is_terminal {
-            Vec::new()
+            MoveList::new()
         } else {
-            if moves.is_empty() {
-                vec![None]
+            if moves.len() == 0 {
+                smallvec![None]
             } else {
-                moves.into_iter().map(
-                    |t| { Some(t) }
-                ).collect()
+                moves.map(Some).collect()
             }
         }
     }
 
     /// Returns `true` if the move is valid for the current player.
     pub fn valid_move(&self, m: Turn) -> bool {
-        self.get_moves().contains(&m)
+        self.cached_moves().contains(&m)
     }
 
     /// Provides a shared reference to the underlying board.
@@ -133,25 +418,74 @@ impl Gamestate {
         &self.board
     }
 
+    /// Checks whether `turn` is a legal move for the side to move and
+    /// returns that player if so, or why not otherwise.
+    pub fn check_move(&self, turn: Turn) -> Result<Players, MoveError> {
+        if self.is_terminal() {
+            return Err(MoveError::GameOver);
+        }
+        let mover = self.side_to_move();
+        if self.valid_move(turn) {
+            return Ok(mover);
+        }
+        let other = match mover {
+            Players::Black => Players::White,
+            Players::White => Players::Black,
+        };
+        Err(match turn {
+            Some(_) if self.cached_moves().as_slice() == [None] => MoveError::MustPass,
+            Some((x, y)) if x < 8 && y < 8
+                && self.cached_mask(other) & 1 << turn_to_index(turn) != 0 => MoveError::NotYourTurn,
+            _ => MoveError::IllegalSquare(turn),
+        })
+    }
+
+    /// Applies the given move to the game state using full flipping logic
+    /// and returns the flipped positions, or why the move is not allowed.
+    ///
+    /// If you do not want to see the list of flipped positions,
+    /// consider [Gamestate::make_move_fast].
+    pub fn try_move(&mut self, turn: Turn) -> Result<Vec<(u8, u8)>, MoveError> {
+        let whose_turn = self.check_move(turn)?;
+        self.turn += 1;
+        *self.cache.borrow_mut() = MoveCache::default();
+        let flips = match turn {
+            // won't fail since check_move found the move to be legal
+            Some((x, y)) => self.board.apply_move(x, y, whose_turn).unwrap(),
+            None => Vec::new(),
+        };
+        if let Some(history) = &mut self.history {
+            history.push((turn, flips.clone()));
+        }
+        Ok(flips)
+    }
+
     /// Applies the given move to the game state using full flipping logic.
     /// Returns a vector of flipped positions if successful,
     /// or [None] if invalid or game is over.
     ///
-    /// If you do not want to see the list of flipped positions,
-    /// consider [Gamestate::make_move_fast].
+    /// See [Gamestate::try_move] to find out why a move was rejected.
     pub fn make_move(&mut self, turn: Turn) -> Option<Vec<(u8, u8)>> {
-        if let States::Taken(whose_turn) = self.whose_turn() {
-            if self.get_moves().contains(&turn) {
-                self.turn += 1;
-                *self.moves.borrow_mut() = None;
-                if let Some((x, y)) = turn {
-                    self.board.change(x, y, States::Taken(whose_turn));
-                    Some(self.board.flip_all(x, y))
-                } else {
-                    Some(Vec::new())
-                }
-            } else { None }
-        } else { None }
+        self.try_move(turn).ok()
+    }
+
+    /// Like [Gamestate::make_move], but returns the flipped positions grouped
+    /// by the direction they were flipped in.
+    pub fn make_move_detailed(&mut self, turn: Turn) -> Option<Vec<FlipLine>> {
+        let whose_turn = self.check_move(turn).ok()?;
+        self.turn += 1;
+        *self.cache.borrow_mut() = MoveCache::default();
+        let lines = if let Some((x, y)) = turn {
+            self.board.change(x, y, States::Taken(whose_turn));
+            self.board.flip_all_detailed(x, y)
+        } else {
+            Vec::new()
+        };
+        if let Some(history) = &mut self.history {
+            let flips = lines.iter().flat_map(|line| line.squares.iter().copied()).collect();
+            history.push((turn, flips));
+        }
+        Some(lines)
     }
 
     /// Applies the given move to the game state using full flipping logic.
@@ -160,18 +494,124 @@ impl Gamestate {
     /// If the move does not go through, maintains original board state.
     ///
     /// Returns [true} if the move was valid and applied, [false] otherwise.
+    /// See [Gamestate::try_move] to find out why a move was rejected.
     pub fn make_move_fast(&mut self, turn: Turn) -> bool {
-        if let States::Taken(whose_turn) = self.whose_turn() {
-            if self.get_moves().contains(&turn) {
-                self.turn += 1;
-                if let Some((x, y)) = turn {
-                    self.board.change(x, y, States::Taken(whose_turn));
-                    self.board.flip_all_fast(x, y);
+        let Ok(whose_turn) = self.check_move(turn) else {
+            return false;
+        };
+        self.turn += 1;
+        if let Some(history) = &mut self.history {
+            // the flips are needed to undo, so the fast path can't be used
+            let flips = match turn {
+                Some((x, y)) => self.board.apply_move(x, y, whose_turn).unwrap(),
+                None => Vec::new(),
+            };
+            history.push((turn, flips));
+        } else if let Some((x, y)) = turn {
+            self.board.change(x, y, States::Taken(whose_turn));
+            self.board.flip_all_fast(x, y);
+        }
+        *self.cache.borrow_mut() = MoveCache::default();
+        true
+    }
+
+    /// Starts recording every move made from now on so that moves can be
+    /// [undone](Gamestate::undo_move) and [viewed](Gamestate::view_history).
+    ///
+    /// Does nothing if history is already enabled.
+    pub fn enable_history(&mut self) {
+        if self.history.is_none() {
+            self.history = Some(Vec::new());
+        }
+    }
+
+    /// Undoes the most recent recorded move and returns it.
+    ///
+    /// Returns [None] if history is not enabled or there are no recorded
+    /// moves left to undo.
+    pub fn undo_move(&mut self) -> Option<Turn> {
+        let (turn, flips) = self.history.as_mut()?.pop()?;
+        self.turn -= 1;
+        if let Some((x, y)) = turn {
+            self.board.unapply_move(x, y, self.side_to_move(), &flips);
+        }
+        *self.cache.borrow_mut() = MoveCache::default();
+        Some(turn)
+    }
+
+    /// Returns the most recent recorded move, or [None] if history is not
+    /// enabled or no moves have been recorded.
+    pub fn last_turn(&self) -> Option<Turn> {
+        self.history.as_ref()?.last().map(|(turn, _)| *turn)
+    }
+
+    /// Returns every recorded move in the order they were made,
+    /// or [None] if history is not enabled.
+    pub fn view_history(&self) -> Option<Vec<Turn>> {
+        self.history.as_ref().map(
+            |history| history.iter().map(|(turn, _)| *turn).collect()
+        )
+    }
+
+    /// Returns a copy of the game state with the board transformed by `sym`.
+    ///
+    /// The turn number stays the same, and any recorded history is
+    /// transformed along with the board so moves can still be undone.
+    pub fn transformed(&self, sym: Symmetry) -> Gamestate {
+        let mut board = self.board;
+        sym.apply(&mut board);
+        Gamestate {
+            board,
+            turn: self.turn,
+            cache: RefCell::default(),
+            history: self.history.as_ref().map(
+                |history| history.iter().map(
+                    |(turn, flips)| (
+                        transform_turn(*turn, sym),
+                        flips.iter().map(|(x, y)| sym.map(*x, *y)).collect(),
+                    )
+                ).collect()
+            ),
+        }
+    }
+
+    /// Plays uniformly random legal moves for both sides until the game is
+    /// over and returns the final score, leaving this game state untouched.
+    ///
+    /// Works directly on a copy of the board and its move masks, so no move
+    /// lists are built along the way.
+    pub fn random_playout(&self, rng: &mut impl rand::Rng) -> i8 {
+        self.random_playout_plies(rng).0
+    }
+
+    /// Like [Gamestate::random_playout], but also returns how many plies,
+    /// passes included, were played.
+    pub fn random_playout_plies(&self, rng: &mut impl rand::Rng) -> (i8, usize) {
+        let mut board = self.board;
+        let mut player = self.side_to_move();
+        let mut opponent = match player {
+            Players::Black => Players::White,
+            Players::White => Players::Black,
+        };
+
+        for plies in 0.. {
+            let mut mask = board.move_mask(player);
+            if mask == 0 && board.move_mask(opponent) == 0 {
+                return (board.score(), plies);
+            }
+            if mask != 0 {
+                // clear a random number of the lowest set bits to pick a move
+                for _ in 0..rng.random_range(0..mask.count_ones()) {
+                    mask &= mask - 1;
                 }
-                *self.moves.borrow_mut() = None;
-                true
-            } else { false }
-        } else { false }
+                let index = mask.trailing_zeros() as u8;
+                let (x, y) = (index / 8, index % 8);
+                board.change(x, y, States::Taken(player));
+                board.flip_all_fast(x, y);
+            }
+            (player, opponent) = (opponent, player);
+        }
+        unreachable!("a game can't go on forever")
     }
 
     /// Applies a sequence of moves and reports whether all moves were valid.
@@ -187,11 +627,38 @@ impl Gamestate {
     }
 }
 
-/// Converts a string matching " *\d *, *\d *" into a tuple of ints.
+/// Converts a location into standard algebraic notation,
+/// where the column `x` is a letter from `a` to `h`
+/// and the row `y` is a number from `1` to `8` (so `(3, 2)` is `d3`).
+///
+/// Does not perform bounds checking.
+pub fn loc_to_alg((x, y): (u8, u8)) -> String {
+    format!("{}{}", char::from(b'a' + x), y + 1)
+}
+
+/// Converts a square in algebraic notation such as `d3` (in either case,
+/// ignoring surrounding whitespace) into a location.
+///
+/// Returns [None] if the string is not a square on the board.
+pub fn alg_to_loc(s: &str) -> Option<(u8, u8)> {
+    if let [column, row] = s.trim().as_bytes() {
+        let x = column.to_ascii_lowercase().wrapping_sub(b'a');
+        let y = row.wrapping_sub(b'1');
+        if x < 8 && y < 8 {
+            Some((x, y))
+        } else { None }
+    } else { None }
+}
+
+/// Converts a string matching " *\d *, *\d *" or an algebraic square
+/// (see [alg_to_loc]) into a tuple of ints.
 /// Does check that they are less than 8.
 ///
 /// Returns [None] if parsing fails or the format is incorrect.
 pub fn str_to_loc(s: &str) -> Option<(u8, u8)> {
+    if let Some(loc) = alg_to_loc(s) {
+        return Some(loc);
+    }
     let stripped = s.replace(" ", "");
     let mut iter = stripped.split(",");
     if let (Some(x), Some(y)) = (iter.next(), iter.next()) {
@@ -202,3 +669,580 @@ pub fn str_to_loc(s: &str) -> Option<(u8, u8)> {
         } else { None }
     } else { None }
 }
+
+/// Converts a list of turns to a transcript in algebraic notation
+/// such as `f5d6c3`, with passes written as `--`.
+pub fn turns_to_transcript(turns: &[Turn]) -> String {
+    turns.iter().map(
+        |t: &Turn| -> String {
+            if let Some(loc) = t {
+                loc_to_alg(*loc)
+            } else {
+                String::from("--")
+            }
+        }
+    ).collect()
+}
+
+/// Errors that can occur when reading a transcript with [transcript_to_turns].
+#[derive(Debug, PartialEq)]
+pub enum TranscriptError {
+    /// The text at the given byte offset is not a square or `--`.
+    BadSquare { offset: usize, text: String },
+    /// The move at the given byte offset is not legal in the game so far.
+    IllegalMove { offset: usize, turn: Turn },
+}
+
+/// Reads a transcript in algebraic notation such as `f5d6c3` (the format of
+/// [turns_to_transcript]) played from the standard starting position.
+///
+/// Passes may be written as `--`, but any pass forced on the side to move
+/// is inserted automatically when it is left out. Whitespace is ignored.
+pub fn transcript_to_turns(transcript: &str) -> Result<Vec<Turn>, TranscriptError> {
+    let mut game = Gamestate::new();
+    let mut turns: Vec<Turn> = Vec::new();
+    let mut offset = 0;
+
+    while offset < transcript.len() {
+        if transcript.as_bytes()[offset].is_ascii_whitespace() {
+            offset += 1;
+            continue;
+        }
+
+        let text = transcript.get(offset..offset + 2).ok_or_else(
+            || TranscriptError::BadSquare {
+                offset,
+                text: transcript[offset..].chars().take(2).collect(),
+            }
+        )?;
+        let turn = if text == "--" {
+            None
+        } else if let Some(loc) = alg_to_loc(text) {
+            Some(loc)
+        } else {
+            return Err(TranscriptError::BadSquare { offset, text: String::from(text) });
+        };
+
+        if turn.is_some() && game.get_moves().as_slice() == [None] {
+            game.make_move_fast(None);
+            turns.push(None);
+        }
+        if !game.make_move_fast(turn) {
+            return Err(TranscriptError::IllegalMove { offset, turn });
+        }
+        turns.push(turn);
+        offset += 2;
+    }
+
+    Ok(turns)
+}
+
+/// Returns where a move ends up when the board is transformed by `sym`.
+/// A pass stays a pass.
+///
+/// Does not perform bounds checking.
+pub fn transform_turn(t: Turn, sym: Symmetry) -> Turn {
+    t.map(|(x, y)| sym.map(x, y))
+}
+
+/// The [turn index](turn_to_index) of a pass.
+pub const PASS_INDEX: u8 = 64;
+
+/// Converts a move into a single index from `0` to `64`, where `(x, y)` is
+/// `8 * x + y` (matching the bits of [Board::move_mask] and the order of
+/// [Board::to_compact]) and a pass is [PASS_INDEX].
+///
+/// Does not perform bounds checking.
+pub fn turn_to_index(turn: Turn) -> u8 {
+    match turn {
+        Some((x, y)) => 8 * x + y,
+        None => PASS_INDEX,
+    }
+}
+
+/// Inverse of [turn_to_index].
+///
+/// Returns [None] if `index` is greater than [PASS_INDEX].
+pub fn index_to_turn(index: u8) -> Option<Turn> {
+    match index {
+        0..PASS_INDEX => Some(Some((index / 8, index % 8))),
+        PASS_INDEX => Some(None),
+        _ => None,
+    }
+}
+
+/// Sets bit [turn_to_index] of every position in `turns`, so the result
+/// can be used to mask the board squares of a policy output.
+///
+/// Passes have no bit and are ignored.
+pub fn moves_to_mask(turns: &[Turn]) -> u64 {
+    turns.iter().flatten().fold(0, |mask, &loc| mask | 1 << turn_to_index(Some(loc)))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use rand::seq::IndexedRandom;
+
+    use super::*;
+    use crate::agent::Agent;
+    use crate::agent::implementations::GreedyAgent;
+
+    #[test]
+    fn test_turns_to_transcript() {
+        assert_eq!(turns_to_transcript(&[Some((5, 4)), Some((3, 5)), None, Some((2, 2))]), "f5d6--c3");
+        assert_eq!(turns_to_transcript(&[]), "");
+    }
+
+    /// A full 60-move game (greedy agent against a random one) in which
+    /// White is forced to pass once near the end.
+    pub(crate) const FULL_GAME: &str = "f5f6f7c5b5f4f3g4e3e2h4h5h6d3d1d6c3b4d7b6a5f2g2g5a7h1g6f1g3d8\
+                             h2c4d2a3c6e1g1b7c7e6e7e8b3f8g7g8h3c8h7c1b8a8c2a4b2h8a2a6--b1a1";
+
+    #[test]
+    fn test_transcript_to_turns() {
+        let turns = transcript_to_turns(FULL_GAME).unwrap();
+        assert_eq!(turns.len(), 61);
+        assert_eq!(turns.iter().filter(|t| t.is_none()).count(), 1);
+        assert_eq!(turns_to_transcript(&turns), FULL_GAME);
+
+        // the forced pass is inferred when left out
+        assert_eq!(transcript_to_turns(&FULL_GAME.replace("--", "")), Ok(turns.clone()));
+        assert_eq!(transcript_to_turns(&FULL_GAME.to_uppercase()), Ok(turns.clone()));
+        assert_eq!(transcript_to_turns("f5 d6\nc3"), transcript_to_turns("f5d6c3"));
+
+        let g = Gamestate::from_transcript(FULL_GAME).unwrap();
+        assert_eq!(g.board().counts(), (27, 37, 0));
+        assert!(g.winner().is_some());
+    }
+
+    #[test]
+    fn test_transcript_errors() {
+        assert_eq!(
+            transcript_to_turns("f5d6i9"),
+            Err(TranscriptError::BadSquare { offset: 4, text: String::from("i9") }),
+        );
+        assert_eq!(
+            transcript_to_turns("f5d"),
+            Err(TranscriptError::BadSquare { offset: 2, text: String::from("d") }),
+        );
+        assert_eq!(
+            transcript_to_turns("f5 a1"),
+            Err(TranscriptError::IllegalMove { offset: 3, turn: Some((0, 0)) }),
+        );
+        assert_eq!(
+            transcript_to_turns("--"),
+            Err(TranscriptError::IllegalMove { offset: 0, turn: None }),
+        );
+        assert_eq!(
+            Gamestate::from_transcript("f5f5"),
+            Err(TranscriptError::IllegalMove { offset: 2, turn: Some((5, 4)) }),
+        );
+    }
+
+    /// A nearly full board where White is to move but has to pass,
+    /// while Black can still play g8 or h8.
+    fn white_must_pass() -> Gamestate {
+        let board = Board::from_ascii(
+            " 01234567\n\
+             0BBBBBBBB\n\
+             1BBBBBBBB\n\
+             2BBBBBBBB\n\
+             3BBBBBBBB\n\
+             4BBBBBBBB\n\
+             5BBBBBBBB\n\
+             6BBBBBBWW\n\
+             7BBBBBB..\n"
+        ).unwrap();
+        Gamestate::new_from(board, 1)
+    }
+
+    #[test]
+    fn test_algebraic() {
+        assert_eq!(loc_to_alg((0, 0)), "a1");
+        assert_eq!(loc_to_alg((3, 2)), "d3");
+        assert_eq!(loc_to_alg((7, 7)), "h8");
+        for x in 0..8 {
+            for y in 0..8 {
+                assert_eq!(alg_to_loc(&loc_to_alg((x, y))), Some((x, y)));
+            }
+        }
+
+        assert_eq!(alg_to_loc("F5"), Some((5, 4)));
+        assert_eq!(alg_to_loc(" c4\n"), Some((2, 3)));
+        for bad in ["i9", "i1", "a9", "a0", "", "d", "d33", "3d", "5,4"] {
+            assert_eq!(alg_to_loc(bad), None, "{bad}");
+        }
+
+        assert_eq!(str_to_loc("E6"), Some((4, 5)));
+        assert_eq!(str_to_loc("4, 5"), Some((4, 5)));
+        assert_eq!(str_to_loc("i9"), None);
+
+        // the standard opening moves for black
+        let mut expected: Vec<Turn> = ["d3", "c4", "f5", "e6"].iter().map(|m| alg_to_loc(m)).collect();
+        let mut actual = Gamestate::new().get_moves().to_vec();
+        expected.sort();
+        actual.sort();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_turn_index() {
+        for index in 0..=PASS_INDEX {
+            let turn = index_to_turn(index).unwrap();
+            assert_eq!(turn_to_index(turn), index);
+        }
+        for index in PASS_INDEX + 1..=u8::MAX {
+            assert_eq!(index_to_turn(index), None);
+        }
+        assert_eq!(index_to_turn(PASS_INDEX), Some(None));
+        assert_eq!(turn_to_index(Some((7, 7))), 63);
+
+        let mut g = Gamestate::new();
+        while !g.is_terminal() {
+            let moves = g.get_moves();
+            assert_eq!(moves_to_mask(&moves), g.board().move_mask(g.side_to_move()));
+            g.make_move_fast(*moves.choose(&mut rand::rng()).unwrap());
+        }
+        assert_eq!(moves_to_mask(&[None]), 0);
+    }
+
+    #[test]
+    fn test_try_move_errors() {
+        let mut g = Gamestate::new();
+        assert_eq!(g.try_move(Some((0, 0))), Err(MoveError::IllegalSquare(Some((0, 0)))));
+        assert_eq!(g.try_move(Some((3, 3))), Err(MoveError::IllegalSquare(Some((3, 3)))));
+        assert_eq!(g.try_move(Some((8, 2))), Err(MoveError::IllegalSquare(Some((8, 2)))));
+        assert_eq!(g.try_move(None), Err(MoveError::IllegalSquare(None)));
+        // d6 is only a legal opening move for White
+        assert_eq!(g.try_move(Some((3, 5))), Err(MoveError::NotYourTurn));
+        assert_eq!(g, Gamestate::new());
+        assert_eq!(g.try_move(Some((3, 2))), Ok(vec![(3, 3)]));
+
+        let mut g = white_must_pass();
+        assert_eq!(g.try_move(Some((6, 7))), Err(MoveError::MustPass));
+        assert_eq!(g.try_move(None), Ok(Vec::new()));
+        g.make_moves_fast(&[Some((6, 7)), None, Some((7, 7))]);
+        assert_eq!(g.try_move(None), Err(MoveError::GameOver));
+
+        assert_eq!(MoveError::GameOver.to_string(), "the game is over");
+        assert_eq!(MoveError::IllegalSquare(Some((0, 0))).to_string(), "a1 is not a legal move");
+        assert_eq!(MoveError::IllegalSquare(Some((8, 2))).to_string(), "(8, 2) is not on the board");
+    }
+
+    #[test]
+    fn test_get_moves_for() {
+        let g = Gamestate::new();
+        for p in [Players::Black, Players::White] {
+            let mut moves = g.get_moves_for(p).to_vec();
+            moves.sort();
+            assert_eq!(moves.len(), 4);
+            if p == Players::Black {
+                let mut expected = g.get_moves().to_vec();
+                expected.sort();
+                assert_eq!(moves, expected);
+            }
+        }
+        assert_eq!(g.mobility_difference(), 0);
+
+        let mut g = white_must_pass();
+        assert_eq!(g.get_moves().as_slice(), &[None]);
+        assert!(g.get_moves_for(Players::White).is_empty());
+        assert_eq!(g.get_moves_for(Players::Black).as_slice(), &[Some((6, 7)), Some((7, 7))]);
+        assert_eq!(g.mobility_difference(), 2);
+
+        g.make_moves_fast(&[None, Some((6, 7)), None]);
+        assert_eq!(g.get_moves_for(Players::Black).as_slice(), &[Some((7, 7))]);
+        assert_eq!(g.mobility_difference(), 1);
+        g.make_move_fast(Some((7, 7)));
+        assert_eq!(g.mobility_difference(), 0);
+        assert!(g.get_moves_for(Players::Black).is_empty());
+    }
+
+    #[test]
+    fn test_random_playout() {
+        let mut rng = rand::rng();
+        let mut g = Gamestate::new();
+        while !g.is_terminal() {
+            let before = g.clone();
+            let score = g.random_playout(&mut rng);
+            assert_eq!(g, before);
+            assert!((-64..=64).contains(&score));
+            g.make_move_fast(*g.get_moves().choose(&mut rng).unwrap());
+        }
+        assert_eq!(g.random_playout(&mut rng), g.score());
+        assert_eq!(g.random_playout_plies(&mut rng), (g.score(), 0));
+
+        // Black fills the board unless h8 first flips White's last tiles,
+        // after which g8 is no one's move
+        let scores: Vec<i8> = (0..20).map(|_| white_must_pass().random_playout(&mut rng)).collect();
+        assert!(scores.iter().all(|score| [63, 64].contains(score)));
+        // which takes White's pass and h8, or another pass and g8 as well
+        let plies: Vec<usize> = (0..20).map(|_| white_must_pass().random_playout_plies(&mut rng).1).collect();
+        assert!(plies.iter().all(|plies| [2, 4].contains(plies)));
+    }
+
+    #[test]
+    fn test_transformed() {
+        let mut rng = rand::rng();
+        let mut states = vec![white_must_pass()];
+        for _ in 0..20 {
+            let mut g = Gamestate::new();
+            while !g.is_terminal() {
+                states.push(g.clone());
+                g.make_move_fast(*g.get_moves().choose(&mut rng).unwrap());
+            }
+            states.push(g);
+        }
+
+        for g in &states {
+            for sym in Symmetry::ALL {
+                let image = g.transformed(sym);
+                let mut expected: Vec<Turn> = g.get_moves().iter().map(|t| transform_turn(*t, sym)).collect();
+                let mut actual = image.get_moves().to_vec();
+                expected.sort();
+                actual.sort();
+                assert_eq!(actual, expected);
+                assert_eq!(image.transformed(sym.inverse()), *g);
+
+                for (turn, child) in g.children() {
+                    let mut moved = image.clone();
+                    assert!(moved.make_move_fast(transform_turn(turn, sym)));
+                    assert_eq!(moved, child.transformed(sym));
+                }
+            }
+        }
+
+        let mut g = Gamestate::new();
+        g.enable_history();
+        g.make_moves_fast(&[Some((3, 2)), Some((2, 2)), Some((2, 3))]);
+        let mut image = g.transformed(Symmetry::FlipMainDiagonal);
+        assert_eq!(image.undo_move(), Some(Some((3, 2))));
+        while image.undo_move().is_some() {}
+        assert_eq!(image, Gamestate::new().transformed(Symmetry::FlipMainDiagonal));
+
+        assert_eq!(transform_turn(None, Symmetry::Rotate90), None);
+        assert_eq!(transform_turn(Some((0, 0)), Symmetry::Rotate90), Some((7, 0)));
+    }
+
+    #[test]
+    fn test_winner() {
+        assert_eq!(Gamestate::new().winner(), None);
+
+        let mut b = Board::new();
+        for x in 0..8 {
+            for y in 0..8 {
+                let color = if x < 5 { Players::Black } else { Players::White };
+                b.change(x, y, States::Taken(color));
+            }
+        }
+        let result = Gamestate::new_from(b, 60).winner();
+        assert_eq!(result, Some(GameResult::BlackWin(16)));
+        assert_eq!(result.unwrap().score(), 16);
+        assert_eq!(result.unwrap().winner(), Some(Players::Black));
+
+        b.flip_colors();
+        assert_eq!(Gamestate::new_from(b, 60).winner(), Some(GameResult::WhiteWin(16)));
+
+        assert_eq!(GameResult::from_score(0), GameResult::Draw);
+        assert_eq!(GameResult::Draw.winner(), None);
+        assert_eq!(GameResult::from_score(-64).score(), -64);
+    }
+
+    #[test]
+    fn test_komi() {
+        let rules = Rules { komi: 6, handicap_corners: 0 };
+        assert_eq!(rules.result(4), GameResult::WhiteWin(2));
+        assert_eq!(rules.result(6), GameResult::Draw);
+        assert_eq!(GameResult::BlackWin(4).with_komi(-6), GameResult::BlackWin(10));
+        assert_eq!(GameResult::WhiteWin(64).with_komi(100), GameResult::WhiteWin(127));
+        assert_eq!(Rules::default().result(-3), GameResult::WhiteWin(3));
+    }
+
+    #[test]
+    fn test_handicap() {
+        let rules = Rules { komi: 0, handicap_corners: 2 };
+        let g = rules.apply_handicap(&Gamestate::new());
+        assert_eq!(g.board().count(Players::Black), 4);
+        for (x, y) in [(0, 0), (7, 7)] {
+            assert_eq!(g.board().at(x, y), Some(States::Taken(Players::Black)));
+        }
+        assert_eq!(g.board().at(0, 7), Some(States::Empty));
+
+        // the game goes on from it as usual
+        let mut g = Gamestate::new_from(*g.board(), 0);
+        assert_eq!(g.whose_turn(), States::Taken(Players::Black));
+        assert_eq!(g.get_moves(), Gamestate::new().get_moves());
+        while !g.is_terminal() {
+            let turn = GreedyAgent::new().make_move(&g);
+            assert!(g.make_move_fast(turn));
+        }
+
+        // taken corners are left alone
+        let mut board = *Gamestate::new().board();
+        board.change(0, 0, States::Taken(Players::White));
+        let g = Rules { komi: 0, handicap_corners: 4 }.apply_handicap(&Gamestate::new_from(board, 0));
+        assert_eq!(g.board().at(0, 0), Some(States::Taken(Players::White)));
+        assert_eq!(g.board().count(Players::Black), 5);
+    }
+
+    #[test]
+    fn test_clone_keeps_cache() {
+        let g = Gamestate::new();
+        g.get_moves();
+        let mut copy = g.clone();
+        assert!(copy.cache.borrow().moves.is_some());
+        assert_eq!(copy.cache.borrow().terminal, Some(false));
+
+        copy.make_move_fast(Some((4, 5)));
+        assert!(copy.cache.borrow().moves.is_none());
+        assert!(copy.cache.borrow().terminal.is_none());
+        assert!(g.cache.borrow().moves.is_some());
+    }
+
+    #[test]
+    fn test_forced_pass_side_to_move() {
+        let mut g = white_must_pass();
+
+        let before = GEN_MOVES_CALLS.with(|calls| calls.get());
+        assert!(!g.is_terminal());
+        assert_eq!(g.side_to_move(), Players::White);
+        assert_eq!(g.whose_turn(), States::Taken(Players::White));
+        assert_eq!(g.winner(), None);
+        assert_eq!(GEN_MOVES_CALLS.with(|calls| calls.get()), before);
+
+        assert_eq!(g.get_moves().as_slice(), &[None]);
+        assert!(g.make_move_fast(None));
+        assert_eq!(g.side_to_move(), Players::Black);
+        assert_eq!(g.whose_turn(), States::Taken(Players::Black));
+
+        assert!(g.make_move_fast(Some((6, 7))));
+        assert_eq!(g.whose_turn(), States::Taken(Players::White));
+        assert!(g.make_move_fast(None));
+        assert!(g.make_move_fast(Some((7, 7))));
+        assert!(g.is_terminal());
+        assert_eq!(g.whose_turn(), States::Empty);
+        assert_eq!(g.side_to_move(), Players::White);
+    }
+
+    #[test]
+    fn test_eq_ignores_cache() {
+        let cached = Gamestate::new();
+        cached.get_moves();
+        let mut recorded = Gamestate::new();
+        recorded.enable_history();
+        assert_eq!(cached, Gamestate::new());
+        assert_eq!(recorded, Gamestate::new());
+        assert_ne!(Gamestate::new_from(*cached.board(), 1), cached);
+    }
+
+    #[test]
+    fn test_greedy_gen_moves_calls() {
+        let mut g = Gamestate::new();
+        g.make_moves_fast(&[Some((4, 5)), Some((5, 3)), Some((3, 2))]);
+        g.get_moves();
+
+        let before = GEN_MOVES_CALLS.with(|calls| calls.get());
+        GreedyAgent::new().make_move(&g);
+        // candidates are judged by counting flips, not by playing them out
+        assert_eq!(GEN_MOVES_CALLS.with(|calls| calls.get()), before);
+    }
+
+    #[test]
+    fn test_children() {
+        let g = Gamestate::new();
+        let children: Vec<(Turn, Gamestate)> = g.children().collect();
+        assert_eq!(children.len(), 4);
+        for (turn, child) in children {
+            let mut expected = g.clone();
+            expected.make_move_fast(turn);
+            assert_eq!(child, expected);
+        }
+
+        // black has nothing to flip, but white can play at (2, 0)
+        let mut b = Board::new();
+        b.change(0, 0, States::Taken(Players::White));
+        b.change(1, 0, States::Taken(Players::Black));
+        let pass = Gamestate::new_from(b, 0);
+        let children: Vec<(Turn, Gamestate)> = pass.children().collect();
+        assert_eq!(children, vec![(None, Gamestate::new_from(b, 1))]);
+
+        let mut full = Board::new();
+        for x in 0..8 {
+            for y in 0..8 {
+                full.change(x, y, States::Taken(Players::Black));
+            }
+        }
+        assert_eq!(Gamestate::new_from(full, 60).children().count(), 0);
+    }
+
+    #[test]
+    fn test_undo_move() {
+        let mut r = rand::rng();
+        for _ in 0..100 {
+            let mut g = Gamestate::new();
+            g.enable_history();
+            let mut states = vec![*g.board()];
+            let mut turns = Vec::new();
+            while let Some(t) = g.get_moves().choose(&mut r).copied() {
+                match turns.len() % 3 {
+                    0 => assert!(g.make_move_fast(t)),
+                    1 => assert!(g.make_move(t).is_some()),
+                    _ => assert!(g.make_move_detailed(t).is_some()),
+                }
+                states.push(*g.board());
+                turns.push(t);
+            }
+            assert_eq!(g.view_history(), Some(turns.clone()));
+
+            while let Some(expected) = turns.pop() {
+                states.pop();
+                assert_eq!(g.last_turn(), Some(expected));
+                assert_eq!(g.undo_move(), Some(expected));
+                assert_eq!(g.board(), states.last().unwrap());
+                assert_eq!(g.turn as usize, turns.len());
+                assert_eq!(g.board().zobrist_hash(), g.board().zobrist());
+            }
+            assert_eq!(g.undo_move(), None);
+            assert_eq!(g.last_turn(), None);
+            assert_eq!(g.get_moves(), Gamestate::new().get_moves());
+        }
+    }
+
+    #[test]
+    fn test_serde_round_trip() {
+        let mut r = rand::rng();
+        for with_history in [false, true] {
+            let mut g = Gamestate::new();
+            if with_history {
+                g.enable_history();
+            }
+            loop {
+                let json = serde_json::to_string(&g).unwrap();
+                let copy: Gamestate = serde_json::from_str(&json).unwrap();
+                assert_eq!(copy.board(), g.board());
+                assert_eq!(copy.turn, g.turn);
+                assert_eq!(copy.get_moves(), g.get_moves());
+                assert_eq!(copy.view_history(), g.view_history());
+
+                match g.get_moves().choose(&mut r) {
+                    Some(t) => g.make_move_fast(*t),
+                    None => break,
+                };
+            }
+        }
+
+        let turns: Vec<Turn> = vec![Some((4, 5)), None];
+        let json = serde_json::to_string(&turns).unwrap();
+        assert_eq!(serde_json::from_str::<Vec<Turn>>(&json).unwrap(), turns);
+        assert!(serde_json::from_str::<Board>(&format!("{}", u128::MAX)).is_err());
+    }
+
+    #[test]
+    fn test_history_disabled() {
+        let mut g = Gamestate::new();
+        assert!(g.make_move_fast(Some((4, 5))));
+        assert_eq!(g.view_history(), None);
+        assert_eq!(g.last_turn(), None);
+        assert_eq!(g.undo_move(), None);
+    }
+}
@@ -0,0 +1,10 @@
+#![recursion_limit = "256"]
+
+pub mod mechanics;
+pub mod gameplay;
+pub mod evaluation;
+pub mod agent;
+pub mod mcst;
+pub mod transposition;
+pub mod data;
+pub mod neural;
@@ -1,30 +1,77 @@
+use std::cell::RefCell;
 use std::collections::HashMap;
-use std::cmp::Ordering;
+use std::collections::hash_map::Entry;
+use std::hash::{BuildHasherDefault, DefaultHasher};
+use std::cmp::{Ordering, Reverse};
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering as AtomicOrdering};
+use std::thread;
 use std::time::{Duration, Instant};
 
 use rand::seq::IndexedRandom;
+use serde::{Deserialize, Serialize};
+use smallvec::SmallVec;
 
-use crate::agent::Agent;
-use crate::gameplay::{Gamestate, Players, States, Turn};
+use crate::agent::{Agent, AgentError};
+use crate::evaluation::{Evaluator, WeightedSquareEvaluator};
+use crate::gameplay::{index_to_turn, loc_to_alg, turn_to_index, Gamestate, MoveError, Players, States, Turn};
+use crate::mechanics::Board;
 
 /// A trait for defining how nodes are selected during MCTS traversal.
 pub trait SelectionPolicy {
     /// Select a path through the tree to expand or evaluate.
     fn select(&mut self, tree: &McstTree) -> Option<Vec<Turn>>;
-    /// Inform the selector of the two most recent moves.
-    /// This is here because [crate::agent::implementations::BfsSelectionFast] 
-    /// is stateful and needs to know when stuff got changed.
-    fn turns_passed(&mut self, tree: &McstTree) {}
-    /// Resets to a certain state.
-    /// This is here because [crate::agent::implementations::BfsSelectionFast] 
-    /// is stateful and needs to know when to reset it.
-    fn set_state(&mut self, state: Gamestate) {}
+    /// Inform the selector that the root of `tree` has
+    /// [advanced](McstAgent::advance_by) past `moves`, so that selectors which
+    /// remember paths, such as
+    /// [BfsSelectionFast](crate::agent::implementations::BfsSelectionFast),
+    /// can forget them. Does nothing by default.
+    fn root_advanced(&mut self, _tree: &McstTree, _moves: &[Turn]) {}
+    /// Inform the selector that the tree has been replaced with one for
+    /// `state`, as by [McstAgent::set_state]. Does nothing by default.
+    fn set_state(&mut self, _state: &Gamestate) {}
 }
 
 /// A trait for defining how the tree expands new nodes.
 pub trait ExpansionPolicy {
     /// Choose which move to expand from the given path.
     fn expand(&mut self, tree: &McstTree, path: &Vec<Turn>) -> Turn;
+    /// Choose every move to expand from the given path at once. The cycle
+    /// rolls out from the first of them, leaving the rest unvisited.
+    ///
+    /// Expands just the move [ExpansionPolicy::expand] chooses by default.
+    fn expand_all(&mut self, tree: &McstTree, path: &Vec<Turn>) -> Vec<Turn> {
+        vec![self.expand(tree, path)]
+    }
+    /// Returns the [prior](McstNode::prior) of `turn`, which is about to be
+    /// expanded from the node at `path`. Priors of the moves from a node
+    /// should add up to 1.
+    ///
+    /// Every move is as likely as any other by default.
+    fn prior(&mut self, tree: &McstTree, path: &[Turn], _turn: Turn) -> f32 {
+        uniform_prior(tree.root().search(path).expect("Node from path given invalid path"))
+    }
+    /// Inform the expander that the moves it chose have been inserted as
+    /// children of the node at `path`, so that expanders which remember
+    /// nodes can forget those with nothing left to expand. Does nothing by
+    /// default.
+    fn expanded(&mut self, _tree: &McstTree, _path: &[Turn]) {}
+    /// Inform the expander that the root of `tree` has
+    /// [advanced](McstAgent::advance_by) past `moves`, so that expanders
+    /// which remember nodes, such as
+    /// [PriorExpansion](crate::neural::PriorExpansion), can forget those no
+    /// longer in the tree. Does nothing by default.
+    fn root_advanced(&mut self, _tree: &McstTree, _moves: &[Turn]) {}
+    /// Inform the expander that the tree has been replaced with one for
+    /// `state`, as by [McstAgent::set_state]. Does nothing by default.
+    fn set_state(&mut self, _state: &Gamestate) {}
+}
+
+/// Returns the prior of each move from `parent` when they are all as likely.
+fn uniform_prior(parent: McstNode) -> f32 {
+    1.0 / parent.game().move_count().max(1) as f32
 }
 
 /// A trait for deciding which move to make from the current root state.
@@ -33,128 +80,1335 @@ pub trait DecisionPolicy {
     fn decide(&mut self, tree: &McstTree) -> Turn;
 }
 
-/// A single node in the Monte Carlo Search Tree.
-pub struct McstNode {
-    /// The children of this node by which turn you take to get there.
-    children: HashMap<Turn, McstNode>,
-    /// How many wins rollouts from this node or its descendants have.
-    wins: u32,
+/// A trait for valuing the game at the end of a cycle's path, in the rollout
+/// phase.
+pub trait RolloutPolicy {
+    /// Returns the reward of `game` for `me`, from 0 for a loss to 1 for a
+    /// win (see [Proven::reward]).
+    fn rollout(&self, game: Gamestate, me: Players) -> Result<f64, RolloutError>;
+    /// Like [RolloutPolicy::rollout], but also returns how many plies the
+    /// rollout played, for [observers](McstAgent::set_observer), if the
+    /// policy keeps count. It doesn't by default.
+    fn rollout_plies(&self, game: Gamestate, me: Players) -> Result<(f64, Option<usize>), RolloutError> {
+        self.rollout(game, me).map(|reward| (reward, None))
+    }
+    /// Like [RolloutPolicy::rollout_plies], but rewards a game played out to
+    /// a draw with the given reward rather than 0.5, for
+    /// [contempt](McstAgent::set_contempt). Policies which judge games
+    /// rather than playing them out never see a draw, so by default this is
+    /// the same as [RolloutPolicy::rollout_plies].
+    fn rollout_drawn_at(&self, game: Gamestate, me: Players, _draw: f64) -> Result<(f64, Option<usize>), RolloutError> {
+        self.rollout_plies(game, me)
+    }
+}
+
+/// The children of a [BoxedNode] by which turn you take to get there.
+///
+/// Uses a fixed hasher instead of a randomly seeded one so that the order
+/// children are visited in (and so how policies break ties) is the same on
+/// every run, which keeps searches with seeded rollouts reproducible.
+type BoxedChildren = HashMap<Turn, BoxedNode, BuildHasherDefault<DefaultHasher>>;
+
+/// How a [McstTree] keeps its nodes (see [McstTree::set_storage]).
+///
+/// Searches grow the same trees either way, but visit children in a
+/// different order, and so may break ties between them differently.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NodeStorage {
+    /// Every node owns its children in a map of its own. This is the default
+    /// unless the `arena` feature is enabled.
+    #[cfg_attr(not(feature = "arena"), default)]
+    Boxed,
+    /// Every node is kept in one vector and refers to its children by their
+    /// indices in it, in the order they were added. Nodes are allocated in
+    /// bulk, and those dropped when the root advances are left where they
+    /// are rather than freed one by one. The vector is compacted once they
+    /// outnumber the rest and it would otherwise have to grow.
+    #[cfg_attr(feature = "arena", default)]
+    Arena,
+}
+
+/// The exact value of a [McstNode]'s game, once it has been proven,
+/// from the point of view of the player to move at the root.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Proven {
+    Win,
+    Loss,
+    Draw,
+}
+
+impl Proven {
+    /// Returns what the final `score` of a game means for `me`.
+    fn from_score(score: i8, me: Players) -> Self {
+        match (me, score.cmp(&0)) {
+            (_, Ordering::Equal) => Proven::Draw,
+            (Players::Black, Ordering::Greater) | (Players::White, Ordering::Less) => Proven::Win,
+            _ => Proven::Loss,
+        }
+    }
+
+    /// Returns the [reward](McstAgent::rollout) every rollout from a node
+    /// proven to have this value would give.
+    pub fn reward(self) -> f64 {
+        self.reward_with_contempt(0.0)
+    }
+
+    /// Like [Proven::reward], but values a draw at `contempt` less than half
+    /// a win (see [McstAgent::set_contempt]).
+    fn reward_with_contempt(self, contempt: f64) -> f64 {
+        match self {
+            Proven::Win => 1.0,
+            Proven::Draw => 0.5 - contempt,
+            Proven::Loss => 0.0,
+        }
+    }
+}
+
+/// The statistics and game of a node in the Monte Carlo Search Tree,
+/// wherever its children are kept.
+#[derive(Clone)]
+struct NodeData {
+    /// How many wins rollouts from this node or its descendants have,
+    /// counting draws as half a win.
+    wins: f64,
+    /// The sum of the squares of those rollouts' rewards, for their variance.
+    squares: f64,
     /// How many rollouts from this node or its descendants have been played.
     total: u32,
+    /// The node's exact value, if it is known.
+    proof: Option<Proven>,
+    /// How likely the move to this node was thought to be the best before any
+    /// rollouts (see [ExpansionPolicy::prior]). The root's is 1.
+    prior: f32,
+    /// Whether the node shares its statistics with another node for the same
+    /// position, which is expanded in its place
+    /// (see [McstTree::with_transpositions]).
+    transposed: bool,
+    /// How many nodes there are under this one, itself included, so that
+    /// the size of a tree is known without walking it.
+    size: usize,
     /// Gamestate at this node.
     game: Gamestate,
 }
 
-impl McstNode {
+impl NodeData {
     /// Create a new node with the given game state.
     fn new(game: Gamestate) -> Self {
-        McstNode {
-            children: HashMap::new(),
-            wins: 0,
+        NodeData {
+            wins: 0.0,
+            squares: 0.0,
             total: 0,
+            proof: None,
+            prior: 1.0,
+            transposed: false,
+            size: 1,
             game: game
         }
     }
 
+    /// Update the win count after a rollout with the given
+    /// [reward](McstAgent::rollout).
+    fn update(&mut self, reward: f64) {
+        self.wins += reward;
+        self.squares += reward * reward;
+        self.total += 1;
+    }
+}
+
+/// A node which owns its children (see [NodeStorage::Boxed]).
+#[derive(Clone)]
+struct BoxedNode {
+    data: NodeData,
+    children: BoxedChildren,
+}
+
+impl BoxedNode {
+    fn new(data: NodeData) -> Self {
+        BoxedNode { data, children: BoxedChildren::default() }
+    }
+
+    /// Copies `node`, along with its descendants.
+    fn copy(node: McstNode) -> Self {
+        let children = node.children().iter().map(|(turn, child)| (*turn, BoxedNode::copy(child))).collect();
+        BoxedNode { data: node.data.clone(), children }
+    }
+
+    /// Adds the statistics of `other`, a node for the same game, to this
+    /// one's, and does the same for the children they share. Children only
+    /// `other` has are taken as they are.
+    fn merge(&mut self, other: BoxedNode) {
+        self.data.wins += other.data.wins;
+        self.data.squares += other.data.squares;
+        self.data.total += other.data.total;
+        self.data.proof = self.data.proof.or(other.data.proof);
+        for (turn, child) in other.children {
+            match self.children.entry(turn) {
+                Entry::Occupied(mut entry) => entry.get_mut().merge(child),
+                Entry::Vacant(entry) => {
+                    entry.insert(child);
+                }
+            }
+        }
+        self.data.size = 1 + self.children.values().map(|child| child.data.size).sum::<usize>();
+    }
+
+    /// Recursively search for a mutable reference to a node along a path.
+    fn search_mut(&mut self, path: &[Turn]) -> Option<&mut BoxedNode> {
+        if let Some(child) = &path.first() {
+            if let Some(child) = self.children.get_mut(child) {
+                child.search_mut(&path[1..])
+            } else { None }
+        } else { Some(self) }
+    }
+}
+
+/// A node of an [arena](NodeStorage::Arena), which refers to its children
+/// by their indices in it.
+#[derive(Clone)]
+struct ArenaNode {
+    data: NodeData,
+    children: SmallVec<[(Turn, u32); 4]>,
+}
+
+/// The nodes of a [McstTree], kept as its [NodeStorage] says.
+#[derive(Clone)]
+enum Nodes {
+    Boxed(Box<BoxedNode>),
+    Arena {
+        nodes: Vec<ArenaNode>,
+        root: u32,
+    },
+}
+
+impl Nodes {
+    /// Keeps a copy of `root`, along with its descendants, as `storage` says.
+    fn from_root(root: McstNode, storage: NodeStorage) -> Self {
+        fn push(node: McstNode, nodes: &mut Vec<ArenaNode>) -> u32 {
+            let index = nodes.len();
+            nodes.push(ArenaNode { data: node.data.clone(), children: SmallVec::new() });
+            let children = node.children().iter().map(|(turn, child)| (*turn, push(child, nodes))).collect();
+            nodes[index].children = children;
+            index as u32
+        }
+
+        match storage {
+            NodeStorage::Boxed => Nodes::Boxed(Box::new(BoxedNode::copy(root))),
+            NodeStorage::Arena => {
+                let mut nodes = Vec::new();
+                let root = push(root, &mut nodes);
+                Nodes::Arena { nodes, root }
+            }
+        }
+    }
+
+    /// Keeps `root`, along with its descendants, as `storage` says.
+    fn from_boxed(root: BoxedNode, storage: NodeStorage) -> Self {
+        match storage {
+            NodeStorage::Boxed => Nodes::Boxed(Box::new(root)),
+            storage => Nodes::from_root(McstNode::boxed(&root), storage),
+        }
+    }
+
+    /// Returns the root, along with its descendants, as a [BoxedNode].
+    fn into_boxed(self) -> BoxedNode {
+        match self {
+            Nodes::Boxed(root) => *root,
+            nodes => BoxedNode::copy(nodes.root()),
+        }
+    }
+
+    fn storage(&self) -> NodeStorage {
+        match self {
+            Nodes::Boxed(_) => NodeStorage::Boxed,
+            Nodes::Arena { .. } => NodeStorage::Arena,
+        }
+    }
+
+    fn root(&self) -> McstNode<'_> {
+        match self {
+            Nodes::Boxed(root) => McstNode::boxed(root),
+            Nodes::Arena { nodes, root } => McstNode::arena(nodes, *root),
+        }
+    }
+
+    /// Returns the node at `path`, if there is one.
+    fn get_mut(&mut self, path: &[Turn]) -> Option<&mut NodeData> {
+        match self {
+            Nodes::Boxed(root) => root.search_mut(path).map(|node| &mut node.data),
+            Nodes::Arena { nodes, root } => {
+                let index = arena_search(nodes, *root, path)?;
+                Some(&mut nodes[index as usize].data)
+            }
+        }
+    }
+
+    /// Calls `visit` on every node along `path`, the root first.
+    ///
+    /// # Panics
+    /// If the path is invalid.
+    fn walk_mut(&mut self, path: &[Turn], mut visit: impl FnMut(&mut NodeData)) {
+        match self {
+            Nodes::Boxed(root) => {
+                let mut node: &mut BoxedNode = root;
+                visit(&mut node.data);
+                for turn in path {
+                    node = node.children.get_mut(turn).expect("Node from path given invalid path");
+                    visit(&mut node.data);
+                }
+            }
+            Nodes::Arena { nodes, root } => {
+                let mut index = *root;
+                visit(&mut nodes[index as usize].data);
+                for turn in path {
+                    index = arena_child(nodes, index, *turn).expect("Node from path given invalid path");
+                    visit(&mut nodes[index as usize].data);
+                }
+            }
+        }
+    }
+
+    /// Adds `child` to the node at `path` by `link`, which it must not
+    /// have a child by yet. `live` is how many nodes are in the tree, for
+    /// deciding whether to [compact](Nodes::compact) an arena first.
+    ///
+    /// # Panics
+    /// If the path is invalid.
+    fn insert(&mut self, path: &[Turn], link: Turn, child: NodeData, live: usize) {
+        let full = matches!(
+            self,
+            Nodes::Arena { nodes, .. } if nodes.len() == nodes.capacity() && nodes.len() >= 2 * live
+        );
+        if full {
+            self.compact();
+        }
+        match self {
+            Nodes::Boxed(root) => {
+                let parent = root.search_mut(path).expect("path was not valid");
+                parent.children.insert(link, BoxedNode::new(child));
+            }
+            Nodes::Arena { nodes, root } => {
+                let parent = arena_search(nodes, *root, path).expect("path was not valid");
+                let index = u32::try_from(nodes.len()).expect("too many nodes for an arena");
+                nodes.push(ArenaNode { data: child, children: SmallVec::new() });
+                nodes[parent as usize].children.push((link, index));
+            }
+        }
+    }
+
+    /// Drops the descendants of the node at `path`.
+    ///
+    /// # Panics
+    /// If the path is invalid.
+    fn clear_children(&mut self, path: &[Turn]) {
+        match self {
+            Nodes::Boxed(root) => root.search_mut(path).expect("Node from path given invalid path").children.clear(),
+            Nodes::Arena { nodes, root } => {
+                let index = arena_search(nodes, *root, path).expect("Node from path given invalid path");
+                nodes[index as usize].children.clear();
+            }
+        }
+    }
+
+    /// Replaces the root with its descendant at `path`, which must not be
+    /// empty, and returns whatever has to be freed of the rest of the tree.
+    /// An arena leaves the rest where it is, so nothing has to be.
+    ///
+    /// # Panics
+    /// If the path is invalid.
+    fn reroot(&mut self, path: &[Turn]) -> Option<BoxedNode> {
+        match self {
+            Nodes::Boxed(root) => {
+                let (last, parent) = path.split_last().expect("rerooted to the root");
+                let child = root.search_mut(parent)
+                    .and_then(|parent| parent.children.remove(last))
+                    .expect("Node from path given invalid path");
+                Some(std::mem::replace(&mut **root, child))
+            }
+            Nodes::Arena { nodes, root } => {
+                *root = arena_search(nodes, *root, path).expect("Node from path given invalid path");
+                None
+            }
+        }
+    }
+
+    /// Moves the nodes of an arena which are still in the tree to its
+    /// front, keeping their order, and drops the rest.
+    fn compact(&mut self) {
+        let Nodes::Arena { nodes, root } = self else {
+            return;
+        };
+        // where each node still in the tree goes
+        let mut moved_to: Vec<Option<u32>> = vec![None; nodes.len()];
+        let mut pending = vec![*root];
+        while let Some(index) = pending.pop() {
+            moved_to[index as usize] = Some(0);
+            pending.extend(nodes[index as usize].children.iter().map(|(_, child)| *child));
+        }
+        for (kept, index) in moved_to.iter_mut().flatten().enumerate() {
+            *index = kept as u32;
+        }
+
+        let mut index = 0;
+        nodes.retain_mut(|node| {
+            let keep = moved_to[index].is_some();
+            for (_, child) in &mut node.children {
+                *child = moved_to[*child as usize].unwrap_or(*child);
+            }
+            index += 1;
+            keep
+        });
+        *root = moved_to[*root as usize].unwrap();
+    }
+}
+
+/// Returns the index of the child of the arena node at `index` by `turn`.
+fn arena_child(nodes: &[ArenaNode], index: u32, turn: Turn) -> Option<u32> {
+    nodes[index as usize].children.iter().find(|(link, _)| *link == turn).map(|(_, child)| *child)
+}
+
+/// Returns the index of the arena node at `path` from the one at `index`.
+fn arena_search(nodes: &[ArenaNode], index: u32, path: &[Turn]) -> Option<u32> {
+    path.iter().try_fold(index, |index, turn| arena_child(nodes, index, *turn))
+}
+
+/// A single node in the Monte Carlo Search Tree, borrowed from its
+/// [McstTree], which keeps it however its [storage](NodeStorage) says.
+#[derive(Clone, Copy)]
+pub struct McstNode<'a> {
+    data: &'a NodeData,
+    children: Links<'a>,
+}
+
+/// Where to find the children of a [McstNode].
+#[derive(Clone, Copy)]
+enum Links<'a> {
+    Boxed(&'a BoxedChildren),
+    Arena(&'a [ArenaNode], &'a [(Turn, u32)]),
+}
+
+impl<'a> McstNode<'a> {
+    fn boxed(node: &'a BoxedNode) -> Self {
+        McstNode { data: &node.data, children: Links::Boxed(&node.children) }
+    }
+
+    fn arena(nodes: &'a [ArenaNode], index: u32) -> Self {
+        let node = &nodes[index as usize];
+        McstNode { data: &node.data, children: Links::Arena(nodes, &node.children) }
+    }
+
     /// Immutable [McstNode::game] getter.
-    pub fn game(&self) -> &Gamestate {
-        &self.game
+    pub fn game(self) -> &'a Gamestate {
+        &self.data.game
     }
 
     /// Immutable [McstNode::wins] getter.
     /// TODO: just return the number?
-    pub fn wins(&self) -> &u32 {
-        &self.wins
+    pub fn wins(self) -> &'a f64 {
+        &self.data.wins
+    }
+
+    /// Immutable [McstNode::prior] getter.
+    pub fn prior(self) -> f32 {
+        self.data.prior
+    }
+
+    /// Immutable [McstNode::squares] getter.
+    pub fn squares(self) -> f64 {
+        self.data.squares
+    }
+
+    /// Returns the variance of the rewards of the rollouts through the node,
+    /// or 0 if there have been none.
+    pub fn variance(self) -> f64 {
+        if self.data.total == 0 {
+            return 0.0;
+        }
+        let total = f64::from(self.data.total);
+        let mean = self.data.wins / total;
+        (self.data.squares / total - mean * mean).max(0.0)
     }
 
     /// Immutable [McstNode::total] getter.
     /// TODO: just return the number?
-    pub fn total(&self) -> &u32 {
-        &self.total
+    pub fn total(self) -> &'a u32 {
+        &self.data.total
+    }
+
+    /// Immutable [McstNode::transposed] getter.
+    pub fn transposed(self) -> bool {
+        self.data.transposed
+    }
+
+    /// Immutable [McstNode::proof] getter.
+    pub fn proof(self) -> Option<Proven> {
+        self.data.proof
+    }
+
+    /// Works out the node's exact value for `me` if it can be known yet:
+    /// when its game is over, when any move the player to move would
+    /// choose is proven, or when every move is.
+    fn provable(self, me: Players) -> Option<Proven> {
+        let game = &self.data.game;
+        if self.data.proof.is_some() {
+            return self.data.proof;
+        }
+        if game.is_terminal() {
+            return Some(Proven::from_score(game.score(), me));
+        }
+        let (best, worst) = if game.side_to_move() == me {
+            (Proven::Win, Proven::Loss)
+        } else {
+            (Proven::Loss, Proven::Win)
+        };
+        let children = self.children();
+        let mut proofs = children.values().map(McstNode::proof);
+        if proofs.clone().any(|proof| proof == Some(best)) {
+            Some(best)
+        } else if children.len() == game.move_count() && proofs.all(|proof| proof.is_some()) {
+            Some(match children.values().any(|child| child.proof() == Some(Proven::Draw)) {
+                true => Proven::Draw,
+                false => worst,
+            })
+        } else {
+            None
+        }
     }
 
     /// Count the number of nodes (plus itself) that descend from this one.
-    pub fn node_count(&self) -> usize {
-        1 + self.children.values().map(Self::node_count).sum::<usize>()
+    pub fn node_count(self) -> usize {
+        1 + self.children().values().map(Self::node_count).sum::<usize>()
     }
 
-    pub fn tree_filledness(&self, data: &mut Vec<usize>, root: usize) {
+    pub fn tree_filledness(self, data: &mut Vec<usize>, root: usize) {
         if data.len() <= root {
             data.push(1);
         } else {
             data[root] += 1;
         }
-        for child in self.children.values() {
+        for child in self.children().values() {
             child.tree_filledness(data, root + 1);
         }
     }
 
     /// Immutable [McstNode::children] getter.
-    pub fn children(&self) -> &HashMap<Turn, McstNode> {
-        &self.children
+    pub fn children(self) -> Children<'a> {
+        Children(self.children)
     }
 
-    /// Update the win count after a rollout.
-    fn update(&mut self, win: bool) {
-        if win { self.wins += 1 };
-        self.total += 1;
+    /// Recursively search for a node along a path.
+    pub fn search(self, path: &[Turn]) -> Option<McstNode<'a>> {
+        path.iter().try_fold(self, |node, turn| node.children().get(turn))
     }
+}
 
-    /// Recursively search for a mutable reference to a node along a path.
-    fn search_mut(&mut self, path: &[Turn]) -> Option<&mut McstNode> {
-        if let Some(child) = &path.first() {
-            if let Some(child) = self.children.get_mut(child) {
-                child.search_mut(&path[1..])
-            } else { None }
-        } else { Some(self) }
+/// The children of a [McstNode] by which turn you take to get there.
+///
+/// With [boxed](NodeStorage::Boxed) nodes they are visited in the order of
+/// a map with a fixed hasher rather than a randomly seeded one, so that it
+/// (and so how policies break ties) is the same on every run, which keeps
+/// searches with seeded rollouts reproducible. With an
+/// [arena](NodeStorage::Arena) they are visited in the order they were added.
+#[derive(Clone, Copy)]
+pub struct Children<'a>(Links<'a>);
+
+impl<'a> Children<'a> {
+    /// Returns how many children there are.
+    pub fn len(&self) -> usize {
+        match self.0 {
+            Links::Boxed(children) => children.len(),
+            Links::Arena(_, children) => children.len(),
+        }
+    }
+
+    /// Returns whether there are no children.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the child by `turn`, if there is one.
+    pub fn get(&self, turn: &Turn) -> Option<McstNode<'a>> {
+        match self.0 {
+            Links::Boxed(children) => children.get(turn).map(McstNode::boxed),
+            Links::Arena(nodes, children) => children.iter()
+                .find(|(link, _)| link == turn)
+                .map(|(_, index)| McstNode::arena(nodes, *index)),
+        }
+    }
+
+    /// Returns whether there is a child by `turn`.
+    pub fn contains_key(&self, turn: &Turn) -> bool {
+        self.get(turn).is_some()
+    }
+
+    /// Returns the children along with the turns to them.
+    pub fn iter(&self) -> ChildrenIter<'a> {
+        match self.0 {
+            Links::Boxed(children) => ChildrenIter(LinksIter::Boxed(children.iter())),
+            Links::Arena(nodes, children) => ChildrenIter(LinksIter::Arena(nodes, children.iter())),
+        }
+    }
+
+    /// Returns the turns to the children.
+    pub fn keys(&self) -> impl Iterator<Item = &'a Turn> + Clone + use<'a> {
+        self.iter().map(|(turn, _)| turn)
+    }
+
+    /// Returns the children.
+    pub fn values(&self) -> impl Iterator<Item = McstNode<'a>> + Clone + use<'a> {
+        self.iter().map(|(_, child)| child)
+    }
+}
+
+impl<'a> IntoIterator for Children<'a> {
+    type Item = (&'a Turn, McstNode<'a>);
+    type IntoIter = ChildrenIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An iterator over [Children] along with the turns to them.
+#[derive(Clone)]
+pub struct ChildrenIter<'a>(LinksIter<'a>);
+
+#[derive(Clone)]
+enum LinksIter<'a> {
+    Boxed(std::collections::hash_map::Iter<'a, Turn, BoxedNode>),
+    Arena(&'a [ArenaNode], std::slice::Iter<'a, (Turn, u32)>),
+}
+
+impl<'a> Iterator for ChildrenIter<'a> {
+    type Item = (&'a Turn, McstNode<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.0 {
+            LinksIter::Boxed(children) => children.next().map(|(turn, child)| (turn, McstNode::boxed(child))),
+            LinksIter::Arena(nodes, children) => {
+                children.next().map(|(turn, index)| (turn, McstNode::arena(nodes, *index)))
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.0 {
+            LinksIter::Boxed(children) => children.size_hint(),
+            LinksIter::Arena(_, children) => children.size_hint(),
+        }
+    }
+}
+
+impl ExactSizeIterator for ChildrenIter<'_> {}
+
+/// A position in the game: the [compact](crate::mechanics::Board::to_compact)
+/// board and whose turn it is.
+type Position = (u128, Players);
+
+fn position(game: &Gamestate) -> Position {
+    (game.board().to_compact(), game.side_to_move())
+}
+
+/// Drops the paths among `occurrences` of a position in the transposition
+/// table to nodes the root has left behind, having advanced by `advanced`,
+/// and returns the rest from the root.
+fn in_tree<'a>(occurrences: &'a mut Vec<Vec<Turn>>, advanced: &[Turn]) -> impl Iterator<Item = &'a [Turn]> + use<'a> {
+    occurrences.retain(|path| path.starts_with(advanced));
+    let skipped = advanced.len();
+    occurrences.iter().map(move |path| &path[skipped..])
+}
+
+/// How one move at the root of a search has done (see [McstAgent::root_stats]).
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MoveStats {
+    pub turn: Turn,
+    /// How many rollouts went through the move.
+    pub visits: u32,
+    /// The fraction of those rollouts won by the player to move at the root,
+    /// counting draws as half a win.
+    pub win_rate: f64,
+}
+
+/// What a search thinks of its root: how every move there has done, and the
+/// line of play it expects (see [McstAgent::analysis]).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Analysis {
+    /// The moves at the root, most visited first.
+    pub moves: Vec<MoveStats>,
+    /// The principal variation, starting with the most visited move.
+    pub pv: Vec<Turn>,
+    /// How the search went, if it was [summarized](SearchSummary).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub search: Option<SearchSummary>,
+    /// How long the search was given, if a
+    /// [time manager](crate::agent::implementations::TimeManager) decided.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clock: Option<TimeAllotment>,
+}
+
+/// How long a [time manager](crate::agent::implementations::TimeManager)
+/// gave the search for one move, and how long it took.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeAllotment {
+    /// How much of the game's time was left before the move.
+    pub remaining: Duration,
+    /// How long the search was given.
+    pub allotted: Duration,
+    /// How long the move took.
+    pub used: Duration,
+}
+
+/// How the search for one move went, as summed up from what an
+/// [observer](McstAgent::set_observer) was told about its cycles.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SearchSummary {
+    /// How many cycles were run.
+    pub cycles: u64,
+    /// How many moves from the root the nodes rolled out from were, on average.
+    pub average_depth: f64,
+    /// How many rollouts were run per second.
+    pub rollouts_per_sec: f64,
+}
+
+/// Shows the principal variation on one line, then each move with its win
+/// rate and visits on a line of its own, after a line summing up the search
+/// if there is a summary and one on its time if it was allotted some.
+impl fmt::Display for Analysis {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let alg = |turn: &Turn| turn.map_or(String::from("pass"), loc_to_alg);
+        let pv: Vec<String> = self.pv.iter().map(alg).collect();
+        write!(f, "pv: {}", pv.join(" "))?;
+        if let Some(search) = &self.search {
+            write!(
+                f,
+                "\n{} cycles, {:.0}/s, {:.1} deep on average",
+                search.cycles, search.rollouts_per_sec, search.average_depth,
+            )?;
+        }
+        if let Some(clock) = &self.clock {
+            write!(
+                f,
+                "\n{:.2}s of {:.2}s allotted, {:.2}s left before",
+                clock.used.as_secs_f64(), clock.allotted.as_secs_f64(), clock.remaining.as_secs_f64(),
+            )?;
+        }
+        for stats in &self.moves {
+            write!(f, "\n{:>4} {:5.1}% {:>8}", alg(&stats.turn), stats.win_rate * 100.0, stats.visits)?;
+        }
+        Ok(())
+    }
+}
+
+/// Errors that can occur when reading a tree with [McstTree::load].
+#[derive(Debug)]
+pub enum TreeIoError {
+    Io(io::Error),
+    /// The data does not start the way a saved tree does.
+    BadHeader,
+    /// The root's board is not the compact form of any board.
+    BadBoard(u128),
+    /// A node's move is not a move or pass.
+    BadTurn(u8),
+    /// A node's proof is not one of those written.
+    BadProof(u8),
+    /// The move to the node at the given path is illegal, or is made twice.
+    IllegalMove(Vec<Turn>),
+}
+
+impl fmt::Display for TreeIoError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TreeIoError::Io(e) => write!(f, "could not read tree: {e}"),
+            TreeIoError::BadHeader => write!(f, "not a saved tree"),
+            TreeIoError::BadBoard(compact) => write!(f, "tree's root is not a board: {compact}"),
+            TreeIoError::BadTurn(index) => write!(f, "tree has a move out of range: {index}"),
+            TreeIoError::BadProof(proof) => write!(f, "tree has an unknown proof: {proof}"),
+            TreeIoError::IllegalMove(path) => write!(f, "tree has an illegal or repeated move at {path:?}"),
+        }
+    }
+}
+
+impl std::error::Error for TreeIoError {}
+
+impl From<io::Error> for TreeIoError {
+    fn from(e: io::Error) -> Self {
+        TreeIoError::Io(e)
+    }
+}
+
+/// The Monte Carlo Search Tree.
+#[derive(Clone)]
+pub struct McstTree {
+    nodes: Nodes,
+    /// If transpositions are shared, the paths to every node for each
+    /// position, starting with the one which is expanded. The paths start
+    /// from where the root was before it advanced by `advanced`.
+    transpositions: Option<HashMap<Position, Vec<Vec<Turn>>>>,
+    /// The moves the root has advanced by since the transposition table's
+    /// paths last started from it. Paths to nodes left behind are dropped
+    /// from the table as it is next used for their positions, rather than
+    /// all at once every time the root advances.
+    advanced: Vec<Turn>,
+    /// At most how many of the paths in the transposition table are to
+    /// nodes left behind by the root advancing.
+    left_behind: usize,
+    /// How many nodes the tree may grow to (see [McstAgent::set_node_limit]).
+    node_limit: Option<usize>,
+    /// The first moves of the paths with virtual losses on them, whose
+    /// subtrees must not be pruned until the losses are replaced.
+    in_flight: Vec<Turn>,
+}
+
+impl McstTree {
+    /// Create a new MCTS tree from a game state, which keeps its nodes the
+    /// [default](NodeStorage::default) way.
+    pub fn new(game: Gamestate) -> Self {
+        McstTree {
+            nodes: Nodes::from_boxed(BoxedNode::new(NodeData::new(game)), NodeStorage::default()),
+            transpositions: None,
+            advanced: Vec::new(),
+            left_behind: 0,
+            node_limit: None,
+            in_flight: Vec::new(),
+        }
+    }
+
+    /// Like [McstTree::new], but nodes for the same position reached by
+    /// different orders of moves share their statistics and proofs, so a
+    /// rollout through any of them counts for all of them.
+    ///
+    /// Only the first node made for a position is ever expanded. The others
+    /// are rolled out from as though they were leaves, until the root
+    /// advances past the expanded one and a cycle reaches one of them to
+    /// expand in its place. So the tree grows fewer nodes in the same number
+    /// of cycles. This makes the search one of a graph rather than a tree: a
+    /// node's visits are no longer the sum of its children's.
+    pub fn with_transpositions(game: Gamestate) -> Self {
+        let mut tree = McstTree::new(game);
+        tree.transpositions = Some(HashMap::new());
+        tree.rebuild_transpositions();
+        tree
+    }
+
+    /// Returns whether transpositions are shared
+    /// (see [McstTree::with_transpositions]).
+    pub fn shares_transpositions(&self) -> bool {
+        self.transpositions.is_some()
+    }
+
+    /// Returns how the tree keeps its nodes.
+    pub fn storage(&self) -> NodeStorage {
+        self.nodes.storage()
+    }
+
+    /// Keeps the tree's nodes as `storage` says from now on, moving them
+    /// if they are kept another way.
+    pub fn set_storage(&mut self, storage: NodeStorage) {
+        if storage != self.storage() {
+            self.nodes = Nodes::from_root(self.root(), storage);
+        }
+    }
+
+    /// Immutable [McstTree::root] getter.
+    pub fn root(&self) -> McstNode<'_> {
+        self.nodes.root()
+    }
+
+    /// What a saved tree starts with, the last byte being the format's version.
+    const HEADER: [u8; 5] = *b"MCST\x03";
+
+    /// Writes the tree in a compact binary form that [McstTree::load] reads.
+    ///
+    /// After a header, the root's game is written as its
+    /// [compact](Board::to_compact) board and [ply](Gamestate::ply), then
+    /// whether transpositions are shared. Nodes follow in depth-first order,
+    /// each as the [index](turn_to_index) of the move to it (except for the
+    /// root), its wins, sum of squared rewards, total, [prior](McstNode::prior),
+    /// [proof](McstNode::proof) and number of
+    /// children. Games are not written, since they follow from the moves.
+    /// Numbers are little-endian.
+    pub fn save(&self, mut w: impl Write) -> io::Result<()> {
+        fn write_node(w: &mut impl Write, node: McstNode) -> io::Result<()> {
+            let proof = match node.proof() {
+                None => 0,
+                Some(Proven::Win) => 1,
+                Some(Proven::Loss) => 2,
+                Some(Proven::Draw) => 3,
+            };
+            w.write_all(&node.wins().to_le_bytes())?;
+            w.write_all(&node.squares().to_le_bytes())?;
+            w.write_all(&node.total().to_le_bytes())?;
+            w.write_all(&node.prior().to_le_bytes())?;
+            // a game has fewer than 64 moves, so the count fits in a byte
+            w.write_all(&[proof, node.children().len() as u8])?;
+            for (turn, child) in node.children() {
+                w.write_all(&[turn_to_index(*turn)])?;
+                write_node(w, child)?;
+            }
+            Ok(())
+        }
+
+        let game = self.root().game();
+        w.write_all(&Self::HEADER)?;
+        w.write_all(&game.board().to_compact().to_le_bytes())?;
+        w.write_all(&[game.ply(), u8::from(self.shares_transpositions())])?;
+        write_node(&mut w, self.root())?;
+        w.flush()
+    }
+
+    /// Reads a tree written by [McstTree::save], replaying the moves to
+    /// each node to find its game. The tree keeps its nodes the
+    /// [default](NodeStorage::default) way.
+    pub fn load(mut r: impl Read) -> Result<McstTree, TreeIoError> {
+        fn read<const N: usize>(r: &mut impl Read) -> io::Result<[u8; N]> {
+            let mut bytes = [0; N];
+            r.read_exact(&mut bytes)?;
+            Ok(bytes)
+        }
+
+        fn read_node(r: &mut impl Read, node: &mut BoxedNode, path: &mut Vec<Turn>) -> Result<(), TreeIoError> {
+            node.data.wins = f64::from_le_bytes(read(r)?);
+            node.data.squares = f64::from_le_bytes(read(r)?);
+            node.data.total = u32::from_le_bytes(read(r)?);
+            node.data.prior = f32::from_le_bytes(read(r)?);
+            let [proof, children] = read(r)?;
+            node.data.proof = match proof {
+                0 => None,
+                1 => Some(Proven::Win),
+                2 => Some(Proven::Loss),
+                3 => Some(Proven::Draw),
+                _ => return Err(TreeIoError::BadProof(proof)),
+            };
+            for _ in 0..children {
+                let [index] = read(r)?;
+                let turn = index_to_turn(index).ok_or(TreeIoError::BadTurn(index))?;
+                path.push(turn);
+                let mut game = node.data.game.clone();
+                if node.children.contains_key(&turn) || !game.make_move_fast(turn) {
+                    return Err(TreeIoError::IllegalMove(path.clone()));
+                }
+                let mut child = BoxedNode::new(NodeData::new(game));
+                read_node(r, &mut child, path)?;
+                node.data.size += child.data.size;
+                node.children.insert(turn, child);
+                path.pop();
+            }
+            Ok(())
+        }
+
+        if read::<5>(&mut r)? != Self::HEADER {
+            return Err(TreeIoError::BadHeader);
+        }
+        let compact = u128::from_le_bytes(read(&mut r)?);
+        let board = Board::from_compact(compact).ok_or(TreeIoError::BadBoard(compact))?;
+        let [ply, transpositions] = read(&mut r)?;
+        let game = Gamestate::new_from(board, ply);
+        let mut tree = match transpositions {
+            0 => McstTree::new(game.clone()),
+            1 => McstTree::with_transpositions(game.clone()),
+            _ => return Err(TreeIoError::BadHeader),
+        };
+        let mut root = BoxedNode::new(NodeData::new(game));
+        read_node(&mut r, &mut root, &mut Vec::new())?;
+        tree.nodes = Nodes::from_boxed(root, tree.storage());
+        tree.rebuild_transpositions();
+        Ok(tree)
+    }
+
+    /// Returns how many nodes are in the tree. Unlike
+    /// [McstNode::node_count], this doesn't walk the tree.
+    pub fn size(&self) -> usize {
+        self.root().data.size
+    }
+
+    /// How many nodes a tree has to have for [McstTree::reroot] to free what
+    /// it drops on another thread.
+    const BACKGROUND_FREE_SIZE: usize = 10_000;
+
+    /// Replaces the root with its descendant at `path`,
+    /// dropping the rest of the tree.
+    ///
+    /// Freeing a big tree of [boxed](NodeStorage::Boxed) nodes one by one
+    /// takes most of the time rerooting it does, so if there are at least
+    /// [McstTree::BACKGROUND_FREE_SIZE] nodes the rest of the tree is freed
+    /// on a thread of its own rather than holding up the search. If no
+    /// thread can be started, it is freed here. Otherwise nothing but the
+    /// path is walked, so rerooting an [arena](NodeStorage::Arena) doesn't
+    /// depend on how big the tree is.
+    ///
+    /// # Panics
+    /// If the path is invalid.
+    fn reroot(&mut self, path: &[Turn]) {
+        let size = self.size();
+        let dropped = self.nodes.reroot(path);
+        if let Some(dropped) = dropped.filter(|_| size >= Self::BACKGROUND_FREE_SIZE) {
+            // on failure the closure, and the tree with it, is dropped here
+            let _ = thread::Builder::new().spawn(move || drop(dropped));
+        }
+        if self.transpositions.is_some() {
+            self.advanced.extend_from_slice(path);
+            self.left_behind += size - self.size();
+            // once they might outnumber the rest, which keeps this from
+            // costing more than rerooting did for each node left behind
+            if self.left_behind > self.size() {
+                self.catch_up_transpositions();
+            }
+        }
+    }
+
+    /// Drops the paths to nodes left behind by the root advancing from the
+    /// transposition table, and makes the rest start from the root.
+    fn catch_up_transpositions(&mut self) {
+        if let Some(table) = &mut self.transpositions {
+            table.retain(|_, occurrences| {
+                let kept: Vec<Vec<Turn>> = in_tree(occurrences, &self.advanced).map(<[Turn]>::to_vec).collect();
+                *occurrences = kept;
+                !occurrences.is_empty()
+            });
+        }
+        self.advanced.clear();
+        self.left_behind = 0;
+    }
+
+    /// Makes room for `room` new nodes if the tree would go over its
+    /// [limit](McstAgent::set_node_limit), by pruning the subtrees of the
+    /// least visited moves at the root until it is down to three quarters of
+    /// the limit. The moves themselves are kept, along with their statistics,
+    /// and are expanded again if selected.
+    ///
+    /// The subtree of `keep`, and of any move with a rollout still running,
+    /// is never pruned. Returns whether there is room for the new nodes.
+    fn make_room(&mut self, keep: Turn, room: usize) -> bool {
+        let Some(limit) = self.node_limit else {
+            return true;
+        };
+        if self.size() + room <= limit {
+            return true;
+        }
+
+        let mut prunable: Vec<(Turn, u32)> = self.root().children().iter()
+            .filter(|(turn, child)| {
+                **turn != keep && !self.in_flight.contains(turn) && !child.children().is_empty()
+            })
+            .map(|(turn, child)| (*turn, *child.total()))
+            .collect();
+        prunable.sort_by_key(|(_, total)| *total);
+        let target = limit - limit / 4;
+        let mut pruned = false;
+        for (turn, _) in prunable {
+            if self.size() <= target {
+                break;
+            }
+            let pruned_nodes = self.root().children().get(&turn).unwrap().data.size - 1;
+            self.nodes.walk_mut(&[turn], |node| node.size -= pruned_nodes);
+            self.nodes.clear_children(&[turn]);
+            pruned = true;
+        }
+        if pruned {
+            self.rebuild_transpositions();
+        }
+        self.size() + room <= limit
+    }
+
+    /// Combines the statistics of `other`, a tree grown from the same game,
+    /// with this one's, as though every rollout either of them ran had been
+    /// run on this one. Since a node's game is determined by its path from the
+    /// root, nodes at the same path in both trees are for the same game.
+    ///
+    /// # Panics
+    /// If the trees' roots are not the same game.
+    pub fn merge(&mut self, other: McstTree) {
+        assert_eq!(self.root().game(), other.root().game(), "merged trees of different games");
+        let other = other.nodes.into_boxed();
+        match &mut self.nodes {
+            Nodes::Boxed(root) => root.merge(other),
+            nodes => {
+                let mut root = BoxedNode::copy(nodes.root());
+                root.merge(other);
+                *nodes = Nodes::from_boxed(root, nodes.storage());
+            }
+        }
+        self.rebuild_transpositions();
+    }
+
+    /// Applies `change` to every node along `path`, the root included, and,
+    /// if transpositions are shared, to every other node for the same
+    /// positions. `change` is also given the player to move at the node's
+    /// parent along the path, which the root has none of.
+    ///
+    /// The path is walked down once, rather than searched for again from
+    /// the root for every node on it.
+    ///
+    /// # Panics
+    /// If the path is invalid.
+    fn update_along(&mut self, path: &[Turn], change: impl Fn(&mut NodeData, Option<Players>)) {
+        let mut positions = Vec::new();
+        let mut mover = None;
+        let shares_transpositions = self.transpositions.is_some();
+        self.nodes.walk_mut(path, |node| {
+            change(node, mover);
+            if shares_transpositions {
+                positions.push((position(&node.game), mover));
+            }
+            mover = Some(node.game.side_to_move());
+        });
+
+        if let Some(table) = &mut self.transpositions {
+            for (index, (position, mover)) in positions.into_iter().enumerate() {
+                let occurrences = table.get_mut(&position).expect("transposition table out of date");
+                for other in in_tree(occurrences, &self.advanced) {
+                    if other != &path[..index] {
+                        change(self.nodes.get_mut(other).expect("transposition table out of date"), mover);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Finds every node for each position again after the tree has been
+    /// pruned, loaded or merged. The node for a position which has children, or
+    /// else the first found, is the one expanded from now on, and the others
+    /// take on its statistics and proof. An unexpanded position loses its
+    /// proof, which may have been found through a node no longer in the tree.
+    fn rebuild_transpositions(&mut self) {
+        fn collect(node: McstNode, path: &mut Vec<Turn>, table: &mut HashMap<Position, Vec<Vec<Turn>>>) {
+            table.entry(position(node.game())).or_default().push(path.clone());
+            for (turn, child) in node.children() {
+                path.push(*turn);
+                collect(child, path, table);
+                path.pop();
+            }
+        }
+
+        self.advanced.clear();
+        self.left_behind = 0;
+        let Some(mut table) = self.transpositions.take() else {
+            return;
+        };
+        table.clear();
+        collect(self.root(), &mut Vec::new(), &mut table);
+        for occurrences in table.values_mut() {
+            let expanded = occurrences.iter()
+                .position(|path| !self.root().search(path).unwrap().children().is_empty())
+                .unwrap_or(0);
+            occurrences.swap(0, expanded);
+            let primary = self.root().search(&occurrences[0]).unwrap();
+            let (wins, squares, total, mut proof) = (*primary.wins(), primary.squares(), *primary.total(), primary.proof());
+            // a proof found through another node's children can't be
+            // followed from a node without any
+            if primary.children().is_empty() && !primary.game().is_terminal() {
+                proof = None;
+            }
+            for (index, path) in occurrences.iter().enumerate() {
+                let node = self.nodes.get_mut(path).unwrap();
+                (node.wins, node.squares, node.total, node.proof) = (wins, squares, total, proof);
+                node.transposed = index != 0;
+            }
+        }
+        let proven: Vec<Vec<Turn>> = table.values().flatten()
+            .filter(|path| !path.is_empty() && self.root().search(path).unwrap().proof().is_some())
+            .map(|path| path[..path.len() - 1].to_vec())
+            .collect();
+        self.transpositions = Some(table);
+        // nodes may have just been given proofs which prove their parents
+        for parent in proven {
+            self.prove(&parent);
+        }
+    }
+
+    /// Makes the [transposed](McstNode::transposed) node at `path` the one
+    /// expanded for its position if the one which was has been left behind
+    /// by the root advancing. Like every node for a position which hasn't
+    /// been expanded, it loses its proof (see
+    /// [McstTree::rebuild_transpositions]), and so do the others.
+    ///
+    /// # Panics
+    /// If the path is invalid.
+    fn untranspose(&mut self, path: &[Turn]) {
+        let Some(table) = &mut self.transpositions else {
+            return;
+        };
+        let game = self.nodes.root().search(path).expect("Node from path given invalid path").game();
+        let terminal = game.is_terminal();
+        let occurrences = table.get_mut(&position(game)).expect("transposition table out of date");
+        let others: Vec<Vec<Turn>> = in_tree(occurrences, &self.advanced).map(<[Turn]>::to_vec).collect();
+        if others.iter().any(|other| !self.nodes.root().search(other).unwrap().transposed()) {
+            return;
+        }
+        for other in others {
+            let node = self.nodes.get_mut(&other).unwrap();
+            node.transposed = other != path;
+            if !terminal {
+                node.proof = None;
+            }
+        }
+    }
+
+    /// Returns the player to move at the root, whom rewards are counted for.
+    ///
+    /// # Panics
+    /// If the game at the root is over.
+    fn root_player(&self) -> Players {
+        match self.root().game().whose_turn() {
+            States::Taken(c) => c,
+            States::Empty => panic!("initial game is over?"),
+        }
+    }
+
+    /// Proves whatever can now be proven along `path`, from its end
+    /// upwards (see [McstNode::provable]). If transpositions are shared, a
+    /// proof is given to every node for the same position, and whatever can
+    /// then be proven above those is too.
+    fn prove(&mut self, path: &[Turn]) {
+        let me = self.root_player();
+        let mut pending = vec![path.to_vec()];
+        while let Some(mut path) = pending.pop() {
+            loop {
+                let node = self.root().search(&path).expect("Node from path given invalid path");
+                let Some(proof) = node.provable(me) else {
+                    break;
+                };
+                let game = node.game().clone();
+                self.nodes.get_mut(&path).unwrap().proof = Some(proof);
+                if let Some(table) = &mut self.transpositions {
+                    let occurrences = table.get_mut(&position(&game)).expect("transposition table out of date");
+                    for other in in_tree(occurrences, &self.advanced) {
+                        let node = self.nodes.get_mut(other).expect("transposition table out of date");
+                        if node.proof.is_none() {
+                            node.proof = Some(proof);
+                            if let Some((_, parent)) = other.split_last() {
+                                pending.push(parent.to_vec());
+                            }
+                        }
+                    }
+                }
+                if path.pop().is_none() {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Returns `decision` unless what has been proven says otherwise:
+    /// a move proven to win for the player to move at the root is always
+    /// chosen, and one proven to lose only if every move is.
+    pub fn apply_proofs(&self, decision: Turn) -> Turn {
+        let children = self.root().children();
+        if let Some((turn, _)) = children.iter().find(|(_, child)| child.proof() == Some(Proven::Win)) {
+            return *turn;
+        }
+        match children.get(&decision) {
+            Some(child) if child.proof() == Some(Proven::Loss) => children.iter()
+                .filter(|(_, child)| child.proof() != Some(Proven::Loss))
+                .max_by_key(|(_, child)| *child.total())
+                .map_or(decision, |(turn, _)| *turn),
+            _ => decision,
+        }
     }
 
-    /// Recursively search for an immutable reference to a node along a path.
-    pub fn search(&self, path: &[Turn]) -> Option<&McstNode> {
-        if let Some(child) = &path.first() {
-            if let Some(child) = self.children.get(child) {
-                child.search(&path[1..])
-            } else { None }
-        } else { Some(&self) }
+    /// Updates every node along `path`, the root included,
+    /// with the [reward](McstAgent::rollout) of a rollout from its end.
+    fn backpropagate(&mut self, path: &[Turn], reward: f64) {
+        self.update_along(path, |node, _| node.update(reward));
     }
-}
 
-/// The Monte Carlo Search Tree.
-pub struct McstTree {
-    root: McstNode,
-}
+    /// The reward counted as a virtual loss for a node whose parent has
+    /// `mover` to move: a loss for whoever chose it. The root, which nobody
+    /// chose, counts 0 like any other node chosen by `me`.
+    fn virtual_loss(me: Players, mover: Option<Players>) -> f64 {
+        if mover.is_some_and(|mover| mover != me) { 1.0 } else { 0.0 }
+    }
 
-impl McstTree {
-    /// Create a new MCTS tree from a game state.
-    pub fn new(game: Gamestate) -> Self {
-        McstTree {
-            root: McstNode::new(game),
+    /// Counts a virtual loss at every node along `path`, as if a rollout from
+    /// its end had been lost by whoever chose each move on the way.
+    fn add_virtual_loss(&mut self, path: &[Turn]) {
+        self.in_flight.extend(path.first());
+        let me = self.root_player();
+        self.update_along(path, |node, mover| node.update(Self::virtual_loss(me, mover)));
+    }
+
+    /// Replaces the virtual losses [added](McstTree::add_virtual_loss) along
+    /// `path` with `reward`, or removes them if the rollout failed.
+    fn replace_virtual_loss(&mut self, path: &[Turn], reward: Option<f64>) {
+        if let Some(first) = path.first() {
+            let index = self.in_flight.iter().position(|turn| turn == first).unwrap();
+            self.in_flight.swap_remove(index);
         }
+        let me = self.root_player();
+        self.update_along(path, |node, mover| {
+            let loss = Self::virtual_loss(me, mover);
+            match reward {
+                Some(reward) => {
+                    node.wins += reward - loss;
+                    node.squares += reward * reward - loss * loss;
+                }
+                None => {
+                    node.wins -= loss;
+                    node.squares -= loss * loss;
+                    node.total -= 1;
+                }
+            }
+        });
     }
 
-    /// Immutable [McstTree::root] getter.
-    pub fn root(&self) -> &McstNode {
-        &self.root
+    /// Sets the statistics of the node at `path`, for building trees to test
+    /// policies on.
+    #[cfg(test)]
+    pub(crate) fn set_stats(&mut self, path: &[Turn], wins: f64, squares: f64, total: u32) {
+        let node = self.nodes.get_mut(path).expect("Node from path given invalid path");
+        (node.wins, node.squares, node.total) = (wins, squares, total);
+    }
+
+    /// Returns how many nodes an arena holds, including those no longer in
+    /// the tree, for testing when it compacts.
+    #[cfg(test)]
+    pub(crate) fn arena_len(&self) -> Option<usize> {
+        match &self.nodes {
+            Nodes::Boxed(_) => None,
+            Nodes::Arena { nodes, .. } => Some(nodes.len()),
+        }
     }
 
     /// Add a child node by performing a move from a given path.
+    /// Its prior is that of every move from the node being as likely.
     ///
     /// # Panics
     /// If the path is invalid or the child already exists.
     pub fn add_child(&mut self, path: &[Turn], link: Turn) {
-        if let Some(old) = self.root.search_mut(path) {
-            if old.children.contains_key(&link) {
-                panic!("already contained child");
-            } else {
-                let mut new_game = old.game.clone();
-                if !new_game.make_move_fast(link) {
-                    panic!("child didn't make real move");
-                }
-                let new_child = McstNode::new(new_game);
-                old.children.insert(link, new_child);
-            }
-        } else {
+        self.insert_child(path, link, None);
+    }
+
+    /// Like [McstTree::add_child], but gives the child `prior`, if there is
+    /// one, instead of the uniform prior.
+    fn insert_child(&mut self, path: &[Turn], link: Turn, prior: Option<f32>) {
+        let Some(old) = self.root().search(path) else {
             panic!("path was not valid");
+        };
+        if old.children().contains_key(&link) {
+            panic!("already contained child");
+        }
+        let mut new_game = old.game().clone();
+        if !new_game.make_move_fast(link) {
+            panic!("child didn't make real move");
+        }
+        let mut new_child = NodeData::new(new_game);
+        new_child.prior = prior.unwrap_or_else(|| uniform_prior(old));
+        self.nodes.insert(path, link, new_child, self.size());
+        self.nodes.walk_mut(path, |node| node.size += 1);
+
+        if let Some(table) = &mut self.transpositions {
+            let path = [path, &[link]].concat();
+            let child = self.nodes.root().search(&path).unwrap();
+            let occurrences = table.entry(position(child.game())).or_default();
+            // the nodes for a position all share their statistics, so any
+            // still in the tree will do
+            if let Some(other) = in_tree(occurrences, &self.advanced).next() {
+                let other = self.nodes.root().search(other).unwrap();
+                let (wins, squares, total, proof) = (*other.wins(), other.squares(), *other.total(), other.proof());
+                let child = self.nodes.get_mut(&path).unwrap();
+                (child.wins, child.squares, child.total, child.proof, child.transposed) =
+                    (wins, squares, total, proof, true);
+            }
+            occurrences.push([self.advanced.as_slice(), &path].concat());
         }
     }
 }
@@ -167,6 +1421,49 @@ pub enum CycleError {
     Rollout(RolloutError),
 }
 
+impl fmt::Display for CycleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CycleError::Selection(e) => write!(f, "selection failed: {e}"),
+            CycleError::Expansion(e) => write!(f, "expansion failed: {e}"),
+            CycleError::Rollout(e) => write!(f, "rollout failed: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for CycleError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CycleError::Selection(e) => Some(e),
+            CycleError::Expansion(e) => Some(e),
+            CycleError::Rollout(e) => Some(e),
+        }
+    }
+}
+
+/// How many moves [describe_turns] shows before leaving the rest out.
+const DESCRIBED_TURNS: usize = 10;
+
+/// Names `turn` for an error message: in algebraic notation, as `pass`,
+/// or by its coordinates if it is off the board.
+fn describe_turn(turn: Turn) -> String {
+    match turn {
+        Some((x, y)) if x < 8 && y < 8 => loc_to_alg((x, y)),
+        Some((x, y)) => format!("({x}, {y})"),
+        Option::None => String::from("pass"),
+    }
+}
+
+/// Lists `turns` for an error message, leaving out all but the first
+/// [DESCRIBED_TURNS] of a long list.
+fn describe_turns(turns: &[Turn]) -> String {
+    let shown: Vec<String> = turns.iter().take(DESCRIBED_TURNS).map(|&turn| describe_turn(turn)).collect();
+    match turns.len().checked_sub(DESCRIBED_TURNS) {
+        Some(hidden) if hidden > 0 => format!("{} and {hidden} more", shown.join(" ")),
+        _ => shown.join(" "),
+    }
+}
+
 /// Errors that can occur during the selection phase.
 #[derive(Debug)]
 pub enum SelectionError {
@@ -174,13 +1471,48 @@ pub enum SelectionError {
     NotANode(Vec<Turn>),
 }
 
+impl fmt::Display for SelectionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SelectionError::NotANode(path) => {
+                write!(f, "the selected path {} does not lead to a node", describe_turns(path))
+            }
+        }
+    }
+}
+
+impl std::error::Error for SelectionError {}
+
 /// Errors that can occur during the expansion phase.
 #[derive(Debug)]
 pub enum ExpansionError {
-    /// The selected move is illegal in the given game state.
-    IllegalMove(Turn),
-    /// A child node for the move already exists.
+    /// The selected move is illegal in the given game state, for the given reason.
+    IllegalMove(Turn, MoveError),
+    /// A child node for the move already exists, or the move was chosen twice.
     AlreadyExpanded(Turn),
+    /// No moves were chosen to expand.
+    NoMoves,
+}
+
+impl fmt::Display for ExpansionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExpansionError::IllegalMove(turn, e) => write!(f, "cannot expand {}: {e}", describe_turn(*turn)),
+            ExpansionError::AlreadyExpanded(turn) => {
+                write!(f, "{} is already expanded or was chosen twice", describe_turn(*turn))
+            }
+            ExpansionError::NoMoves => write!(f, "no moves were chosen to expand"),
+        }
+    }
+}
+
+impl std::error::Error for ExpansionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ExpansionError::IllegalMove(_, e) => Some(e),
+            _ => None,
+        }
+    }
 }
 
 /// Errors that can occur during the rollout (simulation) phase.
@@ -188,52 +1520,284 @@ pub enum ExpansionError {
 pub enum RolloutError {
     /// A move attempted during simulation was invalid.
     IllegalMove(Vec<Turn>),
+    /// A rollout agent could not pick a move.
+    Agent(AgentError),
+}
+
+impl fmt::Display for RolloutError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RolloutError::IllegalMove(moves) => match moves.split_last() {
+                Some((last, [])) => write!(f, "the first move played, {}, is illegal", describe_turn(*last)),
+                Some((last, before)) => {
+                    write!(f, "{} is illegal after {}", describe_turn(*last), describe_turns(before))
+                }
+                Option::None => write!(f, "an illegal move was played"),
+            },
+            RolloutError::Agent(e) => write!(f, "a rollout agent could not move: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for RolloutError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RolloutError::Agent(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// The usual [RolloutPolicy], which [plays the game out](playout) with one
+/// agent playing the root player's moves and another their opponent's. They
+/// need not be the same type of agent, so that the root player can be
+/// modeled as playing greedily, say, and their opponent randomly.
+///
+/// Playouts can be capped at some number of plies, after which the game is
+/// judged by an [Evaluator] as won by whoever it favors, or drawn if it
+/// favors neither. This bounds how long a rollout from early in the game can
+/// take, at the cost of how well it judges the game.
+pub struct AgentRollout<A: Agent, O: Agent = A, V: Evaluator = WeightedSquareEvaluator> {
+    rollout: A,
+    opponent: O,
+    /// Whether both agents are [uniformly random](Agent::is_uniform_random),
+    /// so that rollouts can be left to [Gamestate::random_playout].
+    random: bool,
+    /// How many plies to play before judging the game, and what by.
+    cap: Option<(usize, V)>,
+    /// The moves of the rollout under way, kept between rollouts so that
+    /// the buffer is reused.
+    history: RefCell<Vec<Turn>>,
+}
+
+impl<A: Agent, O: Agent> AgentRollout<A, O> {
+    /// Rolls out with `rollout` playing the root player's moves and
+    /// `opponent` the rest.
+    pub fn new(rollout: A, opponent: O) -> Self {
+        let random = rollout.is_uniform_random() && opponent.is_uniform_random();
+        AgentRollout { rollout, opponent, random, cap: None, history: RefCell::default() }
+    }
+
+    /// Like [AgentRollout::new], but judges games still going after
+    /// `max_plies` by their disc and mobility differences (see
+    /// [WeightedSquareEvaluator::discs_and_mobility]).
+    pub fn capped(rollout: A, opponent: O, max_plies: usize) -> Self {
+        AgentRollout::with_cap(rollout, opponent, max_plies, WeightedSquareEvaluator::discs_and_mobility())
+    }
+}
+
+impl<A: Agent, O: Agent, V: Evaluator> AgentRollout<A, O, V> {
+    /// Like [AgentRollout::new], but judges games still going after
+    /// `max_plies` with `evaluator`.
+    pub fn with_cap(rollout: A, opponent: O, max_plies: usize, evaluator: V) -> Self {
+        let random = rollout.is_uniform_random() && opponent.is_uniform_random();
+        AgentRollout { rollout, opponent, random, cap: Some((max_plies, evaluator)), history: RefCell::default() }
+    }
 }
 
+impl<A: Agent, O: Agent, V: Evaluator> RolloutPolicy for AgentRollout<A, O, V> {
+    fn rollout(&self, game: Gamestate, me: Players) -> Result<f64, RolloutError> {
+        self.rollout_plies(game, me).map(|(reward, _)| reward)
+    }
+
+    /// Counts the plies, passes included.
+    fn rollout_plies(&self, game: Gamestate, me: Players) -> Result<(f64, Option<usize>), RolloutError> {
+        self.rollout_drawn_at(game, me, 0.5)
+    }
+
+    /// Only games played to the end count as drawn, not those
+    /// [capped](AgentRollout::capped) and judged level.
+    fn rollout_drawn_at(&self, game: Gamestate, me: Players, draw: f64) -> Result<(f64, Option<usize>), RolloutError> {
+        let cap = self.cap.as_ref().map(|(max_plies, evaluator)| (*max_plies, evaluator));
+        playout(game, me, (&self.rollout, &self.opponent, self.random), cap, draw, &mut self.history.borrow_mut())
+    }
+}
+
+/// What happened in one [cycle](McstAgent::cycle), as told to an
+/// [observer](McstAgent::set_observer).
+#[derive(Clone, Debug, PartialEq)]
+pub struct CycleInfo {
+    /// How many cycles were observed before this one.
+    pub cycle: u64,
+    /// How many moves from the root the node rolled out from is.
+    pub depth: usize,
+    /// Whether the node rolled out from was newly expanded.
+    pub expanded: bool,
+    /// Whether the value of the node rolled out from was proven, so that it
+    /// was taken as the reward instead of rolling out.
+    pub proven: bool,
+    /// How many plies the rollout played, if its policy
+    /// [kept count](RolloutPolicy::rollout_plies). None are played from a
+    /// node whose value is proven.
+    pub rollout_plies: Option<usize>,
+    /// The reward of the rollout for the root player.
+    pub reward: f64,
+    /// How long the cycle took, from selection to backpropagation. In a
+    /// [parallel search](McstAgent::cycle_parallel) this includes waiting
+    /// for the tree.
+    pub elapsed: Duration,
+}
+
+/// Watches the cycles of an [McstAgent] (see [McstAgent::set_observer]).
+pub type Observer = dyn FnMut(&CycleInfo) + Send;
+
 /// A configurable MCTS agent composed of modular policies for selection,
 /// expansion, rollout, and decision making.
 pub struct McstAgent<
     S: SelectionPolicy,
     E: ExpansionPolicy,
     D: DecisionPolicy,
-    R: Agent,
+    R: RolloutPolicy,
 > {
     selector: S,
     expander: E,
     rollout: R,
-    opponent: R,
     decider: D,
     tree: McstTree,
+    /// How much less than half a win a draw is worth to the root player
+    /// (see [McstAgent::set_contempt]).
+    contempt: f64,
+    /// What to tell about every cycle, and how many it has been told about.
+    observer: Option<(Box<Observer>, u64)>,
 }
 
+/// An [McstAgent] which rolls out with the same type of agent playing both
+/// sides (see [AgentRollout]), as most do.
+pub type SymmetricMcstAgent<S, E, D, A> = McstAgent<S, E, D, AgentRollout<A>>;
+
 impl<
     S: SelectionPolicy,
     E: ExpansionPolicy,
     D: DecisionPolicy,
-    R: Agent,
-> McstAgent<S, E, D, R> {
-    /// Construct a new MCTS agent using the given policies and starting state.
+    A: Agent,
+    O: Agent,
+> McstAgent<S, E, D, AgentRollout<A, O>> {
+    /// Construct a new MCTS agent using the given policies and starting state,
+    /// which rolls out with `rollout` playing the root player's moves and
+    /// `opponent` the rest (see [AgentRollout]).
     pub fn new(
         selector: S,
         expander: E,
         decider: D,
-        rollout: R,
-        opponent: R,
+        rollout: A,
+        opponent: O,
+        game: Gamestate,
+    ) -> Self {
+        McstAgent::with_rollout(selector, expander, decider, AgentRollout::new(rollout, opponent), game)
+    }
+
+    /// Like [McstAgent::new], but shares statistics between transpositions
+    /// (see [McstTree::with_transpositions]). Since this changes what the
+    /// selection policy sees from a tree to a graph, it has to be asked for.
+    pub fn with_transpositions(
+        selector: S,
+        expander: E,
+        decider: D,
+        rollout: A,
+        opponent: O,
         game: Gamestate,
     ) -> Self {
         McstAgent {
-            selector: selector,
-            expander: expander,
-            decider: decider,
-            rollout: rollout,
-            opponent: opponent,
-            tree: McstTree::new(game),
+            tree: McstTree::with_transpositions(game.clone()),
+            ..McstAgent::new(selector, expander, decider, rollout, opponent, game)
         }
     }
 
+    /// Like [McstAgent::new], but searches on from `tree`, such as one
+    /// [loaded](McstTree::load) from an earlier session, rather than from
+    /// scratch.
+    pub fn with_tree(
+        tree: McstTree,
+        mut selector: S,
+        expander: E,
+        decider: D,
+        rollout: A,
+        opponent: O,
+    ) -> Self {
+        selector.set_state(tree.root().game());
+        McstAgent { selector, expander, decider, rollout: AgentRollout::new(rollout, opponent), tree, contempt: 0.0, observer: None }
+    }
+}
+
+impl<
+    S: SelectionPolicy,
+    E: ExpansionPolicy,
+    D: DecisionPolicy,
+    R: RolloutPolicy,
+> McstAgent<S, E, D, R> {
+    /// Construct a new MCTS agent using the given policies and starting state.
+    pub fn with_rollout(
+        selector: S,
+        expander: E,
+        decider: D,
+        rollout: R,
+        game: Gamestate,
+    ) -> Self {
+        McstAgent { selector, expander, decider, rollout, tree: McstTree::new(game), contempt: 0.0, observer: None }
+    }
+
+    /// Starts searching from `state` with a new tree, which keeps the old
+    /// one's node limit, storage and whether it shares transpositions.
     pub fn set_state(&mut self, state: Gamestate) {
-        self.selector.set_state(state.clone());
-        self.tree = McstTree::new(state);
+        self.selector.set_state(&state);
+        self.expander.set_state(&state);
+        let (node_limit, storage) = (self.tree.node_limit, self.tree.storage());
+        self.tree = if self.tree.shares_transpositions() {
+            McstTree::with_transpositions(state)
+        } else {
+            McstTree::new(state)
+        };
+        self.tree.node_limit = node_limit;
+        self.tree.set_storage(storage);
+    }
+
+    /// Keeps the tree to at most `limit` nodes, so that long searches don't
+    /// run out of memory. Once it is full, the subtrees of the least visited
+    /// moves at the root are pruned to make room; if nothing can be pruned,
+    /// cycles roll out from the selected node without expanding it.
+    ///
+    /// The moves at the root are always expanded, so that there is something
+    /// to decide between, even if that takes the tree over the limit.
+    pub fn set_node_limit(&mut self, limit: usize) {
+        self.tree.node_limit = Some(limit);
+    }
+
+    /// Keeps the tree's nodes as `storage` says, from now on and in the trees
+    /// of later [states](McstAgent::set_state) (see [McstTree::set_storage]).
+    pub fn set_node_storage(&mut self, storage: NodeStorage) {
+        self.tree.set_storage(storage);
+    }
+
+    /// Values draws at `contempt` less than the half a win they are usually
+    /// worth to the player to move at the root, so that a positive contempt
+    /// steers the search away from draws, such as against a weaker opponent,
+    /// and a negative one toward them, such as when defending a bad position.
+    /// This applies to nodes [proven](Proven::Draw) to be drawn and to
+    /// rollouts which play the game out to a draw (see
+    /// [RolloutPolicy::rollout_drawn_at]).
+    ///
+    /// # Panics
+    /// If `contempt` is not between -0.5 and 0.5.
+    pub fn set_contempt(&mut self, contempt: f64) {
+        assert!((-0.5..=0.5).contains(&contempt), "contempt {contempt} is not between -0.5 and 0.5");
+        self.contempt = contempt;
+    }
+
+    /// Returns how much less than half a win draws are valued at (see
+    /// [McstAgent::set_contempt]).
+    pub fn contempt(&self) -> f64 {
+        self.contempt
+    }
+
+    /// Calls `observer` after every cycle with what happened in it, such as
+    /// for tuning the selection policy. Replaces any observer already set.
+    pub fn set_observer(&mut self, observer: impl FnMut(&CycleInfo) + Send + 'static) {
+        self.observer = Some((Box::new(observer), 0));
+    }
+
+    /// Stops telling the [observer](McstAgent::set_observer) about cycles.
+    pub fn clear_observer(&mut self) {
+        self.observer = None;
     }
 
     /// Immutable [McstAgent::tree] getter.
@@ -241,72 +1805,30 @@ impl<
         &self.tree
     }
 
-    /// Run the selection phase.
-    ///
-    /// Returns a path iff a node was selected.
-    /// Returns Ok(None) if the selector has decided there is no need to
-    /// consider more cycles.
-    /// Returns an error if the selector gave an invalid path.
-    fn select(&mut self) -> Result<Option<Vec<Turn>>, SelectionError> {
-        if let Some(path) = self.selector.select(&self.tree) {
-            if let Some(_) = &self.tree.root.search(&path) {
-                Ok(Some(path))
-            } else { Err(SelectionError::NotANode(path)) }
-        } else { Ok(None) }
-    }
-
-    /// Expand a new move from the node at the given path.
-    ///
-    /// # Panics
-    /// If the path to the node to expand is invalid.
-    fn expand(&mut self, path: &Vec<Turn>) -> Result<Turn, ExpansionError> {
-        let link = self.expander.expand(&self.tree, path);
-        let node = self.node_from_path(path); // may panic
-        if node.game().get_moves().contains(&link) {
-            if node.children.contains_key(&link) {
-                Err(ExpansionError::AlreadyExpanded(link))
-            } else {
-                Ok(link)
-            }
-        } else {
-            Err(ExpansionError::IllegalMove(link))
-        }
+    /// Immutable expansion policy getter.
+    pub fn expander(&self) -> &E {
+        &self.expander
     }
 
-    /// Perform a simulated playout from the given path and
-    /// return whether the root player won.
-    ///
-    /// # Panics
-    /// On invalid `path`.
-    fn rollout(&mut self, path: &Vec<Turn>, mut my_turn: bool) -> Result<bool, RolloutError> {
-        let mut game = self.node_from_path(path).game().clone(); // panics on invalid path
-        // TODO: optimize by removing move_history?
-        let mut move_history: Vec<Turn> = Vec::new();
-        let my_color = match self.tree.root.game.whose_turn() {
-            States::Taken(c) => c,
-            States::Empty => panic!("initial game is over?"),
-        };
+    /// Gives up the agent's tree, for [merging](McstTree::merge) into another.
+    pub fn into_tree(self) -> McstTree {
+        self.tree
+    }
 
-        loop {
-            if !game.get_moves().is_empty() {
-                let player_move = if my_turn {
-                    self.rollout.make_move(&game)
-                } else {
-                    self.opponent.make_move(&game)
-                };
-                move_history.push(player_move);
+    /// Mutable getter for the decision policy, for changing its settings
+    /// between moves.
+    pub fn decider_mut(&mut self) -> &mut D {
+        &mut self.decider
+    }
 
-                if !game.make_move_fast(player_move) {
-                    break Err(RolloutError::IllegalMove(move_history));
-                }
-                my_turn = !my_turn;
-            } else {
-                break Ok(match (my_color, game.score().cmp(&0)) {
-                    (Players::Black, Ordering::Greater) => true,
-                    (Players::White, Ordering::Less) => true,
-                    _ => false,
-                });
-            }
+    /// Perform a simulated playout from `node` and return the reward for the
+    /// root player (see [RolloutPolicy]), or the reward of its proven value
+    /// if there is one, since there is no need to roll out from a node whose
+    /// value is known. Also returns how many plies it played, if known.
+    fn rollout(&self, node: McstNode) -> Result<(f64, Option<usize>), RolloutError> {
+        match node.proof() {
+            Some(proof) => Ok((proof.reward_with_contempt(self.contempt), Some(0))),
+            Option::None => self.rollout.rollout_drawn_at(node.game().clone(), self.tree.root_player(), 0.5 - self.contempt),
         }
     }
 
@@ -315,129 +1837,532 @@ impl<
     /// Returns `Ok(false)` if the selector chose not to proceed
     /// and `Ok(true)` if it was successful and wants to continue cycling.
     pub fn cycle(&mut self) -> Result<bool, CycleError> {
-        let path = self.select();
-        let mut path = match path {
-            Err(e) => return Err(CycleError::Selection(e)),
-            Ok(Some(path)) => path,
-            Ok(Option::None) => return Ok(false),
+        let start = self.observer.is_some().then(Instant::now);
+        let (path, expanded) = match select_and_expand(&mut self.selector, &mut self.expander, &mut self.tree)? {
+            Some(selected) => selected,
+            Option::None => return Ok(false),
         };
 
-        if !self.node_from_path(&path).game.get_moves().is_empty() {
-            match self.expand(&path) { // won't panic because path is validated above
-                Err(e) => return Err(CycleError::Expansion(e)),
-                Ok(expansion) => {
-                    self.tree.add_child(&path, expansion);
-                    path.push(expansion);
-                },
-            };
+        let node = self.node_from_path(&path);
+        let proven = node.proof().is_some();
+        let (reward, plies) = self.rollout(node).map_err(CycleError::Rollout)?;
+        self.tree.backpropagate(&path, reward);
+        if let Some(start) = start {
+            observe(&mut self.observer, &path, expanded, proven, plies, reward, start);
+        }
+        Ok(true)
+    }
+
+    /// Runs up to `n` cycles, stopping early if the selector decides there is
+    /// no need for more, and returns how many were run.
+    pub fn cycle_n(&mut self, n: usize) -> Result<usize, CycleError> {
+        for done in 0..n {
+            if !self.cycle()? {
+                return Ok(done);
+            }
         }
+        Ok(n)
+    }
 
-        let win = match self.rollout(&path, path.len() & 1 == 0) {
-            Err(e) => return Err(CycleError::Rollout(e)),
-            Ok(win) => win,
+    /// Like [McstAgent::cycle_n], but runs cycles on `threads` threads at
+    /// once, each stopping early after the first cycle it finishes once
+    /// `deadline` has passed.
+    ///
+    /// The calling thread rolls out with the agent's own rollout policy and
+    /// each other thread with the one `rollouts` makes for it. The tree is
+    /// shared between them and locked for selection, expansion and
+    /// backpropagation, but not for rollouts. While a thread rolls out, every
+    /// node on its path counts a virtual loss for whoever chose it, which
+    /// steers the other threads' selections elsewhere. It is replaced by
+    /// the real reward once the rollout is done.
+    ///
+    /// With one thread this runs the same cycles as [McstAgent::cycle_n],
+    /// but with more the order they finish in, and so the tree, varies
+    /// from run to run.
+    pub fn cycle_parallel(
+        &mut self,
+        threads: usize,
+        rollouts: &RolloutFactory,
+        n: usize,
+        deadline: Option<Instant>,
+    ) -> Result<usize, CycleError>
+    where
+        S: Send,
+        E: Send,
+    {
+        let McstAgent { selector, expander, rollout, tree, contempt, observer, .. } = self;
+        let contempt = *contempt;
+        let me = tree.root_player();
+        let shared = Mutex::new((selector, expander, tree, observer));
+        let started = AtomicUsize::new(0);
+        let completed = AtomicUsize::new(0);
+        let stop = AtomicBool::new(false);
+        let error: Mutex<Option<CycleError>> = Mutex::new(None);
+        let fail = |e: CycleError| {
+            stop.store(true, AtomicOrdering::Relaxed);
+            error.lock().unwrap().get_or_insert(e);
         };
 
-        // TODO: should it be ..(index + 1)?
-        for index in 0..=path.len() {
-            self.node_from_path_mut(&path[..index])
-                .update(win);
-        }
+        let work = |rollout: &dyn RolloutPolicy| {
+            while !stop.load(AtomicOrdering::Relaxed) && started.fetch_add(1, AtomicOrdering::Relaxed) < n {
+                let (path, expanded, node, start) = {
+                    let mut shared = shared.lock().unwrap();
+                    let (selector, expander, tree, observer) = &mut *shared;
+                    let start = observer.is_some().then(Instant::now);
+                    match select_and_expand(*selector, *expander, tree) {
+                        Ok(Some((path, expanded))) => {
+                            tree.add_virtual_loss(&path);
+                            let node = tree.root().search(&path).unwrap();
+                            (path, expanded, node.proof().ok_or_else(|| node.game().clone()), start)
+                        }
+                        Ok(Option::None) => {
+                            stop.store(true, AtomicOrdering::Relaxed);
+                            break;
+                        }
+                        Err(e) => {
+                            fail(e);
+                            break;
+                        }
+                    }
+                };
 
-        Ok(true)
+                let proven = node.is_ok();
+                let rollout = match node {
+                    Ok(proof) => Ok((proof.reward_with_contempt(contempt), Some(0))),
+                    Err(game) => rollout.rollout_drawn_at(game, me, 0.5 - contempt),
+                };
+                let mut shared = shared.lock().unwrap();
+                let (_, _, tree, observer) = &mut *shared;
+                match rollout {
+                    Ok((reward, plies)) => {
+                        tree.replace_virtual_loss(&path, Some(reward));
+                        if let Some(start) = start {
+                            observe(observer, &path, expanded, proven, plies, reward, start);
+                        }
+                        completed.fetch_add(1, AtomicOrdering::Relaxed);
+                        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        tree.replace_virtual_loss(&path, None);
+                        fail(CycleError::Rollout(e));
+                        break;
+                    }
+                }
+            }
+        };
+
+        thread::scope(|scope| {
+            for worker in 1..threads {
+                let work = &work;
+                scope.spawn(move || {
+                    let rollout = rollouts(worker);
+                    work(&*rollout);
+                });
+            }
+            work(&*rollout);
+        });
+
+        match error.into_inner().unwrap() {
+            Some(e) => Err(e),
+            None => Ok(completed.into_inner()),
+        }
     }
 
-    /// Choose a move to play based on the current tree.
+    /// Choose a move to play based on the current tree, overruling the
+    /// decision policy with whatever has been [proven](McstTree::apply_proofs).
     ///
     /// Returns `None` if the decision is invalid in the root game state.
     pub fn decide(&mut self) -> Option<Turn> {
-        let decision = self.decider.decide(&self.tree);
-        if self.tree.root.game.valid_move(decision) {
-            let child = &self.tree.root.children[&decision];
-            //println!("Chosen move at game \n{}\n wins {}/{} playouts",self.tree.root.game, child.wins(), child.total());
+        let decision = self.tree.apply_proofs(self.decider.decide(&self.tree));
+        if self.tree.root().game().valid_move(decision) {
             Some(decision)
         } else {
             None
         }
     }
 
-    /// Get a mutable reference to a node at a specific path.
-    ///
-    /// # Panics
-    /// If the path does not refer to a valid node.
-    fn node_from_path_mut(&mut self, path: &[Turn]) -> &mut McstNode {
-        self.tree
-            .root
-            .search_mut(path)
-            .expect("Node from path given invalid path")
+    /// Returns how every expanded move at the root has done, most visited
+    /// first. Their visits add up to the root's, less any rollouts from the
+    /// root itself (which there is one of once the tree has been rerooted).
+    pub fn root_stats(&self) -> Vec<MoveStats> {
+        let mut stats: Vec<MoveStats> = self.tree.root().children().iter()
+            .map(|(turn, child)| MoveStats {
+                turn: *turn,
+                visits: *child.total(),
+                win_rate: match *child.total() {
+                    0 => 0.0,
+                    total => child.wins() / f64::from(total),
+                },
+            })
+            .collect();
+        stats.sort_by_key(|stats| Reverse(stats.visits));
+        stats
     }
 
-    /// Get an immutable reference to a node at a specific path.
+    /// Returns the line of play the search expects: up to `max_len` moves,
+    /// found by following the most visited child from the root.
+    pub fn principal_variation(&self, max_len: usize) -> Vec<Turn> {
+        let mut pv = Vec::new();
+        let mut node = self.tree.root();
+        while pv.len() < max_len {
+            let Some((turn, child)) = node.children().iter().max_by_key(|(_, child)| *child.total()) else {
+                break;
+            };
+            pv.push(*turn);
+            node = child;
+        }
+        pv
+    }
+
+    /// Returns the [root stats](McstAgent::root_stats) and a
+    /// [principal variation](McstAgent::principal_variation) of up to
+    /// `max_len` moves together.
+    pub fn analysis(&self, max_len: usize) -> Analysis {
+        Analysis { moves: self.root_stats(), pv: self.principal_variation(max_len), search: None, clock: None }
+    }
+
+    /// Get the node at a specific path.
     ///
     /// # Panics
     /// If the path does not refer to a valid node.
-    fn node_from_path(&self, path: &[Turn]) -> &McstNode {
+    fn node_from_path(&self, path: &[Turn]) -> McstNode<'_> {
         self.tree
-            .root
+            .root()
             .search(path)
             .expect("Node from path given invalid path")
     }
 
-    /// Advance the tree to reflect two new moves.
+    /// Advance the tree to reflect a new move, whoever made it (a pass
+    /// included).
+    ///
+    /// Replaces the root with the subtree for the move, which is added first
+    /// if it has not been expanded. Returns `false`, leaving the tree as it
+    /// was, if the move is invalid.
+    pub fn advance(&mut self, turn: Turn) -> bool {
+        self.advance_by(&[turn])
+    }
+
+    /// Like [McstAgent::advance], but for several moves in a row, which the
+    /// tree is rerooted past at once.
+    pub fn advance_by(&mut self, turns: &[Turn]) -> bool {
+        let mut test_game = self.tree.root().game().clone();
+        if !test_game.make_moves_fast(turns) {
+            return false;
+        } else if turns.is_empty() {
+            return true;
+        }
+        for index in 0..turns.len() {
+            let (path, turn) = (&turns[..index], turns[index]);
+            // won't panic since every move has been checked
+            if !self.tree.root().search(path).unwrap().children().contains_key(&turn) {
+                self.tree.add_child(path, turn);
+            }
+        }
+        self.tree.reroot(turns);
+        self.selector.root_advanced(&self.tree, turns);
+        self.expander.root_advanced(&self.tree, turns);
+        true
+    }
+
+    /// Advance the tree to reflect two new moves (see [McstAgent::advance]).
     ///
-    /// Replaces the root with the subtree corresponding to the new state.
-    /// Returns `false` if the moves were invalid.
+    /// Returns `false`, leaving the tree as it was, if the moves were invalid.
     pub fn next_two_moves(&mut self, mv1: Turn, mv2: Turn) -> bool {
-        let mut test_game = self.tree.root.game.clone();
-        if !test_game.make_moves_fast(&[mv1, mv2]) {
-            false
-        } else {
-            // add first and second children if not in tree, then replace root
-            if !self.tree.root.children.contains_key(&mv1) {
-                // won't panic since it is verified that mv1 is not in children
-                self.tree.add_child(&[], mv1);
+        self.advance_by(&[mv1, mv2])
+    }
+}
+
+/// Tells `observer`, if there is one, about a cycle which started at `start`
+/// and rolled out from the end of `path`.
+fn observe(
+    observer: &mut Option<(Box<Observer>, u64)>,
+    path: &[Turn],
+    expanded: bool,
+    proven: bool,
+    rollout_plies: Option<usize>,
+    reward: f64,
+    start: Instant,
+) {
+    if let Some((observer, cycles)) = observer {
+        let info = CycleInfo { cycle: *cycles, depth: path.len(), expanded, proven, rollout_plies, reward, elapsed: start.elapsed() };
+        *cycles += 1;
+        observer(&info);
+    }
+}
+
+/// Makes the rollout policy for a worker thread of a
+/// [parallel search](McstAgent::cycle_parallel). It is given the worker's
+/// number, counting from 1, so that seeded rollouts can be seeded differently.
+///
+/// The policies are made on the worker thread, so they don't need to be [Send].
+pub type RolloutFactory = dyn Fn(usize) -> Box<dyn RolloutPolicy> + Send + Sync;
+
+/// Runs the selection and expansion phases of a cycle, then
+/// [proves](McstTree::prove) what it can along the path.
+///
+/// Returns the path to the newly expanded node (the first, if the expander
+/// [expanded several](ExpansionPolicy::expand_all)), or to the selected one if
+/// its game is over, its value already proven, it is
+/// [transposed](McstNode::transposed) or the tree is full and can't be
+/// [pruned](McstAgent::set_node_limit), along with which it was.
+/// Returns Ok(None) if the selector has decided there is no need to
+/// consider more cycles.
+/// Returns an error if the selector gave an invalid path or the expander an
+/// invalid move.
+fn select_and_expand<S: SelectionPolicy, E: ExpansionPolicy>(
+    selector: &mut S,
+    expander: &mut E,
+    tree: &mut McstTree,
+) -> Result<Option<(Vec<Turn>, bool)>, CycleError> {
+    let mut path = match selector.select(tree) {
+        Some(path) => path,
+        Option::None => return Ok(None),
+    };
+    let Some(node) = tree.root().search(&path) else {
+        return Err(CycleError::Selection(SelectionError::NotANode(path)));
+    };
+    if node.transposed() {
+        tree.untranspose(&path);
+    }
+    let node = tree.root().search(&path).unwrap();
+
+    let mut expanded = false;
+    if node.game().move_count() != 0 && node.proof().is_none() && !node.transposed() {
+        let links = expander.expand_all(tree, &path);
+        // check them all first so that a bad one leaves the tree as it was
+        for (i, &link) in links.iter().enumerate() {
+            if let Err(e) = node.game().check_move(link) {
+                return Err(CycleError::Expansion(ExpansionError::IllegalMove(link, e)));
+            } else if node.children().contains_key(&link) || links[..i].contains(&link) {
+                return Err(CycleError::Expansion(ExpansionError::AlreadyExpanded(link)));
             }
-            // won't panic because we just put mv1 into the tree
-            if !self.tree.root.children.get(&mv1).unwrap().children.contains_key(&mv2) {
-                // won't panic since it is verified that mv2 is not in children
-                self.tree.add_child(&[mv1], mv2); // panics on invalid path
+        }
+        let Some(&first) = links.first() else {
+            return Err(CycleError::Expansion(ExpansionError::NoMoves));
+        };
+        if path.is_empty() || tree.make_room(path[0], links.len()) {
+            let priors: Vec<f32> = links.iter().map(|&link| expander.prior(tree, &path, link)).collect();
+            for (link, prior) in links.into_iter().zip(priors) {
+                tree.insert_child(&path, link, Some(prior));
             }
-            // won't panic because we just put mv1 and mv2 into the tree
-            self.tree.root = self.tree
-                                 .root
-                                 .children
-                                 .get_mut(&mv1)
-                                 .unwrap()
-                                 .children
-                                 .remove(&mv2)
-                                 .unwrap();
+            expander.expanded(tree, &path);
+            path.push(first);
+            expanded = true;
+        }
+    }
+    tree.prove(&path);
+    Ok(Some((path, expanded)))
+}
+
+/// Plays `game` out and returns the reward for `me`: 1 for a win, `draw` for
+/// a draw and 0 for a loss, along with how many plies were played.
+/// `rollout` plays `me`'s moves and `opponent` the rest.
+///
+/// With a `cap` of some number of plies and an evaluator, a game still going
+/// after that many plies is rewarded as though whoever the evaluator favors
+/// had won it, or as half a win if it favors neither.
+///
+/// If both agents [play randomly](Agent::random_playout), as `random` says,
+/// and the game can't go on past the cap, the whole playout is left to
+/// `rollout` instead.
+///
+/// The moves played are recorded in `move_history`, which is cleared first,
+/// for reporting an illegal one. Passing the same buffer to every playout
+/// saves allocating a new one each time.
+fn playout<R: Agent + ?Sized, O: Agent + ?Sized, V: Evaluator + ?Sized>(
+    mut game: Gamestate,
+    me: Players,
+    (rollout, opponent, random): (&R, &O, bool),
+    cap: Option<(usize, &V)>,
+    draw: f64,
+    move_history: &mut Vec<Turn>,
+) -> Result<(f64, Option<usize>), RolloutError> {
+    move_history.clear();
+    // given whether Black is ahead, level or behind, and what a level game is worth
+    let reward = |lead: Ordering, level: f64| match (me, lead) {
+        (_, Ordering::Equal) => level,
+        (Players::Black, Ordering::Greater) | (Players::White, Ordering::Less) => 1.0,
+        _ => 0.0,
+    };
+
+    // every empty tile takes at most a pass and a move to fill
+    let uncapped = cap.is_none_or(|(max_plies, _)| max_plies >= 2 * usize::from(game.board().counts().2));
+    if let Some((score, plies)) = (random && uncapped).then(|| rollout.random_playout(&game)).flatten() {
+        return Ok((reward(score.cmp(&0), draw), Some(plies)));
+    }
+
+    loop {
+        if game.move_count() == 0 {
+            break Ok((reward(game.score().cmp(&0), draw), Some(move_history.len())));
+        } else if let Some((_, evaluator)) = cap.filter(|(max_plies, _)| move_history.len() >= *max_plies) {
+            break Ok((reward(evaluator.evaluate(&game).total_cmp(&0.0), 0.5), Some(move_history.len())));
+        }
+
+        let player_move = if game.side_to_move() == me {
+            rollout.try_make_move(&game)
+        } else {
+            opponent.try_make_move(&game)
+        }.map_err(RolloutError::Agent)?;
+        move_history.push(player_move);
+
+        if !game.make_move_fast(player_move) {
+            break Err(RolloutError::IllegalMove(move_history.clone()));
+        }
+    }
+}
 
-            self.selector.turns_passed(&self.tree);
-            true
+/// How many cycles [benchmark_with] runs between looking at the clock.
+const BENCHMARK_BATCH: usize = 64;
+
+/// What [benchmark_with] measured of a search.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BenchmarkReport {
+    /// How long the cycles took altogether.
+    pub elapsed: Duration,
+    /// How many cycles were completed.
+    pub cycles: u64,
+    /// How many nodes the tree grew by, not counting any pruned to keep it
+    /// under a [node limit](McstAgent::set_node_limit).
+    pub nodes_created: usize,
+    /// How many cycles rolled out, rather than taking the value of a
+    /// [proven](Proven) node.
+    pub rollouts: u64,
+    /// How many plies the rollouts played on average, among those whose
+    /// policy [kept count](RolloutPolicy::rollout_plies), if any did.
+    pub average_rollout_plies: Option<f64>,
+    /// How many moves from the root selection went on average.
+    pub average_depth: f64,
+    /// The most nodes the tree had at once.
+    pub peak_nodes: usize,
+    /// How many cycles failed (and were skipped over).
+    pub errors: u64,
+}
+
+impl BenchmarkReport {
+    /// Returns the average number of nodes created per second.
+    pub fn nodes_per_sec(&self) -> usize {
+        (self.nodes_created as f64 / self.elapsed.as_secs_f64()).round() as usize
+    }
+}
+
+/// Shows the rates on one line, then the rest on another.
+impl fmt::Display for BenchmarkReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let per_sec = |n: u64| n as f64 / self.elapsed.as_secs_f64();
+        writeln!(
+            f,
+            "{} nodes/s, {:.0} cycles/s, {:.0} rollouts/s over {:.2}s",
+            self.nodes_per_sec(), per_sec(self.cycles), per_sec(self.rollouts), self.elapsed.as_secs_f64(),
+        )?;
+        write!(f, "{:.1} deep on average, ", self.average_depth)?;
+        if let Some(plies) = self.average_rollout_plies {
+            write!(f, "{plies:.1} plies per rollout, ")?;
         }
+        write!(f, "{} nodes at most, {} errors", self.peak_nodes, self.errors)
     }
 }
 
 /// Benchmarks an MCTS agent by running cycles for 5 seconds and
-/// returnind the average number of nodes generated per second.
+/// returning the average number of nodes generated per second.
+///
+/// See [benchmark_with] for more about the search.
 pub fn benchmark<Sel, Exp, Dec, Roll>(
-    mut agent: McstAgent<Sel, Exp, Dec, Roll>,
+    agent: McstAgent<Sel, Exp, Dec, Roll>,
 ) -> usize
 where
     Sel: SelectionPolicy,
     Exp: ExpansionPolicy,
     Dec: DecisionPolicy,
-    Roll: Agent,
+    Roll: RolloutPolicy,
+{
+    benchmark_with(agent, Duration::from_secs(5)).nodes_per_sec()
+}
+
+/// Benchmarks an MCTS agent by running cycles for `duration`, or until the
+/// selector decides there is no need for more, and reports on them.
+///
+/// Cycles which fail are counted and skipped over rather than ending the
+/// benchmark. The agent's [observer](McstAgent::set_observer) is replaced
+/// by one which keeps the tally.
+pub fn benchmark_with<Sel, Exp, Dec, Roll>(
+    mut agent: McstAgent<Sel, Exp, Dec, Roll>,
+    duration: Duration,
+) -> BenchmarkReport
+where
+    Sel: SelectionPolicy,
+    Exp: ExpansionPolicy,
+    Dec: DecisionPolicy,
+    Roll: RolloutPolicy,
 {
+    // (cycles, rollouts, depths, plies, rollouts whose plies were counted)
+    let tally = Arc::new(Mutex::new((0, 0, 0, 0, 0)));
+    let observed = Arc::clone(&tally);
+    agent.set_observer(move |info| {
+        let (cycles, rollouts, depths, plies, counted) = &mut *observed.lock().unwrap();
+        *cycles += 1;
+        *depths += info.depth;
+        if !info.proven {
+            *rollouts += 1;
+            if let Some(rollout_plies) = info.rollout_plies {
+                *plies += rollout_plies;
+                *counted += 1;
+            }
+        }
+    });
+
     let start_time = Instant::now();
-    let time_limit = Duration::from_secs(5);
+    let mut report = BenchmarkReport { peak_nodes: agent.tree.size(), ..Default::default() };
 
-    // Run as many cycles as possible within the time limit
-    while Instant::now() - start_time < time_limit {
-        if let Err(e) = agent.cycle() {
-            panic!("Cycle failed during benchmarking: {:?}", e);
+    // Run as many cycles as possible within the time limit,
+    // checking the clock only every so often so it doesn't skew the results
+    'timed: while start_time.elapsed() < duration {
+        for _ in 0..BENCHMARK_BATCH {
+            let before = agent.tree.size();
+            match agent.cycle() {
+                Ok(true) => {}
+                Ok(false) => break 'timed,
+                Err(_) => report.errors += 1,
+            }
+            report.nodes_created += agent.tree.size().saturating_sub(before);
+            report.peak_nodes = report.peak_nodes.max(agent.tree.size());
         }
     }
 
+    report.elapsed = start_time.elapsed();
+    let (cycles, rollouts, depths, plies, counted) = *tally.lock().unwrap();
+    report.cycles = cycles;
+    report.rollouts = rollouts;
+    if cycles > 0 {
+        report.average_depth = depths as f64 / cycles as f64;
+    }
+    if counted > 0 {
+        report.average_rollout_plies = Some(plies as f64 / counted as f64);
+    }
+    report
+}
+
+/// Like [benchmark], but runs the cycles on `threads` threads at once
+/// (see [McstAgent::cycle_parallel]).
+pub fn benchmark_parallel<Sel, Exp, Dec, Roll>(
+    mut agent: McstAgent<Sel, Exp, Dec, Roll>,
+    threads: usize,
+    rollouts: &RolloutFactory,
+) -> usize
+where
+    Sel: SelectionPolicy + Send,
+    Exp: ExpansionPolicy + Send,
+    Dec: DecisionPolicy,
+    Roll: RolloutPolicy,
+{
+    let start_time = Instant::now();
+    let deadline = start_time + Duration::from_secs(5);
+
+    if let Err(e) = agent.cycle_parallel(threads, rollouts, usize::MAX, Some(deadline)) {
+        panic!("Cycle failed during benchmarking: {e}");
+    }
+
     let total_nodes = agent.tree().root().node_count();
     let elapsed_secs = (Instant::now() - start_time).as_secs_f64();
 
@@ -1,4 +1,9 @@
+use std::env;
 use std::fmt;
+use std::io::{self, IsTerminal};
+use std::str::FromStr;
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
 /// All 8 surrounding directions in a grid
 static AROUND: [(u8, u8); 8] = [
@@ -7,15 +12,69 @@ static AROUND: [(u8, u8); 8] = [
     (255, 255), (0, 255), (1, 255),
 ];
 
+/// Bits of a move mask with `y == 0`.
+const Y0_BITS: u64 = 0x0101_0101_0101_0101;
+/// Bits of a move mask with `y == 7`.
+const Y7_BITS: u64 = 0x8080_8080_8080_8080;
+
+/// The 8 directions of [AROUND] as shifts of a move mask, paired with the
+/// bits that remain valid after shifting (those that did not wrap around
+/// into the next column).
+static MASK_SHIFTS: [(i8, u64); 8] = [
+    (-7, !Y0_BITS), (1, !Y0_BITS), (9, !Y0_BITS),
+    (-8, !0),                      (8, !0),
+    (-9, !Y7_BITS), (-1, !Y7_BITS), (7, !Y7_BITS),
+];
+
+/// Shifts every bit of a move mask one step in the direction given by
+/// an entry of [MASK_SHIFTS].
+fn shift_mask(mask: u64, (shift, valid): (i8, u64)) -> u64 {
+    if shift > 0 {
+        (mask << shift) & valid
+    } else {
+        (mask >> -shift) & valid
+    }
+}
+
+/// An iterator over the locations set in a move mask,
+/// in the same order as [Board::get_moves].
+///
+/// Created by [Board::moves_iter].
+#[derive(Debug, Clone)]
+pub struct MoveIter {
+    mask: u64,
+}
+
+impl Iterator for MoveIter {
+    type Item = (u8, u8);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.mask == 0 {
+            None
+        } else {
+            let index = self.mask.trailing_zeros() as u8;
+            self.mask &= self.mask - 1;
+            Some((index / 8, index % 8))
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let count = self.mask.count_ones() as usize;
+        (count, Some(count))
+    }
+}
+
+impl ExactSizeIterator for MoveIter {}
+
 /// The two players in the game.
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Players {
     White,
     Black,
 }
 
 /// The state of a board tile
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum States {
     /// Tile is taken by a [Player](Players).
     Taken(Players),
@@ -23,11 +82,149 @@ pub enum States {
     Empty,
 }
 
+/// One of the eight symmetries of the board (the dihedral group of the square).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Symmetry {
+    Identity,
+    /// See [Board::rotate_90].
+    Rotate90,
+    /// See [Board::rotate_180].
+    Rotate180,
+    /// See [Board::rotate_270].
+    Rotate270,
+    /// See [Board::flip_horizontal].
+    FlipHorizontal,
+    /// See [Board::flip_vertical].
+    FlipVertical,
+    /// See [Board::flip_main_diagonal].
+    FlipMainDiagonal,
+    /// See [Board::flip_anti_diagonal].
+    FlipAntiDiagonal,
+}
+
+impl Symmetry {
+    /// Every symmetry, starting with [Symmetry::Identity].
+    pub const ALL: [Symmetry; 8] = [
+        Symmetry::Identity,
+        Symmetry::Rotate90,
+        Symmetry::Rotate180,
+        Symmetry::Rotate270,
+        Symmetry::FlipHorizontal,
+        Symmetry::FlipVertical,
+        Symmetry::FlipMainDiagonal,
+        Symmetry::FlipAntiDiagonal,
+    ];
+
+    /// Applies the transform to a board in place.
+    pub fn apply(&self, board: &mut Board) {
+        match self {
+            Symmetry::Identity => (),
+            Symmetry::Rotate90 => board.rotate_90(),
+            Symmetry::Rotate180 => board.rotate_180(),
+            Symmetry::Rotate270 => board.rotate_270(),
+            Symmetry::FlipHorizontal => board.flip_horizontal(),
+            Symmetry::FlipVertical => board.flip_vertical(),
+            Symmetry::FlipMainDiagonal => board.flip_main_diagonal(),
+            Symmetry::FlipAntiDiagonal => board.flip_anti_diagonal(),
+        }
+    }
+
+    /// Returns the transform that undoes this one.
+    pub fn inverse(&self) -> Symmetry {
+        match self {
+            Symmetry::Rotate90 => Symmetry::Rotate270,
+            Symmetry::Rotate270 => Symmetry::Rotate90,
+            other => *other,
+        }
+    }
+
+    /// Returns where the tile at `(x, y)` ends up after the transform
+    /// is applied.
+    ///
+    /// Does not perform bounds checking.
+    pub fn map(&self, x: u8, y: u8) -> (u8, u8) {
+        match self {
+            Symmetry::Identity => (x, y),
+            Symmetry::Rotate90 => (7 - y, x),
+            Symmetry::Rotate180 => (7 - x, 7 - y),
+            Symmetry::Rotate270 => (y, 7 - x),
+            Symmetry::FlipHorizontal => (7 - x, y),
+            Symmetry::FlipVertical => (x, 7 - y),
+            Symmetry::FlipMainDiagonal => (y, x),
+            Symmetry::FlipAntiDiagonal => (7 - y, 7 - x),
+        }
+    }
+}
+
+/// Generates the table of Zobrist keys at compile time using splitmix64.
+///
+/// Entry `8 * y + x` holds the keys for a black and a white tile at `(x, y)`.
+/// The final entry's black key is used for the side to move.
+const fn zobrist_table() -> [[u64; 2]; 65] {
+    let mut table = [[0_u64; 2]; 65];
+    let mut seed: u64 = 0x4f74_6865_6c6c_6f21;
+    let mut i = 0;
+    while i < 65 * 2 {
+        seed = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = seed;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        table[i / 2][i % 2] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+}
+
+/// Random keys for Zobrist hashing, indexed by tile and then by color.
+static ZOBRIST: [[u64; 2]; 65] = zobrist_table();
+
+/// Zobrist key xor'd into a [Gamestate](crate::gameplay::Gamestate)'s hash
+/// when it is White's turn.
+pub static ZOBRIST_WHITE_TO_MOVE: u64 = ZOBRIST[64][0];
+
+/// Returns the Zobrist key for a tile at `(x, y)` in the given state.
+fn zobrist_key(x: u8, y: u8, state: States) -> u64 {
+    match state {
+        States::Empty => 0,
+        States::Taken(Players::Black) => ZOBRIST[usize::from(8 * y + x)][0],
+        States::Taken(Players::White) => ZOBRIST[usize::from(8 * y + x)][1],
+    }
+}
+
 /// Represents the game board: an 8x8 grid of tile states.
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy)]
 pub struct Board {
     /// 8x8 grid of tile states.
-    pub pieces: [[States; 8]; 8],
+    ///
+    /// Write to it only through [Board::change] so that the
+    /// [Zobrist hash](Board::zobrist_hash) stays in sync.
+    pieces: [[States; 8]; 8],
+    /// Incrementally maintained Zobrist hash of `pieces`.
+    zobrist: u64,
+}
+
+impl Serialize for Board {
+    /// Serializes the board as its [compact form](Board::to_compact).
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u128(self.to_compact())
+    }
+}
+
+impl<'de> Deserialize<'de> for Board {
+    /// Deserializes a board from its [compact form](Board::to_compact).
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let compact = u128::deserialize(deserializer)?;
+        Board::from_compact(compact).ok_or_else(
+            || de::Error::custom(format!("{compact} is not a valid compact board"))
+        )
+    }
+}
+
+impl PartialEq for Board {
+    /// Boards are equal when their tiles are; the hash is derived data.
+    fn eq(&self, other: &Self) -> bool {
+        self.pieces == other.pieces
+    }
 }
 
 
@@ -54,6 +251,66 @@ impl fmt::Display for Board {
     }
 }
 
+/// How [Board::render] draws a board.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RenderStyle {
+    /// `B`, `W` and `.` tiles, the same as the [Display](fmt::Display) format.
+    Ascii,
+    /// `●` (Black) and `○` (White) tiles inside a box-drawing grid.
+    Unicode,
+    /// `●` and `○` tiles on ANSI background colors, with the last move
+    /// and any marked tiles highlighted.
+    Colored,
+}
+
+impl RenderStyle {
+    /// Returns [RenderStyle::Colored] if stdout is a terminal that should
+    /// understand ANSI escapes, and [RenderStyle::Ascii] otherwise.
+    ///
+    /// Respects the `NO_COLOR` convention and `TERM=dumb`.
+    pub fn detect() -> Self {
+        let dumb_term = env::var("TERM").map_or(true, |term| term == "dumb");
+        if io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none() && !dumb_term {
+            RenderStyle::Colored
+        } else {
+            RenderStyle::Ascii
+        }
+    }
+}
+
+/// The tiles flipped along a single direction by a move.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FlipLine {
+    /// The direction `(dx, dy)` from the placed tile towards the flipped ones.
+    pub direction: (i8, i8),
+    /// The flipped tiles, nearest to the placed tile first.
+    pub squares: Vec<(u8, u8)>,
+}
+
+/// Errors that can occur when parsing a [Board] from its [Display](fmt::Display) format.
+#[derive(Debug, PartialEq)]
+pub enum ParseBoardError {
+    /// The first line was not the `" 01234567"` header.
+    BadHeader,
+    /// There were not exactly 8 rows after the header (holds the row count).
+    WrongRowCount(usize),
+    /// A row did not start with its own index.
+    BadRowLabel { row: usize },
+    /// A row did not hold exactly 8 tiles (holds the tile count).
+    WrongRowLength { row: usize, length: usize },
+    /// A tile was not one of `.`, `B` or `W`.
+    BadCharacter { row: usize, column: usize, character: char },
+}
+
+impl FromStr for Board {
+    type Err = ParseBoardError;
+
+    /// See [Board::from_ascii].
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Board::from_ascii(s)
+    }
+}
+
 /// Helper type used to describe flipping outcomes.
 #[derive(Debug, PartialEq)]
 enum FlipType {
@@ -70,7 +327,26 @@ impl Board {
     pub fn new() -> Self {
         Board {
             pieces: [[States::Empty; 8]; 8],
+            zobrist: 0,
+        }
+    }
+
+    /// Returns the incrementally maintained Zobrist hash of the board.
+    ///
+    /// Always equal to [Board::zobrist], but does not scan the board.
+    pub fn zobrist_hash(&self) -> u64 {
+        self.zobrist
+    }
+
+    /// Computes the Zobrist hash of the board from scratch.
+    pub fn zobrist(&self) -> u64 {
+        let mut hash = 0;
+        for x in 0..8 {
+            for y in 0..8 {
+                hash ^= zobrist_key(x, y, self.at(x, y).unwrap());
+            }
         }
+        hash
     }
 
     /// Returns the score of the board.
@@ -92,11 +368,163 @@ impl Board {
         ).sum()
     }
 
+    /// Parses a board from the format produced by its
+    /// [Display](fmt::Display) implementation.
+    ///
+    /// Trailing whitespace on any line and trailing blank lines are ignored.
+    pub fn from_ascii(s: &str) -> Result<Self, ParseBoardError> {
+        let mut lines = s.trim_end().lines().map(str::trim_end);
+        if lines.next() != Some(" 01234567") {
+            return Err(ParseBoardError::BadHeader);
+        }
+
+        let rows: Vec<&str> = lines.collect();
+        if rows.len() != 8 {
+            return Err(ParseBoardError::WrongRowCount(rows.len()));
+        }
+
+        let mut b = Board::new();
+        for (row, line) in rows.iter().enumerate() {
+            let mut chars = line.chars();
+            if chars.next() != char::from_digit(row as u32, 10) {
+                return Err(ParseBoardError::BadRowLabel { row });
+            }
+            let tiles: Vec<char> = chars.collect();
+            if tiles.len() != 8 {
+                return Err(ParseBoardError::WrongRowLength { row, length: tiles.len() });
+            }
+            for (column, character) in tiles.into_iter().enumerate() {
+                let state = match character {
+                    '.' => States::Empty,
+                    'B' => States::Taken(Players::Black),
+                    'W' => States::Taken(Players::White),
+                    _ => return Err(ParseBoardError::BadCharacter { row, column, character }),
+                };
+                b.change(column as u8, row as u8, state);
+            }
+        }
+        Ok(b)
+    }
+
+    /// Draws the board in the given [RenderStyle].
+    ///
+    /// [RenderStyle::Ascii] gives the same output as the
+    /// [Display](fmt::Display) implementation.
+    pub fn render(&self, style: RenderStyle) -> String {
+        self.render_marked(style, &[], None)
+    }
+
+    /// Draws the board in the given [RenderStyle], drawing every empty tile
+    /// in `marks` as `*` and highlighting `last_move`.
+    ///
+    /// Only [RenderStyle::Colored] can show `last_move`; the other styles
+    /// ignore it.
+    pub fn render_marked(&self, style: RenderStyle, marks: &[(u8, u8)], last_move: Option<(u8, u8)>) -> String {
+        let tile = |x: u8, y: u8| -> (States, bool) {
+            (self.at(x, y).unwrap(), marks.contains(&(x, y)))
+        };
+
+        match style {
+            RenderStyle::Ascii => {
+                let mut out = String::from(" 01234567");
+                for y in 0..8 {
+                    out += &format!("\n{y}");
+                    for x in 0..8 {
+                        out.push(match tile(x, y) {
+                            (States::Taken(Players::Black), _) => 'B',
+                            (States::Taken(Players::White), _) => 'W',
+                            (States::Empty, true) => '*',
+                            (States::Empty, false) => '.',
+                        });
+                    }
+                }
+                out
+            }
+            RenderStyle::Unicode => {
+                let mut out = String::from("   0 1 2 3 4 5 6 7\n  ┌");
+                out += &["─"; 8].join("┬");
+                out += "┐";
+                for y in 0..8 {
+                    if y > 0 {
+                        out += "\n  ├";
+                        out += &["─"; 8].join("┼");
+                        out += "┤";
+                    }
+                    out += &format!("\n{y} │");
+                    for x in 0..8 {
+                        out.push(match tile(x, y) {
+                            (States::Taken(Players::Black), _) => '●',
+                            (States::Taken(Players::White), _) => '○',
+                            (States::Empty, true) => '*',
+                            (States::Empty, false) => ' ',
+                        });
+                        out.push('│');
+                    }
+                }
+                out += "\n  └";
+                out += &["─"; 8].join("┴");
+                out += "┘";
+                out
+            }
+            RenderStyle::Colored => {
+                let mut out = String::from("   0  1  2  3  4  5  6  7");
+                for y in 0..8 {
+                    out += &format!("\n{y} ");
+                    for x in 0..8 {
+                        let (state, marked) = tile(x, y);
+                        let background = if last_move == Some((x, y)) {
+                            46
+                        } else if marked && state == States::Empty {
+                            43
+                        } else {
+                            42
+                        };
+                        let (foreground, symbol) = match (state, marked) {
+                            (States::Taken(Players::Black), _) => (30, '●'),
+                            (States::Taken(Players::White), _) => (97, '○'),
+                            (States::Empty, true) => (30, '*'),
+                            (States::Empty, false) => (30, ' '),
+                        };
+                        out += &format!("\x1b[{background};{foreground}m {symbol} ");
+                    }
+                    out += "\x1b[0m";
+                }
+                out
+            }
+        }
+    }
+
+    /// Returns the number of tiles taken by `player`.
+    pub fn count(&self, player: Players) -> u8 {
+        let (black, white, _) = self.counts();
+        match player {
+            Players::Black => black,
+            Players::White => white,
+        }
+    }
+
+    /// Returns the number of black, white, and empty tiles, in that order.
+    pub fn counts(&self) -> (u8, u8, u8) {
+        let mut counts = (0, 0, 0);
+        for row in self.pieces.iter() {
+            for piece in row.iter() {
+                match piece {
+                    States::Taken(Players::Black) => counts.0 += 1,
+                    States::Taken(Players::White) => counts.1 += 1,
+                    States::Empty => counts.2 += 1,
+                }
+            }
+        }
+        counts
+    }
+
     /// Sets the tile at `(x, y)` to a given [States] value.
     ///
     /// Does not perform bounds checking (may panic).
     pub fn change(&mut self, x: u8, y: u8, val: States) {
-        self.pieces[usize::from(y)][usize::from(x)] = val;
+        let tile = &mut self.pieces[usize::from(y)][usize::from(x)];
+        self.zobrist ^= zobrist_key(x, y, *tile) ^ zobrist_key(x, y, val);
+        *tile = val;
     }
 
     /// Returns the tile at `(x, y)` or [None] if out of bounds.
@@ -107,37 +535,156 @@ impl Board {
         None
     }
 
+    /// Places a tile for `p` at `(x, y)` and flips every tile it captures,
+    /// returning the list of flipped tiles.
+    ///
+    /// Returns [None] and leaves the board untouched if the move is illegal.
+    pub fn apply_move(&mut self, x: u8, y: u8, p: Players) -> Option<Vec<(u8, u8)>> {
+        if self.can_move(x, y, p) {
+            self.change(x, y, States::Taken(p));
+            Some(self.flip_all(x, y))
+        } else { None }
+    }
+
+    /// Exactly undoes a call to [Board::apply_move] for `p` at `(x, y)`
+    /// which flipped the tiles in `flips`.
+    ///
+    /// Does not check that the move was actually made.
+    pub fn unapply_move(&mut self, x: u8, y: u8, p: Players, flips: &[(u8, u8)]) {
+        let opponent = match p {
+            Players::Black => Players::White,
+            Players::White => Players::Black,
+        };
+        for (flip_x, flip_y) in flips {
+            self.change(*flip_x, *flip_y, States::Taken(opponent));
+        }
+        self.change(x, y, States::Empty);
+    }
+
     /// Returns a list of all valid moves for a given player.
     pub fn get_moves(&self, p: Players) -> Vec<(u8, u8)> {
-        let mut v: Vec<(u8, u8)> = Vec::new();
-        v.reserve(64);
-        for x in 0..8 {
-            for y in 0..8 {
-                if self.can_move(x, y, p) {
-                    v.push((x, y));
+        self.moves_iter(p).collect()
+    }
+
+    /// Returns an iterator over all valid moves for a given player
+    /// without allocating.
+    pub fn moves_iter(&self, p: Players) -> MoveIter {
+        MoveIter { mask: self.move_mask(p) }
+    }
+
+    /// Returns the number of valid moves for a given player.
+    pub fn move_count(&self, p: Players) -> u32 {
+        self.move_mask(p).count_ones()
+    }
+
+    /// Returns the tiles of `player` that can never be flipped as a bitmask,
+    /// where `(x, y)` is bit `8 * x + y`.
+    ///
+    /// A tile is found to be stable when, along each of the four lines through
+    /// it, either the line is full or the tile is next to the edge or to a stable
+    /// tile of its own color. This misses some stable tiles, but never includes
+    /// a tile that can be flipped.
+    pub fn stable_mask(&self, player: Players) -> u64 {
+        const AXES: [(i8, i8); 4] = [(1, 0), (0, 1), (1, 1), (1, -1)];
+        let tile = |x: i8, y: i8| self.at(x as u8, y as u8);
+        let full_line = |x: i8, y: i8, (dx, dy): (i8, i8)| [1, -1].iter().all(
+            |sign| (1..8)
+                .map(|step| tile(x + sign * step * dx, y + sign * step * dy))
+                .take_while(Option::is_some)
+                .all(|state| state != Some(States::Empty))
+        );
+
+        let mut stable: u64 = 0;
+        loop {
+            let mut changed = false;
+            for x in 0..8_i8 {
+                for y in 0..8_i8 {
+                    let bit = 1 << (8 * x + y);
+                    if stable & bit != 0 || tile(x, y) != Some(States::Taken(player)) {
+                        continue;
+                    }
+                    // off the board, or a stable tile of the same color
+                    let anchored = |x: i8, y: i8| tile(x, y).is_none() || stable & (1 << (8 * x + y)) != 0;
+                    if AXES.iter().all(|&(dx, dy)| {
+                        anchored(x + dx, y + dy) || anchored(x - dx, y - dy) || full_line(x, y, (dx, dy))
+                    }) {
+                        stable |= bit;
+                        changed = true;
+                    }
                 }
             }
+            if !changed {
+                break stable;
+            }
         }
-        v.shrink_to_fit();
-        v
     }
 
-    /// Helper for determining if flipping from `(x, y)`
-    /// towards the direction `(dx, dy)` is possible.
+    /// Returns how many of `player`'s tiles are [stable](Board::stable_mask).
+    pub fn stable_count(&self, player: Players) -> u8 {
+        self.stable_mask(player).count_ones() as u8
+    }
+
+    /// Returns the valid moves for a given player as a bitmask,
+    /// where `(x, y)` is bit `8 * x + y`.
+    pub fn move_mask(&self, p: Players) -> u64 {
+        let mut mine: u64 = 0;
+        let mut theirs: u64 = 0;
+        for (y, row) in self.pieces.iter().enumerate() {
+            for (x, tile) in row.iter().enumerate() {
+                match tile {
+                    States::Taken(owner) if *owner == p => mine |= 1 << (8 * x + y),
+                    States::Taken(_) => theirs |= 1 << (8 * x + y),
+                    States::Empty => (),
+                }
+            }
+        }
+        let empty = !(mine | theirs);
+
+        let mut moves = 0;
+        for direction in MASK_SHIFTS {
+            // opponent's tiles reachable from one of ours along direction
+            let mut line = shift_mask(mine, direction) & theirs;
+            for _ in 0..5 {
+                line |= shift_mask(line, direction) & theirs;
+            }
+            moves |= shift_mask(line, direction) & empty;
+        }
+        moves
+    }
+
+    /// Walks from `(x, y)` (not inclusive) towards the direction `(dx, dy)`
+    /// over tiles not belonging to `origin` until reaching a tile that is
+    /// not the opponent's.
     ///
-    /// Returns [Invalid](FlipType::Invalid) if the flip goes off the side of
-    /// the board without ending, [Degenerate](FlipType::Degenerate) if
-    /// the flip flips exactly 0 tiles, and [Valid](FlipType::Valid) otherwise.
-    fn can_flip_toward_help(&self, x: u8, y: u8, dx: u8, dy: u8, origin: Players) -> FlipType {
-        let new_x = x.wrapping_add(dx);
-        let new_y = y.wrapping_add(dy);
-        if let Some(States::Taken(new_player)) = self.at(new_x, new_y) {
-            if origin != new_player {
-                if self.can_flip_toward_help(new_x, new_y, dx, dy, origin) != FlipType::Invalid {
-                    FlipType::Valid
-                } else { FlipType::Invalid }
-            } else { FlipType::Degenerate }
-        } else { FlipType::Invalid }
+    /// Returns the [FlipType] of the walk along with the tiles that would be
+    /// flipped, nearest first, in a fixed buffer (only the first `len`
+    /// entries are meaningful).
+    /// Returns [Invalid](FlipType::Invalid) if the walk goes off the side of
+    /// the board or reaches an empty tile, [Degenerate](FlipType::Degenerate)
+    /// if it would flip exactly 0 tiles, and [Valid](FlipType::Valid) otherwise.
+    ///
+    /// Does not perform bounds checking - a tile that is not on the board
+    /// but which flips onto the board will be considered valid.
+    fn walk_toward(&self, x: u8, y: u8, dx: u8, dy: u8, origin: Players) -> (FlipType, [(u8, u8); 8], usize) {
+        // a line crosses at most 8 tiles, so the buffer can never overflow
+        let mut tiles = [(0, 0); 8];
+        let mut len = 0;
+        let (mut cur_x, mut cur_y) = (x, y);
+        loop {
+            cur_x = cur_x.wrapping_add(dx);
+            cur_y = cur_y.wrapping_add(dy);
+            match self.at(cur_x, cur_y) {
+                Some(States::Taken(player)) if player != origin => {
+                    tiles[len] = (cur_x, cur_y);
+                    len += 1;
+                }
+                Some(States::Taken(_)) => {
+                    let flip_type = if len == 0 { FlipType::Degenerate } else { FlipType::Valid };
+                    break (flip_type, tiles, len);
+                }
+                _ => break (FlipType::Invalid, tiles, len),
+            }
+        }
     }
 
     /// Checks if flipping is valid in a given direction.
@@ -145,7 +692,34 @@ impl Board {
     /// Does not perform bounds checking - a tile that is not on the board
     /// but which flips onto the board will return [true].
     fn can_flip_toward(&self, x: u8, y: u8, dx: u8, dy: u8, origin: Players) -> bool {
-        self.can_flip_toward_help(x, y, dx, dy, origin) == FlipType::Valid
+        // same walk as walk_toward, but without recording tiles
+        let mut any = false;
+        let (mut cur_x, mut cur_y) = (x, y);
+        loop {
+            cur_x = cur_x.wrapping_add(dx);
+            cur_y = cur_y.wrapping_add(dy);
+            match self.at(cur_x, cur_y) {
+                Some(States::Taken(player)) if player != origin => any = true,
+                Some(States::Taken(_)) => break any,
+                _ => break false,
+            }
+        }
+    }
+
+    /// Returns how many tiles `p` would flip by placing a piece at `(x, y)`,
+    /// without changing the board.
+    ///
+    /// Returns 0 if the move is illegal.
+    pub fn count_flips(&self, x: u8, y: u8, p: Players) -> u8 {
+        if self.at(x, y) != Some(States::Empty) {
+            return 0;
+        }
+        AROUND.iter().map(
+            |(dx, dy)| match self.walk_toward(x, y, *dx, *dy, p) {
+                (FlipType::Valid, _, len) => len as u8,
+                _ => 0,
+            }
+        ).sum()
     }
 
     /// Determines if a player can place a piece at `(x, y)`.
@@ -160,76 +734,76 @@ impl Board {
         false
     }
 
-    // does not check x and y values for being on board
-    // If it goes off the side it returns None
-    // If it has no opposite color in between it returns an empty vec
     /// Attempts to flip pieces from `(x, y)` (not inclusive)
     /// towards a certain direction `(dx, dy)`.
     /// Assumes that the [color](Players) of `(x, y)` is `origin`.
     ///
     /// Returns [None] if the flip would go off the board
-    /// and a list of locations that would be flipped otherwise.
+    /// and a list of locations that would be flipped otherwise,
+    /// nearest first.
     /// Does not check bounds - a flip originating off the board
     /// but which flips onto the board will register as valid.
     fn flip_toward(&mut self, x: u8, y: u8, dx: u8, dy: u8, origin: Players) -> Option<Vec<(u8, u8)>> {
-        let new_x = x.wrapping_add(dx);
-        let new_y = y.wrapping_add(dy);
-        if let Some(States::Taken(new_player)) = self.at(new_x, new_y) {
-            if origin != new_player {
-                if let Some(mut future_list) = self.flip_toward(new_x, new_y, dx, dy, origin) {
-                    self.change(new_x, new_y, States::Taken(origin));
-                    future_list.push((new_x, new_y));
-                    Some(future_list)
-                } else { None }
-            } else { Some(Vec::new()) }
-        } else {
-            None
+        match self.walk_toward(x, y, dx, dy, origin) {
+            (FlipType::Invalid, _, _) => None,
+            (_, tiles, len) => {
+                for (flip_x, flip_y) in &tiles[..len] {
+                    self.change(*flip_x, *flip_y, States::Taken(origin));
+                }
+                Some(tiles[..len].to_vec())
+            }
         }
     }
 
     /// Flips all valid pieces in every direction around `(x, y)`
-    /// and returns a list of pieces that would be flipped.
+    /// and returns the flipped pieces grouped by the direction they lie in.
+    /// Directions which flip nothing are left out.
     /// Assumes the move at `(x, y)` has already been made.
-    ///
-    /// If you do not want the list of flipped tiles but still want to check
-    /// if the flip was valid, consider [Board::flip_all_fast].
-    pub fn flip_all(&mut self, x: u8, y: u8) -> Vec<(u8, u8)> {
-        let mut places: Vec<(u8, u8)> = Vec::new();
+    pub fn flip_all_detailed(&mut self, x: u8, y: u8) -> Vec<FlipLine> {
+        let mut lines: Vec<FlipLine> = Vec::new();
         if let Some(States::Taken(origin)) = self.at(x, y) {
             for (dx, dy) in AROUND {
-                if let Some(partial) = self.flip_toward(x, y, dx, dy, origin) {
-                    places.extend(partial);
+                match self.flip_toward(x, y, dx, dy, origin) {
+                    Some(squares) if !squares.is_empty() => {
+                        lines.push(FlipLine { direction: (dx as i8, dy as i8), squares });
+                    }
+                    _ => (),
                 }
             }
         }
-        places
+        lines
     }
 
-    /// Recursive helper for [Board::flip_toward_fast].
-    fn flip_toward_fast_help(&mut self, x: u8, y: u8, dx: u8, dy: u8, origin: Players) -> FlipType {
-        let new_x = x.wrapping_add(dx);
-        let new_y = y.wrapping_add(dy);
-        if let Some(States::Taken(new_player)) = self.at(new_x, new_y) {
-            if origin != new_player {
-                if self.flip_toward_fast_help(new_x, new_y, dx, dy, origin) != FlipType::Invalid {
-                    self.change(new_x, new_y, States::Taken(origin));
-                    FlipType::Valid
-                } else { FlipType::Invalid }
-            } else { FlipType::Degenerate }
-        } else { FlipType::Invalid }
+    /// Flips all valid pieces in every direction around `(x, y)`
+    /// and returns a list of pieces that would be flipped.
+    /// Assumes the move at `(x, y)` has already been made.
+    ///
+    /// If you do not want the list of flipped tiles but still want to check
+    /// if the flip was valid, consider [Board::flip_all_fast].
+    /// If you want to know which direction each tile was flipped from,
+    /// consider [Board::flip_all_detailed].
+    pub fn flip_all(&mut self, x: u8, y: u8) -> Vec<(u8, u8)> {
+        self.flip_all_detailed(x, y)
+            .into_iter()
+            .flat_map(|line| line.squares.into_iter().rev())
+            .collect()
     }
 
     /// Returns whether a flip from `(x, y)` (not inclusive) towards the
     /// direction `(dx, dy)` is valid and flips tiles if it is valid.
     ///
-    /// Returns [Invalid](FlipType::Invalid) if the flip goes off the side of
-    /// the board without ending, [Degenerate](FlipType::Degenerate) if
-    /// the flip flips exactly 0 tiles, and [Valid](FlipType::Valid) otherwise.
-    ///
     /// Does not perform bounds checking - a tile that is not on the board
     /// but which flips onto the board will be considered valid.
     fn flip_toward_fast(&mut self, x: u8, y: u8, dx: u8, dy: u8, origin: Players) -> bool {
-        self.flip_toward_fast_help(x, y, dx, dy, origin) == FlipType::Valid
+        match self.walk_toward(x, y, dx, dy, origin) {
+            (FlipType::Valid, tiles, len) => {
+                for (flip_x, flip_y) in &tiles[..len] {
+                    self.change(*flip_x, *flip_y, States::Taken(origin));
+                }
+                true
+            }
+            _ => false,
+        }
     }
 
     /// Fast version of [Board::flip_all] that flips tiles and returns
@@ -251,17 +825,58 @@ impl Board {
         }
     }
 
-    /// Rotates the board 90 degrees clockwise.
-    pub fn rotate_90(&mut self) {
+    /// Moves every tile at row `i` and column `j` to the position
+    /// `to(i, j)` (also given as row, column).
+    ///
+    /// `to` must be a permutation of the board's positions.
+    fn permute(&mut self, to: impl Fn(usize, usize) -> (usize, usize)) {
         let mut new_pieces = [[States::Empty; 8]; 8];
 
         for i in 0..8 {
             for j in 0..8 {
-                new_pieces[j][7 - i] = self.pieces[i][j].clone();
+                let (new_i, new_j) = to(i, j);
+                new_pieces[new_i][new_j] = self.pieces[i][j];
             }
         }
 
         self.pieces = new_pieces;
+        self.zobrist = self.zobrist();
+    }
+
+    /// Rotates the board 90 degrees clockwise.
+    pub fn rotate_90(&mut self) {
+        self.permute(|i, j| (j, 7 - i));
+    }
+
+    /// Rotates the board 180 degrees.
+    pub fn rotate_180(&mut self) {
+        self.permute(|i, j| (7 - i, 7 - j));
+    }
+
+    /// Rotates the board 270 degrees clockwise (90 degrees counterclockwise).
+    pub fn rotate_270(&mut self) {
+        self.permute(|i, j| (7 - j, i));
+    }
+
+    /// Mirrors the board left to right (`x` becomes `7 - x`).
+    pub fn flip_horizontal(&mut self) {
+        self.permute(|i, j| (i, 7 - j));
+    }
+
+    /// Mirrors the board top to bottom (`y` becomes `7 - y`).
+    pub fn flip_vertical(&mut self) {
+        self.permute(|i, j| (7 - i, j));
+    }
+
+    /// Mirrors the board across the diagonal from `(0, 0)` to `(7, 7)`
+    /// (`x` and `y` are swapped).
+    pub fn flip_main_diagonal(&mut self) {
+        self.permute(|i, j| (j, i));
+    }
+
+    /// Mirrors the board across the diagonal from `(7, 0)` to `(0, 7)`.
+    pub fn flip_anti_diagonal(&mut self) {
+        self.permute(|i, j| (7 - j, 7 - i));
     }
 
     /// Flips the colors of all taken tiles (Black ↔ White).
@@ -275,6 +890,24 @@ impl Board {
                 };
             }
         }
+        self.zobrist = self.zobrist();
+    }
+
+    /// Returns the smallest [compact form](Board::to_compact) over all eight
+    /// symmetries of the board, the [Symmetry] that produces it, and the
+    /// inverse of that symmetry.
+    ///
+    /// Boards that are rotations or reflections of each other share a
+    /// canonical value. A location chosen on the canonical board can be
+    /// mapped back onto this board with the returned inverse.
+    pub fn canonical(&self) -> (u128, Symmetry, Symmetry) {
+        Symmetry::ALL.iter().map(
+            |sym| -> (u128, Symmetry, Symmetry) {
+                let mut b = *self;
+                sym.apply(&mut b);
+                (b.to_compact(), *sym, sym.inverse())
+            }
+        ).min_by_key(|(compact, _, _)| *compact).unwrap()
     }
 
     /// Compact form of gamestate data
@@ -294,31 +927,42 @@ impl Board {
         acc
     }
 
-    pub fn from_compact(mut compact: u128) -> Self {
+    /// Inverse of [Board::to_compact].
+    ///
+    /// Returns [None] if `compact` is not a valid encoding, which happens
+    /// when it is at least `3^64` (any trailing value left over once all 64
+    /// tiles have been decoded).
+    pub fn from_compact(mut compact: u128) -> Option<Self> {
         let mut b = Board::new();
 
         for x in 0..8 {
             for y in 0..8 {
                 let remainder = compact % 3;
-                compact = compact / 3;
+                compact /= 3;
                 b.change(x, y,
                     match remainder {
                         0 => States::Empty,
                         1 => States::Taken(Players::Black),
-                        2 => States::Taken(Players::White),
-                        _ => panic!(""),
+                        _ => States::Taken(Players::White),
                     }
                 );
             }
         }
-        b
+
+        if compact == 0 {
+            Some(b)
+        } else { None }
     }
 }
 
 
 #[cfg(test)]
 mod tests {
+    use rand::Rng;
+    use rand::seq::IndexedRandom;
+
     use super::*;
+    use crate::gameplay::Gamestate;
 
     #[test]
     fn test_rotate_90() {
@@ -350,10 +994,488 @@ mod tests {
         assert_eq!(board.pieces[7][7], States::Taken(Players::White));
     }
 
+    #[test]
+    fn test_counts() {
+        let g = Gamestate::new();
+        assert_eq!(g.board().counts(), (2, 2, 60));
+        assert_eq!(g.board().count(Players::Black), 2);
+        assert_eq!(g.board().count(Players::White), 2);
+
+        let mut board = Board::new();
+        for x in 0..8 {
+            for y in 0..8 {
+                let color = if x < 5 { Players::Black } else { Players::White };
+                board.change(x, y, States::Taken(color));
+            }
+        }
+        assert_eq!(board.counts(), (40, 24, 0));
+        assert_eq!(board.count(Players::Black), 40);
+        assert_eq!(board.count(Players::White), 24);
+        assert_eq!(board.score(), 16);
+    }
+
+    #[test]
+    fn test_from_ascii() {
+        for b in playout_boards(10) {
+            assert_eq!(Board::from_ascii(&b.to_string()), Ok(b));
+            assert_eq!(format!("{b}  \n\n").parse::<Board>(), Ok(b));
+        }
+
+        let b = Board::from_ascii(
+            " 01234567\n\
+             0........\n\
+             1........\n\
+             2........\n\
+             3...WB...\n\
+             4...BW...\n\
+             5........\n\
+             6........\n\
+             7........\n"
+        ).unwrap();
+        assert_eq!(&b, Gamestate::new().board());
+    }
+
+    #[test]
+    fn test_from_ascii_errors() {
+        let good = Gamestate::new().board().to_string();
+        assert_eq!(Board::from_ascii(""), Err(ParseBoardError::BadHeader));
+        assert_eq!(
+            Board::from_ascii(&good[..good.rfind('\n').unwrap()]),
+            Err(ParseBoardError::WrongRowCount(7)),
+        );
+        assert_eq!(
+            Board::from_ascii(&good.replace("3...WB...", "9...WB...")),
+            Err(ParseBoardError::BadRowLabel { row: 3 }),
+        );
+        assert_eq!(
+            Board::from_ascii(&good.replace("3...WB...", "3...WB..")),
+            Err(ParseBoardError::WrongRowLength { row: 3, length: 7 }),
+        );
+        assert_eq!(
+            Board::from_ascii(&good.replace("4...BW...", "4...BX...")),
+            Err(ParseBoardError::BadCharacter { row: 4, column: 4, character: 'X' }),
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let board = *Gamestate::new().board();
+        assert_eq!(board.render(RenderStyle::Ascii), board.to_string());
+        assert_eq!(
+            board.render_marked(RenderStyle::Ascii, &[(3, 2), (3, 3)], None).lines().nth(3),
+            Some("2...*....")
+        );
+
+        let unicode = board.render_marked(RenderStyle::Unicode, &[(3, 2)], None);
+        let lines: Vec<&str> = unicode.lines().collect();
+        assert_eq!(lines.len(), 18);
+        assert_eq!(lines[1], "  ┌─┬─┬─┬─┬─┬─┬─┬─┐");
+        assert_eq!(lines[6], "2 │ │ │ │*│ │ │ │ │");
+        assert_eq!(lines[8], "3 │ │ │ │○│●│ │ │ │");
+        assert_eq!(lines[17], "  └─┴─┴─┴─┴─┴─┴─┴─┘");
+
+        let colored = board.render_marked(RenderStyle::Colored, &[(3, 2)], Some((4, 4)));
+        let lines: Vec<&str> = colored.lines().collect();
+        assert_eq!(lines.len(), 9);
+        assert!(lines[3].contains("\x1b[43;30m * "));
+        assert!(lines[5].contains("\x1b[46;97m ○ "));
+        assert!(lines.iter().skip(1).all(|line| line.ends_with("\x1b[0m")));
+    }
+
+    /// The original recursive implementation of [Board::flip_toward],
+    /// kept as a reference for [test_iterative_flips].
+    fn recursive_flip_toward(b: &mut Board, x: u8, y: u8, dx: u8, dy: u8, origin: Players) -> Option<Vec<(u8, u8)>> {
+        let new_x = x.wrapping_add(dx);
+        let new_y = y.wrapping_add(dy);
+        if let Some(States::Taken(new_player)) = b.at(new_x, new_y) {
+            if origin != new_player {
+                if let Some(mut future_list) = recursive_flip_toward(b, new_x, new_y, dx, dy, origin) {
+                    b.change(new_x, new_y, States::Taken(origin));
+                    future_list.push((new_x, new_y));
+                    Some(future_list)
+                } else { None }
+            } else { Some(Vec::new()) }
+        } else {
+            None
+        }
+    }
+
+    /// The original recursive implementation of [Board::can_flip_toward].
+    fn recursive_can_flip_toward(b: &Board, x: u8, y: u8, dx: u8, dy: u8, origin: Players) -> FlipType {
+        let new_x = x.wrapping_add(dx);
+        let new_y = y.wrapping_add(dy);
+        if let Some(States::Taken(new_player)) = b.at(new_x, new_y) {
+            if origin != new_player {
+                if recursive_can_flip_toward(b, new_x, new_y, dx, dy, origin) != FlipType::Invalid {
+                    FlipType::Valid
+                } else { FlipType::Invalid }
+            } else { FlipType::Degenerate }
+        } else { FlipType::Invalid }
+    }
+
+    #[test]
+    fn test_iterative_flips() {
+        for b in playout_boards(50) {
+            for x in 0..8 {
+                for y in 0..8 {
+                    if b.at(x, y) != Some(States::Empty) {
+                        continue;
+                    }
+                    for p in [Players::Black, Players::White] {
+                        let mut placed = b;
+                        placed.change(x, y, States::Taken(p));
+
+                        let mut expected_board = placed;
+                        let mut expected_flips = Vec::new();
+                        let mut expected_can_move = false;
+                        for (dx, dy) in AROUND {
+                            if recursive_can_flip_toward(&b, x, y, dx, dy, p) == FlipType::Valid {
+                                expected_can_move = true;
+                            }
+                            if let Some(partial) = recursive_flip_toward(&mut expected_board, x, y, dx, dy, p) {
+                                expected_flips.extend(partial);
+                            }
+                        }
+
+                        assert_eq!(b.can_move(x, y, p), expected_can_move);
+
+                        let mut slow = placed;
+                        assert_eq!(slow.flip_all(x, y), expected_flips);
+                        assert_eq!(slow, expected_board);
+
+                        let mut fast = placed;
+                        assert_eq!(fast.flip_all_fast(x, y), !expected_flips.is_empty());
+                        assert_eq!(fast, expected_board);
+                        assert_eq!(fast.zobrist_hash(), fast.zobrist());
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_move_mask() {
+        for b in playout_boards(50) {
+            for p in [Players::Black, Players::White] {
+                let mut legacy: Vec<(u8, u8)> = Vec::new();
+                for x in 0..8 {
+                    for y in 0..8 {
+                        if b.can_move(x, y, p) {
+                            legacy.push((x, y));
+                        }
+                    }
+                }
+
+                assert_eq!(b.get_moves(p), legacy);
+                assert_eq!(b.moves_iter(p).len(), legacy.len());
+                assert_eq!(b.move_count(p) as usize, legacy.len());
+                let mask = b.move_mask(p);
+                for x in 0..8 {
+                    for y in 0..8 {
+                        assert_eq!(mask & (1 << (8 * x + y)) != 0, legacy.contains(&(x, y)));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_apply_move() {
+        for b in playout_boards(20) {
+            for x in 0..8 {
+                for y in 0..8 {
+                    for p in [Players::Black, Players::White] {
+                        let mut applied = b;
+                        match applied.apply_move(x, y, p) {
+                            Some(flips) => {
+                                assert!(b.can_move(x, y, p));
+                                assert!(!flips.is_empty());
+                                assert_eq!(usize::from(b.count_flips(x, y, p)), flips.len());
+                                let mut expected = b;
+                                expected.change(x, y, States::Taken(p));
+                                expected.flip_all_fast(x, y);
+                                assert_eq!(applied, expected);
+
+                                applied.unapply_move(x, y, p, &flips);
+                                assert_eq!(applied, b);
+                                assert_eq!(applied.zobrist_hash(), b.zobrist_hash());
+                            }
+                            None => {
+                                assert!(!b.can_move(x, y, p));
+                                assert_eq!(b.count_flips(x, y, p), 0);
+                                assert_eq!(applied, b);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_flip_all_detailed() {
+        let mut b = Board::from_ascii(
+            " 01234567\n\
+             0........\n\
+             1........\n\
+             2........\n\
+             3....WB..\n\
+             4...WW...\n\
+             5...W.B..\n\
+             6...B....\n\
+             7........"
+        ).unwrap();
+        b.change(3, 3, States::Taken(Players::Black));
+        let mut lines = b.flip_all_detailed(3, 3);
+        lines.sort_by_key(|line| line.direction);
+        assert_eq!(lines, vec![
+            FlipLine { direction: (0, 1), squares: vec![(3, 4), (3, 5)] },
+            FlipLine { direction: (1, 0), squares: vec![(4, 3)] },
+            FlipLine { direction: (1, 1), squares: vec![(4, 4)] },
+        ]);
+        assert_eq!(b.count(Players::White), 0);
+
+        let mut b = Board::from_ascii(
+            " 01234567\n\
+             0.WWWWWWB\n\
+             1........\n\
+             2........\n\
+             3........\n\
+             4........\n\
+             5........\n\
+             6........\n\
+             7........"
+        ).unwrap();
+        b.change(0, 0, States::Taken(Players::Black));
+        assert_eq!(b.flip_all_detailed(0, 0), vec![
+            FlipLine { direction: (1, 0), squares: (1..7).map(|x| (x, 0)).collect() },
+        ]);
+        assert_eq!(b.counts(), (8, 0, 56));
+    }
+
     #[test]
     fn test_compact() {
         for compact in [0, 18273465, 2192384765, 1982736452134, 91278365417926354197236812] {
-            assert_eq!(compact, Board::from_compact(compact).to_compact());
+            assert_eq!(compact, Board::from_compact(compact).unwrap().to_compact());
+        }
+    }
+
+    #[test]
+    fn test_compact_invalid() {
+        assert_eq!(Board::from_compact(3_u128.pow(64)), None);
+        assert_eq!(Board::from_compact(u128::MAX), None);
+        assert!(Board::from_compact(3_u128.pow(64) - 1).is_some());
+    }
+
+    /// Collects every board seen over `games` random playouts.
+    fn playout_boards(games: usize) -> Vec<Board> {
+        let mut r = rand::rng();
+        let mut boards = Vec::new();
+        for _ in 0..games {
+            let mut g = Gamestate::new();
+            loop {
+                boards.push(*g.board());
+                let moves = g.get_moves();
+                if let Some(t) = moves.choose(&mut r) {
+                    g.make_move_fast(*t);
+                } else {
+                    break;
+                }
+            }
+        }
+        boards
+    }
+
+    /// Applies each transform in `steps` to a copy of `board` in order.
+    fn compose<F: Fn(&mut Board)>(board: &Board, steps: &[F]) -> Board {
+        let mut b = *board;
+        for step in steps {
+            step(&mut b);
+        }
+        b
+    }
+
+    #[test]
+    fn test_symmetry_enum() {
+        for b in playout_boards(5) {
+            for sym in Symmetry::ALL {
+                let mut t = b;
+                sym.apply(&mut t);
+                for x in 0..8 {
+                    for y in 0..8 {
+                        let (tx, ty) = sym.map(x, y);
+                        assert_eq!(t.at(tx, ty), b.at(x, y));
+                        assert_eq!(sym.inverse().map(tx, ty), (x, y));
+                    }
+                }
+                sym.inverse().apply(&mut t);
+                assert_eq!(t, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_canonical() {
+        for b in playout_boards(10) {
+            let (compact, sym, inverse) = b.canonical();
+            let mut canon = b;
+            sym.apply(&mut canon);
+            assert_eq!(canon.to_compact(), compact);
+            inverse.apply(&mut canon);
+            assert_eq!(canon, b);
+
+            for transform in Symmetry::ALL {
+                let mut t = b;
+                transform.apply(&mut t);
+                assert!(compact <= t.to_compact());
+                assert_eq!(t.canonical().0, compact);
+            }
+        }
+
+        let mut b = Board::new();
+        b.change(2, 3, States::Taken(Players::Black));
+        b.change(3, 3, States::Taken(Players::Black));
+        b.change(4, 3, States::Taken(Players::White));
+        b.change(3, 4, States::Taken(Players::White));
+        let mut rotated = b;
+        rotated.rotate_90();
+        assert_ne!(rotated, b);
+        assert_eq!(rotated.canonical().0, b.canonical().0);
+    }
+
+    #[test]
+    fn test_zobrist_incremental() {
+        let mut r = rand::rng();
+        for _ in 0..2000 {
+            let mut g = Gamestate::new();
+            loop {
+                assert_eq!(g.board().zobrist_hash(), g.board().zobrist());
+                let moves = g.get_moves();
+                if let Some(t) = moves.choose(&mut r) {
+                    if r.random_bool(0.5) {
+                        g.make_move(*t);
+                    } else {
+                        g.make_move_fast(*t);
+                    }
+                } else {
+                    break;
+                }
+            }
+        }
+        for b in playout_boards(10) {
+            for sym in Symmetry::ALL {
+                let mut t = b;
+                sym.apply(&mut t);
+                assert_eq!(t.zobrist_hash(), t.zobrist());
+            }
+            let mut t = b;
+            t.flip_colors();
+            assert_eq!(t.zobrist_hash(), t.zobrist());
+        }
+    }
+
+    #[test]
+    fn test_stable_tiles() {
+        assert_eq!(Board::new().stable_mask(Players::Black), 0);
+
+        let b = Board::from_ascii(
+            " 01234567\n\
+             0BBBW....\n\
+             1BW......\n\
+             2W.......\n\
+             3...BW...\n\
+             4...WB...\n\
+             5........\n\
+             6........\n\
+             7WWWWWWWW\n"
+        ).unwrap();
+        // the black corner and the black tiles joined to it along both edges
+        assert_eq!(b.stable_count(Players::Black), 4);
+        assert_eq!(b.stable_mask(Players::Black) & 1 << (8 * 2), 1 << (8 * 2));
+        // the full bottom edge, but not the tiles cut off from the black corner
+        assert_eq!(b.stable_count(Players::White), 8);
+
+        // stable tiles never change color for the rest of the game
+        let mut r = rand::rng();
+        for _ in 0..50 {
+            let mut g = Gamestate::new();
+            let mut stable = [0_u64; 2];
+            while let Some(t) = g.get_moves().choose(&mut r) {
+                g.make_move_fast(*t);
+                for (i, p) in [Players::Black, Players::White].into_iter().enumerate() {
+                    let now = g.board().stable_mask(p);
+                    assert_eq!(now & stable[i], stable[i], "\n{}", g.board());
+                    stable[i] = now;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_compact_playouts() {
+        for b in playout_boards(100) {
+            assert_eq!(Board::from_compact(b.to_compact()), Some(b));
+        }
+    }
+
+    #[test]
+    fn test_rotations() {
+        for b in playout_boards(10) {
+            assert_eq!(compose(&b, &[Board::rotate_90; 4]), b);
+            assert_eq!(compose(&b, &[Board::rotate_90; 2]), compose(&b, &[Board::rotate_180]));
+            assert_eq!(compose(&b, &[Board::rotate_90; 3]), compose(&b, &[Board::rotate_270]));
+            assert_eq!(compose(&b, &[Board::rotate_90, Board::rotate_270]), b);
+        }
+    }
+
+    #[test]
+    fn test_dihedral_relations() {
+        for b in playout_boards(10) {
+            for flip in [
+                Board::flip_horizontal,
+                Board::flip_vertical,
+                Board::flip_main_diagonal,
+                Board::flip_anti_diagonal,
+            ] {
+                assert_eq!(compose(&b, &[flip, flip]), b);
+            }
+            assert_eq!(
+                compose(&b, &[Board::flip_main_diagonal, Board::flip_horizontal]),
+                compose(&b, &[Board::rotate_90]),
+            );
+            assert_eq!(
+                compose(&b, &[Board::flip_horizontal, Board::flip_vertical]),
+                compose(&b, &[Board::rotate_180]),
+            );
+            assert_eq!(
+                compose(&b, &[Board::flip_main_diagonal, Board::flip_anti_diagonal]),
+                compose(&b, &[Board::rotate_180]),
+            );
+            assert_eq!(
+                compose(&b, &[Board::rotate_90, Board::flip_horizontal]),
+                compose(&b, &[Board::flip_main_diagonal]),
+            );
+        }
+    }
+
+    #[test]
+    fn test_symmetry_invariants() {
+        for b in playout_boards(10) {
+            for sym in Symmetry::ALL {
+                let mut t = b;
+                sym.apply(&mut t);
+                assert_eq!(t.score(), b.score());
+                for p in [Players::Black, Players::White] {
+                    let mut expected: Vec<(u8, u8)> = b.get_moves(p)
+                        .into_iter()
+                        .map(|(x, y)| sym.map(x, y))
+                        .collect();
+                    let mut actual = t.get_moves(p);
+                    expected.sort();
+                    actual.sort();
+                    assert_eq!(actual, expected);
+                }
+            }
         }
     }
 }
@@ -2,15 +2,24 @@ pub mod data;
 pub mod model_a;
 pub mod model_b;
 
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
 use burn::{
-    data::dataset::InMemDataset,
+    data::dataset::{Dataset, InMemDataset},
     prelude::{Backend, Module}, tensor::{Tensor}
 };
 
 use crate::{
     agent::Agent,
-    gameplay::{Gamestate, Turn},
-    neural::data::compact_to_tensor,
+    agent::implementations::{PuctSelection, RandomAgent, UctDecision},
+    evaluation::Evaluator,
+    gameplay::{Gamestate, Players, Turn},
+    mcst::{ExpansionPolicy, McstAgent, McstNode, McstTree, RolloutError, RolloutPolicy, SymmetricMcstAgent},
+    data::{read_csv, read_weighted_records},
+    neural::data::{board_to_tensor, BinDataset, WeightedDataDataset},
 };
 
 fn create_artifact_dir(artifact_dir: &str) {
@@ -19,18 +28,76 @@ fn create_artifact_dir(artifact_dir: &str) {
     std::fs::create_dir_all(artifact_dir).ok();
 }
 
-fn get_train_data() -> InMemDataset<(u128, f32)> {
-    InMemDataset::<(u128, f32)>::from_csv("train.csv", &csv::ReaderBuilder::new()).unwrap()
+/// Loads a dataset of `(compact, label)` rows from `path`: a
+/// [binary record file](crate::data::write_records) if it ends in `.bin`,
+/// and CSV otherwise.
+pub fn load_dataset(path: &Path) -> io::Result<Box<dyn Dataset<(u128, f32)>>> {
+    if path.extension().is_some_and(|extension| extension == "bin") {
+        Ok(Box::new(BinDataset::open(path)?))
+    } else {
+        InMemDataset::<(u128, f32)>::from_csv(path, &csv::ReaderBuilder::new())
+            .map(|dataset| Box::new(dataset) as Box<dyn Dataset<(u128, f32)>>)
+    }
+}
+
+/// Like [load_dataset], but for `(compact, label, weight)` rows, as written
+/// by [write_weighted_records](crate::data::write_weighted_records) or
+/// [write_weighted_csv](crate::data::write_weighted_csv). Rows written
+/// without a weight have a weight of `1`.
+pub fn load_weighted_dataset(path: &Path) -> io::Result<Box<dyn Dataset<(u128, f32, f32)>>> {
+    let data = if path.extension().is_some_and(|extension| extension == "bin") {
+        read_weighted_records(path)?
+    } else {
+        read_csv(path)?
+    };
+    Ok(Box::new(WeightedDataDataset { data }))
 }
 
-fn get_validation_data() -> InMemDataset<(u128, f32)> {
-    InMemDataset::<(u128, f32)>::from_csv("valid.csv", &csv::ReaderBuilder::new()).unwrap()
+/// Returns `<name>.bin` if there is one, and `<name>.csv` otherwise.
+fn named_dataset_path(name: &str) -> PathBuf {
+    let bin = PathBuf::from(format!("{name}.bin"));
+    if bin.exists() { bin } else { PathBuf::from(format!("{name}.csv")) }
+}
+
+/// Loads `<name>.bin` if there is one, and `<name>.csv` otherwise.
+fn load_named_dataset(name: &str) -> Box<dyn Dataset<(u128, f32)>> {
+    let path = named_dataset_path(name);
+    load_dataset(&path).unwrap_or_else(|e| panic!("couldn't load {}: {e}", path.display()))
+}
+
+/// Like [load_named_dataset], but keeps the weights of the rows.
+fn load_named_weighted_dataset(name: &str) -> Box<dyn Dataset<(u128, f32, f32)>> {
+    let path = named_dataset_path(name);
+    load_weighted_dataset(&path).unwrap_or_else(|e| panic!("couldn't load {}: {e}", path.display()))
+}
+
+fn get_train_data() -> Box<dyn Dataset<(u128, f32)>> {
+    load_named_dataset("train")
+}
+
+fn get_validation_data() -> Box<dyn Dataset<(u128, f32)>> {
+    load_named_dataset("valid")
+}
+
+fn get_weighted_train_data() -> Box<dyn Dataset<(u128, f32, f32)>> {
+    load_named_weighted_dataset("train")
+}
+
+fn get_weighted_validation_data() -> Box<dyn Dataset<(u128, f32, f32)>> {
+    load_named_weighted_dataset("valid")
 }
 
 pub trait StaticNeuralEval {
     type B: Backend;
 
     fn eval(&self, tensor: Tensor<Self::B, 1>) -> f32;
+
+    /// Whether the network was trained on [canonical](crate::mechanics::Board::canonical)
+    /// boards, and so has to be given them to evaluate (see
+    /// [board_to_tensor]). It wasn't by default.
+    fn canonical(&self) -> bool {
+        false
+    }
 }
 
 pub struct ModuleAgent<M, B>
@@ -56,7 +123,7 @@ where
     }
 
     fn eval_state(&self, state: &Gamestate) -> f32 {
-        let in_tensor = compact_to_tensor::<B>(state.board().to_compact(), &self.device);
+        let in_tensor = board_to_tensor::<B>(state.board(), self.module.canonical(), &self.device);
         self.module.eval(in_tensor)
     }
 }
@@ -67,16 +134,8 @@ where
     M: Module<B> + StaticNeuralEval<B = B>
 {
     fn make_move(&self, state: &Gamestate) -> Turn {
-        let moves = state.get_moves();
-        let games = moves
-            .iter()
-            .map(|t: &Turn| {
-                let mut next = state.clone();
-                next.make_move_fast(*t);
-                self.eval_state(&next)
-            });
-        *moves.iter()
-            .zip(games)
+        state.children()
+            .map(|(t, next)| (t, self.eval_state(&next)))
             .max_by(|(_t1, value1), (_t2, value2)| {
                 value1.total_cmp(value2)
             })
@@ -84,3 +143,432 @@ where
             .0
     }
 }
+
+impl<M, B> Evaluator for ModuleAgent<M, B>
+where
+    B: Backend,
+    M: Module<B> + StaticNeuralEval<B = B>
+{
+    /// Judges positions by the network's evaluation, from -1 (White wins) to
+    /// 1 (Black wins), such as where
+    /// [capped rollouts](crate::mcst::AgentRollout::with_cap) stop.
+    fn evaluate(&self, state: &Gamestate) -> f64 {
+        f64::from(self.eval_state(state))
+    }
+}
+
+/// Expansion policy which expands the moves a network thinks best first, and
+/// gives each child the network's [prior](crate::mcst::McstNode::prior) for
+/// its move, for [PuctSelection] to search by.
+///
+/// Until there is a network with a policy head, priors are a softmax over a
+/// value network's evaluations of the positions after each move, from the
+/// point of view of the player making it. They are worked out the first time
+/// a node is expanded and kept until every move from it has been, or until
+/// the node leaves the tree as the root [advances](crate::mcst::McstAgent::advance_by).
+pub struct PriorExpansion<E: StaticNeuralEval> {
+    model: E,
+    device: <E::B as Backend>::Device,
+    /// The priors of the moves from each partly expanded position,
+    /// by its compact board and ply.
+    priors: PriorCache,
+}
+
+/// The priors a [PriorExpansion] keeps, by compact board and ply.
+type PriorCache = HashMap<(u128, u8), Vec<(Turn, f32)>>;
+
+impl<E: StaticNeuralEval> PriorExpansion<E> {
+    /// How much the softmax sharpens the differences between evaluations,
+    /// which go from -1 (White wins) to 1 (Black wins).
+    const TEMPERATURE: f32 = 0.25;
+
+    pub fn new(model: E, device: <E::B as Backend>::Device) -> Self {
+        PriorExpansion { model, device, priors: HashMap::new() }
+    }
+
+    /// Returns the priors of the moves from `game`, evaluating them if they
+    /// haven't been already.
+    fn priors(&mut self, game: &Gamestate) -> &[(Turn, f32)] {
+        let (model, device) = (&self.model, &self.device);
+        self.priors.entry((game.board().to_compact(), game.ply())).or_insert_with(|| {
+            let sign = match game.side_to_move() {
+                Players::Black => 1.0,
+                Players::White => -1.0,
+            };
+            let (turns, values): (Vec<Turn>, Vec<f32>) = game.children()
+                .map(|(turn, next)| {
+                    (turn, sign * model.eval(board_to_tensor::<E::B>(next.board(), model.canonical(), device)))
+                })
+                .unzip();
+            turns.into_iter().zip(softmax(&values, Self::TEMPERATURE)).collect()
+        })
+    }
+}
+
+impl<E: StaticNeuralEval> ExpansionPolicy for PriorExpansion<E> {
+    /// Returns the unexpanded move with the highest prior.
+    fn expand(&mut self, tree: &McstTree, path: &Vec<Turn>) -> Turn {
+        let node = tree.root().search(path).expect("Node from path given invalid path");
+        self.priors(node.game()).iter()
+            .filter(|(turn, _)| !node.children().contains_key(turn))
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(turn, _)| *turn)
+            .unwrap_or_else(|| panic!("No nodes to expand on given path {:?}", path))
+    }
+
+    fn prior(&mut self, tree: &McstTree, path: &[Turn], turn: Turn) -> f32 {
+        let node = tree.root().search(path).expect("Node from path given invalid path");
+        self.priors(node.game()).iter()
+            .find(|(other, _)| *other == turn)
+            .map_or(0.0, |(_, prior)| *prior)
+    }
+
+    /// Forgets the priors of the node at `path` once every move from it has
+    /// been expanded.
+    fn expanded(&mut self, tree: &McstTree, path: &[Turn]) {
+        let node = tree.root().search(path).expect("Node from path given invalid path");
+        if node.children().len() >= node.game().move_count() {
+            self.priors.remove(&(node.game().board().to_compact(), node.game().ply()));
+        }
+    }
+
+    /// Forgets the priors of the positions which were left behind.
+    fn root_advanced(&mut self, tree: &McstTree, _moves: &[Turn]) {
+        // moves the priors of `node` and its descendants from `old` to `kept`
+        fn keep(node: McstNode, old: &mut PriorCache, kept: &mut PriorCache) {
+            let key = (node.game().board().to_compact(), node.game().ply());
+            if let Some(priors) = old.remove(&key) {
+                kept.insert(key, priors);
+            }
+            for child in node.children().values() {
+                if old.is_empty() {
+                    return;
+                }
+                keep(child, old, kept);
+            }
+        }
+        let mut kept = HashMap::new();
+        keep(tree.root(), &mut self.priors, &mut kept);
+        self.priors = kept;
+    }
+
+    fn set_state(&mut self, _state: &Gamestate) {
+        self.priors.clear();
+    }
+}
+
+/// Returns the softmax of `values` at `temperature`: each value's share of
+/// the sum of `exp(value / temperature)`, so the shares add up to 1.
+fn softmax(values: &[f32], temperature: f32) -> Vec<f32> {
+    let most = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let weights: Vec<f32> = values.iter().map(|value| ((value - most) / temperature).exp()).collect();
+    let total: f32 = weights.iter().sum();
+    weights.into_iter().map(|weight| weight / total).collect()
+}
+
+/// Rollout policy which plays at most `plies` random moves and then, rather
+/// than playing the game out, scores the position with a value network. Its
+/// evaluation, from -1 (White wins) to 1 (Black wins), is taken as a win
+/// probability. Games that end within `plies` are scored exactly.
+///
+/// With 0 plies every node is simply scored as it is expanded.
+pub struct NeuralRollout<E: StaticNeuralEval> {
+    model: E,
+    device: <E::B as Backend>::Device,
+    plies: usize,
+    agent: RandomAgent,
+}
+
+impl<E: StaticNeuralEval> NeuralRollout<E> {
+    pub fn new(model: E, device: <E::B as Backend>::Device, plies: usize) -> Self {
+        NeuralRollout { model, device, plies, agent: RandomAgent::new() }
+    }
+}
+
+impl<E: StaticNeuralEval> RolloutPolicy for NeuralRollout<E> {
+    fn rollout(&self, mut game: Gamestate, me: Players) -> Result<f64, RolloutError> {
+        let mut moves = Vec::new();
+        while moves.len() < self.plies && game.move_count() != 0 {
+            let turn = self.agent.try_make_move(&game).map_err(RolloutError::Agent)?;
+            moves.push(turn);
+            if !game.make_move_fast(turn) {
+                return Err(RolloutError::IllegalMove(moves));
+            }
+        }
+
+        let black = if game.move_count() == 0 {
+            match game.score().cmp(&0) {
+                Ordering::Greater => 1.0,
+                Ordering::Equal => 0.5,
+                Ordering::Less => 0.0,
+            }
+        } else {
+            let value = self.model.eval(board_to_tensor::<E::B>(game.board(), self.model.canonical(), &self.device));
+            (f64::from(value).clamp(-1.0, 1.0) + 1.0) / 2.0
+        };
+        Ok(match me {
+            Players::Black => black,
+            Players::White => 1.0 - black,
+        })
+    }
+}
+
+/// Builds an MCTS agent guided by `model` in the manner of AlphaZero, which
+/// expands moves by the priors it gives them and selects by [PUCT](PuctSelection)
+/// with exploration constant `c_puct`, rolling out randomly and playing the
+/// most visited move.
+pub fn neural_mcst_agent<E: StaticNeuralEval>(
+    model: E,
+    device: <E::B as Backend>::Device,
+    c_puct: f64,
+    game: Gamestate,
+) -> SymmetricMcstAgent<PuctSelection, PriorExpansion<E>, UctDecision, RandomAgent> {
+    McstAgent::new(
+        PuctSelection::new(c_puct),
+        PriorExpansion::new(model, device),
+        UctDecision {},
+        RandomAgent::new(),
+        RandomAgent::new(),
+        game,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use burn::backend::Wgpu;
+    use burn::backend::wgpu::WgpuDevice;
+    use burn::data::dataloader::batcher::Batcher;
+    use burn::record::CompactRecorder;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+    use rand::seq::IndexedRandom;
+
+    use super::*;
+    use crate::agent::{play_game_from, MemoryAgent};
+    use crate::agent::implementations::{BfsExpansion, Budget, McstMemoryAgent, UctSelection};
+    use crate::mechanics::Symmetry;
+    use crate::neural::data::{canonical_compact, compact_to_tensor, DataBatcher};
+    use crate::neural::model_a::{Model, ModelConfig};
+
+    #[test]
+    fn test_softmax() {
+        for values in [vec![0.3], vec![-1.0, 0.0, 1.0], vec![0.5, 0.5, 0.5, 0.5], vec![200.0, -200.0]] {
+            let shares = softmax(&values, 0.25);
+            assert!((shares.iter().sum::<f32>() - 1.0).abs() < 1e-6, "{shares:?}");
+            assert!(shares.iter().all(|share| share.is_finite() && *share >= 0.0));
+            // higher values get bigger shares
+            for i in 0..values.len() {
+                for j in 0..values.len() {
+                    assert_eq!(values[i].total_cmp(&values[j]), shares[i].total_cmp(&shares[j]));
+                }
+            }
+        }
+    }
+
+    /// Compares loading 10k records from CSV and from a binary record file,
+    /// run with `cargo test --release -- --ignored --nocapture`.
+    #[test]
+    #[ignore = "benchmark"]
+    fn bench_load_dataset() {
+        let dir = std::env::temp_dir().join(format!("othello-load-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (csv_path, bin_path) = (dir.join("train.csv"), dir.join("train.bin"));
+        let mut rng = StdRng::seed_from_u64(95);
+        let records: Vec<(u128, f32)> = (0..10_000)
+            .map(|_| (rng.random_range(0..3_u128.pow(64)), rng.random()))
+            .collect();
+        let rows: String = records.iter().map(|(compact, label)| format!("{compact},{label}\n")).collect();
+        std::fs::write(&csv_path, format!("board,label\n{rows}")).unwrap();
+        crate::data::write_records(&bin_path, &records).unwrap();
+
+        for path in [&csv_path, &bin_path] {
+            let start = std::time::Instant::now();
+            let dataset = load_dataset(path).unwrap();
+            let elapsed = start.elapsed();
+            assert_eq!((0..dataset.len()).map(|i| dataset.get(i).unwrap()).collect::<Vec<_>>(), records);
+            println!(
+                "{}: {} bytes loaded in {elapsed:?}",
+                path.display(),
+                std::fs::metadata(path).unwrap().len(),
+            );
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Values every position as even.
+    struct Even {}
+
+    impl StaticNeuralEval for Even {
+        type B = Wgpu;
+
+        fn eval(&self, _tensor: Tensor<Wgpu, 1>) -> f32 {
+            0.0
+        }
+    }
+
+    #[test]
+    fn test_prior_expansion_forgets() {
+        let mut expander = PriorExpansion::new(Even {}, WgpuDevice::default());
+        let g = Gamestate::new();
+        let tree = McstTree::new(g.clone());
+        // expanding a move from the root works out the priors of them all
+        let first = expander.expand(&tree, &vec![]);
+        assert!((expander.prior(&tree, &[], first) - 0.25).abs() < 1e-6);
+        assert_eq!(expander.priors.len(), 1);
+        // which are kept while the root is in the tree
+        expander.root_advanced(&tree, &[]);
+        assert_eq!(expander.priors.len(), 1);
+        // and forgotten once it isn't
+        let mut next = g.clone();
+        next.make_move_fast(first);
+        expander.root_advanced(&McstTree::new(next.clone()), &[first]);
+        assert!(expander.priors.is_empty());
+        expander.expand(&McstTree::new(next.clone()), &vec![]);
+        expander.set_state(&next);
+        assert!(expander.priors.is_empty());
+    }
+
+    /// Expands every move at once, with the priors a [PriorExpansion] gives them.
+    struct ExpandAll(PriorExpansion<Even>);
+
+    impl ExpansionPolicy for ExpandAll {
+        fn expand(&mut self, tree: &McstTree, path: &Vec<Turn>) -> Turn {
+            self.0.expand(tree, path)
+        }
+
+        fn expand_all(&mut self, tree: &McstTree, path: &Vec<Turn>) -> Vec<Turn> {
+            let node = tree.root().search(path).expect("Node from path given invalid path");
+            node.game().children().map(|(turn, _)| turn).collect()
+        }
+
+        fn prior(&mut self, tree: &McstTree, path: &[Turn], turn: Turn) -> f32 {
+            self.0.prior(tree, path, turn)
+        }
+
+        fn expanded(&mut self, tree: &McstTree, path: &[Turn]) {
+            self.0.expanded(tree, path)
+        }
+    }
+
+    #[test]
+    fn test_prior_expansion_forgets_expanded() {
+        let g = Gamestate::new();
+        // the priors of a node are kept while it has moves left to expand
+        let mut agent = McstAgent::new(
+            UctSelection::new(1.4),
+            PriorExpansion::new(Even {}, WgpuDevice::default()),
+            UctDecision {},
+            RandomAgent::new(),
+            RandomAgent::new(),
+            g.clone(),
+        );
+        agent.cycle_n(3).unwrap();
+        assert_eq!(agent.expander().priors.len(), 1);
+        // and forgotten once it has none
+        agent.cycle().unwrap();
+        assert_eq!(agent.tree().root().children().len(), g.move_count());
+        assert!(agent.expander().priors.is_empty());
+
+        // all at once too
+        let mut agent = McstAgent::new(
+            UctSelection::new(1.4),
+            ExpandAll(PriorExpansion::new(Even {}, WgpuDevice::default())),
+            UctDecision {},
+            RandomAgent::new(),
+            RandomAgent::new(),
+            g.clone(),
+        );
+        agent.cycle().unwrap();
+        assert_eq!(agent.tree().root().children().len(), g.move_count());
+        assert!(agent.expander().0.priors.is_empty());
+    }
+
+    #[test]
+    fn test_canonical_input() {
+        let device = WgpuDevice::default();
+        let input = |tensor: Tensor<Wgpu, 1>| tensor.to_data().to_vec::<f32>().unwrap();
+        let mut game = Gamestate::new();
+        game.make_moves_fast(&[Some((4, 5)), Some((5, 3))]);
+        let board = *game.board();
+        let expected = input(board_to_tensor::<Wgpu>(&board, true, &device));
+        let mut raw_inputs = Vec::new();
+        for symmetry in Symmetry::ALL {
+            let mut turned = board;
+            symmetry.apply(&mut turned);
+            // the board as it is round trips to a different input for most
+            // orientations, but its canonical board doesn't
+            let raw = input(compact_to_tensor::<Wgpu>(turned.to_compact(), &device));
+            assert_eq!(input(board_to_tensor::<Wgpu>(&turned, false, &device)), raw);
+            assert_eq!(input(board_to_tensor::<Wgpu>(&turned, true, &device)), expected);
+            assert_eq!(
+                input(compact_to_tensor::<Wgpu>(canonical_compact(turned.to_compact()), &device)),
+                expected,
+            );
+            // and training sees the same input as evaluation does, either way
+            let batch = DataBatcher { canonical: true }.batch(vec![(turned.to_compact(), 1.0)], &device);
+            assert_eq!(input(batch.states.reshape([64 * 3])), expected);
+            let batch = DataBatcher::default().batch(vec![(turned.to_compact(), 1.0)], &device);
+            assert_eq!(input(batch.states.reshape([64 * 3])), raw);
+            raw_inputs.push(raw);
+        }
+        assert!(raw_inputs.iter().any(|raw| *raw != expected));
+
+        // a model trained on canonical boards is given them to evaluate
+        assert!(!ModelConfig::new().init::<Wgpu>(&device).canonical());
+        assert!(ModelConfig::new().with_canonical(true).init::<Wgpu>(&device).canonical());
+    }
+
+    /// Needs a model_a checkpoint trained by `main`, given by its path without
+    /// the extension, such as `MODEL_A=artifacts/model`.
+    #[test]
+    #[ignore = "needs a trained model_a checkpoint in $MODEL_A"]
+    fn test_neural_rollout() {
+        type B = Wgpu<f32, i32>;
+        let device = WgpuDevice::default();
+        let path = std::env::var("MODEL_A").expect("MODEL_A should name a model_a checkpoint");
+        let model: Model<B> = ModelConfig::new().init::<B>(&device)
+            .load_file(path, &CompactRecorder::new(), &device)
+            .expect("MODEL_A should be a model_a checkpoint");
+
+        // given the same time per move, rollouts cut short by the network
+        // play better than random rollouts to the end
+        let budget = Budget::Millis(200);
+        let new_agent = |neural: bool, game: Gamestate| {
+            let (selector, expander, decider) = (UctSelection::new(2_f64.sqrt()), BfsExpansion {}, UctDecision {});
+            match neural {
+                true => Box::new(McstMemoryAgent::with_budget(
+                    McstAgent::with_rollout(selector, expander, decider, NeuralRollout::new(model.clone(), device.clone(), 4), game),
+                    budget,
+                )) as Box<dyn MemoryAgent>,
+                false => Box::new(McstMemoryAgent::with_budget(
+                    McstAgent::new(selector, expander, decider, RandomAgent::new(), RandomAgent::new(), game),
+                    budget,
+                )),
+            }
+        };
+
+        let mut rng = StdRng::seed_from_u64(1);
+        let (mut neural_wins, mut random_wins) = (0, 0);
+        for _ in 0..5 {
+            let mut g = Gamestate::new();
+            for _ in 0..4 {
+                g.make_move_fast(*g.get_moves().choose(&mut rng).unwrap());
+            }
+            for neural_black in [true, false] {
+                let mut neural = new_agent(true, g.clone());
+                let mut random = new_agent(false, g.clone());
+                let (result, _) = match neural_black {
+                    true => play_game_from(&mut *neural, &mut *random, g.clone()),
+                    false => play_game_from(&mut *random, &mut *neural, g.clone()),
+                };
+                match result.winner() {
+                    Some(Players::Black) if neural_black => neural_wins += 1,
+                    Some(Players::White) if !neural_black => neural_wins += 1,
+                    Some(_) => random_wins += 1,
+                    None => (),
+                }
+            }
+        }
+        assert!(neural_wins > random_wins, "{neural_wins} {random_wins}");
+    }
+}
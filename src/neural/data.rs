@@ -3,13 +3,31 @@ use burn::{
     prelude::*,
 };
 
-#[derive(Clone)]
-pub struct DataBatcher {}
+use std::io;
+use std::path::Path;
+
+use crate::data::read_records;
+use crate::mechanics::Board;
+
+/// Batches `(compact, win rate)` rows, or `(compact, win rate, weight)`
+/// rows, for training.
+#[derive(Clone, Default)]
+pub struct DataBatcher {
+    /// Whether every board is [canonicalized](canonical_compact) first, so
+    /// that a network trained on a dataset of raw boards and one trained on
+    /// the same dataset [deduplicated](crate::data::dedup_file) see the same
+    /// boards. A network trained this way has to be given canonical boards to
+    /// evaluate too (see [board_to_tensor] and
+    /// [ModelConfig::canonical](super::model_a::ModelConfig::canonical)).
+    pub canonical: bool,
+}
 
 #[derive(Clone, Debug)]
 pub struct DataBatch<B: Backend> {
     pub states: Tensor<B, 2, Float>,
     pub targets: Tensor<B, 2, Float>,
+    /// How much each row counts towards the loss, if the rows were weighted.
+    pub weights: Option<Tensor<B, 2, Float>>,
 }
 
 pub fn compact_to_tensor<B: Backend>(mut compact: u128, device: &B::Device) -> Tensor<B, 1> {
@@ -27,11 +45,36 @@ pub fn compact_to_tensor<B: Backend>(mut compact: u128, device: &B::Device) -> T
     Tensor::from_data(v, device)
 }
 
+/// Returns the [canonical](Board::canonical) board of a compact board, which
+/// is what networks are trained on and given to evaluate.
+///
+/// # Panics
+///
+/// Panics if `compact` is not a [compact board](Board::to_compact).
+pub fn canonical_compact(compact: u128) -> u128 {
+    Board::from_compact(compact).expect("not a compact board").canonical().0
+}
+
+/// Returns the network input for `board`. With `canonical`, it is the tensor
+/// of its [canonical](Board::canonical) board, for a network trained on
+/// canonical boards (see [DataBatcher::canonical]), which then judges every
+/// rotation and reflection of a board alike.
+pub fn board_to_tensor<B: Backend>(board: &Board, canonical: bool, device: &B::Device) -> Tensor<B, 1> {
+    match canonical {
+        true => compact_to_tensor(board.canonical().0, device),
+        false => compact_to_tensor(board.to_compact(), device),
+    }
+}
+
 impl<B: Backend> Batcher<B, (u128, f32), DataBatch<B>> for DataBatcher {
     fn batch(&self, items: Vec<(u128, f32)>, device: &B::Device) -> DataBatch<B> {
         let states = items
             .iter()
-            .map(|(compact, _)| -> Tensor<B, 1> {compact_to_tensor(*compact, device)})
+            .map(|(compact, _)| match self.canonical {
+                true => canonical_compact(*compact),
+                false => *compact,
+            })
+            .map(|compact| -> Tensor<B, 1> {compact_to_tensor(compact, device)})
             .map(|t| -> Tensor<B, 2> {t.reshape([1, 64 * 3])})
             .collect();
 
@@ -44,7 +87,18 @@ impl<B: Backend> Batcher<B, (u128, f32), DataBatch<B>> for DataBatcher {
         let states = Tensor::cat(states, 0);
         let targets = Tensor::cat(targets, 0);
 
-        DataBatch { states, targets }
+        DataBatch { states, targets, weights: None }
+    }
+}
+
+impl<B: Backend> Batcher<B, (u128, f32, f32), DataBatch<B>> for DataBatcher {
+    fn batch(&self, items: Vec<(u128, f32, f32)>, device: &B::Device) -> DataBatch<B> {
+        let weights: Vec<f32> = items.iter().map(|(_, _, weight)| *weight).collect();
+        let weights = Tensor::from_data(TensorData::new(weights, [items.len(), 1]), device);
+        let labeled = items.into_iter().map(|(compact, label, _)| (compact, label)).collect();
+
+        let batch = <Self as Batcher<B, (u128, f32), DataBatch<B>>>::batch(self, labeled, device);
+        DataBatch { weights: Some(weights), ..batch }
     }
 }
 
@@ -61,3 +115,42 @@ impl Dataset<(u128, f32)> for DataDataset {
     }
 }
 
+/// Like [DataDataset], but of `(compact, win rate, weight)` rows, such as
+/// those read by [load_weighted_dataset](super::load_weighted_dataset).
+pub struct WeightedDataDataset {
+    pub data: Vec<(u128, f32, f32)>,
+}
+
+impl Dataset<(u128, f32, f32)> for WeightedDataDataset {
+    fn get(&self, index: usize) -> Option<(u128, f32, f32)> {
+        self.data.get(index).copied()
+    }
+
+    fn len(&self) -> usize {
+        self.data.len()
+    }
+}
+
+/// Dataset of the records of a [binary record file](crate::data::write_records),
+/// read into memory in one go.
+pub struct BinDataset {
+    records: Vec<(u128, f32)>,
+}
+
+impl BinDataset {
+    /// Reads the records at `path`, failing if it isn't a whole record file
+    /// (see [read_records]).
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Ok(BinDataset { records: read_records(path)? })
+    }
+}
+
+impl Dataset<(u128, f32)> for BinDataset {
+    fn get(&self, index: usize) -> Option<(u128, f32)> {
+        self.records.get(index).copied()
+    }
+
+    fn len(&self) -> usize {
+        self.records.len()
+    }
+}
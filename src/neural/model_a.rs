@@ -1,5 +1,10 @@
+use std::sync::Arc;
+
 use burn::{
-    data::{dataloader::DataLoaderBuilder},
+    data::{
+        dataloader::{batcher::Batcher, DataLoader, DataLoaderBuilder},
+        dataset::Dataset,
+    },
     nn::{loss::MseLoss, Dropout, DropoutConfig, Linear, LinearConfig, Relu},
     optim::AdamConfig,
     prelude::*,
@@ -13,13 +18,19 @@ use burn::{
 
 use super::{
     data::{DataBatch, DataBatcher},
-    create_artifact_dir,  get_train_data, get_validation_data, StaticNeuralEval
+    create_artifact_dir,  get_train_data, get_validation_data, get_weighted_train_data,
+    get_weighted_validation_data, StaticNeuralEval
 };
 
 #[derive(Config, Debug)]
 pub struct ModelConfig {
     #[config(default = "0.3")]
     dropout: f64,
+    /// Train on and evaluate [canonical](crate::mechanics::Board::canonical)
+    /// boards rather than boards as they are (see
+    /// [DataBatcher::canonical]).
+    #[config(default = false)]
+    pub canonical: bool,
 }
 
 impl ModelConfig {
@@ -32,6 +43,7 @@ impl ModelConfig {
             linear3: LinearConfig::new(100, 100).init(device),
             linear4: LinearConfig::new(100, 100).init(device),
             activation: Relu::new(),
+            canonical: self.canonical,
         }
     }
 }
@@ -44,6 +56,7 @@ pub struct Model<B: Backend> {
     linear3: Linear<B>,
     linear4: Linear<B>,
     activation: Relu,
+    canonical: bool,
 }
 
 impl<B: Backend> Model<B> {
@@ -69,19 +82,39 @@ impl<B: Backend> Model<B> {
         x
     }
 
+    /// Returns the output for `states` with its loss against `targets`,
+    /// which is the mean squared error, weighted by `weights`
+    /// if there are any.
     pub fn forward_step(
         &self,
         states: Tensor<B, 2>,
         targets: Tensor<B, 2, Float>,
+        weights: Option<Tensor<B, 2, Float>>,
     ) -> RegressionOutput<B> {
         let output = self.forward(states);
-        let loss = MseLoss::new()
-            .forward(output.clone(), targets.clone(), nn::loss::Reduction::Mean);
+        let loss = mse_loss(output.clone(), targets.clone(), weights);
 
         RegressionOutput::new(loss, output, targets)
     }
 }
 
+/// Returns the mean squared error of `output` against `targets`. With
+/// `weights` (one for each row), each row's error counts as many times as
+/// its weight, so a row of weight 2 counts as much as two rows of weight 1.
+fn mse_loss<B: Backend>(
+    output: Tensor<B, 2>,
+    targets: Tensor<B, 2>,
+    weights: Option<Tensor<B, 2>>,
+) -> Tensor<B, 1> {
+    match weights {
+        None => MseLoss::new().forward(output, targets, nn::loss::Reduction::Mean),
+        Some(weights) => {
+            let errors = MseLoss::new().forward_no_reduction(output, targets).mean_dim(1);
+            (errors * weights.clone()).sum() / weights.sum()
+        }
+    }
+}
+
 impl<Be: Backend> StaticNeuralEval for Model<Be> {
     type B = Be;
 
@@ -90,6 +123,10 @@ impl<Be: Backend> StaticNeuralEval for Model<Be> {
         result.to_data().to_vec().unwrap()[0]
     }
 
+    fn canonical(&self) -> bool {
+        self.canonical
+    }
+
 //    fn eval(&self, state: &Gamestate, device: &<<Self as StaticNeuralEval>::B as Backend>::Device) -> f64 {
 //        let result = self.forward(compact_to_tensor::<Be>(state.board().to_compact(), device).reshape([1, 3 * 64]));
 //        result.to_data().to_vec().unwrap()[0]
@@ -98,7 +135,7 @@ impl<Be: Backend> StaticNeuralEval for Model<Be> {
 
 impl<B: AutodiffBackend> TrainStep<DataBatch<B>, RegressionOutput<B>> for Model<B> {
     fn step(&self, batch: DataBatch<B>) -> TrainOutput<RegressionOutput<B>> {
-        let item = self.forward_step(batch.states, batch.targets);
+        let item = self.forward_step(batch.states, batch.targets, batch.weights);
 
         TrainOutput::new(self, item.loss.backward(), item)
     }
@@ -106,7 +143,7 @@ impl<B: AutodiffBackend> TrainStep<DataBatch<B>, RegressionOutput<B>> for Model<
 
 impl<B: Backend> ValidStep<DataBatch<B>, RegressionOutput<B>> for Model<B> {
     fn step(&self, batch: DataBatch<B>) -> RegressionOutput<B> {
-        self.forward_step(batch.states, batch.targets)
+        self.forward_step(batch.states, batch.targets, batch.weights)
     }
 }
 
@@ -124,6 +161,29 @@ pub struct TrainingConfig {
     pub seed: u64,
     #[config(default = 1.0e-4)]
     pub learning_rate: f64,
+    /// Train on the weights of the datasets' rows (see
+    /// [load_weighted_dataset](super::load_weighted_dataset)), rather than
+    /// counting every row once.
+    #[config(default = false)]
+    pub weighted: bool,
+}
+
+/// Loads `dataset` in shuffled batches, as `config` says.
+fn dataloader<B, I>(
+    batcher: DataBatcher,
+    dataset: Box<dyn Dataset<I>>,
+    config: &TrainingConfig,
+) -> Arc<dyn DataLoader<B, DataBatch<B>>>
+where
+    B: Backend,
+    I: Send + Sync + Clone + std::fmt::Debug + 'static,
+    DataBatcher: Batcher<B, I, DataBatch<B>>,
+{
+    DataLoaderBuilder::new(batcher)
+        .batch_size(config.batch_size)
+        .shuffle(config.seed)
+        .num_workers(config.num_workers)
+        .build(dataset)
 }
 
 pub fn train<B: AutodiffBackend>(artifact_dir: &str, config: TrainingConfig, device: B::Device) {
@@ -133,19 +193,21 @@ pub fn train<B: AutodiffBackend>(artifact_dir: &str, config: TrainingConfig, dev
 
     B::seed(config.seed);
 
-    let batcher = DataBatcher {};
+    let batcher = DataBatcher { canonical: config.model.canonical };
 
-    let dataloader_train = DataLoaderBuilder::new(batcher.clone())
-        .batch_size(config.batch_size)
-        .shuffle(config.seed)
-        .num_workers(config.num_workers)
-        .build(get_train_data());
-
-    let dataloader_test = DataLoaderBuilder::new(batcher)
-        .batch_size(config.batch_size)
-        .shuffle(config.seed)
-        .num_workers(config.num_workers)
-        .build(get_validation_data());
+    // the rows are batched alike either way, but weighted rows give batches
+    // with weights, which the loss is then weighted by
+    let (dataloader_train, dataloader_test) = if config.weighted {
+        (
+            dataloader(batcher.clone(), get_weighted_train_data(), &config),
+            dataloader(batcher, get_weighted_validation_data(), &config),
+        )
+    } else {
+        (
+            dataloader(batcher.clone(), get_train_data(), &config),
+            dataloader(batcher, get_validation_data(), &config),
+        )
+    };
 
     let learner = LearnerBuilder::new(artifact_dir)
         .metric_train_numeric(LossMetric::new())
@@ -167,3 +229,43 @@ pub fn train<B: AutodiffBackend>(artifact_dir: &str, config: TrainingConfig, dev
         .save_file(format!("{artifact_dir}/model"), &CompactRecorder::new())
         .expect("Trained model should be saved successfully");
 }
+
+#[cfg(test)]
+mod tests {
+    use burn::backend::{Autodiff, Wgpu};
+    use burn::backend::wgpu::WgpuDevice;
+    use burn::optim::{GradientsParams, Optimizer, SgdConfig};
+
+    use super::*;
+    use crate::gameplay::Gamestate;
+
+    #[test]
+    fn test_weighted_loss() {
+        type B = Autodiff<Wgpu>;
+        let device = WgpuDevice::default();
+        // three losses and a win, the win counting as much as thirty games
+        let compact = Gamestate::new().board().to_compact();
+        let rows = vec![(compact, 0.0, 1.0), (compact, 0.0, 1.0), (compact, 0.0, 1.0), (compact, 1.0, 30.0)];
+        let batch: DataBatch<B> = DataBatcher::default().batch(rows, &device);
+        assert_eq!(batch.weights.as_ref().unwrap().dims(), [4, 1]);
+
+        // fits a model whose only parameter is its output to the batch
+        let fit = |weights: Option<Tensor<B, 2>>| -> f32 {
+            let mut model = LinearConfig::new(1, 1).with_bias(false).init::<B>(&device);
+            let mut optimizer = SgdConfig::new().init();
+            let ones = Tensor::<B, 2>::ones([4, 1], &device);
+            for _ in 0..200 {
+                let output = model.forward(ones.clone());
+                let loss = mse_loss(output, batch.targets.clone(), weights.clone());
+                let grads = GradientsParams::from_grads(loss.backward(), &model);
+                model = optimizer.step(0.1, model, grads);
+            }
+            model.forward(ones.slice([0..1, 0..1])).into_scalar()
+        };
+        // targets are from -1 for a loss to 1 for a win
+        let unweighted = fit(None);
+        assert!((unweighted - -0.5).abs() < 1e-3, "{unweighted}");
+        let weighted = fit(batch.weights.clone());
+        assert!((weighted - 27.0 / 33.0).abs() < 1e-3, "{weighted}");
+    }
+}
@@ -0,0 +1,184 @@
+use crate::gameplay::Turn;
+
+/// How the value stored in a [TableEntry] relates to the position's true value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bound {
+    /// The value is exact.
+    Exact,
+    /// The search was cut off, so the true value is at least this.
+    Lower,
+    /// No move reached the search window, so the true value is at most this.
+    Upper,
+}
+
+/// The result of searching one position, as stored in a [TranspositionTable].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TableEntry {
+    /// The full hash of the position, to tell apart positions sharing a slot.
+    pub key: u64,
+    /// How many plies deep the position was searched.
+    pub depth: u32,
+    pub bound: Bound,
+    /// The value for the side to move.
+    pub value: f64,
+    /// The best move found, or [None] if no move was searched.
+    pub best: Option<Turn>,
+    /// The [search](TranspositionTable::new_search) the entry was stored in.
+    generation: u32,
+}
+
+impl TableEntry {
+    /// Creates a new entry, which belongs to whichever search it is stored in.
+    pub fn new(key: u64, depth: u32, bound: Bound, value: f64, best: Option<Turn>) -> Self {
+        TableEntry { key, depth, bound, value, best, generation: 0 }
+    }
+}
+
+/// A fixed-size table of search results keyed by
+/// [Zobrist hash](crate::gameplay::Gamestate::zobrist_hash).
+///
+/// Each hash maps to a single slot. A slot is overwritten by results for
+/// the same position, by results at least as deep as the one it holds, and
+/// by anything once its own result is left over from an earlier search.
+pub struct TranspositionTable {
+    entries: Vec<Option<TableEntry>>,
+    generation: u32,
+    probes: u64,
+    hits: u64,
+    stores: u64,
+}
+
+impl TranspositionTable {
+    /// Creates an empty table with room for at least `capacity` entries
+    /// (rounded up to a power of two).
+    pub fn new(capacity: usize) -> Self {
+        TranspositionTable {
+            entries: vec![None; capacity.max(1).next_power_of_two()],
+            generation: 0,
+            probes: 0,
+            hits: 0,
+            stores: 0,
+        }
+    }
+
+    /// Returns how many entries the table can hold.
+    pub fn capacity(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns the slot `key` maps to.
+    fn slot(&self, key: u64) -> usize {
+        (key & (self.entries.len() as u64 - 1)) as usize
+    }
+
+    /// Marks every entry stored so far as belonging to an earlier search,
+    /// so that they are replaced first.
+    pub fn new_search(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    /// Looks up the entry for `key`, if there is one.
+    pub fn probe(&mut self, key: u64) -> Option<TableEntry> {
+        self.probes += 1;
+        let entry = self.entries[self.slot(key)].filter(|entry| entry.key == key);
+        if entry.is_some() {
+            self.hits += 1;
+        }
+        entry
+    }
+
+    /// Stores `entry` unless its slot holds a deeper result for another
+    /// position from the current search.
+    pub fn store(&mut self, mut entry: TableEntry) {
+        entry.generation = self.generation;
+        let slot = self.slot(entry.key);
+        let replace = match &self.entries[slot] {
+            None => true,
+            Some(old) => old.key == entry.key
+                || old.generation != self.generation
+                || entry.depth >= old.depth,
+        };
+        if replace {
+            self.stores += 1;
+            self.entries[slot] = Some(entry);
+        }
+    }
+
+    /// Removes every entry, leaving the statistics alone.
+    pub fn clear(&mut self) {
+        self.entries.fill(None);
+    }
+
+    /// Returns how many times the table has been [probed](TranspositionTable::probe).
+    pub fn probes(&self) -> u64 {
+        self.probes
+    }
+
+    /// Returns how many probes found an entry.
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// Returns how many entries have been stored, counting replacements.
+    pub fn stores(&self) -> u64 {
+        self.stores
+    }
+
+    /// Returns the fraction of probes which found an entry,
+    /// or `0` if there have been none.
+    pub fn hit_rate(&self) -> f64 {
+        if self.probes == 0 {
+            0.0
+        } else {
+            self.hits as f64 / self.probes as f64
+        }
+    }
+
+    /// Resets the probe, hit and store counts.
+    pub fn reset_stats(&mut self) {
+        self.probes = 0;
+        self.hits = 0;
+        self.stores = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replacement() {
+        let mut table = TranspositionTable::new(3);
+        assert_eq!(table.capacity(), 4);
+        assert_eq!(table.probe(1), None);
+
+        table.store(TableEntry::new(1, 3, Bound::Exact, 1.0, Some(None)));
+        assert_eq!(table.probe(1).map(|entry| entry.value), Some(1.0));
+        // same slot, but a different position
+        assert_eq!(table.probe(5), None);
+
+        // shallower results for other positions don't replace deeper ones...
+        table.store(TableEntry::new(5, 2, Bound::Lower, 2.0, None));
+        assert_eq!(table.probe(5), None);
+        // ...but results for the same position always do
+        table.store(TableEntry::new(1, 1, Bound::Upper, 3.0, None));
+        assert_eq!(table.probe(1).map(|entry| entry.bound), Some(Bound::Upper));
+        table.store(TableEntry::new(5, 1, Bound::Lower, 2.0, None));
+        assert_eq!(table.probe(5).map(|entry| entry.depth), Some(1));
+
+        // anything replaces results left over from an earlier search
+        table.store(TableEntry::new(9, 8, Bound::Exact, 4.0, None));
+        table.new_search();
+        table.store(TableEntry::new(13, 0, Bound::Exact, 5.0, None));
+        assert_eq!(table.probe(9), None);
+        assert!(table.probe(13).is_some());
+
+        assert_eq!(table.probes(), 8);
+        assert_eq!(table.hits(), 4);
+        assert_eq!(table.stores(), 5);
+        table.clear();
+        assert_eq!(table.probe(13), None);
+        table.reset_stats();
+        assert_eq!(table.hit_rate(), 0.0);
+    }
+}
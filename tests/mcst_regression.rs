@@ -0,0 +1,67 @@
+//! Golden tests of whole MCTS searches and games. Every search here runs a
+//! set number of cycles with seeded rollouts, so it goes the same way on
+//! every run, and a change to how searches select, expand or back up
+//! rewards which changes how they play shows up as a failure here.
+
+use othello::agent::implementations::{Budget, McstMemoryAgent};
+use othello::agent::play_game;
+use othello::data::turns_to_transcript;
+use othello::gameplay::{alg_to_loc, GameResult, Gamestate};
+use othello::mcst::{McstAgent, NodeStorage};
+
+/// Searches `game` for `cycles` cycles with the
+/// [standard agent](McstAgent::seeded_default) seeded with `seed`,
+/// and returns the move it decides on.
+fn decide(seed: u64, game: Gamestate, cycles: usize) -> String {
+    let mut agent = McstAgent::seeded_default(seed, game);
+    assert_eq!(agent.cycle_n(cycles).unwrap(), cycles);
+    turns_to_transcript(&[agent.decide().unwrap()])
+}
+
+#[test]
+fn opening_is_a_book_move() {
+    // the four opening moves are the same up to symmetry
+    for seed in 0..3 {
+        let turn = decide(seed, Gamestate::new(), 10_000);
+        assert!(["d3", "c4", "f5", "e6"].contains(&turn.as_str()), "opened with {turn}");
+    }
+}
+
+#[test]
+fn takes_winning_corners() {
+    // positions where taking the one corner on offer wins and every other
+    // move loses with perfect play
+    let positions = [
+        // Black to move with 14 empties
+        ("f5f6e6d6g7f4d3f3d7f7e3d2g3h8g8c5c6c7b5c4b8e2g6e8c1h5d8c8b3a2f2a5a6a4a3g1b7g5e1c2b6f8h4g4e7h3", "a1"),
+        // Black to move with 12 empties
+        ("f5f6e6d6f7e3c5g6e2f2g7b4g2f8d7c6g4e8d3h7b6c4f3c7e7d2g8h8h6f1b8h2h1a7c1d1a6g5d8g3h3b7h5b5f4h4c8c2", "a8"),
+        // White to move with 15 empties
+        ("c4e3f5c6d3e6f3g2d6c2b6b3d7c7h1a6f6e8b7c5d8f7b1g5b8g6f8e2d2e7e1c3h6a7a2a3a5g7b5a4h4f2b2c1a8", "a1"),
+    ];
+    for (transcript, corner) in positions {
+        let game = Gamestate::from_transcript(transcript).unwrap();
+        assert!(game.valid_move(alg_to_loc(corner)));
+        for seed in 0..2 {
+            assert_eq!(decide(seed, game.clone(), 10_000), corner, "after {transcript}");
+        }
+    }
+}
+
+#[test]
+fn seeded_game_is_unchanged() {
+    // boxed nodes whichever the default is, since an arena breaks ties
+    // between children differently and so plays another game
+    let new_agent = |seed| {
+        let mut agent = McstAgent::seeded_default(seed, Gamestate::new());
+        agent.set_node_storage(NodeStorage::Boxed);
+        McstMemoryAgent::with_budget(agent, Budget::Cycles(300))
+    };
+    let (result, turns) = play_game(&mut new_agent(1), &mut new_agent(3));
+    assert_eq!(
+        turns_to_transcript(&turns),
+        "e6f4g3c6c5e7d6g4g5c4e8h4c3g2g1f2b4f7f6c2g8g6b5a5g7h8c7c8f3f5b6f8b3h1c1f1e3a4a3a6h5d2d7\
+         d8a7b7a8h2h3b8e2h6d3d1e1b1b2a1a2--h7",
+    );
+    assert_eq!(result, GameResult::WhiteWin(4));
+}
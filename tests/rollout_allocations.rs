@@ -0,0 +1,47 @@
+//! Counts the heap allocations rollouts make, which slow down every cycle of
+//! a search. This is its own test binary so that it can count every
+//! allocation, with no other tests running alongside it.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use othello::agent::implementations::RandomAgent;
+use othello::gameplay::{Gamestate, Players, Turn};
+use othello::mcst::{AgentRollout, RolloutPolicy};
+
+/// The system allocator, counting how many allocations it makes.
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+#[test]
+fn rollouts_barely_allocate() {
+    // an opponent which isn't random, so that rollouts are played out a move
+    // at a time rather than left to Gamestate::random_playout
+    let first = |state: &Gamestate| -> Turn { state.get_moves()[0] };
+    let rollout = AgentRollout::new(RandomAgent::seeded(1), first);
+    let game = Gamestate::from_transcript("f5d6c3d3c4").unwrap();
+
+    let rollouts = 1000;
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for _ in 0..rollouts {
+        rollout.rollout(game.clone(), Players::Black).unwrap();
+    }
+    // only the odd move list too long to keep on the stack should need any
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    assert!(allocations < rollouts / 10, "{allocations} allocations in {rollouts} rollouts");
+}
@@ -0,0 +1,49 @@
+//! Runs the whole self-play pipeline on a tiny budget and checks that the
+//! datasets it writes can be loaded and batched for training.
+
+use burn::backend::Wgpu;
+use burn::backend::wgpu::WgpuDevice;
+use burn::data::dataloader::batcher::Batcher;
+use burn::data::dataset::Dataset;
+
+use othello::data::{selfplay_pipeline, PipelineConfig};
+use othello::neural::data::{DataBatch, DataBatcher};
+use othello::neural::{load_dataset, load_weighted_dataset};
+
+#[test]
+fn random_games_make_a_dataset() {
+    let dir = std::env::temp_dir().join(format!("othello-selfplay-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut config = PipelineConfig::new("random", "random", 20, &dir);
+    config.threads = 2;
+    config.temperatures = vec![10.0; 4];
+    config.records.dedup_canonical = true;
+    config.binary = true;
+    let report = selfplay_pipeline(config).unwrap();
+    assert_eq!(report.games, 20);
+    assert_eq!(report.train + report.valid, report.records);
+    assert!(report.valid > 0 && report.train > report.valid, "{report:?}");
+
+    let device = WgpuDevice::default();
+    for (name, len) in [("train", report.train), ("valid", report.valid)] {
+        for extension in ["csv", "bin"] {
+            let dataset = load_dataset(&dir.join(format!("{name}.{extension}"))).unwrap();
+            assert_eq!(dataset.len(), len, "{name}.{extension}");
+            let items: Vec<(u128, f32)> = (0..len.min(32)).map(|i| dataset.get(i).unwrap()).collect();
+            assert!(items.iter().all(|(_, label)| (0.0..=1.0).contains(label)));
+            let batch: DataBatch<Wgpu> = DataBatcher {}.batch(items.clone(), &device);
+            assert_eq!(batch.states.dims(), [items.len(), 64 * 3]);
+            assert_eq!(batch.targets.dims(), [items.len(), 1]);
+            assert!(batch.weights.is_none());
+
+            // the pipeline's datasets aren't weighted, so every row counts once
+            let weighted = load_weighted_dataset(&dir.join(format!("{name}.{extension}"))).unwrap();
+            assert_eq!(weighted.len(), len, "{name}.{extension}");
+            let weighted_items: Vec<(u128, f32, f32)> = (0..len.min(32)).map(|i| weighted.get(i).unwrap()).collect();
+            assert!(weighted_items.iter().zip(&items).all(|(row, item)| *row == (item.0, item.1, 1.0)));
+            let batch: DataBatch<Wgpu> = DataBatcher {}.batch(weighted_items, &device);
+            assert_eq!(batch.weights.unwrap().dims(), [items.len(), 1]);
+        }
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
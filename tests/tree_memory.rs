@@ -0,0 +1,78 @@
+//! Compares how fast trees grow, and how much memory they take at most,
+//! when they keep their nodes boxed and when they keep them in an arena.
+//! This is its own test binary so that it can track every allocation, with
+//! no other tests running alongside it.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use othello::gameplay::Gamestate;
+use othello::mcst::{benchmark_with, McstAgent, NodeStorage};
+
+/// The system allocator, tracking how many bytes are allocated at once.
+struct Tracking;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Tracking {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let current = CURRENT.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+        PEAK.fetch_max(current, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Tracking = Tracking;
+
+/// Runs `run`, returning what it does and the most bytes allocated at once
+/// while it did, beyond those allocated before.
+fn peak_bytes<T>(run: impl FnOnce() -> T) -> (T, usize) {
+    let before = CURRENT.load(Ordering::Relaxed);
+    PEAK.store(before, Ordering::Relaxed);
+    let result = run();
+    (result, PEAK.load(Ordering::Relaxed) - before)
+}
+
+#[test]
+#[ignore = "benchmark; run with --ignored --nocapture to see the results"]
+fn boxed_against_arena() {
+    for storage in [NodeStorage::Boxed, NodeStorage::Arena] {
+        // growing one tree from the opening
+        let (report, grown) = peak_bytes(|| {
+            let mut agent = McstAgent::seeded_default(1, Gamestate::new());
+            agent.set_node_storage(storage);
+            benchmark_with(agent, Duration::from_secs(5))
+        });
+        println!(
+            "{storage:?}: {} nodes/sec, {} bytes at most for {} nodes ({:.1} bytes a node)",
+            report.nodes_per_sec(), grown, report.peak_nodes, grown as f64 / report.peak_nodes as f64,
+        );
+
+        // playing a game, advancing the root past every move
+        let ((nodes, elapsed), played) = peak_bytes(|| {
+            let mut agent = McstAgent::seeded_default(1, Gamestate::new());
+            agent.set_node_storage(storage);
+            let (mut nodes, start) = (0, Instant::now());
+            while !agent.tree().root().game().is_terminal() {
+                let size = agent.tree().size();
+                agent.cycle_n(20_000).unwrap();
+                nodes += agent.tree().size() - size;
+                let turn = agent.decide().unwrap();
+                assert!(agent.advance(turn));
+            }
+            (nodes, start.elapsed())
+        });
+        println!(
+            "{storage:?}: {:.0} nodes/sec, {} bytes at most over a game",
+            nodes as f64 / elapsed.as_secs_f64(), played,
+        );
+    }
+}